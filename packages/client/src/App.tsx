@@ -2,6 +2,7 @@ import { useState, useEffect, useCallback, useRef } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
 import { api, type ServerStatus } from './lib/api';
 import { liveReload } from './lib/websocket';
+import { startReminders } from './lib/reminders';
 import { useTheme } from './lib/theme';
 import Dashboard from './components/Dashboard';
 import DocumentList from './components/DocumentList';
@@ -72,12 +73,14 @@ function App() {
     fetchStatus();
     liveReload.connect();
 
-    const unsubReload = liveReload.onReload(() => {
+    const unsubStatus = liveReload.onServerStatus(() => {
       fetchStatus();
     });
+    const stopReminders = startReminders();
 
     return () => {
-      unsubReload();
+      unsubStatus();
+      stopReminders();
       liveReload.disconnect();
     };
   }, [fetchStatus]);
@@ -115,6 +118,12 @@ function App() {
         }
       }
 
+      if (e.key === 'n' && !e.ctrlKey && !e.metaKey && !isTyping) {
+        e.preventDefault();
+        createDocument();
+        return;
+      }
+
       if (!selectedPath && !isTyping) {
         if (e.key === '1') { e.preventDefault(); setView('dashboard'); }
         if (e.key === '2') { e.preventDefault(); setView('tasks'); }
@@ -138,6 +147,20 @@ function App() {
     setView('document');
   };
 
+  // New note in the folder of the list being shown, then open it
+  const createDocument = async () => {
+    const folder = ['tasks', 'knowledge', 'inbox', 'reminders'].includes(view) ? `${view}/` : '';
+    const input = window.prompt('New document path', folder);
+    if (!input || input.endsWith('/')) return;
+    const path = /\.(md|org)$/.test(input) ? input : `${input}.md`;
+    try {
+      await api.createFile(path);
+      handleSelectDocument(path);
+    } catch (err) {
+      window.alert(`Could not create ${path}: ${err instanceof Error ? err.message : err}`);
+    }
+  };
+
   if (loading || serverStarting) {
     return (
       <div className="h-full flex flex-col items-center justify-center gap-2">
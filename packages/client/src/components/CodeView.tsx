@@ -1,5 +1,5 @@
 import { useState, useEffect, useCallback, useRef } from 'react';
-import { api, type Project, type TreeEntry, type ProjectFile } from '../lib/api';
+import { api, isConflict, type Project, type TreeEntry, type ProjectFile } from '../lib/api';
 import { liveReload } from '../lib/websocket';
 import FileTree from './FileTree';
 import CodeEditor from './CodeEditor';
@@ -72,8 +72,8 @@ export default function CodeView() {
 
   // Live reload: refresh tree and file data when files change
   useEffect(() => {
-    const unsubReload = liveReload.onReload(() => {
-      // Full reload — refresh project list and tree
+    const unsubStatus = liveReload.onServerStatus(() => {
+      // e.g. the watcher resuming after missing changes: refresh project list and tree
       api.listProjects().then(setProjects).catch(console.error);
       if (selectedProject) {
         api.getProjectTree(selectedProject).then(setTree).catch(console.error);
@@ -100,10 +100,31 @@ export default function CodeView() {
       }
     });
 
+    // Changes to non-document files under projects/
+    const unsubProjectTree = liveReload.onProjectTreeChanged((project, paths) => {
+      // An empty path is the project directory itself being added or removed
+      if (paths.includes('')) {
+        api.listProjects().then(setProjects).catch(console.error);
+      }
+      if (project === selectedProject) {
+        api.getProjectTree(selectedProject).then(setTree).catch(console.error);
+      }
+    });
+
+    const unsubProjectFile = liveReload.onProjectFileChanged((project, path) => {
+      if (project === selectedProject && path === selectedFile && !isEditing) {
+        api.getProjectFile(selectedProject, selectedFile)
+          .then(setFileData)
+          .catch(console.error);
+      }
+    });
+
     return () => {
-      unsubReload();
+      unsubStatus();
       unsubUpdate();
       unsubRemove();
+      unsubProjectTree();
+      unsubProjectFile();
     };
   }, [selectedProject, selectedFile, isEditing]);
 
@@ -134,11 +155,18 @@ export default function CodeView() {
 
   // Save handler
   const handleSave = useCallback(async (content: string) => {
-    if (!selectedProject || !selectedFile) return;
+    if (!selectedProject || !selectedFile || !fileData) return;
 
     setSaving(true);
     try {
-      await api.updateProjectFile(selectedProject, selectedFile, content);
+      try {
+        await api.updateProjectFile(selectedProject, selectedFile, content, fileData.revision);
+      } catch (err) {
+        // Changed on disk since the file was loaded
+        if (!isConflict(err)) throw err;
+        if (!window.confirm('This file has changed since you opened it. Overwrite it with your version?')) return;
+        await api.updateProjectFile(selectedProject, selectedFile, content, '*');
+      }
       // Reload to get fresh data
       const data = await api.getProjectFile(selectedProject, selectedFile);
       setFileData(data);
@@ -148,7 +176,7 @@ export default function CodeView() {
     } finally {
       setSaving(false);
     }
-  }, [selectedProject, selectedFile]);
+  }, [selectedProject, selectedFile, fileData]);
 
   // Keyboard shortcuts
   useEffect(() => {
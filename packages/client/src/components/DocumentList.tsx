@@ -2,6 +2,9 @@ import { useState, useEffect, useCallback, useRef } from 'react';
 import { api, type FileListItem } from '../lib/api';
 import { liveReload } from '../lib/websocket';
 
+/** Documents fetched at a time; more load on request */
+const PAGE_SIZE = 200;
+
 interface DocumentListProps {
   type: 'task' | 'knowledge' | 'inbox' | 'reminder';
   title: string;
@@ -15,6 +18,10 @@ export default function DocumentList({ type, title, onSelect }: DocumentListProp
   const [searchQuery, setSearchQuery] = useState('');
   const [statusFilter, setStatusFilter] = useState('');
   const [selectedIndex, setSelectedIndex] = useState(0);
+  const [nextOffset, setNextOffset] = useState<number | undefined>(undefined);
+  const [total, setTotal] = useState(0);
+  // Listed documents, so a refetch after a change keeps as many pages as were loaded
+  const loadedCount = useRef(0);
   const itemRefs = useRef<(HTMLButtonElement | null)[]>([]);
 
   const fetchDocuments = useCallback(async () => {
@@ -32,8 +39,13 @@ export default function DocumentList({ type, title, onSelect }: DocumentListProp
           linkCount: 0,
           backlinkCount: 0,
         })));
+        setNextOffset(undefined);
       } else {
-        const result = await api.listFiles({ type });
+        const limit = Math.max(PAGE_SIZE, loadedCount.current);
+        const result = await api.listFiles({ type, sort: '-modified', limit });
+        loadedCount.current = result.count;
+        setNextOffset(result.nextOffset);
+        setTotal(result.total);
         let items = result.items;
 
         if (statusFilter) {
@@ -51,15 +63,36 @@ export default function DocumentList({ type, title, onSelect }: DocumentListProp
     }
   }, [type, searchQuery, statusFilter]);
 
+  const loadMore = useCallback(async () => {
+    if (nextOffset === undefined) return;
+    try {
+      const result = await api.listFiles({ type, sort: '-modified', limit: PAGE_SIZE, offset: nextOffset });
+      loadedCount.current = nextOffset + result.count;
+      setNextOffset(result.nextOffset);
+      setTotal(result.total);
+      const items = statusFilter ? result.items.filter(d => d.status === statusFilter) : result.items;
+      setDocuments(prev => [...prev, ...items]);
+    } catch (err) {
+      setError(err instanceof Error ? err.message : 'Failed to load documents');
+    }
+  }, [type, statusFilter, nextOffset]);
+
+  // A different list starts again from its first page
+  useEffect(() => {
+    loadedCount.current = 0;
+  }, [type, searchQuery, statusFilter]);
+
   useEffect(() => {
     fetchDocuments();
 
     const unsubUpdate = liveReload.onUpdate(() => fetchDocuments());
     const unsubRemove = liveReload.onRemove(() => fetchDocuments());
+    const unsubResync = liveReload.onResync(() => fetchDocuments());
 
     return () => {
       unsubUpdate();
       unsubRemove();
+      unsubResync();
     };
   }, [fetchDocuments]);
 
@@ -129,7 +162,7 @@ export default function DocumentList({ type, title, onSelect }: DocumentListProp
         <h1 style={{ color: 'var(--term-primary)' }} className="text-lg font-bold">
           {title}
           <span className="ml-2 text-sm font-normal" style={{ color: 'var(--term-muted)' }}>
-            ({documents.length})
+            ({nextOffset !== undefined && !statusFilter ? `${documents.length} of ${total}` : documents.length})
           </span>
         </h1>
         <span className="text-xs hidden sm:block" style={{ color: 'var(--term-muted)' }}>
@@ -228,6 +261,15 @@ export default function DocumentList({ type, title, onSelect }: DocumentListProp
             </button>
           ))
         )}
+        {nextOffset !== undefined && (
+          <button
+            onClick={loadMore}
+            className="w-full px-4 py-2 border text-sm hover:bg-white/5 transition-colors"
+            style={{ borderColor: 'var(--term-border)', color: 'var(--term-muted)' }}
+          >
+            Load more
+          </button>
+        )}
       </div>
     </div>
   );
@@ -1,10 +1,15 @@
-import { useState, useEffect, useCallback } from 'react';
+import { useState, useEffect, useCallback, useRef } from 'react';
 import ReactMarkdown from 'react-markdown';
 import remarkGfm from 'remark-gfm';
-import { api, type OrgDocument } from '../lib/api';
-import { liveReload } from '../lib/websocket';
+import { api, isConflict, type OrgDocument } from '../lib/api';
+import { liveReload, applyPatch, type ClientPresence } from '../lib/websocket';
+import { presence } from '../lib/presence';
+import { CollabSession } from '../lib/collab';
 import TuiEditor, { type EditorData } from './TuiEditor';
-import { getEditorFields, documentToEditorData, editorDataToPayload } from '../lib/editor-helpers';
+import { getEditorFields, documentToEditorData, editorDataToPayload, splitBody } from '../lib/editor-helpers';
+
+/** How long after the last keystroke an unsaved edit is kept as a draft */
+const DRAFT_DELAY_MS = 2000;
 
 interface DocumentViewProps {
   path: string;
@@ -18,6 +23,21 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
   const [error, setError] = useState<string | null>(null);
   const [isEditing, setIsEditing] = useState(false);
   const [saving, setSaving] = useState(false);
+  const documentRef = useRef<OrgDocument | null>(null);
+  documentRef.current = document;
+  // The document as stored, which the editor works on; `document` is rendered
+  const [editDocument, setEditDocument] = useState<OrgDocument | null>(null);
+  const editDocumentRef = useRef<OrgDocument | null>(null);
+  editDocumentRef.current = editDocument;
+  const editingRef = useRef(false);
+  editingRef.current = isEditing;
+  const collabRef = useRef<CollabSession | null>(null);
+  const [remoteData, setRemoteData] = useState<EditorData | undefined>(undefined);
+  const [peers, setPeers] = useState<ClientPresence[]>([]);
+  const draftTimerRef = useRef<number | null>(null);
+  // A restored draft, applied again over the text the editing session starts with
+  const restoredDraftRef = useRef<EditorData | null>(null);
+  const draftCheckedRef = useRef<string | null>(null);
 
   const fetchDocument = useCallback(async () => {
     try {
@@ -35,16 +55,155 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
   useEffect(() => {
     fetchDocument();
 
-    // Refresh when this specific document changes
-    const unsubUpdate = liveReload.onUpdate((changedPath) => {
-      if (changedPath === path) {
+    // Refresh when this specific document changes, patching it in place
+    // when the server sent a patch so the scroll position stays put
+    const unsubUpdate = liveReload.onUpdate((changedPath, patch) => {
+      // While editing, the editing session keeps the text current
+      if (changedPath !== path || editingRef.current) return;
+      const current = documentRef.current;
+      const patched = patch && current?.content != null ? applyPatch(current.content, patch) : null;
+      if (current && patched !== null) {
+        setDocument({ ...current, content: patched });
+      } else {
         fetchDocument();
       }
     });
 
-    return () => unsubUpdate();
+    // Changes missed while disconnected couldn't be replayed
+    const unsubResync = liveReload.onResync(() => {
+      if (!editingRef.current) fetchDocument();
+    });
+
+    // Nothing else is on screen, so other documents' changes can wait
+    liveReload.subscribe([path]);
+
+    return () => {
+      unsubUpdate();
+      unsubResync();
+      liveReload.unsubscribe();
+    };
   }, [path, fetchDocument]);
 
+  // Close the view when its file is deleted, rather than 404 on the next
+  // refetch. An open editor stays so unsaved text can be copied out.
+  useEffect(() => {
+    return liveReload.onFileDeleted((deleted) => {
+      if (deleted.path !== path) return;
+      if (isEditing) {
+        alert('This document was deleted on disk; saving it will fail.');
+      } else {
+        onBack();
+      }
+    });
+  }, [path, isEditing, onBack]);
+
+  // Let other devices know this document is open, and show which of them
+  // have it open too
+  useEffect(() => {
+    const close = presence.open(path);
+    setPeers(presence.peersOf(path));
+    const unsubPresence = presence.onChange(() => setPeers(presence.peersOf(path)));
+    return () => {
+      unsubPresence();
+      close();
+    };
+  }, [path]);
+
+  useEffect(() => {
+    presence.setEditing(path, isEditing);
+  }, [path, isEditing]);
+
+  // While editing, share the body with other devices editing the same
+  // document, so neither overwrites the other's typing
+  useEffect(() => {
+    if (!isEditing) return;
+    const session = new CollabSession(path, (content) => {
+      const restored = restoredDraftRef.current;
+      restoredDraftRef.current = null;
+      setRemoteData(restored ?? { content: splitBody(content, path).body });
+    });
+    collabRef.current = session;
+    return () => {
+      session.close();
+      collabRef.current = null;
+      setRemoteData(undefined);
+    };
+  }, [isEditing, path]);
+
+  const clearDraftTimer = useCallback(() => {
+    if (draftTimerRef.current) clearTimeout(draftTimerRef.current);
+    draftTimerRef.current = null;
+  }, []);
+
+  // A pending draft save is dropped with the view; the last one stays
+  useEffect(() => clearDraftTimer, [path, clearDraftTimer]);
+
+  // Load the document as stored and open it in the editor
+  const startEditing = useCallback(async () => {
+    try {
+      const stored = await api.getFile(path, { raw: true });
+      setEditDocument(stored);
+      setIsEditing(true);
+      return stored;
+    } catch (err) {
+      alert(`Failed to open the editor: ${err instanceof Error ? err.message : 'Unknown error'}`);
+      return null;
+    }
+  }, [path]);
+
+  // Offer an edit a crashed or closed session left unsaved, once the
+  // document it's restored over has loaded
+  useEffect(() => {
+    if (loading || !documentRef.current || draftCheckedRef.current === path) return;
+    draftCheckedRef.current = path;
+    api.getDraft(path).then(async (draft) => {
+      if (!draft || documentRef.current?.path !== path) return;
+      const when = new Date(draft.savedAt).toLocaleString();
+      const stale = draft.stale ? ', and the document has changed since' : '';
+      if (!window.confirm(`You have an unsaved edit of this document from ${when}${stale}. Restore it?`)) {
+        api.deleteDraft(path).catch(() => {});
+        return;
+      }
+      const stored = await startEditing();
+      if (!stored) return;
+      const tags = draft.frontmatter?.tags as string[] | undefined;
+      const restored = documentToEditorData({
+        ...stored,
+        content: draft.content,
+        title: (draft.frontmatter?.title as string | undefined) ?? stored.title,
+        tags: tags ?? stored.tags,
+        fileTags: tags ?? stored.fileTags,
+        status: (draft.frontmatter?.status as string | undefined) ?? stored.status,
+      });
+      restoredDraftRef.current = restored;
+      setRemoteData(restored);
+    });
+  }, [path, loading, startEditing]);
+
+  const handleEditorChange = useCallback((data: EditorData) => {
+    // Keep the edit on the server a moment after typing stops, so a crash
+    // doesn't lose it; the document itself only changes on save
+    const doc = editDocumentRef.current;
+    clearDraftTimer();
+    if (doc) {
+      const edited = editorDataToPayload(data, doc);
+      const loaded = editorDataToPayload(documentToEditorData(doc), doc);
+      if (JSON.stringify(edited) !== JSON.stringify(loaded)) {
+        draftTimerRef.current = window.setTimeout(() => {
+          draftTimerRef.current = null;
+          api.saveDraft(path, { ...edited, revision: doc.revision }).catch((err) => {
+            console.error('Failed to save draft:', err);
+          });
+        }, DRAFT_DELAY_MS);
+      }
+    }
+
+    const session = collabRef.current;
+    // Until the session has the text, there's no head to put the body under
+    if (!session?.content) return;
+    session.update(splitBody(session.content, path).head + (data.content ?? ''));
+  }, [path, clearDraftTimer]);
+
   // Keyboard shortcut for edit mode
   useEffect(() => {
     const handleKeyDown = (e: KeyboardEvent) => {
@@ -57,22 +216,49 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
         !(e.target instanceof HTMLTextAreaElement)
       ) {
         e.preventDefault();
-        setIsEditing(true);
+        startEditing();
       }
     };
 
     window.addEventListener('keydown', handleKeyDown);
     return () => window.removeEventListener('keydown', handleKeyDown);
-  }, [isEditing, document]);
+  }, [isEditing, document, startEditing]);
 
   const handleSave = useCallback(async (data: EditorData) => {
-    if (!document) return;
+    const stored = editDocument;
+    if (!stored) return;
 
+    // Saving discards the draft; one written after it would come back
+    clearDraftTimer();
     try {
       setSaving(true);
-      const { frontmatter, content } = editorDataToPayload(data, document);
-      await api.updateFile(path, frontmatter, content);
+      const { frontmatter, content } = editorDataToPayload(data, stored);
+      try {
+        // The editing session writes the file as it goes, so its last write
+        // is what this save replaces
+        const revision = collabRef.current?.revision ?? stored.revision ?? '*';
+        await api.updateFile(path, frontmatter, content, revision);
+      } catch (err) {
+        if (!isConflict(err)) throw err;
+        // Changed on disk or by another device since editing started: merge
+        // those changes into the editor to review, and save over them next time
+        const current = await api.getFile(path, { raw: true });
+        const merged = await api.mergeFile(path, {
+          base: documentToEditorData(stored).content ?? '',
+          ours: data.content ?? '',
+          theirs: documentToEditorData(current).content ?? '',
+        });
+        setEditDocument(current);
+        setRemoteData({ content: merged.content });
+        alert(
+          merged.conflicts > 0
+            ? `This document changed since you started editing. ${merged.conflicts} conflicting change(s) are marked in the text; resolve them and save again.`
+            : 'This document changed since you started editing. The other changes have been merged in; review and save again.'
+        );
+        return;
+      }
       setIsEditing(false);
+      setEditDocument(null);
       // Refresh document to show updated content
       await fetchDocument();
     } catch (err) {
@@ -80,11 +266,40 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
     } finally {
       setSaving(false);
     }
-  }, [document, path, fetchDocument]);
+  }, [editDocument, path, fetchDocument, clearDraftTimer]);
 
   const handleCancelEdit = useCallback(() => {
+    clearDraftTimer();
+    api.deleteDraft(path).catch(() => {});
     setIsEditing(false);
-  }, []);
+    setEditDocument(null);
+  }, [path, clearDraftTimer]);
+
+  const handleDelete = useCallback(async () => {
+    if (!window.confirm(`Move ${path} to the trash?`)) return;
+    try {
+      await api.deleteFile(path);
+      onBack();
+    } catch (err) {
+      alert(`Failed to delete: ${err instanceof Error ? err.message : 'Unknown error'}`);
+    }
+  }, [path, onBack]);
+
+  const handlePasteFiles = useCallback(async (files: File[]) => {
+    const uploaded = await api.uploadAttachments(path, files);
+    return uploaded.map(a => a.link).join('\n');
+  }, [path]);
+
+  const handleMove = useCallback(async () => {
+    const to = window.prompt('Move to:', path)?.trim();
+    if (!to || to === path) return;
+    try {
+      const result = await api.moveFile(path, to);
+      onNavigate(result.to);
+    } catch (err) {
+      alert(`Failed to move: ${err instanceof Error ? err.message : 'Unknown error'}`);
+    }
+  }, [path, onNavigate]);
 
   // Process content to handle wikilinks
   const processContent = (content: string): string => {
@@ -108,6 +323,17 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
     if (href?.startsWith('wikilink:')) {
       e.preventDefault();
       const target = href.replace('wikilink:', '');
+      // Links to files other than documents, e.g. PDFs, open the file itself
+      const file = target.match(/^file:([^:]+)/)?.[1];
+      if (file && !/\.(md|org)$/i.test(file) && !file.startsWith('/') && !file.startsWith('~')) {
+        const resolved: string[] = path.split('/').slice(0, -1);
+        for (const segment of file.split('/')) {
+          if (segment === '..') resolved.pop();
+          else if (segment && segment !== '.') resolved.push(segment);
+        }
+        window.open(api.rawUrl(resolved.join('/')), '_blank');
+        return;
+      }
       onNavigate(target);
     }
   };
@@ -137,8 +363,11 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
 
   if (!document) return null;
 
+  const peerNames = peers.map((peer) => (peer.editing.includes(path) ? `${peer.name} (editing)` : peer.name));
+  const editingPeers = peers.filter((peer) => peer.editing.includes(path)).map((peer) => peer.name);
+
   // Edit mode - show TuiEditor
-  if (isEditing) {
+  if (isEditing && editDocument) {
     return (
       <div className="h-full relative">
         {saving && (
@@ -150,11 +379,14 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
           </div>
         )}
         <TuiEditor
-          title={`Edit: ${document.title}`}
-          fields={getEditorFields(document.type)}
-          initialData={documentToEditorData(document)}
+          title={`Edit: ${editDocument.title}${editingPeers.length > 0 ? ` · also editing on ${editingPeers.join(', ')}` : ''}`}
+          fields={getEditorFields(editDocument.type)}
+          initialData={documentToEditorData(editDocument)}
           onSave={handleSave}
           onCancel={handleCancelEdit}
+          onChange={handleEditorChange}
+          remoteData={remoteData}
+          onPasteFiles={handlePasteFiles}
         />
       </div>
     );
@@ -172,16 +404,37 @@ export default function DocumentView({ path, onBack, onNavigate }: DocumentViewP
             <div className="text-xs mt-1" style={{ color: 'var(--term-muted)' }}>
               {document.path}
             </div>
+            {peerNames.length > 0 && (
+              <div className="text-xs mt-1" style={{ color: 'var(--term-warning)' }}>
+                Also open on {peerNames.join(', ')}
+              </div>
+            )}
           </div>
           <div className="flex items-center gap-2 shrink-0">
             <button
-              onClick={() => setIsEditing(true)}
+              onClick={startEditing}
               className="text-xs px-2 py-1 border hover:bg-white/5 transition-colors"
               style={{ borderColor: 'var(--term-border)', color: 'var(--term-info)' }}
               title="Press 'e' to edit"
             >
               [e] Edit
             </button>
+            <button
+              onClick={handleMove}
+              className="text-xs px-2 py-1 border hover:bg-white/5 transition-colors"
+              style={{ borderColor: 'var(--term-border)', color: 'var(--term-foreground)' }}
+              title="Move or rename, updating links to it"
+            >
+              Move
+            </button>
+            <button
+              onClick={handleDelete}
+              className="text-xs px-2 py-1 border hover:bg-white/5 transition-colors"
+              style={{ borderColor: 'var(--term-border)', color: 'var(--term-error)' }}
+              title="Move to trash"
+            >
+              Delete
+            </button>
             {document.type && (
               <span
                 className="text-xs px-2 py-1"
@@ -1,6 +1,7 @@
 import { useState, useEffect, useRef } from 'react';
 import * as d3 from 'd3';
 import { api, type GraphData } from '../lib/api';
+import { liveReload } from '../lib/websocket';
 import { useTheme } from '../lib/theme';
 
 interface GraphProps {
@@ -56,6 +57,9 @@ export default function Graph({ onSelectDocument }: GraphProps) {
     };
 
     fetchGraph();
+
+    // Deleted documents take their nodes and edges with them
+    return liveReload.onFileDeleted(() => fetchGraph());
   }, []);
 
   // Handle resize with ResizeObserver for accurate flex container sizing
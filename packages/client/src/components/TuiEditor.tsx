@@ -1,4 +1,4 @@
-import React, { useState, useRef, useEffect, useCallback } from 'react';
+import React, { useState, useRef, useEffect, useLayoutEffect, useCallback } from 'react';
 import { motion } from 'framer-motion';
 
 export interface EditorField {
@@ -19,6 +19,12 @@ interface TuiEditorProps {
   onSave: (data: EditorData) => void;
   onCancel: () => void;
   initialData?: EditorData;
+  /** Called with every edit, e.g. to share it with other devices */
+  onChange?: (data: EditorData) => void;
+  /** Field values changed elsewhere, applied over the current ones */
+  remoteData?: EditorData;
+  /** Store files pasted into a text area, resolving to the text to insert */
+  onPasteFiles?: (files: File[]) => Promise<string>;
 }
 
 /**
@@ -31,6 +37,9 @@ const TuiEditor: React.FC<TuiEditorProps> = ({
   onSave,
   onCancel,
   initialData = {},
+  onChange,
+  remoteData,
+  onPasteFiles,
 }) => {
   const [data, setData] = useState<EditorData>(() => {
     const initial: EditorData = {};
@@ -49,11 +58,62 @@ const TuiEditor: React.FC<TuiEditorProps> = ({
     textareaRefs.current[activeField]?.focus();
   }, [activeField]);
 
+  // Caret to restore after a remote change re-renders the focused field
+  const pendingSelection = useRef<{ index: number; start: number; end: number } | null>(null);
+
+  useEffect(() => {
+    onChange?.(data);
+  }, [data, onChange]);
+
+  // Apply remote changes. The caret keeps its place in the text around it:
+  // text inserted or removed before it moves it along.
+  useEffect(() => {
+    if (!remoteData) return;
+    const index = fields.findIndex(f => f.name in remoteData);
+    const el = textareaRefs.current[index];
+    if (el && el === window.document.activeElement && el.selectionStart !== null && el.selectionEnd !== null) {
+      const before = el.value;
+      const after = remoteData[fields[index].name];
+      let prefix = 0;
+      while (prefix < before.length && prefix < after.length && before[prefix] === after[prefix]) prefix++;
+      const shift = (pos: number) => (pos <= prefix ? pos : Math.max(prefix, pos + after.length - before.length));
+      pendingSelection.current = { index, start: shift(el.selectionStart), end: shift(el.selectionEnd) };
+    }
+    setData(prev => ({ ...prev, ...remoteData }));
+  }, [remoteData, fields]);
+
+  useLayoutEffect(() => {
+    const selection = pendingSelection.current;
+    if (!selection) return;
+    pendingSelection.current = null;
+    textareaRefs.current[selection.index]?.setSelectionRange(selection.start, selection.end);
+  }, [data]);
+
   const handleChange = useCallback((name: string, value: string) => {
     setData(prev => ({ ...prev, [name]: value }));
     setHasChanges(true);
   }, []);
 
+  // Pasted files, e.g. screenshots, are uploaded and replaced by their links
+  const handlePaste = useCallback(async (index: number, e: React.ClipboardEvent<HTMLTextAreaElement>) => {
+    const files = Array.from(e.clipboardData.files);
+    if (!onPasteFiles || files.length === 0) return;
+    e.preventDefault();
+    const { selectionStart: start, selectionEnd: end } = e.currentTarget;
+    try {
+      const text = await onPasteFiles(files);
+      const name = fields[index].name;
+      pendingSelection.current = { index, start: start + text.length, end: start + text.length };
+      setData(prev => {
+        const value = prev[name] ?? '';
+        return { ...prev, [name]: value.slice(0, start) + text + value.slice(end) };
+      });
+      setHasChanges(true);
+    } catch (err) {
+      alert(`Failed to upload: ${err instanceof Error ? err.message : 'Unknown error'}`);
+    }
+  }, [onPasteFiles, fields]);
+
   const handleSave = useCallback(() => {
     // Validate required fields
     const missingRequired = fields.filter(f => f.required && !data[f.name]?.trim());
@@ -191,6 +251,7 @@ const TuiEditor: React.FC<TuiEditorProps> = ({
                 ref={el => textareaRefs.current[index] = el}
                 value={data[field.name]}
                 onChange={e => handleChange(field.name, e.target.value)}
+                onPaste={e => handlePaste(index, e)}
                 onFocus={() => setActiveField(index)}
                 onKeyDown={handleInputKeyDown}
                 placeholder={field.placeholder}
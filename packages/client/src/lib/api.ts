@@ -4,7 +4,11 @@
  * DIAGNOSTIC VERSION - logs via Tauri IPC
  */
 
+import { liveReload, CommandError, type Command } from './websocket';
+
 const SERVER_URL = 'http://127.0.0.1:3847';
+// Versioned prefix; the server also answers under plain /api
+const API_BASE = '/api/v1';
 
 // Log via Tauri IPC (bypasses mixed content restrictions)
 async function log(msg: string) {
@@ -27,6 +31,34 @@ function logSync(msg: string) {
 // Log startup immediately
 logSync('api.ts loading, __TAURI__ in window: ' + ('__TAURI__' in window));
 
+// Remote access token (server's ORG_VIEWER_TOKEN). Open the app once as
+// https://host/?token=... and it's kept in a cookie, which the browser then
+// sends with every request, images included.
+const TOKEN_COOKIE = 'org-viewer-token';
+
+function rememberToken(): string | null {
+  const params = new URLSearchParams(window.location.search);
+  const fromUrl = params.get('token');
+  if (fromUrl) {
+    const secure = window.location.protocol === 'https:' ? '; Secure' : '';
+    document.cookie = `${TOKEN_COOKIE}=${encodeURIComponent(fromUrl)}; Path=/; Max-Age=31536000; SameSite=Strict${secure}`;
+    // Keep the token out of the address bar and history
+    params.delete('token');
+    const query = params.toString();
+    window.history.replaceState(null, '', window.location.pathname + (query ? `?${query}` : '') + window.location.hash);
+    return fromUrl;
+  }
+  const cookie = document.cookie.split('; ').find((c) => c.startsWith(`${TOKEN_COOKIE}=`));
+  return cookie ? decodeURIComponent(cookie.slice(TOKEN_COOKIE.length + 1)) : null;
+}
+
+const authToken = rememberToken();
+
+/** Token for connections that don't carry the cookie, like WebSockets */
+export function getAuthToken(): string | null {
+  return authToken;
+}
+
 // Cached Tauri fetch function
 let tauriFetch: typeof fetch | null = null;
 let tauriFetchInitialized = false;
@@ -86,6 +118,8 @@ export interface OrgDocument {
   type: string;
   status?: string;
   tags: string[];
+  /** Tags from an org file's `#+FILETAGS:` */
+  fileTags?: string[];
   content: string;
   links: string[];
   backlinks: string[];
@@ -96,6 +130,8 @@ export interface OrgDocument {
     title: string;
     type: string;
   }>;
+  /** Revision of the file as stored; saving sends it back as `If-Match` */
+  revision?: string;
 }
 
 export interface SearchResult {
@@ -108,6 +144,52 @@ export interface SearchResult {
   snippet: string;
 }
 
+export interface TrashedFile {
+  id: string;
+  path: string;
+  deletedAt: string;
+  size: number;
+}
+
+export interface Draft {
+  path: string;
+  frontmatter?: Record<string, unknown>;
+  content: string;
+  /** Revision of the document the edit started from */
+  revision?: string;
+  savedAt: string;
+  /** The document changed since the edit started */
+  stale: boolean;
+}
+
+export interface MergeResult {
+  /** Merged text, with `<<<<<<< ours` … `>>>>>>> theirs` markers around conflicts */
+  content: string;
+  conflicts: number;
+  /** Revision `theirs` was read at, when the server read it */
+  revision?: string;
+}
+
+export interface UploadedAttachment {
+  path: string;
+  /** Link to the file, relative to the document it was uploaded to, in its syntax (org or Markdown) */
+  link: string;
+}
+
+export interface BatchItem<T> {
+  path: string;
+  status: number;
+  /** Present when status is 200 */
+  document?: T;
+}
+
+export interface MoveResult {
+  from: string;
+  to: string;
+  /** Documents whose links were rewritten, with how many in each */
+  rewritten: Array<{ path: string; links: number }>;
+}
+
 export interface GraphData {
   nodes: Array<{
     id: string;
@@ -153,7 +235,7 @@ async function fetchJSON<T>(path: string): Promise<T> {
     logSync(`getTauriFetch returned: ${tFetch ? 'function' : 'null'}`);
 
     if (tFetch) {
-      const url = `${SERVER_URL}/api${path}`;
+      const url = `${SERVER_URL}${API_BASE}${path}`;
       logSync(`using tauriFetch for: ${url}`);
 
       try {
@@ -174,7 +256,7 @@ async function fetchJSON<T>(path: string): Promise<T> {
     }
 
     // Fallback to browser fetch
-    const url = `/api${path}`;
+    const url = `${API_BASE}${path}`;
     logSync(`using browser fetch for: ${url}`);
 
     const response = await fetch(url);
@@ -195,88 +277,250 @@ async function fetchWithMethod<T>(path: string, method: string): Promise<T> {
   const tFetch = await getTauriFetch();
 
   if (tFetch) {
-    const url = `${SERVER_URL}/api${path}`;
+    const url = `${SERVER_URL}${API_BASE}${path}`;
     const response = await tFetch(url, { method });
 
     if (!response.ok) {
       throw new Error(`API error: ${response.status}`);
     }
 
-    return response.json();
+    return response.status === 204 ? (undefined as T) : response.json();
   }
 
-  const response = await fetch(`/api${path}`, { method });
+  const response = await fetch(`${API_BASE}${path}`, { method });
 
   if (!response.ok) {
     throw new Error(`API error: ${response.status}`);
   }
 
-  return response.json();
+  return response.status === 204 ? (undefined as T) : response.json();
+}
+
+/** A request the server answered with an error status */
+export class ApiError extends Error {
+  constructor(public status: number) {
+    super(`API error: ${status}`);
+  }
+}
+
+/** Whether a save failed because the file changed since it was loaded */
+export function isConflict(err: unknown): boolean {
+  return (err instanceof ApiError || err instanceof CommandError) && err.status === 412;
 }
 
-async function putJSON<T>(path: string, body: unknown): Promise<T> {
+async function putJSON<T>(path: string, body: unknown, ifMatch?: string): Promise<T> {
   logSync(`putJSON called for path: ${path}`);
 
   const tFetch = await getTauriFetch();
+  const headers: Record<string, string> = { 'Content-Type': 'application/json' };
+  if (ifMatch) headers['If-Match'] = ifMatch;
 
   if (tFetch) {
-    const url = `${SERVER_URL}/api${path}`;
+    const url = `${SERVER_URL}${API_BASE}${path}`;
     logSync(`using tauriFetch PUT for: ${url}`);
 
     const response = await tFetch(url, {
       method: 'PUT',
-      headers: { 'Content-Type': 'application/json' },
+      headers,
       body: JSON.stringify(body),
     });
 
     logSync(`tauriFetch PUT response status: ${response.status}`);
 
     if (!response.ok) {
-      throw new Error(`API error: ${response.status}`);
+      throw new ApiError(response.status);
     }
 
-    // PUT returns 200 OK with no body
-    return {} as T;
+    return response.json();
   }
 
   // Fallback to browser fetch
-  const url = `/api${path}`;
+  const url = `${API_BASE}${path}`;
   logSync(`using browser fetch PUT for: ${url}`);
 
   const response = await fetch(url, {
     method: 'PUT',
-    headers: { 'Content-Type': 'application/json' },
+    headers,
     body: JSON.stringify(body),
   });
 
+  if (!response.ok) {
+    throw new ApiError(response.status);
+  }
+
+  return response.json();
+}
+
+/**
+ * Run a request as a command over the live reload socket while it's open,
+ * which skips the HTTP round trips on high-latency links, and over HTTP
+ * otherwise or if the socket fails on the way
+ */
+async function viaSocket<T>(
+  command: Command,
+  options: { path?: string; args?: unknown; ifMatch?: string },
+  fallback: () => Promise<T>
+): Promise<T> {
+  if (!liveReload.connected) return fallback();
+  try {
+    return await liveReload.command<T>(command, options);
+  } catch (err) {
+    if (err instanceof CommandError) throw err;
+    logSync(`command ${command} failed over the socket, retrying over HTTP: ${err}`);
+    return fallback();
+  }
+}
+
+async function postJSON<T>(path: string, body: unknown): Promise<T> {
+  const tFetch = await getTauriFetch();
+  const init = {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(body),
+  };
+  const response = tFetch ? await tFetch(`${SERVER_URL}${API_BASE}${path}`, init) : await fetch(`${API_BASE}${path}`, init);
+
   if (!response.ok) {
     throw new Error(`API error: ${response.status}`);
   }
 
-  return {} as T;
+  return response.json();
 }
 
 export const api = {
   // Files
-  async listFiles(filters?: { type?: string; tag?: string; folder?: string }): Promise<{ count: number; items: FileListItem[] }> {
+  async listFiles(filters?: {
+    type?: string;
+    tag?: string;
+    dir?: string;
+    /** RFC 3339 instant, or a `YYYY-MM-DD` day */
+    modifiedAfter?: string;
+    /** `path`, `title` or `modified`, with a leading `-` for descending */
+    sort?: string;
+    limit?: number;
+    offset?: number;
+  }): Promise<{ count: number; total: number; offset: number; nextOffset?: number; items: FileListItem[] }> {
     const params = new URLSearchParams();
     if (filters?.type) params.set('type', filters.type);
     if (filters?.tag) params.set('tag', filters.tag);
-    if (filters?.folder) params.set('folder', filters.folder);
+    if (filters?.dir) params.set('dir', filters.dir);
+    if (filters?.modifiedAfter) params.set('modified_after', filters.modifiedAfter);
+    if (filters?.sort) params.set('sort', filters.sort);
+    if (filters?.limit) params.set('limit', String(filters.limit));
+    if (filters?.offset) params.set('offset', String(filters.offset));
     const query = params.toString();
     return fetchJSON(`/files${query ? `?${query}` : ''}`);
   },
 
-  async getFile(path: string): Promise<OrgDocument> {
-    return fetchJSON(`/files/${path}`);
+  /**
+   * A document as rendered for viewing, or with `raw` its text as stored,
+   * includes, macros and links untouched, which is what an editor must load
+   * so saving doesn't write the rendering back
+   */
+  async getFile(path: string, options: { raw?: boolean } = {}): Promise<OrgDocument> {
+    const args = options.raw ? { raw: true } : undefined;
+    return viaSocket('document', { path, args }, () => fetchJSON(`/files/${path}${options.raw ? '?raw=true' : ''}`));
   },
 
+  /** Several documents in one request; each item has the status its own fetch would have */
+  async getFiles(paths: string[], options: { raw?: boolean } = {}): Promise<BatchItem<OrgDocument>[]> {
+    const result = await postJSON<{ items: BatchItem<OrgDocument>[] }>('/files/batch', { paths, ...options });
+    return result.items;
+  },
+
+  /**
+   * Replace a document. `revision` is the one the edit started from, or `*`
+   * to overwrite whatever is there; rejects with a 412 (see `isConflict`)
+   * if the file has changed since. Resolves to the new revision.
+   */
   async updateFile(
     path: string,
     frontmatter: Record<string, unknown>,
-    content: string
-  ): Promise<void> {
-    await putJSON(`/files/${path}`, { frontmatter, content });
+    content: string,
+    revision: string
+  ): Promise<string> {
+    const result = await viaSocket<{ revision: string }>(
+      'save',
+      { path, args: { frontmatter, content }, ifMatch: revision },
+      () => putJSON(`/files/${path}`, { frontmatter, content }, revision)
+    );
+    return result.revision;
+  },
+
+  /** Create a new document, empty or from a template document; rejects if it exists */
+  async createFile(
+    path: string,
+    options: { frontmatter?: Record<string, unknown>; content?: string; template?: string } = {}
+  ): Promise<{ path: string }> {
+    return postJSON(`/files/${path}`, options);
+  },
+
+  /** Keep an unsaved edit on the server, without touching the document */
+  async saveDraft(
+    path: string,
+    draft: { frontmatter?: Record<string, unknown>; content: string; revision?: string }
+  ): Promise<Draft> {
+    return putJSON(`/drafts/${path}`, draft);
+  },
+
+  /** The unsaved edit of a document left by an earlier session, if any */
+  async getDraft(path: string): Promise<Draft | null> {
+    try {
+      return await fetchJSON(`/drafts/${path}`);
+    } catch {
+      return null;
+    }
+  },
+
+  async deleteDraft(path: string): Promise<void> {
+    await fetchWithMethod(`/drafts/${path}`, 'DELETE');
+  },
+
+  /** Move a document to the trash; it can be restored with its trash id */
+  async deleteFile(path: string): Promise<TrashedFile> {
+    return fetchWithMethod(`/files/${path}`, 'DELETE');
+  },
+
+  /** Move or rename a document, rewriting the file links to it across the vault */
+  async moveFile(path: string, to: string): Promise<MoveResult> {
+    return postJSON(`/files/${path}/move`, { to });
+  },
+
+  /**
+   * Three-way merge of an edit that failed to save with a conflict. Without
+   * `theirs`, merges with the file as stored and returns its revision.
+   */
+  async mergeFile(path: string, texts: { base: string; ours: string; theirs?: string }): Promise<MergeResult> {
+    return postJSON(`/files/${path}/merge`, texts);
+  },
+
+  /** Store files next to a document, e.g. a pasted screenshot; returns the links to insert */
+  async uploadAttachments(path: string, files: File[]): Promise<UploadedAttachment[]> {
+    const form = new FormData();
+    for (const file of files) form.append('file', file, file.name);
+    const tFetch = await getTauriFetch();
+    const init = { method: 'POST', body: form };
+    const url = `/files/${path}/attachments`;
+    const response = tFetch ? await tFetch(`${SERVER_URL}${API_BASE}${url}`, init) : await fetch(`${API_BASE}${url}`, init);
+    if (!response.ok) {
+      throw new Error(`API error: ${response.status}`);
+    }
+    return response.json();
+  },
+
+  /** URL of any file under the roots as stored, for PDFs and media to stream from */
+  rawUrl(path: string): string {
+    const encoded = path.split('/').map(encodeURIComponent).join('/');
+    return `${'__TAURI_INTERNALS__' in window ? SERVER_URL : ''}${API_BASE}/raw/${encoded}`;
+  },
+
+  async listTrash(): Promise<TrashedFile[]> {
+    return fetchJSON('/trash');
+  },
+
+  /** Put a trashed document back at its old path; rejects if one exists there now */
+  async restoreFile(id: string): Promise<TrashedFile> {
+    return postJSON('/trash/restore', { id });
   },
 
   // Search
@@ -285,7 +529,8 @@ export const api = {
     if (filters?.type) params.set('type', filters.type);
     if (filters?.tag) params.set('tag', filters.tag);
     if (filters?.limit) params.set('limit', String(filters.limit));
-    return fetchJSON(`/search?${params}`);
+    const args = { q: query, type: filters?.type, tag: filters?.tag, limit: filters?.limit };
+    return viaSocket('search', { args }, () => fetchJSON(`/search?${params}`));
   },
 
   // Graph
@@ -325,8 +570,14 @@ export const api = {
     return fetchJSON(`/projects/${encodeURIComponent(project)}/file/${path}`);
   },
 
-  async updateProjectFile(project: string, path: string, content: string): Promise<void> {
-    await putJSON(`/projects/${encodeURIComponent(project)}/file/${path}`, { content });
+  /** Write a project file; `revision` as for `updateFile`. Resolves to the new revision. */
+  async updateProjectFile(project: string, path: string, content: string, revision: string): Promise<string> {
+    const result = await putJSON<{ revision: string }>(
+      `/projects/${encodeURIComponent(project)}/file/${path}`,
+      { content },
+      revision
+    );
+    return result.revision;
   },
 };
 
@@ -352,6 +603,8 @@ export interface ProjectFile {
   content: string;
   language: string | null;
   size: number;
+  /** Revision of the file as stored; saving sends it back as `If-Match` */
+  revision: string;
 }
 
 logSync('api.ts fully loaded');
@@ -0,0 +1,159 @@
+/**
+ * Editing a document together with other devices
+ *
+ * The server keeps the text in a CRDT. This side only tracks which version
+ * of it (`heads`) its text started from, and sends "the text at these heads
+ * is now X"; the server merges that with whatever other devices did in the
+ * meantime and sends everyone the result.
+ */
+
+import { liveReload, type ServerEvent } from './websocket';
+
+/** Typing is sent after this long without a keystroke */
+const SEND_DELAY_MS = 300;
+
+interface CollabPayload {
+  heads?: string[];
+  content?: string;
+  /** Revision of the file as the session last wrote or read it */
+  revision?: string;
+  error?: string;
+}
+
+export class CollabSession {
+  /** Server's name for the version `base` is, null until it's sent one */
+  private heads: string[] | null = null;
+  /** Text at `heads` */
+  private base = '';
+  /** Text in the editor: `base` plus typing not yet acknowledged */
+  private local = '';
+  /** Content of the edit waiting for its ack */
+  private inFlight: string | null = null;
+  private timer: number | null = null;
+  private savedRevision: string | null = null;
+  private unsubscribe: (() => void)[];
+
+  /**
+   * Join the editing session of `path`. `onChange` gets the whole new text
+   * whenever someone else's edits arrive.
+   */
+  constructor(
+    private path: string,
+    private onChange: (content: string) => void,
+    private onError?: (error: string) => void,
+  ) {
+    this.unsubscribe = [
+      liveReload.onCollab((message) => this.handle(message)),
+      // The server forgets sessions with the connection
+      liveReload.onConnect(() => this.open()),
+    ];
+    this.open();
+  }
+
+  /** The editor's text as this session knows it */
+  get content(): string {
+    return this.local;
+  }
+
+  /**
+   * Revision of the file the session writes as it goes, which a save made
+   * during the session must name in `If-Match`; null until the server sent one
+   */
+  get revision(): string | null {
+    return this.savedRevision;
+  }
+
+  /** Report the editor's text after local typing */
+  update(content: string) {
+    if (content === this.local) return;
+    this.local = content;
+    if (this.timer) return;
+    this.timer = window.setTimeout(() => {
+      this.timer = null;
+      this.send();
+    }, SEND_DELAY_MS);
+  }
+
+  /** Send any pending typing and leave the session */
+  close() {
+    if (this.timer) {
+      clearTimeout(this.timer);
+      this.timer = null;
+    }
+    this.send();
+    this.unsubscribe.forEach((unsubscribe) => unsubscribe());
+    liveReload.send({ type: 'edit-close', path: this.path });
+  }
+
+  private open() {
+    this.inFlight = null;
+    liveReload.send({ type: 'edit-open', path: this.path });
+  }
+
+  // One edit at a time, so each is made against heads the server named
+  private send() {
+    if (this.heads === null || this.inFlight !== null || this.local === this.base) return;
+    if (liveReload.send({ type: 'edit', path: this.path, heads: this.heads, content: this.local })) {
+      this.inFlight = this.local;
+    }
+  }
+
+  private adopt(heads: string[], content: string) {
+    this.heads = heads;
+    this.base = content;
+    if (content !== this.local) {
+      this.local = content;
+      this.onChange(content);
+    }
+  }
+
+  private handle(message: ServerEvent) {
+    if (message.path !== this.path) return;
+    const payload = message.payload as CollabPayload;
+    // The file's revision holds whether or not the text below is adopted
+    if (payload.revision) this.savedRevision = payload.revision;
+
+    switch (message.type) {
+      case 'collab-ack':
+        if (this.inFlight !== null && payload.heads) {
+          this.heads = payload.heads;
+          this.base = this.inFlight;
+        }
+        this.inFlight = null;
+        // Typing that came in while the edit was out
+        this.send();
+        break;
+
+      case 'collab-state': {
+        if (!payload.heads || payload.content === undefined) break;
+        const first = this.heads === null;
+        this.inFlight = null;
+        if (first || this.local === this.base) {
+          this.adopt(payload.heads, payload.content);
+        } else {
+          // Rejoined after a disconnect with typing the server never got.
+          // It's resent on top of the current text, so it wins over
+          // anything others changed in the same place meanwhile.
+          this.heads = payload.heads;
+          this.base = payload.content;
+          this.send();
+        }
+        break;
+      }
+
+      case 'collab-update':
+        // With an edit out or typing pending, a later update will carry
+        // the merge of both; adopting this one would drop the typing
+        if (this.inFlight !== null || this.local !== this.base) break;
+        if (payload.heads && payload.content !== undefined) {
+          this.adopt(payload.heads, payload.content);
+        }
+        break;
+
+      case 'collab-error':
+        console.warn(`Editing ${this.path} together failed: ${payload.error}`);
+        this.onError?.(payload.error ?? 'unknown error');
+        break;
+    }
+  }
+}
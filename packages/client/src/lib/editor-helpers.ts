@@ -47,10 +47,34 @@ export function getEditorFields(docType: string): EditorField[] {
   }
 }
 
+/**
+ * Whether `path` is an org file, which keeps its metadata in `#+KEYWORD:`
+ * lines rather than YAML front matter
+ */
+export function isOrgPath(path: string): boolean {
+  return path.toLowerCase().endsWith('.org');
+}
+
+/** Leading org keyword line the editor shows as a field, and blank lines */
+const ORG_FIELD_LINE = /^(?:#\+(?:TITLE|TYPE|STATUS|DATE|UPDATED|FILETAGS|REMIND-AT):[^\n]*)?(?:\n|$)/i;
+
 /**
  * Convert OrgDocument to EditorData for the editor
  */
 export function documentToEditorData(doc: OrgDocument): EditorData {
+  if (isOrgPath(doc.path)) {
+    // The title and fields come from keyword lines, which the server
+    // rewrites from the fields on save
+    return {
+      title: doc.title,
+      status: doc.status || '',
+      tags: doc.fileTags?.join(', ') || '',
+      content: splitBody(doc.content || '', doc.path).body,
+      source: '',
+      remindAt: '',
+    };
+  }
+
   // Extract title from content (first # heading) or use doc.title
   let title = doc.title;
   let bodyContent = doc.content || '';
@@ -76,6 +100,25 @@ export function documentToEditorData(doc: OrgDocument): EditorData {
   };
 }
 
+/**
+ * Split a document's raw text into the part the editor shows as fields (front
+ * matter and `# title` line, or an org file's leading field keywords) and the
+ * body it edits as content, such that `head + body` is the text again
+ */
+export function splitBody(raw: string, path: string): { head: string; body: string } {
+  if (isOrgPath(path)) {
+    let head = 0;
+    for (let line = raw.match(ORG_FIELD_LINE); line?.[0]; line = raw.slice(head).match(ORG_FIELD_LINE)) {
+      head += line[0].length;
+    }
+    return { head: raw.slice(0, head), body: raw.slice(head) };
+  }
+  const frontmatter = raw.match(/^---[\s\S]*?---\n?/)?.[0] ?? '';
+  const title = raw.slice(frontmatter.length).match(/^# [^\n]*\n*/)?.[0] ?? '';
+  const head = frontmatter + title;
+  return { head, body: raw.slice(head.length) };
+}
+
 /**
  * Convert EditorData back to frontmatter and content for API
  */
@@ -115,7 +158,12 @@ export function editorDataToPayload(
   // Add updated timestamp
   frontmatter.updated = new Date().toISOString().split('T')[0];
 
-  // Build content with title as heading
+  // Build content with title as heading; an org file's title is a
+  // keyword like the other fields
+  if (isOrgPath(originalDoc.path)) {
+    frontmatter.title = data.title;
+    return { frontmatter, content: `${data.content || ''}`.trimEnd() + '\n' };
+  }
   const content = `# ${data.title}\n\n${data.content || ''}`.trimEnd() + '\n';
 
   return { frontmatter, content };
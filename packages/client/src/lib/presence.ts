@@ -0,0 +1,95 @@
+/**
+ * Presence: which other devices have a document open
+ *
+ * Each tab reports the documents it shows and edits; the server sends
+ * everyone the full list whenever it changes.
+ */
+
+import { liveReload, type ClientPresence } from './websocket';
+
+const NAME_KEY = 'org-viewer-device-name';
+
+/** This tab, told apart from others on the same device */
+const clientId = typeof crypto.randomUUID === 'function'
+  ? crypto.randomUUID()
+  : Math.random().toString(36).slice(2);
+
+/** Name other devices see, e.g. "phone"; set it in localStorage to change it */
+function deviceName(): string {
+  const saved = localStorage.getItem(NAME_KEY);
+  if (saved) return saved;
+  if ('__TAURI_INTERNALS__' in window) return 'desktop';
+  const agent = navigator.userAgent;
+  if (/iPad|Tablet/i.test(agent)) return 'tablet';
+  if (/Mobi|Android|iPhone/i.test(agent)) return 'phone';
+  return 'browser';
+}
+
+type PeersCallback = (peers: ClientPresence[]) => void;
+
+class PresenceTracker {
+  /** Open documents, counted so two views of one document both count */
+  private documents = new Map<string, number>();
+  private editing = new Set<string>();
+  private peers: ClientPresence[] = [];
+  private callbacks: PeersCallback[] = [];
+
+  constructor() {
+    liveReload.onPresence((clients) => {
+      this.peers = clients.filter((client) => client.id !== clientId);
+      this.callbacks.forEach((cb) => cb(this.peers));
+    });
+    // The server forgets presence with the connection
+    liveReload.onConnect(() => this.announce());
+  }
+
+  /** Report a document as open until the returned function is called */
+  open(path: string): () => void {
+    this.documents.set(path, (this.documents.get(path) ?? 0) + 1);
+    this.announce();
+    return () => {
+      const count = (this.documents.get(path) ?? 1) - 1;
+      if (count > 0) {
+        this.documents.set(path, count);
+      } else {
+        this.documents.delete(path);
+        this.editing.delete(path);
+      }
+      this.announce();
+    };
+  }
+
+  setEditing(path: string, editing: boolean) {
+    if (editing === this.editing.has(path)) return;
+    if (editing) {
+      this.editing.add(path);
+    } else {
+      this.editing.delete(path);
+    }
+    this.announce();
+  }
+
+  /** Other tabs and devices with `path` open */
+  peersOf(path: string): ClientPresence[] {
+    return this.peers.filter((peer) => peer.documents.includes(path) || peer.editing.includes(path));
+  }
+
+  onChange(callback: PeersCallback) {
+    this.callbacks.push(callback);
+    return () => {
+      this.callbacks = this.callbacks.filter((cb) => cb !== callback);
+    };
+  }
+
+  private announce() {
+    liveReload.send({
+      type: 'presence',
+      id: clientId,
+      name: deviceName(),
+      documents: [...this.documents.keys()],
+      editing: [...this.editing],
+    });
+  }
+}
+
+export const presence = new PresenceTracker();
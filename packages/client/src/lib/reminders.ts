@@ -0,0 +1,51 @@
+/**
+ * Agenda reminders: the server announces timed SCHEDULED and DEADLINE
+ * entries shortly before they're due, and this shows them as system
+ * notifications, natively in the desktop app and through the browser's
+ * Notification API elsewhere.
+ */
+
+import { liveReload, type Reminder } from './websocket';
+
+const isTauri = '__TAURI_INTERNALS__' in window;
+
+function describe(reminder: Reminder): { title: string; body: string } {
+  const when = reminder.minutes <= 0 ? 'now' : `in ${reminder.minutes} min`;
+  const what = reminder.kind === 'deadline' ? 'Deadline' : 'Scheduled';
+  const title = reminder.todo ? `${reminder.todo} ${reminder.title}` : reminder.title;
+  return { title, body: `${what} ${when} (${reminder.time.slice(11)}) · ${reminder.file}` };
+}
+
+async function notifyNative(reminder: Reminder): Promise<boolean> {
+  try {
+    const notification = await import('@tauri-apps/plugin-notification');
+    let granted = await notification.isPermissionGranted();
+    if (!granted) granted = (await notification.requestPermission()) === 'granted';
+    if (granted) notification.sendNotification(describe(reminder));
+    return granted;
+  } catch (e) {
+    console.warn(`Native notification failed: ${e}`);
+    return false;
+  }
+}
+
+async function notifyBrowser(reminder: Reminder): Promise<boolean> {
+  if (!('Notification' in window)) return false;
+  let permission = Notification.permission;
+  if (permission === 'default') permission = await Notification.requestPermission();
+  if (permission !== 'granted') return false;
+  const { title, body } = describe(reminder);
+  new Notification(title, { body, tag: `${reminder.file}:${reminder.line}:${reminder.kind}` });
+  return true;
+}
+
+/** Show reminders as they arrive, until the returned function is called */
+export function startReminders(): () => void {
+  return liveReload.onReminder(async (reminder) => {
+    const notified = isTauri ? await notifyNative(reminder) : await notifyBrowser(reminder);
+    if (!notified) {
+      const { title, body } = describe(reminder);
+      console.info(`Reminder (notifications not allowed): ${title} - ${body}`);
+    }
+  });
+}
@@ -2,27 +2,211 @@
  * WebSocket client for live reload
  */
 
-type ReloadCallback = () => void;
-type UpdateCallback = (path: string) => void;
+import { getAuthToken } from './api';
+
+type StatusCallback = (status: Record<string, unknown>) => void;
+type IndexProgressCallback = (progress: IndexProgress) => void;
+type UpdateCallback = (path: string, patch?: Patch) => void;
+type RenameCallback = (rename: Rename) => void;
+type FileDeletedCallback = (deleted: FileDeleted) => void;
+type ProjectTreeCallback = (project: string, paths: string[]) => void;
+type ProjectFileCallback = (project: string, path: string) => void;
+type CollabCallback = (message: ServerEvent) => void;
+type PresenceCallback = (clients: ClientPresence[]) => void;
+type ReminderCallback = (reminder: Reminder) => void;
+
+/** Version of the server's event schema this client understands */
+const PROTOCOL_VERSION = 1;
+
+export type EventType =
+  | 'file-changed'
+  | 'file-deleted'
+  | 'file-renamed'
+  | 'files-changed'
+  | 'index-progress'
+  | 'server-status'
+  | 'project-tree-changed'
+  | 'project-file-changed'
+  | 'collab-update'
+  | 'collab-state'
+  | 'collab-ack'
+  | 'collab-closed'
+  | 'collab-error'
+  | 'presence'
+  | 'reminder'
+  | 'replay'
+  | 'command-result'
+  | 'command-error';
+
+/** Every message from the server has this shape */
+export interface ServerEvent {
+  v: number;
+  type: EventType;
+  /** Document the event is about, when it's about one */
+  path?: string;
+  /**
+   * Goes up by one per event; a jump means events were missed. Absent on
+   * replies to this client's own messages.
+   */
+  revision?: number;
+  /** The `id` of the command a command result answers */
+  id?: number;
+  payload: Record<string, unknown>;
+  timestamp: number;
+}
+
+/** Commands the server runs for `LiveReloadClient.command` */
+export type Command = 'document' | 'save' | 'search' | 'outline';
+
+/** A command the server ran and answered with an HTTP error status */
+export class CommandError extends Error {
+  constructor(public status: number, message: string) {
+    super(`API error: ${status} ${message}`);
+  }
+}
+
+/** How long a command waits for its result before giving up */
+const COMMAND_TIMEOUT_MS = 15000;
+
+interface PendingCommand {
+  resolve: (body: unknown) => void;
+  reject: (error: Error) => void;
+  timer: number;
+}
+
+export interface StaleLink {
+  source: string;
+  link: string;
+}
+
+export interface Rename {
+  from: string;
+  to: string;
+  /** Links still written against the old path */
+  brokenLinks?: number;
+  links?: StaleLink[];
+  /** e.g. "3 links now broken", only when there are some */
+  warning?: string;
+}
+
+export interface FileDeleted {
+  path: string;
+  /** Documents that still link to the deleted one */
+  linkedFrom?: string[];
+}
+
+/** Lines replaced starting at 0-based `line` of the old content */
+export interface Hunk {
+  line: number;
+  delete: string[];
+  insert: string[];
+}
+
+/**
+ * Sent with `file-changed` to clients that recently fetched the document:
+ * edits from the content they were served to the current one
+ */
+export interface Patch {
+  hunks: Hunk[];
+}
+
+/**
+ * Apply a patch to a document's content, or return null if the content
+ * isn't what the patch was made against and should be refetched
+ */
+export function applyPatch(content: string, patch: Patch): string | null {
+  const lines = content.split('\n');
+  // Last hunk first, so earlier line numbers stay valid
+  for (const hunk of [...patch.hunks].reverse()) {
+    const current = lines.slice(hunk.line, hunk.line + hunk.delete.length);
+    if (current.length !== hunk.delete.length || current.some((line, i) => line !== hunk.delete[i])) {
+      return null;
+    }
+    lines.splice(hunk.line, hunk.delete.length, ...hunk.insert);
+  }
+  return lines.join('\n');
+}
+
+/** What one connected client has open, as it reported */
+export interface ClientPresence {
+  id: string;
+  name: string;
+  documents: string[];
+  editing: string[];
+}
+
+/** A timed agenda entry coming up */
+export interface Reminder {
+  file: string;
+  line: number;
+  title: string;
+  todo: string | null;
+  kind: 'scheduled' | 'deadline';
+  /** Due time, `YYYY-MM-DD HH:MM` */
+  time: string;
+  /** Minutes until it's due */
+  minutes: number;
+}
+
+export interface FilesChanged {
+  paths?: string[];
+  deleted?: FileDeleted[];
+  renamed?: Rename[];
+}
+
+export interface IndexProgress {
+  /** `reindex` of full-text search, or `cache-rebuild` */
+  task: string;
+  phase: string;
+  done?: number;
+  total?: number;
+  reason?: string;
+}
 
 class LiveReloadClient {
   private ws: WebSocket | null = null;
   private reconnectTimer: number | null = null;
-  private onReloadCallbacks: ReloadCallback[] = [];
+  /** Revision and timestamp of the latest event received */
+  private revision = 0;
+  private timestamp = 0;
+  /** Documents to hear about, or null for everything */
+  private subscribed: string[] | null = null;
+  private onStatusCallbacks: StatusCallback[] = [];
+  private onIndexProgressCallbacks: IndexProgressCallback[] = [];
   private onUpdateCallbacks: UpdateCallback[] = [];
   private onRemoveCallbacks: UpdateCallback[] = [];
+  private onRenameCallbacks: RenameCallback[] = [];
+  private onFileDeletedCallbacks: FileDeletedCallback[] = [];
+  private onProjectTreeCallbacks: ProjectTreeCallback[] = [];
+  private onProjectFileCallbacks: ProjectFileCallback[] = [];
+  private onCollabCallbacks: CollabCallback[] = [];
+  private onPresenceCallbacks: PresenceCallback[] = [];
+  private onReminderCallbacks: ReminderCallback[] = [];
+  private onConnectCallbacks: (() => void)[] = [];
+  private onResyncCallbacks: (() => void)[] = [];
+  /** Commands sent and waiting for their result, by id */
+  private pending = new Map<number, PendingCommand>();
+  private nextCommandId = 1;
 
   connect() {
     if (this.ws?.readyState === WebSocket.OPEN) return;
 
     const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
-    const wsUrl = `${protocol}//${window.location.host}/ws`;
+    const token = getAuthToken();
+    const query = token ? `?token=${encodeURIComponent(token)}` : '';
+    const wsUrl = `${protocol}//${window.location.host}/ws${query}`;
 
     try {
       this.ws = new WebSocket(wsUrl);
 
       this.ws.onopen = () => {
         console.log('Live reload connected');
+        if (this.subscribed) this.sendSubscription();
+        // Catch up on events missed while disconnected
+        if (this.revision) {
+          this.send({ type: 'replay', since: this.revision, timestamp: this.timestamp });
+        }
+        this.onConnectCallbacks.forEach(cb => cb());
         if (this.reconnectTimer) {
           clearTimeout(this.reconnectTimer);
           this.reconnectTimer = null;
@@ -38,8 +222,12 @@ class LiveReloadClient {
         }
       };
 
-      this.ws.onclose = () => {
+      this.ws.onclose = (event) => {
+        if (event.code === 1008) {
+          console.warn('Live reload rejected: open the app with ?token=... to authenticate');
+        }
         console.log('Live reload disconnected, reconnecting...');
+        this.failPending(new Error('Live reload disconnected'));
         this.scheduleReconnect();
       };
 
@@ -68,28 +256,193 @@ class LiveReloadClient {
     }, 3000);
   }
 
-  private handleMessage(message: { type: string; path?: string }) {
-    switch (message.type) {
-      case 'reload':
-        this.onReloadCallbacks.forEach(cb => cb());
+  private handleMessage(event: ServerEvent) {
+    if (event.v !== PROTOCOL_VERSION) {
+      console.warn(`Ignoring event with unsupported protocol version ${event.v}`);
+      return;
+    }
+    if (event.revision !== undefined) {
+      // With a subscription, skipped revisions are other documents' events
+      if (!this.subscribed && this.revision && event.revision > this.revision + 1) {
+        console.warn(`Missed ${event.revision - this.revision - 1} live reload events`);
+      }
+      this.revision = event.revision;
+      this.timestamp = event.timestamp;
+    }
+
+    switch (event.type) {
+      case 'collab-update':
+      case 'collab-state':
+      case 'collab-ack':
+      case 'collab-closed':
+      case 'collab-error':
+        this.onCollabCallbacks.forEach(cb => cb(event));
         break;
-      case 'update':
-        if (message.path) {
-          this.onUpdateCallbacks.forEach(cb => cb(message.path!));
+      case 'command-result':
+      case 'command-error': {
+        const pending = event.id !== undefined ? this.pending.get(event.id) : undefined;
+        if (!pending) break;
+        this.pending.delete(event.id!);
+        clearTimeout(pending.timer);
+        if (event.type === 'command-result') {
+          pending.resolve(event.payload.body);
+        } else {
+          pending.reject(new CommandError(event.payload.status as number, String(event.payload.error)));
         }
         break;
-      case 'remove':
-        if (message.path) {
-          this.onRemoveCallbacks.forEach(cb => cb(message.path!));
+      }
+      case 'replay': {
+        const payload = event.payload as { complete?: boolean; revision?: number };
+        if (payload.revision !== undefined) this.revision = payload.revision;
+        if (!payload.complete) {
+          console.warn('Missed live reload events while disconnected, refreshing');
+          this.onResyncCallbacks.forEach(cb => cb());
         }
         break;
+      }
+      case 'presence': {
+        const clients = (event.payload.clients as ClientPresence[] | undefined) ?? [];
+        this.onPresenceCallbacks.forEach(cb => cb(clients));
+        break;
+      }
+      case 'reminder':
+        this.onReminderCallbacks.forEach(cb => cb(event.payload as unknown as Reminder));
+        break;
+      case 'file-changed':
+        if (event.path) {
+          const patch = event.payload.patch as Patch | undefined;
+          this.onUpdateCallbacks.forEach(cb => cb(event.path!, patch));
+        }
+        break;
+      case 'file-deleted':
+        if (event.path) {
+          const payload = event.payload as Partial<FileDeleted>;
+          this.handleDeleted({ path: event.path, linkedFrom: payload.linkedFrom });
+        }
+        break;
+      case 'file-renamed':
+        this.handleRename(event.payload as unknown as Rename);
+        break;
+      case 'files-changed': {
+        const payload = event.payload as unknown as FilesChanged;
+        payload.paths?.forEach(path => this.onUpdateCallbacks.forEach(cb => cb(path)));
+        payload.deleted?.forEach(deleted => this.handleDeleted(deleted));
+        payload.renamed?.forEach(rename => this.handleRename(rename));
+        break;
+      }
+      case 'index-progress':
+        this.onIndexProgressCallbacks.forEach(cb => cb(event.payload as unknown as IndexProgress));
+        break;
+      case 'server-status':
+        this.onStatusCallbacks.forEach(cb => cb(event.payload));
+        break;
+      case 'project-tree-changed': {
+        const payload = event.payload as { project?: string; paths?: string[] };
+        if (payload.project) {
+          this.onProjectTreeCallbacks.forEach(cb => cb(payload.project!, payload.paths ?? []));
+        }
+        break;
+      }
+      case 'project-file-changed': {
+        const payload = event.payload as { project?: string; path?: string };
+        if (payload.project && payload.path !== undefined) {
+          this.onProjectFileCallbacks.forEach(cb => cb(payload.project!, payload.path!));
+        }
+        break;
+      }
+    }
+  }
+
+  /**
+   * Only receive events about these documents (and server-wide ones), e.g.
+   * while a single document is open, so idle devices aren't woken by every
+   * change. A path ending in `/` covers a whole directory.
+   */
+  subscribe(paths: string[]) {
+    this.subscribed = paths;
+    this.sendSubscription();
+  }
+
+  /** Receive events about every document again */
+  unsubscribe() {
+    this.subscribed = null;
+    this.sendSubscription();
+  }
+
+  /** Send a message to the server; false if not connected */
+  get connected(): boolean {
+    return this.ws?.readyState === WebSocket.OPEN;
+  }
+
+  /**
+   * Run a command on the server over the open socket, saving the request
+   * round trips HTTP would take. Rejects with `CommandError` when the
+   * server answered with an error, or a plain Error when the socket isn't
+   * open, drops or the result doesn't come in time.
+   */
+  command<T>(command: Command, options: { path?: string; args?: unknown; ifMatch?: string } = {}): Promise<T> {
+    const id = this.nextCommandId++;
+    return new Promise<T>((resolve, reject) => {
+      if (!this.send({ type: 'command', id, command, ...options })) {
+        reject(new Error('Live reload not connected'));
+        return;
+      }
+      const timer = window.setTimeout(() => {
+        this.pending.delete(id);
+        reject(new Error(`Command ${command} timed out`));
+      }, COMMAND_TIMEOUT_MS);
+      this.pending.set(id, { resolve: resolve as (body: unknown) => void, reject, timer });
+    });
+  }
+
+  private failPending(error: Error) {
+    this.pending.forEach((pending) => {
+      clearTimeout(pending.timer);
+      pending.reject(error);
+    });
+    this.pending.clear();
+  }
+
+  send(message: Record<string, unknown>): boolean {
+    if (this.ws?.readyState !== WebSocket.OPEN) return false;
+    this.ws.send(JSON.stringify(message));
+    return true;
+  }
+
+  // Resent on reconnect, as the server forgets it with the connection
+  private sendSubscription() {
+    if (this.ws?.readyState !== WebSocket.OPEN) return;
+    if (this.subscribed) {
+      this.ws.send(JSON.stringify({ type: 'subscribe', paths: this.subscribed }));
+    } else {
+      this.ws.send(JSON.stringify({ type: 'unsubscribe' }));
     }
   }
 
-  onReload(callback: ReloadCallback) {
-    this.onReloadCallbacks.push(callback);
+  // Listeners that only track updates still see the document at its new path
+  private handleRename(rename: Rename) {
+    this.onRenameCallbacks.forEach(cb => cb(rename));
+    this.onUpdateCallbacks.forEach(cb => cb(rename.to));
+  }
+
+  // Documents that linked to the deleted one now show a broken link
+  private handleDeleted(deleted: FileDeleted) {
+    this.onFileDeletedCallbacks.forEach(cb => cb(deleted));
+    this.onRemoveCallbacks.forEach(cb => cb(deleted.path));
+    deleted.linkedFrom?.forEach(path => this.onUpdateCallbacks.forEach(cb => cb(path)));
+  }
+
+  onServerStatus(callback: StatusCallback) {
+    this.onStatusCallbacks.push(callback);
+    return () => {
+      this.onStatusCallbacks = this.onStatusCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  onIndexProgress(callback: IndexProgressCallback) {
+    this.onIndexProgressCallbacks.push(callback);
     return () => {
-      this.onReloadCallbacks = this.onReloadCallbacks.filter(cb => cb !== callback);
+      this.onIndexProgressCallbacks = this.onIndexProgressCallbacks.filter(cb => cb !== callback);
     };
   }
 
@@ -106,6 +459,76 @@ class LiveReloadClient {
       this.onRemoveCallbacks = this.onRemoveCallbacks.filter(cb => cb !== callback);
     };
   }
+
+  onRename(callback: RenameCallback) {
+    this.onRenameCallbacks.push(callback);
+    return () => {
+      this.onRenameCallbacks = this.onRenameCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  onFileDeleted(callback: FileDeletedCallback) {
+    this.onFileDeletedCallbacks.push(callback);
+    return () => {
+      this.onFileDeletedCallbacks = this.onFileDeletedCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  onProjectTreeChanged(callback: ProjectTreeCallback) {
+    this.onProjectTreeCallbacks.push(callback);
+    return () => {
+      this.onProjectTreeCallbacks = this.onProjectTreeCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  /** Messages about documents being edited together, see `collab.ts` */
+  onCollab(callback: CollabCallback) {
+    this.onCollabCallbacks.push(callback);
+    return () => {
+      this.onCollabCallbacks = this.onCollabCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  /** Everyone's open documents, whenever a client reports a change */
+  onPresence(callback: PresenceCallback) {
+    this.onPresenceCallbacks.push(callback);
+    return () => {
+      this.onPresenceCallbacks = this.onPresenceCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  onReminder(callback: ReminderCallback) {
+    this.onReminderCallbacks.push(callback);
+    return () => {
+      this.onReminderCallbacks = this.onReminderCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  /**
+   * Called after a reconnect when the server no longer had the events
+   * missed meanwhile, so anything shown may be out of date
+   */
+  onResync(callback: () => void) {
+    this.onResyncCallbacks.push(callback);
+    return () => {
+      this.onResyncCallbacks = this.onResyncCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  /** Called on every (re)connect, for state the server forgets with a connection */
+  onConnect(callback: () => void) {
+    this.onConnectCallbacks.push(callback);
+    return () => {
+      this.onConnectCallbacks = this.onConnectCallbacks.filter(cb => cb !== callback);
+    };
+  }
+
+  onProjectFileChanged(callback: ProjectFileCallback) {
+    this.onProjectFileCallbacks.push(callback);
+    return () => {
+      this.onProjectFileCallbacks = this.onProjectFileCallbacks.filter(cb => cb !== callback);
+    };
+  }
 }
 
 export const liveReload = new LiveReloadClient();
@@ -148,6 +148,7 @@ fn main() {
 
     tauri::Builder::default()
         .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_notification::init())
         .invoke_handler(tauri::generate_handler![api_request, frontend_log, get_org_root])
         .setup(move |_app| {
             log_to_file("Tauri setup starting");
@@ -0,0 +1,170 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::diary::{diary_match, find_diary_entries, format_entry};
+use crate::server::effort::parse_org_timestamp;
+use crate::server::org::parse_todo_keywords;
+use crate::server::timezone;
+use crate::server::AppState;
+
+/// Days before a deadline that it starts showing up on today (org's default)
+const DEADLINE_WARNING_DAYS: i64 = 14;
+const MAX_AGENDA_DAYS: i64 = 366;
+
+#[derive(Deserialize)]
+pub struct AgendaQuery {
+    /// First day, `YYYY-MM-DD`; defaults to today
+    start: Option<String>,
+    days: Option<i64>,
+    /// IANA timezone deciding what "today" is; defaults to the configured one
+    tz: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct AgendaItem {
+    file: String,
+    line: usize,
+    title: String,
+    todo: Option<String>,
+    priority: Option<char>,
+    tags: Vec<String>,
+    /// `scheduled`, `deadline`, or `diary` for `%%(...)` sexp entries
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<String>,
+    /// For entries carried onto today: days until (positive) or since (negative) the timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relative: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct AgendaDay {
+    date: String,
+    items: Vec<AgendaItem>,
+}
+
+#[derive(Serialize)]
+pub struct AgendaResponse {
+    today: String,
+    days: Vec<AgendaDay>,
+}
+
+/// GET /api/agenda?start=&days=&tz= - Scheduled, deadline and diary sexp entries
+/// bucketed by day, with overdue and upcoming-deadline entries also listed on today
+pub async fn get_agenda(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AgendaQuery>,
+) -> Result<Json<AgendaResponse>, StatusCode> {
+    let today = timezone::now(&state.config, query.tz.as_deref())?.date();
+    let start = match &query.start {
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => today,
+    };
+    let span = query.days.unwrap_or(7).clamp(1, MAX_AGENDA_DAYS);
+    let end = start + Duration::days(span);
+    let in_range = |d: NaiveDate| d >= start && d < end;
+
+    let mut days: Vec<AgendaDay> = (0..span)
+        .map(|offset| AgendaDay {
+            date: (start + Duration::days(offset)).format("%Y-%m-%d").to_string(),
+            items: Vec::new(),
+        })
+        .collect();
+    let mut push = |date: NaiveDate, item: AgendaItem| {
+        if in_range(date) {
+            days[(date - start).num_days() as usize].items.push(item);
+        }
+    };
+
+    let index = state.index.read().await;
+    for (doc, headings) in index.documents_with_headings() {
+        let content = match tokio::fs::read_to_string(state.roots.resolve(&doc.path)).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let keywords = parse_todo_keywords(&content);
+
+        for heading in headings {
+            if heading.todo.as_deref().is_some_and(|t| keywords.is_done(t)) {
+                continue;
+            }
+            for (kind, value) in [("scheduled", &heading.scheduled), ("deadline", &heading.deadline)] {
+                let timestamp = match value.as_deref().and_then(parse_org_timestamp) {
+                    Some(t) => t,
+                    None => continue,
+                };
+                let date = timestamp.date();
+                let has_time = value.as_deref().is_some_and(|v| v.contains(':'));
+                let item = AgendaItem {
+                    file: doc.path.clone(),
+                    line: heading.line,
+                    title: heading.title.clone(),
+                    todo: heading.todo.clone(),
+                    priority: heading.priority,
+                    tags: heading.tags.clone(),
+                    kind: kind.to_string(),
+                    time: has_time.then(|| timestamp.format("%H:%M").to_string()),
+                    relative: None,
+                };
+
+                // Past-due entries and approaching deadlines also show on today
+                let until = (date - today).num_days();
+                let carried = if kind == "deadline" {
+                    until != 0 && until <= DEADLINE_WARNING_DAYS
+                } else {
+                    until < 0
+                };
+                if carried {
+                    push(today, AgendaItem { relative: Some(until), time: None, ..item.clone() });
+                }
+                push(date, item);
+            }
+        }
+
+        // Diary sexps (holidays, anniversaries, floating dates) are evaluated per day
+        for entry in find_diary_entries(&content, headings) {
+            let heading = entry.heading.map(|idx| &headings[idx]);
+            if heading.and_then(|h| h.todo.as_deref()).is_some_and(|t| keywords.is_done(t)) {
+                continue;
+            }
+            let text = match heading {
+                Some(h) if entry.text.is_empty() => h.title.clone(),
+                _ => entry.text.clone(),
+            };
+            for offset in 0..span {
+                let date = start + Duration::days(offset);
+                if let Some(count) = diary_match(&entry.sexp, date) {
+                    push(date, AgendaItem {
+                        file: doc.path.clone(),
+                        line: entry.line,
+                        title: format_entry(&text, count),
+                        todo: heading.and_then(|h| h.todo.clone()),
+                        priority: heading.and_then(|h| h.priority),
+                        tags: heading.map(|h| h.tags.clone()).unwrap_or_default(),
+                        kind: "diary".to_string(),
+                        time: None,
+                        relative: None,
+                    });
+                }
+            }
+        }
+    }
+
+    for day in &mut days {
+        day.items.sort_by(|a, b| {
+            (a.time.is_none(), &a.time, &a.kind, &a.file, a.line)
+                .cmp(&(b.time.is_none(), &b.time, &b.kind, &b.file, b.line))
+        });
+    }
+
+    Ok(Json(AgendaResponse {
+        today: today.format("%Y-%m-%d").to_string(),
+        days,
+    }))
+}
@@ -0,0 +1,290 @@
+use axum::{
+    body::Body,
+    extract::{Multipart, Path, State},
+    http::{header, StatusCode},
+    response::{Json, Response},
+};
+use regex::Regex;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path as FsPath;
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+use crate::server::document::{is_document_file, is_org_file};
+use crate::server::images::resolve_relative;
+use crate::server::roots::Roots;
+use crate::server::{log_to_file, timezone, AppState};
+
+/// Default org-attach base directory, relative to the owning document
+const DEFAULT_ATTACH_DIR: &str = "data";
+
+/// Where uploads go when `attachmentsDir` isn't configured, relative to the
+/// owning document
+pub const DEFAULT_UPLOAD_DIR: &str = "attachments";
+
+/// Request body limit for uploads; screenshots on high-DPI screens run to
+/// several MB
+pub const UPLOAD_LIMIT_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Serialize)]
+pub struct UploadedAttachment {
+    /// Document path of the stored file
+    path: String,
+    /// Link to insert in the document, relative to it, in the document's
+    /// syntax: `[[file:...]]` in org, an image or plain link in Markdown
+    link: String,
+}
+
+/// Collect attachment directories declared by a document, relative to its own directory.
+///
+/// Handles explicit `:ATTACH_DIR:` / `:DIR:` properties and org-attach's ID-based
+/// layout (`data/<first two chars of ID>/<rest of ID>`).
+pub fn attachment_dirs(content: &str) -> Vec<String> {
+    let prop_re = Regex::new(r"(?m)^\s*:(ATTACH_DIR|DIR|ID):\s+(\S.*?)\s*$").unwrap();
+    let mut dirs: Vec<String> = Vec::new();
+
+    for caps in prop_re.captures_iter(content) {
+        let value = caps[2].to_string();
+        let dir = match &caps[1] {
+            "ID" => {
+                if value.len() <= 2 || !value.is_char_boundary(2) {
+                    continue;
+                }
+                format!("{}/{}/{}", DEFAULT_ATTACH_DIR, &value[..2], &value[2..])
+            }
+            _ => value,
+        };
+
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    dirs
+}
+
+/// List attachment files for a document as paths relative to its root
+pub fn list_attachments(roots: &Roots, path: &str, content: &str) -> Vec<String> {
+    let (root, relative) = roots.split(path);
+    let base = match FsPath::new(relative).parent() {
+        Some(p) => p,
+        None => return Vec::new(),
+    };
+    let canonical_root = match root.canonicalize() {
+        Ok(p) => p,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut files = Vec::new();
+    for dir in attachment_dirs(content) {
+        let within_root = base.join(&dir);
+        let canonical_dir = match root.join(&within_root).canonicalize() {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+
+        // Attachment directories must stay inside the root
+        if !roots.contains_relative(root, &within_root, &canonical_dir) || !canonical_dir.is_dir() {
+            continue;
+        }
+
+        // A directory behind a followed symlink is listed under the link's path
+        let (dir, dir_root) = if canonical_dir.starts_with(&canonical_root) {
+            (canonical_dir, canonical_root.as_path())
+        } else {
+            (root.join(&within_root), root)
+        };
+
+        for entry in WalkDir::new(&dir)
+            .follow_links(roots.follow_symlinks())
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            if entry.file_type().is_file() {
+                let relative = entry
+                    .path()
+                    .strip_prefix(dir_root)
+                    .unwrap_or(entry.path())
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                files.push(relative);
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// GET /api/attachments/*path - Serve an attachment file from the org root
+pub async fn get_attachment(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Response, StatusCode> {
+    serve_from_root(&state.roots, &path).await
+}
+
+/// Serve a binary file under the document path's root with a guessed content type
+pub async fn serve_from_root(roots: &Roots, path: &str) -> Result<Response, StatusCode> {
+    let full_path = roots.resolve(path);
+
+    // Validate no path traversal — must stay within the root
+    let canonical_path = full_path
+        .canonicalize()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !roots.contains(path, &canonical_path) {
+        log_to_file(&format!("[attachments] Rejected path traversal: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if !canonical_path.is_file() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let data = tokio::fs::read(&canonical_path).await.map_err(|e| {
+        log_to_file(&format!("[attachments] Failed to read file: {}", e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mime = mime_guess::from_path(&canonical_path)
+        .first_or_octet_stream()
+        .to_string();
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, mime)
+        .body(Body::from(data))
+        .unwrap())
+}
+
+/// A file name safe to store, from the one the client sent: no directories,
+/// and only letters, digits, `-`, `_` and `.`
+fn sanitize_file_name(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || "-_.".contains(c) { c } else { '-' })
+        .collect();
+    name.trim_start_matches('.').to_string()
+}
+
+/// `name`, or `name-1`, `name-2`, ... for the first one not taken in `dir`
+fn unique_file_name(dir: &FsPath, name: &str) -> String {
+    if !dir.join(name).exists() {
+        return name.to_string();
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| format!("{}-{}{}", stem, n, ext))
+        .find(|candidate| !dir.join(candidate).exists())
+        .unwrap_or_default()
+}
+
+/// Link from `document` to the attachment at `target`, relative to it
+fn attachment_link(document: &FsPath, target: &str, name: &str) -> String {
+    if is_org_file(document) {
+        format!("[[file:{}]]", target)
+    } else if mime_guess::from_path(name).first().is_some_and(|m| m.type_() == "image") {
+        format!("![]({})", target)
+    } else {
+        format!("[{}]({})", name, target)
+    }
+}
+
+/// POST /api/files/*path/attachments - Store the files of a multipart body
+/// in the `attachmentsDir` next to the document, e.g. a pasted screenshot,
+/// and return the links to insert. Files without a name, as pasted images
+/// usually are, get a timestamped one; existing files are never replaced.
+pub async fn upload_attachments(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<Vec<UploadedAttachment>>), StatusCode> {
+    log_to_file(&format!("[server] POST /api/files/{}/attachments", path));
+    let full_path = state.roots.resolve(&path);
+    let canonical = full_path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&path, &canonical) {
+        log_to_file(&format!("[attachments] Rejected path traversal: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !canonical.is_file() || !is_document_file(&full_path) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let attach_dir = state.config.attachments_dir.trim_end_matches('/');
+    let dir_path = resolve_relative(&path, attach_dir).ok_or_else(|| {
+        log_to_file(&format!("[attachments] attachmentsDir must be relative: {}", attach_dir));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let dir = state.roots.resolve(&dir_path);
+    // Checked before anything is created, so a symlink out of the root can't
+    // get directories made on the other side of it
+    let existing = dir.ancestors().find(|d| d.exists()).unwrap_or(&dir);
+    let canonical_dir = existing.canonicalize().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !state.roots.contains(&dir_path, &canonical_dir) {
+        log_to_file(&format!("[attachments] Rejected directory outside root: {}", dir_path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    tokio::fs::create_dir_all(&dir).await.map_err(|e| {
+        log_to_file(&format!("[attachments] Failed to create {}: {}", dir_path, e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut uploaded = Vec::new();
+    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+        let name = match field.file_name().map(sanitize_file_name).filter(|n| !n.is_empty()) {
+            Some(name) => name,
+            None => {
+                // Not a file, unless it carries a type that gives an extension
+                let ext = match field.content_type().and_then(|t| mime_guess::get_mime_extensions_str(t)) {
+                    Some(exts) => exts.first().copied().unwrap_or("bin"),
+                    None => continue,
+                };
+                let now = timezone::now(&state.config, None)?;
+                format!("paste-{}.{}", now.format("%Y%m%d-%H%M%S"), ext)
+            }
+        };
+        let data = field.bytes().await.map_err(|e| {
+            log_to_file(&format!("[attachments] Upload failed: {}", e));
+            StatusCode::PAYLOAD_TOO_LARGE
+        })?;
+
+        let (target_dir, size) = (dir.clone(), data.len());
+        let store = move || {
+            let name = unique_file_name(&target_dir, &name);
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(target_dir.join(&name))
+                .and_then(|mut file| file.write_all(&data))
+                .map(|()| name.clone())
+                .map_err(|e| (name, e))
+        };
+        let name = match tokio::task::spawn_blocking(store).await {
+            Ok(Ok(name)) => name,
+            Ok(Err((name, e))) => {
+                log_to_file(&format!("[attachments] Failed to store {}: {}", name, e));
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+            Err(e) => {
+                log_to_file(&format!("[attachments] Failed to store upload: {}", e));
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+
+        log_to_file(&format!("[attachments] Stored {}/{} ({} bytes)", dir_path, name, size));
+        uploaded.push(UploadedAttachment {
+            path: format!("{}/{}", dir_path, name),
+            link: attachment_link(&full_path, &format!("{}/{}", attach_dir, name), &name),
+        });
+    }
+
+    if uploaded.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok((StatusCode::CREATED, Json(uploaded)))
+}
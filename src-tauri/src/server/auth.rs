@@ -0,0 +1,140 @@
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        ConnectInfo, Query, Request, State,
+    },
+    http::{header, HeaderMap, StatusCode, Uri},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::server::{log_to_file, AppState, API_PREFIX};
+
+/// Environment variable holding the token remote clients must present
+pub const TOKEN_ENV: &str = "ORG_VIEWER_TOKEN";
+
+/// Cookie the client keeps the token in, so image and attachment URLs
+/// work without it in every link
+pub const TOKEN_COOKIE: &str = "org-viewer-token";
+
+/// Query parameter for clients that can't set headers, like WebSockets
+pub const TOKEN_PARAM: &str = "token";
+
+/// How long a WebSocket client that didn't pass the token in its URL has
+/// to send it
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The configured token, if any; an empty variable counts as unset
+pub fn load_token() -> Option<String> {
+    let token = std::env::var(TOKEN_ENV).ok().filter(|t| !t.trim().is_empty());
+    if token.is_none() {
+        log_to_file(&format!(
+            "[auth] WARNING: {} not set, remote clients can read and write without authenticating",
+            TOKEN_ENV
+        ));
+    }
+    token.map(|t| t.trim().to_string())
+}
+
+/// Whether a connection from `peer` needs to authenticate. The local
+/// listener serves the desktop WebView, which never has a token. A reverse
+/// proxy on the same machine also connects from loopback, so don't put one
+/// in front of the server without its own authentication.
+pub fn required(state: &AppState, peer: &SocketAddr) -> bool {
+    state.token.is_some() && !peer.ip().is_loopback()
+}
+
+/// Whether `presented` is the configured token. Compares every byte so the
+/// time taken doesn't reveal how much of a guess was right.
+pub fn matches(state: &AppState, presented: &str) -> bool {
+    let token = match &state.token {
+        Some(t) => t.as_bytes(),
+        None => return true,
+    };
+    let presented = presented.as_bytes();
+    presented.len() == token.len() && presented.iter().zip(token).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Token from an `Authorization: Bearer` header, the `token` query
+/// parameter or the token cookie, in that order
+pub fn presented(headers: &HeaderMap, uri: &Uri) -> Option<String> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if let Some(token) = bearer {
+        return Some(token.trim().to_string());
+    }
+
+    if let Ok(Query(mut params)) = Query::<HashMap<String, String>>::try_from_uri(uri) {
+        if let Some(token) = params.remove(TOKEN_PARAM) {
+            return Some(token);
+        }
+    }
+
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|cookie| {
+            let (name, value) = cookie.trim().split_once('=')?;
+            (name == TOKEN_COOKIE).then(|| value.to_string())
+        })
+}
+
+/// Middleware rejecting `/api` requests from remote clients without the
+/// token. `/api/v1/health` stays open for uptime checks; `/ws` checks the token
+/// itself, since browsers can't add headers to a WebSocket and a client may
+/// send it in its first message instead. Static files stay open so the app
+/// can load and ask for the token.
+pub async fn require_token(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let path = request.uri().path();
+    let health = [API_PREFIX, "/api"].iter().any(|prefix| path.strip_prefix(prefix) == Some("/health"));
+    if !path.starts_with("/api/") || health || !required(&state, &peer) {
+        return Ok(next.run(request).await);
+    }
+    match presented(request.headers(), request.uri()) {
+        Some(token) if matches(&state, &token) => Ok(next.run(request).await),
+        _ => {
+            log_to_file(&format!("[auth] Rejected {} from {}", path, peer));
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+/// Wait for a WebSocket client that connected without the token to send
+/// `{"type": "auth", "token": "..."}` as its first message. Closes the
+/// socket and returns false if it sends anything else, the wrong token, or
+/// nothing within `HANDSHAKE_TIMEOUT`.
+pub async fn handshake(socket: &mut WebSocket, state: &AppState, peer: &SocketAddr) -> bool {
+    let message = match tokio::time::timeout(HANDSHAKE_TIMEOUT, socket.recv()).await {
+        Ok(Some(Ok(Message::Text(text)))) => serde_json::from_str::<serde_json::Value>(&text).ok(),
+        _ => None,
+    };
+    let accepted = message
+        .filter(|m| m["type"] == "auth")
+        .and_then(|m| m["token"].as_str().map(|t| matches(state, t)))
+        .unwrap_or(false);
+    if accepted {
+        return true;
+    }
+
+    log_to_file(&format!("[auth] Rejected WebSocket from {}", peer));
+    let _ = socket
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::POLICY,
+            reason: "authentication required".into(),
+        })))
+        .await;
+    false
+}
@@ -0,0 +1,93 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::server::document::extract_wikilinks;
+use crate::server::AppState;
+
+#[derive(Serialize)]
+pub struct BacklinkHeading {
+    line: usize,
+    title: String,
+}
+
+#[derive(Serialize)]
+pub struct Backlink {
+    /// Linking document
+    file: String,
+    title: String,
+    /// 1-based line containing the link
+    line: usize,
+    /// Org heading the link sits under, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heading: Option<BacklinkHeading>,
+    context: String,
+}
+
+#[derive(Serialize)]
+pub struct BacklinksResponse {
+    path: String,
+    count: usize,
+    items: Vec<Backlink>,
+}
+
+/// GET /api/files/*path/backlinks - Every file and heading linking to a document
+pub async fn get_backlinks(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<BacklinksResponse>, StatusCode> {
+    let index = state.index.read().await;
+    let doc = index.get_document(&path).ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut sources = doc.backlinks.clone();
+    sources.sort();
+
+    let mut items = Vec::new();
+    for source in &sources {
+        let source_doc = match index.get_document(source) {
+            Some(d) => d,
+            None => continue,
+        };
+        let content = match tokio::fs::read_to_string(state.roots.resolve(source)).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let headings = index.get_headings(source);
+
+        for (i, line) in content.lines().enumerate() {
+            let links_here = extract_wikilinks(line)
+                .iter()
+                .any(|link| index.link_resolves_to(link, source, &path));
+            if !links_here {
+                continue;
+            }
+
+            let heading = headings
+                .iter()
+                .take_while(|h| h.line <= i + 1)
+                .last()
+                .map(|h| BacklinkHeading {
+                    line: h.line,
+                    title: h.title.clone(),
+                });
+
+            items.push(Backlink {
+                file: source.clone(),
+                title: source_doc.title.clone(),
+                line: i + 1,
+                heading,
+                context: line.trim().chars().take(200).collect(),
+            });
+        }
+    }
+
+    Ok(Json(BacklinksResponse {
+        path,
+        count: items.len(),
+        items,
+    }))
+}
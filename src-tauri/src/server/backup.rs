@@ -0,0 +1,81 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::server::index::{CachedEntry, ImportReport};
+use crate::server::store::PARSER_VERSION;
+use crate::server::{log_to_file, AppState};
+
+/// Layout of the archive, bumped when `CachedEntry` changes shape
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Request body limit for imports; archives of large vaults run to many MB
+pub const IMPORT_LIMIT_BYTES: usize = 512 * 1024 * 1024;
+
+/// Portable dump of the parsed index: documents with their links and tags,
+/// and headings, keyed by document path
+#[derive(Serialize, Deserialize)]
+pub struct IndexArchive {
+    version: u32,
+    /// Entries from a different parser would disagree with local parsing
+    #[serde(rename = "parserVersion")]
+    parser_version: String,
+    #[serde(rename = "exportedAt")]
+    exported_at: String,
+    entries: HashMap<String, CachedEntry>,
+}
+
+/// GET /api/index/export - Download the parsed index as a JSON archive
+pub async fn export_index(State(state): State<Arc<AppState>>) -> Response {
+    let archive = IndexArchive {
+        version: ARCHIVE_VERSION,
+        parser_version: PARSER_VERSION.to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        entries: state.index.read().await.entries(),
+    };
+    let filename = format!(
+        "attachment; filename=\"org-viewer-index-{}.json\"",
+        chrono::Local::now().format("%Y-%m-%d")
+    );
+
+    let mut response = Json(archive).into_response();
+    if let Ok(v) = HeaderValue::from_str(&filename) {
+        response.headers_mut().insert(header::CONTENT_DISPOSITION, v);
+    }
+    response
+}
+
+/// POST /api/index/import - Load an exported archive so a new install can skip
+/// parsing documents it shares with the exporting machine
+pub async fn import_index(
+    State(state): State<Arc<AppState>>,
+    Json(archive): Json<IndexArchive>,
+) -> Result<Json<ImportReport>, StatusCode> {
+    if archive.version != ARCHIVE_VERSION || archive.parser_version != PARSER_VERSION {
+        log_to_file(&format!(
+            "[index] Rejected archive version {} (parser {})",
+            archive.version, archive.parser_version
+        ));
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // Every imported file is read to check it matches its entry
+    let mut index = state.index.clone().write_owned().await;
+    let entries = archive.entries;
+    let report = tokio::task::spawn_blocking(move || index.import_entries(entries))
+        .await
+        .map_err(|e| {
+            log_to_file(&format!("[index] Import failed: {}", e));
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    log_to_file(&format!(
+        "[index] Imported archive from {}: {} entries, {} reparsed, {} missing",
+        archive.exported_at, report.imported, report.reparsed, report.missing
+    ));
+    Ok(Json(report))
+}
@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::server::commands::response_json;
+use crate::server::crypt::CRYPT_SESSION_HEADER;
+use crate::server::{log_to_file, meta, routes, AppState};
+
+/// Most documents one request can ask for
+const MAX_BATCH_PATHS: usize = 200;
+
+#[derive(Deserialize)]
+pub struct BatchRequest {
+    paths: Vec<String>,
+    /// Return each document's summary from `/meta` rather than the document
+    #[serde(default)]
+    meta: bool,
+    /// Content as stored on disk, as with `?raw=true`
+    #[serde(default)]
+    raw: bool,
+}
+
+#[derive(Serialize)]
+pub struct BatchItem {
+    path: String,
+    /// HTTP status the document's own request would have had
+    status: u16,
+    /// The document, or its summary with `meta`; absent unless status is 200
+    #[serde(skip_serializing_if = "Option::is_none")]
+    document: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct BatchResponse {
+    items: Vec<BatchItem>,
+}
+
+/// POST /api/files/batch - Several documents in one response, in the order
+/// asked for, so a client reopening many views needs one round trip. Each
+/// is read like `GET /api/files/{path}` (or `/meta`), so a missing one just
+/// has a 404 status of its own. The crypt session header applies to all.
+pub async fn get_files(
+    State(state): State<Arc<AppState>>,
+    request_headers: HeaderMap,
+    Json(payload): Json<BatchRequest>,
+) -> Result<Json<BatchResponse>, StatusCode> {
+    log_to_file(&format!("[server] POST /api/files/batch ({} paths)", payload.paths.len()));
+    if payload.paths.len() > MAX_BATCH_PATHS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // Only the session carries over: conditional headers are for one document
+    let mut headers = HeaderMap::new();
+    if let Some(session) = request_headers.get(CRYPT_SESSION_HEADER) {
+        headers.insert(CRYPT_SESSION_HEADER, session.clone());
+    }
+
+    let mut items = Vec::with_capacity(payload.paths.len());
+    for path in payload.paths {
+        let response = if payload.meta {
+            meta::get_meta(State(state.clone()), Path(path.clone())).await.into_response()
+        } else {
+            let query = serde_json::from_value(json!({ "raw": payload.raw })).map_err(|_| StatusCode::BAD_REQUEST)?;
+            routes::get_file(State(state.clone()), Path(path.clone()), Query(query), headers.clone()).await
+        };
+        let status = response.status();
+        let (status, document) = match response_json(response).await {
+            Ok(document) => (status, Some(document)),
+            Err(status) => (status, None),
+        };
+        items.push(BatchItem {
+            path,
+            status: status.as_u16(),
+            document,
+        });
+    }
+    Ok(Json(BatchResponse { items }))
+}
@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::org::{parse_document_headings, parse_todo_keywords};
+use crate::server::{log_to_file, AppState};
+
+#[derive(Deserialize)]
+pub struct BoardQuery {
+    /// Comma-separated document paths; all documents when omitted
+    files: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BoardCard {
+    file: String,
+    line: usize,
+    title: String,
+    priority: Option<char>,
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scheduled: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deadline: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BoardColumn {
+    keyword: String,
+    done: bool,
+    cards: Vec<BoardCard>,
+}
+
+#[derive(Serialize)]
+pub struct BoardResponse {
+    columns: Vec<BoardColumn>,
+}
+
+/// GET /api/board?files= - Group TODO headings into columns by keyword, in the
+/// order the files' `#+TODO:` lines declare them
+pub async fn get_board(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BoardQuery>,
+) -> Json<BoardResponse> {
+    let index = state.index.read().await;
+    let mut paths: Vec<String> = match &query.files {
+        Some(files) => files
+            .split(',')
+            .map(|f| f.trim().to_string())
+            .filter(|f| index.get_document(f).is_some())
+            .collect(),
+        None => index
+            .documents_with_headings()
+            .filter(|(_, headings)| headings.iter().any(|h| h.todo.is_some()))
+            .map(|(doc, _)| doc.path.clone())
+            .collect(),
+    };
+    paths.sort();
+
+    let mut columns: Vec<BoardColumn> = Vec::new();
+    for path in &paths {
+        let content = match tokio::fs::read_to_string(state.roots.resolve(path)).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+
+        // Merge this file's workflow into the column list, keeping first-seen order
+        // with active states ahead of done states
+        let keywords = parse_todo_keywords(&content);
+        for keyword in &keywords.todo {
+            if !columns.iter().any(|c| &c.keyword == keyword) {
+                let at = columns.iter().position(|c| c.done).unwrap_or(columns.len());
+                columns.insert(at, BoardColumn { keyword: keyword.clone(), done: false, cards: Vec::new() });
+            }
+        }
+        for keyword in &keywords.done {
+            if !columns.iter().any(|c| &c.keyword == keyword) {
+                columns.push(BoardColumn { keyword: keyword.clone(), done: true, cards: Vec::new() });
+            }
+        }
+
+        for heading in index.get_headings(path) {
+            let todo = match &heading.todo {
+                Some(t) => t,
+                None => continue,
+            };
+            if let Some(column) = columns.iter_mut().find(|c| &c.keyword == todo) {
+                column.cards.push(BoardCard {
+                    file: path.clone(),
+                    line: heading.line,
+                    title: heading.title.clone(),
+                    priority: heading.priority,
+                    tags: heading.tags.clone(),
+                    scheduled: heading.scheduled.clone(),
+                    deadline: heading.deadline.clone(),
+                });
+            }
+        }
+    }
+
+    Json(BoardResponse { columns })
+}
+
+#[derive(Deserialize)]
+pub struct MoveCardRequest {
+    file: String,
+    /// 1-based heading line, as returned by `GET /api/board`
+    line: usize,
+    /// Heading title the client saw; guards against moving a stale line
+    title: String,
+    /// Target TODO keyword
+    to: String,
+}
+
+/// POST /api/board/move - Move a card to another column by rewriting its TODO keyword
+pub async fn move_card(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MoveCardRequest>,
+) -> Result<StatusCode, StatusCode> {
+    // Validate path - prevent directory traversal
+    let canonical_path = state.roots.resolve(&payload.file).canonicalize()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&payload.file, &canonical_path) {
+        log_to_file(&format!("[board] Rejected path traversal: {}", payload.file));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let content = tokio::fs::read_to_string(&canonical_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !parse_todo_keywords(&content).contains(&payload.to) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let heading = parse_document_headings(&canonical_path, &content)
+        .into_iter()
+        .find(|h| h.line == payload.line)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if heading.title != payload.title {
+        log_to_file(&format!("[board] Stale move for {}:{}", payload.file, payload.line));
+        return Err(StatusCode::CONFLICT);
+    }
+
+    // Replace the existing keyword, or insert one right after the stars
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    let line = &lines[heading.line - 1];
+    let stars_re = Regex::new(r"^(\*+\s+)").unwrap();
+    let stars = stars_re.captures(line).map(|c| c[1].to_string()).ok_or(StatusCode::NOT_FOUND)?;
+    let rest = &line[stars.len()..];
+    let rest = match &heading.todo {
+        Some(old) => rest[old.len()..].trim_start(),
+        None => rest,
+    };
+    lines[heading.line - 1] = format!("{}{} {}", stars, payload.to, rest);
+
+    let mut new_content = lines.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    if let Err(e) = tokio::fs::write(&canonical_path, new_content).await {
+        log_to_file(&format!("[board] Failed to write: {}", e));
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    log_to_file(&format!(
+        "[board] {}:{} {} -> {}",
+        payload.file,
+        payload.line,
+        heading.todo.as_deref().unwrap_or("-"),
+        payload.to
+    ));
+    // File watcher will auto-refresh index
+    Ok(StatusCode::OK)
+}
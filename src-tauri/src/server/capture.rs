@@ -0,0 +1,225 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::server::document::{is_document_file, is_org_file};
+use crate::server::org::parse_document_headings;
+use crate::server::timezone::{self, TzQuery};
+use crate::server::{log_to_file, AppState};
+
+/// Templates live next to `.org-viewer-config.json` in the org root
+const TEMPLATES_FILENAME: &str = ".org-viewer-capture.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureTemplate {
+    /// File (relative to org root) the entry is filed into
+    pub target: String,
+    /// Title of the heading to file under; appended to the end of the file if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    /// Entry skeleton, e.g. `* TODO %?\n  %U`
+    pub body: String,
+}
+
+fn load_templates(state: &AppState) -> BTreeMap<String, CaptureTemplate> {
+    let path = state.org_root.join(TEMPLATES_FILENAME);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_to_file(&format!("[capture] Invalid templates file {:?}: {}", path, e));
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn save_templates(state: &AppState, templates: &BTreeMap<String, CaptureTemplate>) -> Result<(), StatusCode> {
+    let json = serde_json::to_string_pretty(templates).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    std::fs::write(state.org_root.join(TEMPLATES_FILENAME), json).map_err(|e| {
+        log_to_file(&format!("[capture] Failed to save templates: {}", e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Expand org-capture style placeholders: `%t`/`%T` active date/timestamp,
+/// `%u`/`%U` inactive, `%i` and `%?` the captured text, `%%` a literal percent
+fn expand_placeholders(body: &str, text: &str, now: NaiveDateTime) -> String {
+    let mut out = String::with_capacity(body.len() + text.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push_str(&now.format("<%Y-%m-%d %a>").to_string()),
+            Some('T') => out.push_str(&now.format("<%Y-%m-%d %a %H:%M>").to_string()),
+            Some('u') => out.push_str(&now.format("[%Y-%m-%d %a]").to_string()),
+            Some('U') => out.push_str(&now.format("[%Y-%m-%d %a %H:%M]").to_string()),
+            Some('i') | Some('?') => out.push_str(text),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+/// Shift heading markers (`*` in org, `#` in Markdown) in an entry so its
+/// top level sits at `level`
+fn demote_entry(entry: &str, level: usize, marker: char) -> String {
+    let stars = |l: &str| {
+        let n = l.chars().take_while(|c| *c == marker).count();
+        (n > 0 && l[n..].starts_with(' ')).then_some(n)
+    };
+    let top = match entry.lines().filter_map(stars).min() {
+        Some(t) => t,
+        None => return entry.to_string(),
+    };
+
+    entry
+        .lines()
+        .map(|l| match stars(l) {
+            Some(n) => format!("{}{}", marker.to_string().repeat(n - top + level), &l[n..]),
+            None => l.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Insert an entry at the end of the named heading's subtree, or the end of
+/// the file at `path`
+fn insert_entry(
+    path: &std::path::Path,
+    content: &str,
+    heading: Option<&str>,
+    entry: &str,
+) -> Result<String, StatusCode> {
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+
+    let (at, entry) = match heading {
+        Some(title) => {
+            let target = parse_document_headings(path, content)
+                .into_iter()
+                .find(|h| h.title.eq_ignore_ascii_case(title))
+                .ok_or(StatusCode::NOT_FOUND)?;
+            let marker = if is_org_file(path) { '*' } else { '#' };
+            (target.subtree_end, demote_entry(entry, target.level + 1, marker))
+        }
+        None => (lines.len(), entry.to_string()),
+    };
+
+    lines.splice(at..at, entry.trim_end_matches('\n').lines().map(|l| l.to_string()));
+    let mut result = lines.join("\n");
+    result.push('\n');
+    Ok(result)
+}
+
+/// GET /api/capture/templates - List stored capture templates by name
+pub async fn list_templates(State(state): State<Arc<AppState>>) -> Json<BTreeMap<String, CaptureTemplate>> {
+    Json(load_templates(&state))
+}
+
+/// PUT /api/capture/templates/{name} - Create or replace a capture template
+pub async fn put_template(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(template): Json<CaptureTemplate>,
+) -> Result<StatusCode, StatusCode> {
+    let mut templates = load_templates(&state);
+    let created = templates.insert(name.clone(), template).is_none();
+    save_templates(&state, &templates)?;
+    log_to_file(&format!("[capture] Saved template {}", name));
+    Ok(if created { StatusCode::CREATED } else { StatusCode::OK })
+}
+
+/// DELETE /api/capture/templates/{name} - Remove a capture template
+pub async fn delete_template(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let mut templates = load_templates(&state);
+    if templates.remove(&name).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    save_templates(&state, &templates)?;
+    log_to_file(&format!("[capture] Deleted template {}", name));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct CaptureRequest {
+    /// Stored template name; when absent `target` is required
+    template: Option<String>,
+    #[serde(default)]
+    text: String,
+    /// Override or supply the target file / heading
+    target: Option<String>,
+    heading: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CaptureResponse {
+    path: String,
+    entry: String,
+}
+
+/// POST /api/capture?tz= - File captured text into a target document
+pub async fn capture(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TzQuery>,
+    Json(payload): Json<CaptureRequest>,
+) -> Result<Json<CaptureResponse>, StatusCode> {
+    let template = match &payload.template {
+        Some(name) => Some(load_templates(&state).remove(name).ok_or(StatusCode::NOT_FOUND)?),
+        None => None,
+    };
+
+    let target = payload
+        .target
+        .clone()
+        .or_else(|| template.as_ref().map(|t| t.target.clone()))
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let heading = payload
+        .heading
+        .clone()
+        .or_else(|| template.as_ref().and_then(|t| t.heading.clone()));
+    let body = template.map(|t| t.body).unwrap_or_else(|| "%?".to_string());
+
+    // Validate path - the target may not exist yet, so check it lexically
+    let full_path = state.roots.resolve(&target);
+    if target.split(['/', '\\']).any(|s| s == "..") || !full_path.starts_with(state.roots.root_of(&target)) {
+        log_to_file(&format!("[capture] Rejected target outside org root: {}", target));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    // Captures are filed under headings, so only into documents
+    if !is_document_file(&full_path) {
+        log_to_file(&format!("[capture] Rejected target that isn't a document: {}", target));
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let now = timezone::now(&state.config, query.tz.as_deref())?;
+    let entry = expand_placeholders(&body, &payload.text, now);
+    let content = std::fs::read_to_string(&full_path).unwrap_or_default();
+    let new_content = insert_entry(&full_path, &content, heading.as_deref(), &entry)?;
+
+    if let Some(parent) = full_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    if let Err(e) = std::fs::write(&full_path, new_content) {
+        log_to_file(&format!("[capture] Failed to write {}: {}", target, e));
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    log_to_file(&format!("[capture] Captured into {}", target));
+    // File watcher will auto-refresh index
+    Ok(Json(CaptureResponse { path: target, entry }))
+}
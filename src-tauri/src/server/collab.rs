@@ -0,0 +1,316 @@
+use automerge::{transaction::Transactable, AutoCommit, ChangeHash, ObjId, ObjType, ReadDoc, ROOT};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::server::conditional::etag;
+use crate::server::crypt::find_encrypted;
+use crate::server::events::{EventKind, PROTOCOL_VERSION};
+use crate::server::{log_to_file, AppState};
+
+/// How long a session waits after the last edit before writing the file
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// A document open for editing on one or more connections. Its text lives in
+/// an Automerge document; each client edits against the version it last
+/// saw, so concurrent edits from two devices merge instead of the later
+/// save overwriting the earlier one.
+struct Session {
+    doc: AutoCommit,
+    /// The text object holding the file's content
+    text: ObjId,
+    /// Connections with the document open
+    participants: HashSet<u64>,
+    /// Content last written to or read from the file, and the version of
+    /// the session it corresponds to
+    saved: String,
+    saved_heads: Vec<ChangeHash>,
+    /// Bumped by every edit, so a pending save can tell a newer one is due
+    generation: u64,
+}
+
+impl Session {
+    fn open(content: &str) -> Result<Self, automerge::AutomergeError> {
+        let mut doc = AutoCommit::new();
+        let text = doc.put_object(ROOT, "content", ObjType::Text)?;
+        doc.splice_text(&text, 0, 0, content)?;
+        let saved_heads = doc.get_heads();
+        Ok(Self {
+            doc,
+            text,
+            participants: HashSet::new(),
+            saved: content.to_string(),
+            saved_heads,
+            generation: 0,
+        })
+    }
+
+    fn content(&self) -> String {
+        self.doc.text(&self.text).unwrap_or_default()
+    }
+
+    /// Revision of the file as the session last wrote or read it, which a
+    /// participant's own save names in `If-Match`
+    fn revision(&self) -> String {
+        etag(self.saved.as_bytes())
+    }
+
+    /// Apply `content` as an edit made on top of the version at `heads`,
+    /// merging it with whatever happened since. Returns the heads of the
+    /// edit itself, which are the version the editor now holds.
+    fn edit(&mut self, heads: &[ChangeHash], content: &str) -> Result<Vec<ChangeHash>, automerge::AutomergeError> {
+        let mut fork = self.doc.fork_at(heads)?;
+        fork.update_text(&self.text, content)?;
+        let edited = fork.get_heads();
+        self.doc.merge(&mut fork)?;
+        self.generation += 1;
+        Ok(edited)
+    }
+
+    /// Merge in the file's content if something other than this session
+    /// wrote it, as an edit on top of what the session last saved
+    fn merge_file(&mut self, on_disk: &str) -> Result<bool, automerge::AutomergeError> {
+        if on_disk == self.saved {
+            return Ok(false);
+        }
+        let heads = self.saved_heads.clone();
+        self.saved_heads = self.edit(&heads, on_disk)?;
+        self.saved = on_disk.to_string();
+        Ok(true)
+    }
+}
+
+/// Editing sessions by document path
+#[derive(Default)]
+pub struct CollabSessions {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+/// A reply to the connection that sent a message, shaped like an event but
+/// without a revision, since other connections don't see it
+fn reply(kind: &str, path: &str, payload: serde_json::Value) -> serde_json::Value {
+    json!({ "v": PROTOCOL_VERSION, "type": kind, "path": path, "payload": payload })
+}
+
+fn error(path: &str, message: &str) -> serde_json::Value {
+    reply("collab-error", path, json!({ "error": message }))
+}
+
+fn heads_json(heads: &[ChangeHash]) -> Vec<String> {
+    heads.iter().map(|h| h.to_string()).collect()
+}
+
+/// Handle an editing message from connection `conn`, returning the reply to
+/// send it, or `None` if the message isn't about editing:
+///
+/// - `{"type": "edit-open", "path"}` joins the document's session, starting
+///   one from the file if needed, and replies `collab-state` with the
+///   content, the `heads` naming its version and the file's `revision`
+/// - `{"type": "edit", "path", "heads", "content"}` replaces the text the
+///   client had at `heads` with `content`, replying `collab-ack` with the
+///   heads of that edit. Everyone gets the merged result as a
+///   `collab-update` event, and again with the new `revision` once the
+///   session has written it to the file.
+/// - `{"type": "edit-close", "path"}` leaves the session, replying
+///   `collab-closed`; the last one out saves the file
+pub async fn handle(state: &Arc<AppState>, conn: u64, message: &serde_json::Value) -> Option<serde_json::Value> {
+    let kind = message["type"].as_str()?;
+    if !matches!(kind, "edit-open" | "edit" | "edit-close") {
+        return None;
+    }
+    let path = match message["path"].as_str() {
+        Some(p) => p,
+        None => return Some(error("", "missing path")),
+    };
+    Some(match kind {
+        "edit-open" => open(state, conn, path).await,
+        "edit" => {
+            let heads: Option<Vec<ChangeHash>> = message["heads"]
+                .as_array()
+                .and_then(|heads| heads.iter().map(|h| h.as_str()?.parse().ok()).collect());
+            match (heads, message["content"].as_str()) {
+                (Some(heads), Some(content)) => edit(state, conn, path, &heads, content).await,
+                _ => error(path, "edit needs heads and content"),
+            }
+        }
+        _ => {
+            close(state, conn, path).await;
+            reply("collab-closed", path, json!({}))
+        }
+    })
+}
+
+async fn open(state: &Arc<AppState>, conn: u64, path: &str) -> serde_json::Value {
+    let full_path = state.roots.resolve(path);
+    match full_path.canonicalize() {
+        Ok(canonical) if state.roots.contains(path, &canonical) => {}
+        _ => return error(path, "not found"),
+    }
+
+    let mut sessions = state.collab.sessions.lock().await;
+    if !sessions.contains_key(path) {
+        let content = match tokio::fs::read_to_string(&full_path).await {
+            Ok(c) => c,
+            Err(_) => return error(path, "not found"),
+        };
+        // Viewers hold these decrypted, which the raw text can't reflect
+        if !find_encrypted(&full_path, &content).is_empty() {
+            return error(path, "documents with encrypted subtrees can't be edited together");
+        }
+        match Session::open(&content) {
+            Ok(session) => {
+                log_to_file(&format!("[collab] Opened session for {}", path));
+                sessions.insert(path.to_string(), session);
+            }
+            Err(e) => return error(path, &e.to_string()),
+        }
+    }
+    let session = sessions.get_mut(path).unwrap();
+    session.participants.insert(conn);
+    let heads = heads_json(&session.doc.get_heads());
+    reply(
+        "collab-state",
+        path,
+        json!({ "heads": heads, "content": session.content(), "revision": session.revision() }),
+    )
+}
+
+async fn edit(state: &Arc<AppState>, conn: u64, path: &str, heads: &[ChangeHash], content: &str) -> serde_json::Value {
+    let mut sessions = state.collab.sessions.lock().await;
+    let session = match sessions.get_mut(path) {
+        Some(s) if s.participants.contains(&conn) => s,
+        _ => return error(path, "not open"),
+    };
+    let edited = match session.edit(heads, content) {
+        Ok(h) => h,
+        // Heads from before a server restart; the client starts over from
+        // the current state
+        Err(e) => {
+            log_to_file(&format!("[collab] Rejected edit to {}: {}", path, e));
+            let heads = heads_json(&session.doc.get_heads());
+            return reply(
+                "collab-state",
+                path,
+                json!({ "heads": heads, "content": session.content(), "revision": session.revision() }),
+            );
+        }
+    };
+
+    broadcast(state, path, session);
+    schedule_save(state.clone(), path.to_string(), session.generation);
+    reply(
+        "collab-ack",
+        path,
+        json!({ "heads": heads_json(&edited), "revision": session.revision() }),
+    )
+}
+
+async fn close(state: &Arc<AppState>, conn: u64, path: &str) {
+    let mut sessions = state.collab.sessions.lock().await;
+    let empty = match sessions.get_mut(path) {
+        Some(session) => {
+            session.participants.remove(&conn);
+            session.participants.is_empty()
+        }
+        None => return,
+    };
+    if empty {
+        if let Some(mut session) = sessions.remove(path) {
+            save(state, path, &mut session).await;
+            log_to_file(&format!("[collab] Closed session for {}", path));
+        }
+    }
+}
+
+/// Leave every session, when a connection drops
+pub async fn leave_all(state: &Arc<AppState>, conn: u64) {
+    let paths: Vec<String> = {
+        let sessions = state.collab.sessions.lock().await;
+        sessions
+            .iter()
+            .filter(|(_, s)| s.participants.contains(&conn))
+            .map(|(p, _)| p.clone())
+            .collect()
+    };
+    for path in paths {
+        close(state, conn, &path).await;
+    }
+}
+
+/// Merge a change the watcher saw into the document's session, if it has
+/// one, so edits made outside it (an external editor, a PUT) aren't lost or
+/// overwritten by the next save
+pub async fn file_changed(state: &Arc<AppState>, path: &str) {
+    let mut sessions = state.collab.sessions.lock().await;
+    let session = match sessions.get_mut(path) {
+        Some(s) => s,
+        None => return,
+    };
+    let on_disk = match tokio::fs::read_to_string(state.roots.resolve(path)).await {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+    match session.merge_file(&on_disk) {
+        Ok(true) => {
+            log_to_file(&format!("[collab] Merged outside change to {}", path));
+            broadcast(state, path, session);
+            if session.content() != on_disk {
+                schedule_save(state.clone(), path.to_string(), session.generation);
+            }
+        }
+        Ok(false) => {}
+        Err(e) => log_to_file(&format!("[collab] Failed to merge outside change to {}: {}", path, e)),
+    }
+}
+
+fn broadcast(state: &AppState, path: &str, session: &mut Session) {
+    let heads = heads_json(&session.doc.get_heads());
+    let payload = json!({ "heads": heads, "content": session.content(), "revision": session.revision() });
+    state.events.send(EventKind::CollabUpdate, Some(path), payload);
+}
+
+fn schedule_save(state: Arc<AppState>, path: String, generation: u64) {
+    tokio::spawn(async move {
+        tokio::time::sleep(SAVE_DELAY).await;
+        let mut sessions = state.collab.sessions.lock().await;
+        if let Some(session) = sessions.get_mut(&path).filter(|s| s.generation == generation) {
+            save(&state, &path, session).await;
+        }
+    });
+}
+
+/// Write the session's content to the file, first merging in anything
+/// written there since the last save
+async fn save(state: &AppState, path: &str, session: &mut Session) {
+    let full_path = state.roots.resolve(path);
+    let on_disk = match tokio::fs::read_to_string(&full_path).await {
+        Ok(c) => c,
+        Err(_) => {
+            log_to_file(&format!("[collab] {} is gone, not saving its session", path));
+            return;
+        }
+    };
+    match session.merge_file(&on_disk) {
+        Ok(true) => broadcast(state, path, session),
+        Ok(false) => {}
+        Err(e) => {
+            log_to_file(&format!("[collab] Failed to merge outside change to {}: {}", path, e));
+            return;
+        }
+    }
+    let content = session.content();
+    if content == on_disk {
+        return;
+    }
+    match tokio::fs::write(&full_path, &content).await {
+        Ok(()) => {
+            session.saved_heads = session.doc.get_heads();
+            session.saved = content;
+            // Participants save over this write next
+            broadcast(state, path, session);
+        }
+        Err(e) => log_to_file(&format!("[collab] Failed to save {}: {}", path, e)),
+    }
+}
@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::server::crypt::CRYPT_SESSION_HEADER;
+use crate::server::events::PROTOCOL_VERSION;
+use crate::server::{log_to_file, outline, routes, AppState};
+
+/// Largest response body a command sends back, as for HTTP downloads
+const MAX_RESULT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Start a command sent over the WebSocket, which runs the same handler as
+/// the HTTP route it stands for without a request of its own:
+///
+/// `{"type": "command", "id", "command", "path", "args", "cryptSession", "ifMatch"}`
+///
+/// - `document` reads `path` like `GET /api/files/{path}`, `args` being its query
+/// - `save` writes `path` like `PUT /api/files/{path}`, `args` being its body
+///   and `ifMatch` its `If-Match` header
+/// - `search` runs `GET /api/search`, `args` being its query
+/// - `outline` reads `path` like `GET /api/files/{path}/outline`
+///
+/// Commands run concurrently; each result is sent to `replies` as
+/// `{"type": "command-result", "id", "payload": {"status", "body"}}`, or
+/// `command-error` with `{"status", "error"}`, carrying the message's `id`
+/// so the client can match it up. Returns false for other messages.
+pub fn spawn(state: &Arc<AppState>, message: &serde_json::Value, replies: &mpsc::UnboundedSender<String>) -> bool {
+    if message["type"] != "command" {
+        return false;
+    }
+    let (state, message, replies) = (state.clone(), message.clone(), replies.clone());
+    tokio::spawn(async move {
+        let id = message["id"].clone();
+        let reply = match run(state, &message).await {
+            Ok(response) => result(&id, response).await,
+            Err(status) => error(&id, status),
+        };
+        // The connection is gone if nobody receives it
+        let _ = replies.send(reply.to_string());
+    });
+    true
+}
+
+async fn run(state: Arc<AppState>, message: &serde_json::Value) -> Result<Response, StatusCode> {
+    let command = message["command"].as_str().ok_or(StatusCode::BAD_REQUEST)?;
+    let path = || message["path"].as_str().map(|p| p.to_string()).ok_or(StatusCode::BAD_REQUEST);
+    let mut headers = HeaderMap::new();
+    if let Some(session) = message["cryptSession"].as_str().and_then(|s| HeaderValue::from_str(s).ok()) {
+        headers.insert(CRYPT_SESSION_HEADER, session);
+    }
+    // The revision a `save` started from, sent as `If-Match` would be
+    if let Some(revision) = message["ifMatch"].as_str().and_then(|s| HeaderValue::from_str(s).ok()) {
+        headers.insert(header::IF_MATCH, revision);
+    }
+    log_to_file(&format!("[ws] Command {} {}", command, message["path"].as_str().unwrap_or("")));
+
+    Ok(match command {
+        "document" => routes::get_file(State(state), Path(path()?), Query(args(message)?), headers).await,
+        "save" => routes::put_file(State(state), Path(path()?), headers, Json(args(message)?))
+            .await
+            .into_response(),
+        "search" => routes::search(State(state), Query(args(message)?)).await.into_response(),
+        "outline" => outline::get_outline(State(state), Path(path()?)).await.into_response(),
+        _ => return Err(StatusCode::NOT_FOUND),
+    })
+}
+
+/// A command's `args`, parsed as the route's query or body would be
+fn args<T: DeserializeOwned>(message: &serde_json::Value) -> Result<T, StatusCode> {
+    let args = match &message["args"] {
+        serde_json::Value::Null => json!({}),
+        args => args.clone(),
+    };
+    serde_json::from_value(args).map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// A handler's successful JSON response as a value, or its error status
+pub async fn response_json(response: Response) -> Result<serde_json::Value, StatusCode> {
+    let status = response.status();
+    if !status.is_success() {
+        return Err(status);
+    }
+    let body = axum::body::to_bytes(response.into_body(), MAX_RESULT_BYTES)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Handlers that only report success have an empty body
+    Ok(serde_json::from_slice(&body).unwrap_or_default())
+}
+
+async fn result(id: &serde_json::Value, response: Response) -> serde_json::Value {
+    let status = response.status();
+    let body = match response_json(response).await {
+        Ok(body) => body,
+        Err(status) => return error(id, status),
+    };
+    json!({
+        "v": PROTOCOL_VERSION,
+        "type": "command-result",
+        "id": id,
+        "payload": { "status": status.as_u16(), "body": body }
+    })
+}
+
+fn error(id: &serde_json::Value, status: StatusCode) -> serde_json::Value {
+    json!({
+        "v": PROTOCOL_VERSION,
+        "type": "command-error",
+        "id": id,
+        "payload": { "status": status.as_u16(), "error": status.canonical_reason().unwrap_or("error") }
+    })
+}
@@ -0,0 +1,148 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::server::index::stable_hash;
+
+/// Strong validator for a response body. Revisions are kept in drafts, so
+/// this must not change between builds.
+pub fn etag(body: &[u8]) -> String {
+    format!("\"{:016x}\"", stable_hash(body))
+}
+
+/// Revision of a file as stored: the strong validator of its bytes. Writes
+/// name the revision they started from in `If-Match`.
+pub async fn file_revision(path: &Path) -> Option<String> {
+    tokio::fs::read(path).await.ok().map(|bytes| etag(&bytes))
+}
+
+/// Body of a successful write: the file's new revision, for the next one
+#[derive(Serialize)]
+pub struct Revision {
+    revision: String,
+}
+
+impl Revision {
+    pub fn of(content: &[u8]) -> Self {
+        Revision { revision: etag(content) }
+    }
+}
+
+/// `ETag` of a rendering of a file at `revision`: the revision and the
+/// body's own validator, so a write can name either the revision or the tag
+/// the client read it with
+fn revision_etag(revision: &str, body: &[u8]) -> String {
+    format!("\"{}-{}\"", revision.trim_matches('"'), etag(body).trim_matches('"'))
+}
+
+/// Whether `tag` names the file at `revision`: the revision itself, or the
+/// `ETag` of a rendering of it
+fn names_revision(tag: &str, revision: &str) -> bool {
+    if tag == revision {
+        return true;
+    }
+    let inner = revision.trim_matches('"');
+    tag.strip_prefix('"')
+        .and_then(|t| t.strip_prefix(inner))
+        .is_some_and(|rest| rest.starts_with('-') && rest.ends_with('"'))
+}
+
+/// Whether a write may replace the file at `path`, so one device can't
+/// silently overwrite another's save: 428 without `If-Match`, 412 when it
+/// names neither the current revision, the `ETag` a GET of the document
+/// returned for it, nor `*` (strong comparison, so weak tags never match).
+pub async fn check_if_match(headers: &HeaderMap, path: &Path) -> Result<(), StatusCode> {
+    let value = headers
+        .get(header::IF_MATCH)
+        .ok_or(StatusCode::PRECONDITION_REQUIRED)?
+        .to_str()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let current = file_revision(path).await.ok_or(StatusCode::NOT_FOUND)?;
+    if value.split(',').map(|t| t.trim()).any(|t| t == "*" || names_revision(t, &current)) {
+        Ok(())
+    } else {
+        Err(StatusCode::PRECONDITION_FAILED)
+    }
+}
+
+/// IMF-fixdate, as used by `Last-Modified` and `If-Modified-Since`
+fn http_date(secs: u64) -> Option<String> {
+    let date = Utc.timestamp_opt(secs as i64, 0).single()?;
+    Some(date.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+}
+
+fn parse_http_date(value: &str) -> Option<u64> {
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    u64::try_from(date.timestamp()).ok()
+}
+
+/// Whether an `If-None-Match` header lists `etag` (weak comparison)
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.trim_start_matches("W/") == etag)
+}
+
+/// Whether the client's copy is current. `If-None-Match` wins when present;
+/// `If-Modified-Since` is only trusted when `last_modified` covers everything
+/// in the body.
+fn not_modified(headers: &HeaderMap, etag: &str, last_modified: Option<u64>) -> bool {
+    if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+        return value.to_str().map(|v| etag_matches(v, etag)).unwrap_or(false);
+    }
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_http_date);
+    matches!((since, last_modified), (Some(since), Some(modified)) if modified <= since)
+}
+
+/// A JSON response carrying `ETag`/`Last-Modified`, or a bodyless 304 when the
+/// request's validators show the client already has it. Responses that vary
+/// by request header should name it in `vary`. A response rendering a file
+/// passes the file's `revision`, which the `ETag` then carries so it can be
+/// sent back in `If-Match` when writing the file.
+pub fn json_response(
+    headers: &HeaderMap,
+    value: &serde_json::Value,
+    last_modified: Option<u64>,
+    vary: Option<&'static str>,
+    revision: Option<&str>,
+) -> Response {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    let tag = match revision {
+        Some(revision) => revision_etag(revision, &body),
+        None => etag(&body),
+    };
+
+    let mut response = if not_modified(headers, &tag, last_modified) {
+        let mut r = Response::new(Body::empty());
+        *r.status_mut() = StatusCode::NOT_MODIFIED;
+        r
+    } else {
+        let mut r = Response::new(Body::from(body));
+        r.headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        r
+    };
+
+    let out = response.headers_mut();
+    if let Ok(v) = HeaderValue::from_str(&tag) {
+        out.insert(header::ETAG, v);
+    }
+    if let Some(v) = last_modified.and_then(http_date).and_then(|d| HeaderValue::from_str(&d).ok()) {
+        out.insert(header::LAST_MODIFIED, v);
+    }
+    // Cacheable, but always revalidated: files change underneath the server
+    out.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if let Some(vary) = vary {
+        out.insert(header::VARY, HeaderValue::from_static(vary));
+    }
+    response
+}
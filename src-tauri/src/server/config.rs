@@ -0,0 +1,144 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::server::attachments::DEFAULT_UPLOAD_DIR;
+use crate::server::dirty::DEFAULT_DEBOUNCE_MS;
+use crate::server::index::DEFAULT_BODY_BUDGET_MB;
+use crate::server::{log_to_file, DEFAULT_WS_IDLE_TIMEOUT_SECONDS, DEFAULT_WS_PING_SECONDS};
+use crate::server::reconcile::DEFAULT_RECONCILE_MINUTES;
+use crate::server::reminders::DEFAULT_REMINDER_MINUTES;
+use crate::server::watcher::{WatchMode, DEFAULT_POLL_SECONDS};
+
+const CONFIG_FILENAME: &str = ".org-viewer-config.json";
+
+/// Server settings read from `.org-viewer-config.json` at the org root.
+/// Every field is optional; missing fields fall back to the defaults below.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// strftime pattern (relative to org root) for daily journal files
+    #[serde(rename = "journalPattern")]
+    pub journal_pattern: String,
+    /// Template file (relative to org root) for new journal entries
+    #[serde(rename = "journalTemplate")]
+    pub journal_template: Option<String>,
+    /// Typst CLI used for PDF export
+    #[serde(rename = "typstPath")]
+    pub typst_path: String,
+    /// Default TODO sequence for files without `#+TODO:` lines, e.g. `TODO NEXT | DONE CANCELLED`
+    #[serde(rename = "todoKeywords")]
+    pub todo_keywords: Option<String>,
+    /// IANA timezone (e.g. `Europe/Berlin`) for "today" and timestamps; server local time when unset
+    pub timezone: Option<String>,
+    /// Embedding service for semantic search; semantic search is off when unset
+    pub embeddings: Option<EmbeddingsConfig>,
+    /// Memory for parsed bodies of recently viewed documents, in megabytes.
+    /// Older bodies are dropped and reparsed when next viewed.
+    #[serde(rename = "bodyCacheMb")]
+    pub body_cache_mb: usize,
+    /// More directories to index alongside the org root, by name. Their
+    /// documents are served as `@name/...`, e.g. `{"work": "/home/me/work-notes"}`.
+    pub roots: BTreeMap<String, PathBuf>,
+    /// Minutes between background scans that catch changes the file watcher
+    /// missed, e.g. on network drives; 0 turns them off
+    #[serde(rename = "reconcileMinutes")]
+    pub reconcile_minutes: u64,
+    /// Quiet period in milliseconds before changed files are reindexed.
+    /// Editors often save in several steps (temp file, rename, chmod); all
+    /// events for a file within this window become one reparse.
+    #[serde(rename = "watchDebounceMs")]
+    pub debounce_ms: u64,
+    /// `auto` (native file events, polling on network shares or when the OS
+    /// runs out of watches), `native` or `poll`
+    #[serde(rename = "watchMode")]
+    pub watch_mode: WatchMode,
+    /// Seconds between scans when polling
+    #[serde(rename = "watchPollSeconds")]
+    pub poll_seconds: u64,
+    /// Follow symlinks inside the roots when indexing and watching, e.g. an
+    /// attachments directory linked in from another drive. Loops are skipped
+    /// and a file reachable by several paths is indexed once.
+    #[serde(rename = "followSymlinks")]
+    pub follow_symlinks: bool,
+    /// Directories, as document paths, whose documents are indexed by title
+    /// and file-level metadata only: bodies aren't parsed or searched and the
+    /// directories aren't watched, which keeps memory and watch handles in
+    /// check on huge reference dumps. Periodic rescans still catch changes.
+    #[serde(rename = "metadataOnly")]
+    pub metadata_only: Vec<String>,
+    /// Seconds between pings to each WebSocket client; 0 turns pings, and
+    /// with them the idle timeout, off
+    #[serde(rename = "wsPingSeconds")]
+    pub ws_ping_seconds: u64,
+    /// Seconds without hearing from a WebSocket client, pongs included,
+    /// before its connection is closed as dead, e.g. a phone that dropped
+    /// off Wi-Fi without closing it; 0 keeps connections open indefinitely
+    #[serde(rename = "wsIdleTimeoutSeconds")]
+    pub ws_idle_timeout_seconds: u64,
+    /// Minutes before a timed SCHEDULED or DEADLINE entry is due that
+    /// clients get a reminder; 0 turns reminders off
+    #[serde(rename = "reminderMinutes")]
+    pub reminder_minutes: u64,
+    /// Directory, relative to a document, that files uploaded to it are
+    /// stored in, e.g. `img` or `../assets`
+    #[serde(rename = "attachmentsDir")]
+    pub attachments_dir: String,
+}
+
+/// An OpenAI-compatible embeddings endpoint. Local models work through any
+/// server speaking that API, e.g. Ollama at `http://localhost:11434/v1/embeddings`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EmbeddingsConfig {
+    pub url: String,
+    pub model: String,
+    /// Sent as a bearer token when set
+    #[serde(rename = "apiKey")]
+    pub api_key: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            journal_pattern: "journal/%Y-%m-%d.md".to_string(),
+            journal_template: None,
+            typst_path: "typst".to_string(),
+            todo_keywords: None,
+            timezone: None,
+            embeddings: None,
+            body_cache_mb: DEFAULT_BODY_BUDGET_MB,
+            roots: BTreeMap::new(),
+            reconcile_minutes: DEFAULT_RECONCILE_MINUTES,
+            debounce_ms: DEFAULT_DEBOUNCE_MS,
+            watch_mode: WatchMode::Auto,
+            poll_seconds: DEFAULT_POLL_SECONDS,
+            follow_symlinks: false,
+            metadata_only: Vec::new(),
+            ws_ping_seconds: DEFAULT_WS_PING_SECONDS,
+            ws_idle_timeout_seconds: DEFAULT_WS_IDLE_TIMEOUT_SECONDS,
+            reminder_minutes: DEFAULT_REMINDER_MINUTES,
+            attachments_dir: DEFAULT_UPLOAD_DIR.to_string(),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn load(org_root: &Path) -> Self {
+        let path = org_root.join(CONFIG_FILENAME);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(_) => return Self::default(),
+        };
+
+        match serde_json::from_str(&content) {
+            Ok(config) => {
+                log_to_file(&format!("Loaded config from {:?}", path));
+                config
+            }
+            Err(e) => {
+                log_to_file(&format!("Invalid config file {:?}: {}", path, e));
+                Self::default()
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use axum::{http::StatusCode, response::Json};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::server::document::{is_document_file, serialize_document};
+use crate::server::timezone;
+use crate::server::{dirty, log_to_file, AppState};
+
+#[derive(Deserialize, Default)]
+pub struct CreateFileRequest {
+    /// Written as a YAML block ahead of the content when given, or as
+    /// `#+KEYWORD:` lines in an org file
+    frontmatter: Option<HashMap<String, serde_json::Value>>,
+    /// Initial content; empty when neither this nor `template` is given
+    content: Option<String>,
+    /// Document path of a file to start from, with `{{title}}`, `{{date}}`,
+    /// `{{time}}` and `{{weekday}}` filled in
+    template: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreateFileResponse {
+    path: String,
+}
+
+/// Fill template placeholders; the title comes from the new file's name,
+/// e.g. `Weekly review` for `notes/weekly-review.org`
+fn render_template(template: &str, path: &str, now: NaiveDateTime) -> String {
+    let stem = Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    let title = stem.replace(['-', '_'], " ");
+    let mut chars = title.chars();
+    let title = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    };
+    template
+        .replace("{{title}}", &title)
+        .replace("{{date}}", &now.format("%Y-%m-%d").to_string())
+        .replace("{{time}}", &now.format("%H:%M").to_string())
+        .replace("{{weekday}}", &now.format("%A").to_string())
+}
+
+/// Filesystem path for a document that doesn't exist yet, if `path` names a
+/// document inside its root. The file and maybe its directories are
+/// missing, so the path is checked lexically, then the deepest directory
+/// that does exist once resolved, which catches a symlinked directory
+/// leading out of the root.
+pub fn new_document_path(state: &AppState, path: &str) -> Result<PathBuf, StatusCode> {
+    let full_path = state.roots.resolve(path);
+    if !is_document_file(&full_path) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let root = state.roots.root_of(path);
+    if path.split(['/', '\\']).any(|s| s == "..") || !full_path.starts_with(root) {
+        log_to_file(&format!("[server] Rejected path traversal attempt: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let existing = full_path.ancestors().skip(1).find(|p| p.is_dir()).ok_or(StatusCode::NOT_FOUND)?;
+    let canonical = existing.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    let relative = existing.strip_prefix(root).map_err(|_| StatusCode::FORBIDDEN)?;
+    if !state.roots.contains_relative(root, relative, &canonical) {
+        log_to_file(&format!("[server] Rejected directory outside root: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(full_path)
+}
+
+/// POST /api/files/*path - Create a new document, with missing directories.
+/// 409 if the file exists already.
+pub async fn create_file(
+    state: &Arc<AppState>,
+    path: &str,
+    payload: CreateFileRequest,
+) -> Result<(StatusCode, Json<CreateFileResponse>), StatusCode> {
+    log_to_file(&format!("[server] POST /api/files/{}", path));
+    let full_path = new_document_path(state, path)?;
+
+    let body = match (payload.template, payload.content) {
+        (Some(_), Some(_)) => return Err(StatusCode::BAD_REQUEST),
+        (Some(template), None) => {
+            let template_path = state.roots.resolve(&template);
+            let canonical = template_path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+            if !state.roots.contains(&template, &canonical) {
+                return Err(StatusCode::FORBIDDEN);
+            }
+            let template = std::fs::read_to_string(&canonical).map_err(|_| StatusCode::NOT_FOUND)?;
+            render_template(&template, path, timezone::now(&state.config, None)?)
+        }
+        (None, content) => content.unwrap_or_default(),
+    };
+    let file_content = match &payload.frontmatter {
+        Some(frontmatter) => serialize_document(&full_path, frontmatter, &body),
+        None => body,
+    };
+
+    std::fs::create_dir_all(full_path.parent().unwrap_or(state.roots.root_of(path))).map_err(|e| {
+        log_to_file(&format!("[server] POST failed to create directory: {}", e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // Refuses to replace a file, even one written since the checks above
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&full_path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => StatusCode::CONFLICT,
+            _ => {
+                log_to_file(&format!("[server] POST failed to create: {}", e));
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+    file.write_all(file_content.as_bytes()).map_err(|e| {
+        log_to_file(&format!("[server] POST failed to write: {}", e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    log_to_file(&format!("[server] POST created: {}", path));
+    dirty::apply_now(state, vec![full_path]).await;
+    Ok((StatusCode::CREATED, Json(CreateFileResponse { path: path.to_string() })))
+}
@@ -0,0 +1,298 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::server::document::is_org_file;
+use crate::server::org::parse_headings;
+use crate::server::{log_to_file, AppState};
+
+/// Tag org-crypt uses to mark subtrees for encryption
+const CRYPT_TAG: &str = "crypt";
+
+/// Header carrying the session token from `POST /api/crypt/unlock`
+pub const CRYPT_SESSION_HEADER: &str = "x-crypt-session";
+
+const PGP_BEGIN: &str = "-----BEGIN PGP MESSAGE-----";
+const PGP_END: &str = "-----END PGP MESSAGE-----";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedHeading {
+    pub line: usize,
+    pub title: String,
+}
+
+/// A `:crypt:` subtree's body, as 0-based line range (end exclusive)
+struct CryptBody {
+    start: usize,
+    end: usize,
+    /// `:CRYPTKEY:` property — public-key recipient instead of symmetric passphrase
+    key: Option<String>,
+}
+
+/// Locate outermost `:crypt:` subtrees of the file at `path`. The body starts
+/// after the heading's planning line and property drawer, which org-crypt
+/// leaves in plain text. Only org files have them; a Markdown `* item` isn't
+/// a heading.
+fn crypt_bodies(path: &Path, content: &str) -> Vec<(usize, String, CryptBody)> {
+    if !is_org_file(path) {
+        return Vec::new();
+    }
+    let lines: Vec<&str> = content.lines().collect();
+    let mut bodies = Vec::new();
+    let mut covered_until = 0;
+
+    for heading in parse_headings(content) {
+        if heading.line <= covered_until || !heading.tags.iter().any(|t| t == CRYPT_TAG) {
+            continue;
+        }
+
+        let mut start = heading.line;
+        let mut in_drawer = false;
+        while start < heading.subtree_end {
+            let trimmed = lines[start].trim();
+            if in_drawer {
+                in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+            } else if trimmed.eq_ignore_ascii_case(":PROPERTIES:") {
+                in_drawer = true;
+            } else if !(trimmed.starts_with("SCHEDULED:")
+                || trimmed.starts_with("DEADLINE:")
+                || trimmed.starts_with("CLOSED:"))
+            {
+                break;
+            }
+            start += 1;
+        }
+
+        covered_until = heading.subtree_end;
+        bodies.push((
+            heading.line,
+            heading.title.clone(),
+            CryptBody {
+                start,
+                end: heading.subtree_end,
+                key: heading.properties.get("CRYPTKEY").cloned(),
+            },
+        ));
+    }
+
+    bodies
+}
+
+/// List `:crypt:` headings whose bodies are currently ASCII-armored
+pub fn find_encrypted(path: &Path, content: &str) -> Vec<EncryptedHeading> {
+    let lines: Vec<&str> = content.lines().collect();
+    crypt_bodies(path, content)
+        .into_iter()
+        .filter(|(_, _, body)| lines[body.start..body.end].iter().any(|l| l.trim() == PGP_BEGIN))
+        .map(|(line, title, _)| EncryptedHeading { line, title })
+        .collect()
+}
+
+/// Run gpg with the passphrase fed on stdin ahead of the payload. Input is
+/// written from its own task while the output is read, since gpg stops
+/// reading once its stdout pipe fills up.
+async fn run_gpg(args: &[&str], passphrase: &str, input: &str) -> Result<String, String> {
+    let mut child = Command::new("gpg")
+        .args(["--batch", "--quiet", "--yes", "--pinentry-mode", "loopback", "--passphrase-fd", "0"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start gpg: {}", e))?;
+
+    // Dropping stdin at the end of the task closes it, so gpg sees the end
+    // of the payload
+    let writer = child.stdin.take().map(|mut stdin| {
+        let payload = format!("{}\n{}", passphrase, input);
+        tokio::spawn(async move { stdin.write_all(payload.as_bytes()).await })
+    });
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("gpg failed: {}", e))?;
+    // gpg's own complaint explains a failure better than the broken pipe
+    // it leaves the writer with
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    if let Some(writer) = writer {
+        writer
+            .await
+            .map_err(|e| format!("Failed to write to gpg: {}", e))?
+            .map_err(|e| format!("Failed to write to gpg: {}", e))?;
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The ASCII-armored message in a `:crypt:` body, if it's encrypted
+fn armor_in(block: &str) -> Option<&str> {
+    match (block.find(PGP_BEGIN), block.find(PGP_END)) {
+        (Some(b), Some(e)) if e > b => Some(&block[b..e + PGP_END.len()]),
+        _ => None,
+    }
+}
+
+/// Replace armored `:crypt:` bodies with their decrypted text
+pub async fn decrypt_content(path: &Path, content: &str, passphrase: &str) -> Result<String, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut output: Vec<String> = Vec::new();
+    let mut cursor = 0;
+
+    for (_, _, body) in crypt_bodies(path, content) {
+        let block = lines[body.start..body.end].join("\n");
+        let armor = match armor_in(&block) {
+            Some(armor) => armor,
+            None => continue,
+        };
+
+        let plain = run_gpg(&["--decrypt"], passphrase, &format!("{}\n", armor)).await?;
+
+        output.extend(lines[cursor..body.start].iter().map(|l| l.to_string()));
+        output.push(plain.trim_end_matches('\n').to_string());
+        cursor = body.end;
+    }
+
+    output.extend(lines[cursor..].iter().map(|l| l.to_string()));
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// Encrypt any `:crypt:` bodies that are in plain text. Bodies with a
+/// `:CRYPTKEY:` property are encrypted to that recipient, others symmetrically.
+pub async fn encrypt_content(path: &Path, content: &str, passphrase: &str) -> Result<String, String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut output: Vec<String> = Vec::new();
+    let mut cursor = 0;
+
+    for (_, _, body) in crypt_bodies(path, content) {
+        let block = lines[body.start..body.end].join("\n");
+        if block.trim().is_empty() || block.contains(PGP_BEGIN) {
+            continue;
+        }
+
+        let armor = match &body.key {
+            Some(key) => run_gpg(&["--armor", "--encrypt", "--recipient", key], passphrase, &block).await?,
+            None => run_gpg(&["--armor", "--symmetric"], passphrase, &block).await?,
+        };
+
+        output.extend(lines[cursor..body.start].iter().map(|l| l.to_string()));
+        output.push(armor.trim_end_matches('\n').to_string());
+        cursor = body.end;
+    }
+
+    output.extend(lines[cursor..].iter().map(|l| l.to_string()));
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// True if any `:crypt:` subtree currently holds plain text
+pub fn has_plaintext_crypt(path: &Path, content: &str) -> bool {
+    let lines: Vec<&str> = content.lines().collect();
+    crypt_bodies(path, content).into_iter().any(|(_, _, body)| {
+        let block = lines[body.start..body.end].join("\n");
+        !block.trim().is_empty() && !block.contains(PGP_BEGIN)
+    })
+}
+
+/// Look up the passphrase for the request's crypt session header, if any
+pub async fn session_passphrase(state: &AppState, headers: &HeaderMap) -> Option<String> {
+    let token = headers.get(CRYPT_SESSION_HEADER)?.to_str().ok()?;
+    state.crypt_sessions.read().await.get(token).cloned()
+}
+
+/// Some symmetrically encrypted `:crypt:` body in the vault, to try a
+/// passphrase on. Bodies encrypted to a `:CRYPTKEY:` need that key in the
+/// keyring, so they can't tell a wrong passphrase from a missing key.
+async fn sample_armor(state: &AppState) -> Option<String> {
+    let paths: Vec<String> = state
+        .index
+        .read()
+        .await
+        .documents_with_headings()
+        .filter(|(_, headings)| headings.iter().any(|h| h.tags.iter().any(|t| t == CRYPT_TAG)))
+        .map(|(doc, _)| doc.path.clone())
+        .collect();
+    for path in paths {
+        let full_path = state.roots.resolve(&path);
+        let content = match tokio::fs::read_to_string(&full_path).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        for (_, _, body) in crypt_bodies(&full_path, &content) {
+            let block = lines[body.start..body.end].join("\n");
+            if let (None, Some(armor)) = (&body.key, armor_in(&block)) {
+                return Some(armor.to_string());
+            }
+        }
+    }
+    None
+}
+
+#[derive(Deserialize)]
+pub struct UnlockRequest {
+    passphrase: String,
+}
+
+#[derive(Serialize)]
+pub struct UnlockResponse {
+    session: String,
+}
+
+/// POST /api/crypt/unlock - Start a decryption session. The passphrase is held
+/// in memory only and dropped on lock or server restart. It's first tried on
+/// an encrypted subtree in the vault, when there is one, so a mistyped
+/// passphrase gets 401 rather than encrypting new subtrees with it.
+pub async fn unlock(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<UnlockRequest>,
+) -> Result<Json<UnlockResponse>, StatusCode> {
+    if let Some(armor) = sample_armor(&state).await {
+        // Without the symmetric key cache, gpg-agent can't stand in for a
+        // wrong passphrase with one it remembers
+        let args = ["--no-symkey-cache", "--decrypt"];
+        if let Err(e) = run_gpg(&args, &payload.passphrase, &format!("{}\n", armor)).await {
+            log_to_file(&format!("[crypt] Unlock rejected: {}", e));
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    let session = uuid::Uuid::new_v4().to_string();
+    state
+        .crypt_sessions
+        .write()
+        .await
+        .insert(session.clone(), payload.passphrase);
+    log_to_file("[crypt] Session unlocked");
+    Ok(Json(UnlockResponse { session }))
+}
+
+/// POST /api/crypt/lock - End the decryption session named by the session header
+pub async fn lock(State(state): State<Arc<AppState>>, headers: HeaderMap) -> StatusCode {
+    let token = match headers.get(CRYPT_SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(t) => t.to_string(),
+        None => return StatusCode::BAD_REQUEST,
+    };
+    if state.crypt_sessions.write().await.remove(&token).is_some() {
+        log_to_file("[crypt] Session locked");
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
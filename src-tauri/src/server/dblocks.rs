@@ -0,0 +1,248 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::server::effort::{parse_clock_minutes, parse_org_timestamp};
+use crate::server::org::{format_minutes, parse_headings, Heading};
+use crate::server::tables::align_table;
+use crate::server::timezone;
+use crate::server::{log_to_file, AppState};
+
+/// Split `:key value :flag :other "quoted value"` into a parameter map
+fn parse_params(args: &str) -> HashMap<String, String> {
+    let token_re = Regex::new(r#""[^"]*"|\S+"#).unwrap();
+    let mut params = HashMap::new();
+    let mut key: Option<String> = None;
+
+    for token in token_re.find_iter(args).map(|m| m.as_str()) {
+        if let Some(name) = token.strip_prefix(':') {
+            if let Some(k) = key.take() {
+                params.insert(k, String::new());
+            }
+            key = Some(name.to_lowercase());
+        } else if let Some(k) = key.take() {
+            params.insert(k, token.trim_matches('"').to_string());
+        }
+    }
+    if let Some(k) = key {
+        params.insert(k, String::new());
+    }
+    params
+}
+
+/// Clock range for `:block` / `:tstart` / `:tend`, as [start, end)
+fn clock_range(params: &HashMap<String, String>, today: NaiveDate) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+    let midnight = |d: NaiveDate| d.and_hms_opt(0, 0, 0);
+    let month_start = |year: i32, month: u32| NaiveDate::from_ymd_opt(year, month, 1);
+    let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let this_month = month_start(today.year(), today.month());
+    let last_month = this_month.and_then(|m| m.pred_opt()).and_then(|d| month_start(d.year(), d.month()));
+    let this_year = NaiveDate::from_ymd_opt(today.year(), 1, 1);
+    let last_year = NaiveDate::from_ymd_opt(today.year() - 1, 1, 1);
+
+    let block = match params.get("block").map(|b| b.as_str()) {
+        Some("today") => Some((Some(today), Some(today + Duration::days(1)))),
+        Some("yesterday") => Some((Some(today - Duration::days(1)), Some(today))),
+        Some("thisweek") => Some((Some(week_start), Some(week_start + Duration::days(7)))),
+        Some("lastweek") => Some((Some(week_start - Duration::days(7)), Some(week_start))),
+        Some("thismonth") => Some((this_month, this_month.and_then(|m| m.checked_add_months(chrono::Months::new(1))))),
+        Some("lastmonth") => Some((last_month, this_month)),
+        Some("thisyear") => Some((this_year, NaiveDate::from_ymd_opt(today.year() + 1, 1, 1))),
+        Some("lastyear") => Some((last_year, this_year)),
+        _ => None,
+    };
+    if let Some((start, end)) = block {
+        return (start.and_then(midnight), end.and_then(midnight));
+    }
+
+    (
+        params.get("tstart").and_then(|t| parse_org_timestamp(t)),
+        params.get("tend").and_then(|t| parse_org_timestamp(t)),
+    )
+}
+
+/// Render a clocktable body for the block starting at 0-based `block_line`
+fn render_clocktable(
+    lines: &[&str],
+    headings: &[Heading],
+    block_line: usize,
+    args: &str,
+    now: NaiveDateTime,
+) -> Vec<String> {
+    let params = parse_params(args);
+    let max_level: usize = params.get("maxlevel").and_then(|m| m.parse().ok()).unwrap_or(3);
+    let (start, end) = clock_range(&params, now.date());
+    let clock_start_re = Regex::new(r"^\s*CLOCK:\s*\[([^\]]+)\]").unwrap();
+
+    // Limit to the enclosing subtree (`subtree`) or top-level tree (`tree`)
+    let enclosing = headings
+        .iter()
+        .filter(|h| h.line - 1 < block_line && block_line < h.subtree_end)
+        .collect::<Vec<_>>();
+    let scope = match params.get("scope").map(|s| s.as_str()) {
+        Some("subtree") => enclosing.last().map(|h| (h.line, h.subtree_end)),
+        Some("tree") => enclosing.first().map(|h| (h.line, h.subtree_end)),
+        _ => None,
+    }
+    .unwrap_or((1, lines.len()));
+
+    // Minutes clocked directly on each heading within the range
+    let own: Vec<i64> = headings
+        .iter()
+        .map(|h| {
+            lines[h.line..h.section_end]
+                .iter()
+                .filter(|l| {
+                    let started = clock_start_re
+                        .captures(l)
+                        .and_then(|c| parse_org_timestamp(&c[1]));
+                    match started {
+                        Some(t) => start.is_none_or(|s| t >= s) && end.is_none_or(|e| t < e),
+                        None => false,
+                    }
+                })
+                .filter_map(|l| parse_clock_minutes(l))
+                .sum()
+        })
+        .collect();
+
+    let mut rows: Vec<(usize, String, i64)> = Vec::new();
+    let mut total = 0;
+    for (idx, h) in headings.iter().enumerate() {
+        if h.line < scope.0 || h.line > scope.1 {
+            continue;
+        }
+        total += own[idx];
+        let subtree: i64 = headings[idx..]
+            .iter()
+            .zip(&own[idx..])
+            .take_while(|(sub, _)| sub.line <= h.subtree_end)
+            .map(|(_, minutes)| minutes)
+            .sum();
+        if h.level <= max_level && subtree > 0 {
+            rows.push((h.level, h.title.clone(), subtree));
+        }
+    }
+
+    // One time column per heading level shown, like org's own layout
+    let columns = rows.iter().map(|r| r.0).max().unwrap_or(1);
+    let empty = |n: usize| vec![String::new(); n];
+
+    let mut header = vec!["Headline".to_string(), "Time".to_string()];
+    header.extend(empty(columns - 1));
+    let mut total_row = vec!["*Total time*".to_string(), format!("*{}*", format_minutes(total))];
+    total_row.extend(empty(columns - 1));
+    let rule = format!("|{}|", vec!["-"; columns + 1].join("+"));
+
+    let mut table = vec![
+        format!("| {} |", header.join(" | ")),
+        rule.clone(),
+        format!("| {} |", total_row.join(" | ")),
+    ];
+    if !rows.is_empty() {
+        table.push(rule);
+    }
+    for (level, title, minutes) in rows {
+        let indent = if level > 1 { format!("\\_{} ", " ".repeat(2 * (level - 1) - 1)) } else { String::new() };
+        let mut cells = vec![format!("{}{}", indent, title.replace('|', "\\vert"))];
+        cells.extend(empty(level - 1));
+        cells.push(format_minutes(minutes));
+        cells.extend(empty(columns - level));
+        table.push(format!("| {} |", cells.join(" | ")));
+    }
+
+    let mut body = vec![format!(
+        "#+CAPTION: Clock summary at {}",
+        now.format("[%Y-%m-%d %a %H:%M]")
+    )];
+    body.extend(align_table(&table));
+    body
+}
+
+/// Regenerate every `#+BEGIN: clocktable` block, returning the new content and
+/// the number of blocks updated. Supports `:maxlevel`, `:scope file|subtree|tree`,
+/// `:block today|yesterday|thisweek|lastweek|thismonth|lastmonth|thisyear|lastyear`
+/// and `:tstart` / `:tend`, with relative blocks measured from `now`.
+pub fn update_clocktables(content: &str, now: NaiveDateTime) -> (String, usize) {
+    let begin_re = Regex::new(r"(?i)^\s*#\+BEGIN:\s+clocktable\b(.*)$").unwrap();
+    let end_re = Regex::new(r"(?i)^\s*#\+END:?\s*$").unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    let headings = parse_headings(content);
+
+    let mut output: Vec<String> = Vec::new();
+    let mut updated = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        let caps = match begin_re.captures(lines[i]) {
+            Some(c) => c,
+            None => {
+                output.push(lines[i].to_string());
+                i += 1;
+                continue;
+            }
+        };
+        let end = match lines[i + 1..].iter().position(|l| end_re.is_match(l)) {
+            Some(offset) => i + 1 + offset,
+            None => {
+                output.push(lines[i].to_string());
+                i += 1;
+                continue;
+            }
+        };
+
+        output.push(lines[i].to_string());
+        output.extend(render_clocktable(&lines, &headings, i, &caps[1], now));
+        output.push(lines[end].to_string());
+        updated += 1;
+        i = end + 1;
+    }
+
+    let mut new_content = output.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    (new_content, updated)
+}
+
+#[derive(Serialize)]
+pub struct UpdateDblocksResponse {
+    updated: usize,
+}
+
+/// POST /api/files/*path/update-dblocks - Recompute clocktable dynamic blocks from CLOCK lines
+pub async fn update_dblocks(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<UpdateDblocksResponse>, StatusCode> {
+    // Validate path - prevent directory traversal
+    let canonical_path = state.roots.resolve(&path).canonicalize()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&path, &canonical_path) {
+        log_to_file(&format!("[dblocks] Rejected path traversal: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let content = tokio::fs::read_to_string(&canonical_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let now = timezone::now(&state.config, None)?;
+    let (new_content, updated) = update_clocktables(&content, now);
+
+    if updated > 0 {
+        if let Err(e) = tokio::fs::write(&canonical_path, new_content).await {
+            log_to_file(&format!("[dblocks] Failed to write: {}", e));
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        log_to_file(&format!("[dblocks] Updated {} clocktable(s) in {}", updated, path));
+    }
+
+    // File watcher will auto-refresh index
+    Ok(Json(UpdateDblocksResponse { updated }))
+}
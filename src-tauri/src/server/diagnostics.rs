@@ -0,0 +1,59 @@
+use axum::{extract::State, response::Json};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::server::index::{LoadReport, MemoryEstimate};
+use crate::server::store::DB_FILENAME;
+use crate::server::AppState;
+
+/// Key for documents directly in the org root
+const ROOT_DIR: &str = ".";
+
+#[derive(Serialize)]
+pub struct IndexDiagnostics {
+    documents: usize,
+    headings: usize,
+    /// Absent until the startup load has finished
+    #[serde(rename = "lastLoad", skip_serializing_if = "Option::is_none")]
+    last_load: Option<LoadReport>,
+    /// Estimated bytes, from serialized sizes
+    memory: MemoryEstimate,
+    /// Size of the on-disk index cache
+    #[serde(rename = "cacheFileBytes")]
+    cache_file_bytes: u64,
+    /// Indexed documents per directory
+    directories: BTreeMap<String, usize>,
+}
+
+/// GET /api/index/stats - Index size, last load timings and cache use, for
+/// debugging slow startups
+pub async fn get_index_stats(State(state): State<Arc<AppState>>) -> Json<IndexDiagnostics> {
+    let index = state.index.read().await;
+    let docs = index.get_documents();
+
+    let mut directories: BTreeMap<String, usize> = BTreeMap::new();
+    for doc in &docs {
+        let dir = match doc.path.rsplit_once('/') {
+            Some((dir, _)) => dir,
+            None => ROOT_DIR,
+        };
+        *directories.entry(dir.to_string()).or_insert(0) += 1;
+    }
+
+    // Recent writes sit in the write-ahead log until checkpointed
+    let cache_file_bytes = [DB_FILENAME.to_string(), format!("{}-wal", DB_FILENAME)]
+        .iter()
+        .filter_map(|name| std::fs::metadata(state.org_root.join(name)).ok())
+        .map(|m| m.len())
+        .sum();
+
+    Json(IndexDiagnostics {
+        documents: docs.len(),
+        headings: docs.iter().map(|d| index.get_headings(&d.path).len()).sum(),
+        last_load: index.last_load().cloned(),
+        memory: index.memory_estimate(),
+        cache_file_bytes,
+        directories,
+    })
+}
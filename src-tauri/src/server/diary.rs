@@ -0,0 +1,216 @@
+use chrono::{Datelike, NaiveDate};
+
+use crate::server::org::Heading;
+
+/// A `%%(...)` diary sexp found in a document
+#[derive(Debug, Clone)]
+pub struct DiaryEntry {
+    /// 1-based line of the owning heading, or of the entry itself when bare
+    pub line: usize,
+    /// Index into the document's headings when the sexp is a heading timestamp
+    pub heading: Option<usize>,
+    pub sexp: String,
+    /// Text after a bare `%%(...)` line
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Arg {
+    Int(i64),
+    /// `t`, matching any value
+    Any,
+    List(Vec<i64>),
+    Str(String),
+}
+
+impl Arg {
+    fn matches(&self, value: i64) -> bool {
+        match self {
+            Arg::Int(n) => *n == value,
+            Arg::Any => true,
+            Arg::List(values) => values.contains(&value),
+            Arg::Str(_) => false,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match self {
+            Arg::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// The balanced `(...)` at the start of `s`
+fn balanced(s: &str) -> Option<&str> {
+    let mut depth = 0;
+    let mut in_string = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&s[..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split `(name arg ...)` into the function name and its arguments
+fn parse_sexp(sexp: &str) -> Option<(String, Vec<Arg>)> {
+    let inner = sexp.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = inner.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let text: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            tokens.push(format!("\"{}\"", text));
+        } else if c == '(' || c == '\'' {
+            // Quoted or bare list of numbers
+            let list: String = chars.by_ref().skip_while(|&c| c != '(').skip(1).take_while(|&c| c != ')').collect();
+            tokens.push(format!("({})", list));
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+
+    let mut tokens = tokens.into_iter();
+    let name = tokens.next()?;
+    let args = tokens
+        .map(|t| {
+            if t == "t" || t == "nil" {
+                Arg::Any
+            } else if let Some(list) = t.strip_prefix('(').and_then(|l| l.strip_suffix(')')) {
+                Arg::List(list.split_whitespace().filter_map(|n| n.parse().ok()).collect())
+            } else if let Some(text) = t.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                Arg::Str(text.to_string())
+            } else {
+                t.parse().map(Arg::Int).unwrap_or(Arg::Str(t))
+            }
+        })
+        .collect();
+    Some((name, args))
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|d| d.pred_opt())
+        .map(|d| d.day())
+        .unwrap_or(28)
+}
+
+/// Evaluate a diary sexp for one day. Returns `None` when it doesn't apply,
+/// otherwise the count for `%d` substitution (years for anniversaries, repeats
+/// for cyclic entries). Dates use Emacs' default american order (month day year),
+/// except `org-anniversary` which takes year month day.
+pub fn diary_match(sexp: &str, date: NaiveDate) -> Option<Option<i64>> {
+    let (name, args) = parse_sexp(sexp)?;
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(Arg::Any);
+    let (year, month, day) = (date.year() as i64, date.month() as i64, date.day() as i64);
+
+    match name.as_str() {
+        "diary-date" => (arg(0).matches(month) && arg(1).matches(day) && arg(2).matches(year)).then_some(None),
+        "diary-block" => {
+            let start = NaiveDate::from_ymd_opt(arg(2).int()? as i32, arg(0).int()? as u32, arg(1).int()? as u32)?;
+            let end = NaiveDate::from_ymd_opt(arg(5).int()? as i32, arg(3).int()? as u32, arg(4).int()? as u32)?;
+            (date >= start && date <= end).then_some(None)
+        }
+        "diary-anniversary" | "org-anniversary" => {
+            let (m, d, y) = if name == "org-anniversary" { (arg(1), arg(2), arg(0)) } else { (arg(0), arg(1), arg(2)) };
+            let years = y.int().map(|y| year - y);
+            (m.matches(month) && d.matches(day) && years.is_none_or(|n| n > 0)).then_some(years)
+        }
+        "diary-cyclic" => {
+            let every = arg(0).int().filter(|n| *n > 0)?;
+            let start = NaiveDate::from_ymd_opt(arg(3).int()? as i32, arg(1).int()? as u32, arg(2).int()? as u32)?;
+            let elapsed = (date - start).num_days();
+            (elapsed >= 0 && elapsed % every == 0).then_some(Some(elapsed / every + 1))
+        }
+        "diary-float" => {
+            // (diary-float MONTH DAYNAME N &optional DAY): the Nth DAYNAME of the month,
+            // counting back from the end for negative N; DAYNAME 0 is Sunday
+            let n = arg(2).int().filter(|n| *n != 0)?;
+            let weekday = date.weekday().num_days_from_sunday() as i64;
+            if !arg(0).matches(month) || !arg(1).matches(weekday) {
+                return None;
+            }
+            let matched = if n > 0 {
+                let first = arg(3).int().unwrap_or(1);
+                day >= first && (day - first) / 7 + 1 == n
+            } else {
+                let last = arg(3).int().unwrap_or(last_day_of_month(date) as i64);
+                day <= last && (last - day) / 7 + 1 == -n
+            };
+            matched.then_some(None)
+        }
+        _ => None,
+    }
+}
+
+/// Fill `%d` (count) and `%s` (its ordinal suffix) in an entry's text
+pub fn format_entry(text: &str, count: Option<i64>) -> String {
+    let n = match count {
+        Some(n) => n,
+        None => return text.to_string(),
+    };
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    text.replace("%d", &n.to_string()).replace("%s", suffix)
+}
+
+/// Find bare `%%(...) text` lines and `<%%(...)>` timestamps under headings
+pub fn find_diary_entries(content: &str, headings: &[Heading]) -> Vec<DiaryEntry> {
+    let mut entries = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if let Some(rest) = line.trim_start().strip_prefix("%%") {
+            if let Some(sexp) = balanced(rest) {
+                entries.push(DiaryEntry {
+                    line: i + 1,
+                    heading: None,
+                    sexp: sexp.to_string(),
+                    text: rest[sexp.len()..].trim().to_string(),
+                });
+                continue;
+            }
+        }
+
+        let owner = headings.iter().rposition(|h| h.line <= i + 1 && i < h.section_end);
+        let owner = match owner {
+            Some(idx) => idx,
+            None => continue,
+        };
+        for (at, _) in line.match_indices("<%%") {
+            if let Some(sexp) = balanced(&line[at + 3..]) {
+                entries.push(DiaryEntry {
+                    line: headings[owner].line,
+                    heading: Some(owner),
+                    sexp: sexp.to_string(),
+                    text: String::new(),
+                });
+            }
+        }
+    }
+    entries
+}
@@ -0,0 +1,269 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::server::events::EventKind;
+use crate::server::index::{AppliedChanges, DocumentIndex, StaleLink};
+use crate::server::patch::{document_patch, Patch};
+use crate::server::{collab, log_to_file, recent, reconcile, semantic, AppState};
+
+/// Quiet period that ends a batch when `watchDebounceMs` isn't configured:
+/// changes arriving closer together than this are reindexed together, and
+/// repeated events for one file collapse into a single reparse
+pub const DEFAULT_DEBOUNCE_MS: u64 = 200;
+
+/// Longest a change waits while a burst keeps going, unless the debounce
+/// window is longer
+const BATCH_MAX_WAIT: Duration = Duration::from_secs(2);
+
+/// Batches up to this size get a WS message per document; larger ones a
+/// single `bulk-updated`
+const BULK_THRESHOLD: usize = 10;
+
+/// More distinct files than this within STORM_WINDOW of a batch starting is
+/// a bulk change, like `git checkout` or `git pull`
+const STORM_FILES: usize = 50;
+const STORM_WINDOW: Duration = Duration::from_secs(1);
+
+/// A bulk change is over once no changes arrive for this long, or the
+/// debounce window if that's longer
+const STORM_QUIET: Duration = Duration::from_secs(1);
+
+/// Longest a bulk change is waited out before rescanning anyway
+const STORM_MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// Files changed on disk and waiting to be reindexed. The watcher and the
+/// reconciliation scan push paths; a worker drains them in batches, so a
+/// burst like a `git pull` costs one backlink rebuild and one broadcast
+/// rather than one per file.
+#[derive(Clone)]
+pub struct DirtyQueue {
+    tx: mpsc::UnboundedSender<PathBuf>,
+}
+
+impl DirtyQueue {
+    /// The queue and its receiving end, to hand to `run`
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<PathBuf>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, rx)
+    }
+
+    /// Mark a document file as changed or deleted; which one is decided when
+    /// its batch is applied
+    pub fn push(&self, path: PathBuf) {
+        let _ = self.tx.send(path);
+    }
+}
+
+/// Drain the queue until the server stops. A bulk change isn't processed
+/// file by file: its paths are dropped until it's over, then one rescan of
+/// the roots is applied as a single batch with a single message.
+pub async fn run(state: Arc<AppState>, mut rx: mpsc::UnboundedReceiver<PathBuf>) {
+    let window = Duration::from_millis(state.config.debounce_ms);
+    let max_wait = BATCH_MAX_WAIT.max(window);
+    while let Some(first) = rx.recv().await {
+        let started = Instant::now();
+        let mut deadline = started + max_wait;
+        let mut seen = HashSet::from([first.clone()]);
+        let mut batch = vec![first];
+        let mut storm = false;
+        loop {
+            let wait = if storm { STORM_QUIET.max(window) } else { window };
+            let wait = wait.min(deadline.saturating_duration_since(Instant::now()));
+            match tokio::time::timeout(wait, rx.recv()).await {
+                Ok(Some(_)) if storm => {}
+                Ok(Some(path)) => {
+                    if seen.insert(path.clone()) && seen.len() > STORM_FILES && started.elapsed() <= STORM_WINDOW {
+                        log_to_file(&format!(
+                            "Bulk change: {} files within {:?}; rescanning once it's over",
+                            seen.len(),
+                            STORM_WINDOW
+                        ));
+                        storm = true;
+                        deadline = started + STORM_MAX_WAIT;
+                        batch.clear();
+                        seen.clear();
+                        continue;
+                    }
+                    batch.push(path);
+                }
+                _ => break,
+            }
+        }
+
+        if storm {
+            match reconcile::scan_changes(&state).await {
+                Some((changed, removed)) => apply_bulk(&state, changed, removed).await,
+                None => continue,
+            }
+        } else {
+            apply(&state, batch).await;
+        }
+    }
+}
+
+/// Apply the rescan after a bulk change
+async fn apply_bulk(state: &Arc<AppState>, changed: Vec<PathBuf>, removed: Vec<PathBuf>) {
+    log_to_file(&format!(
+        "Bulk change over: {} changed and {} removed files",
+        changed.len(),
+        removed.len()
+    ));
+    run_batch(state, changed, removed, true).await;
+}
+
+/// Reindex files the server itself just created, moved or removed, and tell
+/// clients, without waiting for the watcher, so a client can open a file as
+/// soon as its request returns. The watcher then finds nothing left to do.
+pub async fn apply_now(state: &Arc<AppState>, paths: Vec<PathBuf>) {
+    apply(state, paths).await;
+}
+
+async fn apply(state: &Arc<AppState>, batch: Vec<PathBuf>) {
+    let mut seen = HashSet::new();
+    let (changed, removed): (Vec<PathBuf>, Vec<PathBuf>) = batch
+        .into_iter()
+        .filter(|p| seen.insert(p.clone()))
+        .partition(|p| DocumentIndex::is_indexable(&state.roots, p));
+    run_batch(state, changed, removed, false).await;
+}
+
+/// Reindex a batch and tell clients: a message per document for small
+/// batches, one `bulk-updated` for large ones or when `bulk` is set
+async fn run_batch(state: &Arc<AppState>, changed: Vec<PathBuf>, removed: Vec<PathBuf>, bulk: bool) {
+    // Parsing is CPU-bound and a single huge file can take a while, so the
+    // batch runs on a blocking thread, holding the index lock the whole time
+    let mut index = state.index.clone().write_owned().await;
+    let changes = match tokio::task::spawn_blocking(move || index.apply_changes(&changed, &removed)).await {
+        Ok(result) => result,
+        Err(e) => {
+            log_to_file(&format!("Reindexing changed files failed: {}", e));
+            return;
+        }
+    };
+    let AppliedChanges {
+        updated,
+        removed: gone,
+        renamed,
+        linked_from,
+        previous,
+    } = changes;
+    if updated.is_empty() && gone.is_empty() && renamed.is_empty() {
+        return;
+    }
+
+    // Links still written against a renamed document's old path, so clients
+    // can offer to fix them
+    let stale: Vec<Vec<StaleLink>> = if renamed.is_empty() {
+        Vec::new()
+    } else {
+        let index = state.index.read().await;
+        renamed.iter().map(|(from, to)| index.stale_links(from, to)).collect()
+    };
+    let renames: Vec<serde_json::Value> = renamed
+        .iter()
+        .zip(&stale)
+        .map(|((from, to), links)| rename_json(from, to, links))
+        .collect();
+
+    let deletions: Vec<serde_json::Value> = gone
+        .iter()
+        .map(|path| {
+            serde_json::json!({
+                "path": path,
+                "linkedFrom": linked_from.get(path).cloned().unwrap_or_default()
+            })
+        })
+        .collect();
+
+    if bulk || updated.len() + gone.len() + renamed.len() > BULK_THRESHOLD {
+        log_to_file(&format!(
+            "Files changed: {} updated, {} removed, {} renamed",
+            updated.len(),
+            gone.len(),
+            renamed.len()
+        ));
+        let payload = serde_json::json!({
+            "paths": updated,
+            "deleted": deletions,
+            "renamed": renames
+        });
+        state.events.send(EventKind::FilesChanged, None, payload);
+    } else {
+        // Viewers of a document that was recently served get a patch to
+        // apply in place rather than refetching it
+        let mut patches: HashMap<&str, Patch> = HashMap::new();
+        if !previous.is_empty() {
+            let index = state.index.read().await;
+            for (path, old) in &previous {
+                let new = match tokio::fs::read_to_string(state.roots.resolve(path)).await {
+                    Ok(new) => new,
+                    Err(_) => continue,
+                };
+                if let Some(patch) = document_patch(state, &index, path, old, &new).await {
+                    patches.insert(path.as_str(), patch);
+                }
+            }
+        }
+        for path in &updated {
+            log_to_file(&format!("File changed: {}", path));
+            let payload = match patches.get(path.as_str()) {
+                Some(patch) => serde_json::json!({ "patch": patch }),
+                None => serde_json::json!({}),
+            };
+            state.events.send(EventKind::FileChanged, Some(path), payload);
+        }
+        // Clients close views of a deleted document rather than refetch it
+        // into a 404, and refresh the ones now linking to nothing
+        for (path, payload) in gone.iter().zip(deletions) {
+            log_to_file(&format!("File removed: {}", path));
+            state.events.send(EventKind::FileDeleted, Some(path), payload);
+        }
+        for ((from, to), payload) in renamed.iter().zip(renames) {
+            log_to_file(&format!("File renamed: {} -> {}", from, to));
+            state.events.send(EventKind::FileRenamed, Some(to), payload);
+        }
+    }
+
+    // Documents being edited together take in changes made around them
+    for path in &updated {
+        collab::file_changed(state, path).await;
+    }
+
+    // Views and embeddings follow a renamed document rather than being
+    // dropped with its old path
+    if !renamed.is_empty() {
+        recent::rename_views(state, &renamed);
+    }
+    let touched: Vec<String> = updated
+        .into_iter()
+        .chain(gone)
+        .chain(renamed.iter().map(|(_, to)| to.clone()))
+        .collect();
+    let state = state.clone();
+    tokio::spawn(async move {
+        semantic::rename(state.clone(), renamed).await;
+        semantic::refresh(state, Some(touched)).await;
+    });
+}
+
+/// A rename for events, with the links it left pointing at the old path
+fn rename_json(from: &str, to: &str, stale: &[StaleLink]) -> serde_json::Value {
+    if !stale.is_empty() {
+        log_to_file(&format!("{} links to {} now broken", stale.len(), from));
+    }
+    let mut msg = serde_json::json!({
+        "from": from,
+        "to": to,
+        "brokenLinks": stale.len(),
+        "links": stale
+    });
+    if !stale.is_empty() {
+        let noun = if stale.len() == 1 { "link" } else { "links" };
+        msg["warning"] = format!("{} {} now broken", stale.len(), noun).into();
+    }
+    msg
+}
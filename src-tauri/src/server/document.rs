@@ -1,23 +1,97 @@
+use crate::server::crypt::EncryptedHeading;
+use crate::server::effort::EffortRollup;
+use crate::server::footnotes::Footnote;
+use crate::server::highlight::SourceBlock;
+use crate::server::ids::{file_id, file_properties};
+use crate::server::logbook::TaskHistory;
+use crate::server::math::MathFragment;
+use crate::server::org::{Anchor, TodoKeywords};
 use gray_matter::{engine::YAML, Matter};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrgDocument {
     pub path: String,
     pub title: String,
+    /// File-level org `:ID:` property
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     #[serde(rename = "type")]
     pub doc_type: String,
     pub status: Option<String>,
     pub tags: Vec<String>,
+    /// Tags from `#+FILETAGS:`, inherited by every heading
+    #[serde(rename = "fileTags", default, skip_serializing_if = "Vec::is_empty")]
+    pub file_tags: Vec<String>,
+    /// `#+CATEGORY:` keyword
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Other names links may use for this document: `#+ALIAS:`, a file-level
+    /// `:ROAM_ALIASES:` property, or `aliases` in frontmatter
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
     pub created: Option<String>,
     pub updated: Option<String>,
     pub links: Vec<String>,
     pub backlinks: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    /// Attachment files (relative to org root), populated when content is loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+    /// Footnote definitions and references, populated when content is loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub footnotes: Vec<Footnote>,
+    /// LaTeX fragments pre-rendered to MathML, populated when content is loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub math: Vec<MathFragment>,
+    /// Syntax-highlighted src blocks, populated when content is loaded
+    #[serde(rename = "srcBlocks", default, skip_serializing_if = "Vec::is_empty")]
+    pub src_blocks: Vec<SourceBlock>,
+    /// Effort vs clocked time per subtree, populated when content is loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rollups: Vec<EffortRollup>,
+    /// LOGBOOK state changes, notes, and clocks per heading, populated when content is loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<TaskHistory>,
+    /// `:crypt:` headings whose bodies are still encrypted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub encrypted: Vec<EncryptedHeading>,
+    /// `:CUSTOM_ID:` deep-link anchors, populated when content is loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anchors: Vec<Anchor>,
+    /// Effective TODO workflow: the file's `#+TODO:` lines or the configured default
+    #[serde(rename = "todoKeywords", default, skip_serializing_if = "Option::is_none")]
+    pub todo_keywords: Option<TodoKeywords>,
+    /// Revision of the file as stored, to send back in `If-Match` when
+    /// saving; set when the document is served on its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+}
+
+/// File extensions indexed as documents: Markdown notes and org files
+pub const DOCUMENT_EXTENSIONS: &[&str] = &["md", "org"];
+
+/// Whether a file is a Markdown or org document
+pub fn is_document_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| DOCUMENT_EXTENSIONS.contains(&e))
+}
+
+/// A document path without its `.md`/`.org` extension, as wikilinks name it
+pub fn strip_document_extension(path: &str) -> &str {
+    DOCUMENT_EXTENSIONS
+        .iter()
+        .find_map(|ext| path.strip_suffix(ext).and_then(|p| p.strip_suffix('.')))
+        .unwrap_or(path)
+}
+
+pub fn is_org_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "org")
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -26,6 +100,7 @@ struct Frontmatter {
     doc_type: Option<String>,
     status: Option<String>,
     tags: Option<Vec<String>>,
+    aliases: Option<Vec<String>>,
     created: Option<String>,
     updated: Option<String>,
 }
@@ -34,11 +109,16 @@ pub fn parse_document(path: &Path, org_root: &Path, content: &str) -> OrgDocumen
     let matter = Matter::<YAML>::new();
     let result = matter.parse(content);
 
-    // Parse frontmatter
-    let frontmatter: Frontmatter = result
+    // Parse frontmatter. Org files carry the same fields as `#+KEYWORD:` lines,
+    // which Markdown files may use too when they have no YAML for a field.
+    let mut frontmatter: Frontmatter = result
         .data
         .and_then(|d| d.deserialize().ok())
         .unwrap_or_default();
+    frontmatter.doc_type = frontmatter.doc_type.or_else(|| file_keyword(content, "TYPE"));
+    frontmatter.status = frontmatter.status.or_else(|| file_keyword(content, "STATUS"));
+    frontmatter.created = frontmatter.created.or_else(|| file_keyword(content, "DATE"));
+    frontmatter.updated = frontmatter.updated.or_else(|| file_keyword(content, "UPDATED"));
 
     // Extract title from first heading or filename
     let title = extract_title(content, path);
@@ -59,23 +139,86 @@ pub fn parse_document(path: &Path, org_root: &Path, content: &str) -> OrgDocumen
     OrgDocument {
         path: relative_path,
         title,
+        id: file_id(content),
         doc_type,
         status: frontmatter.status,
         tags: frontmatter.tags.unwrap_or_default(),
+        file_tags: file_keyword(content, "FILETAGS")
+            .map(|tags| {
+                tags.split([':', ' '])
+                    .filter(|t| !t.is_empty())
+                    .map(|t| t.to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        category: file_keyword(content, "CATEGORY"),
+        aliases: extract_aliases(content, frontmatter.aliases.unwrap_or_default()),
         created: frontmatter.created,
         updated: frontmatter.updated,
         links,
         backlinks: Vec::new(), // Populated later
         content: None,
+        attachments: Vec::new(),
+        footnotes: Vec::new(),
+        math: Vec::new(),
+        src_blocks: Vec::new(),
+        rollups: Vec::new(),
+        history: Vec::new(),
+        encrypted: Vec::new(),
+        anchors: Vec::new(),
+        todo_keywords: None,
+        revision: None,
+    }
+}
+
+/// Value of the first `#+KEYWORD:` line in the file, if non-empty
+pub fn file_keyword(content: &str, keyword: &str) -> Option<String> {
+    let prefix = format!("#+{}:", keyword.to_lowercase());
+    content
+        .lines()
+        .map(|l| l.trim())
+        .find(|l| l.to_lowercase().starts_with(&prefix))
+        .map(|l| l[prefix.len()..].trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// The part of a document before its body: YAML front matter, the file's
+/// property drawer, `#+KEYWORD:` and blank lines, up to and including a
+/// first `# ` heading, which may be the title
+pub fn metadata_head(content: &str) -> &str {
+    let mut end = 0;
+    let mut in_frontmatter = false;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim();
+        if in_frontmatter {
+            in_frontmatter = trimmed != "---";
+        } else if i == 0 && trimmed == "---" {
+            in_frontmatter = true;
+        } else if trimmed.starts_with("# ") {
+            end += line.len();
+            break;
+        } else if !(trimmed.is_empty() || trimmed.starts_with("#+") || trimmed.starts_with(':')) {
+            break;
+        }
+        end += line.len();
     }
+    &content[..end]
 }
 
 fn extract_title(content: &str, path: &Path) -> String {
-    // Try to find first H1 heading
+    // An explicit #+TITLE wins
+    if let Some(title) = file_keyword(content, "TITLE") {
+        return title;
+    }
+
+    // Try to find first H1 heading. Org reads `# ...` as a comment, so org
+    // files without #+TITLE are named after the file, as Emacs does.
     let heading_re = Regex::new(r"^#\s+(.+)$").unwrap();
-    for line in content.lines() {
-        if let Some(caps) = heading_re.captures(line) {
-            return caps[1].to_string();
+    if !is_org_file(path) {
+        for line in content.lines() {
+            if let Some(caps) = heading_re.captures(line) {
+                return caps[1].to_string();
+            }
         }
     }
 
@@ -85,8 +228,30 @@ fn extract_title(content: &str, path: &Path) -> String {
         .unwrap_or_else(|| "Untitled".to_string())
 }
 
-fn extract_wikilinks(content: &str) -> Vec<String> {
-    let link_re = Regex::new(r"\[\[([^\]|]+)(?:\|[^\]]+)?\]\]").unwrap();
+/// Aliases from `#+ALIAS:` lines and `:ROAM_ALIASES:`, whose entries are
+/// space-separated with multi-word ones quoted: `"Some Title" other`
+fn extract_aliases(content: &str, mut aliases: Vec<String>) -> Vec<String> {
+    let keyword_re = Regex::new(r"(?im)^\s*#\+ALIAS:[ \t]*(.+?)\s*$").unwrap();
+    aliases.extend(keyword_re.captures_iter(content).map(|c| c[1].to_string()));
+
+    if let Some(roam) = file_properties(content).get("ROAM_ALIASES") {
+        let entry_re = Regex::new(r#""([^"]+)"|(\S+)"#).unwrap();
+        aliases.extend(
+            entry_re
+                .captures_iter(roam)
+                .filter_map(|c| c.get(1).or(c.get(2)))
+                .map(|m| m.as_str().to_string()),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    aliases.retain(|a| !a.trim().is_empty() && seen.insert(a.to_lowercase()));
+    aliases
+}
+
+pub fn extract_wikilinks(content: &str) -> Vec<String> {
+    // Matches [[target]], [[target|desc]] and org-style [[target][desc]]
+    let link_re = Regex::new(r"\[\[([^\]|]+)(?:\|[^\]]+)?\](?:\[[^\]]*\])?\]").unwrap();
     link_re
         .captures_iter(content)
         .map(|cap| cap[1].to_string())
@@ -128,11 +293,16 @@ fn infer_type(frontmatter_type: &Option<String>, path: &Path, org_root: &Path) -
     }
 }
 
-/// Serialize frontmatter and content back to a markdown file with YAML frontmatter
+/// Serialize frontmatter and content back to a markdown file with YAML
+/// frontmatter, or for an org file, to `#+KEYWORD:` lines
 pub fn serialize_document(
+    path: &Path,
     frontmatter: &HashMap<String, serde_json::Value>,
     content: &str,
 ) -> String {
+    if is_org_file(path) {
+        return serialize_org_document(frontmatter, content);
+    }
     let mut yaml = String::from("---\n");
 
     // Order frontmatter fields for consistency
@@ -157,6 +327,68 @@ pub fn serialize_document(
     yaml
 }
 
+/// Org keyword a front matter field is kept in, as `parse_document` reads it
+fn org_keyword(key: &str) -> String {
+    match key {
+        "created" => "DATE".to_string(),
+        "tags" => "FILETAGS".to_string(),
+        _ => key.to_uppercase(),
+    }
+}
+
+/// Value of an org keyword line; tags as `:a:b:`, other lists space-separated
+fn format_org_value(key: &str, value: &serde_json::Value) -> Option<String> {
+    let item = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => s.clone(),
+        _ => v.to_string(),
+    };
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::Array(arr) if arr.is_empty() => None,
+        serde_json::Value::Array(arr) if key == "tags" => {
+            Some(format!(":{}:", arr.iter().map(item).collect::<Vec<_>>().join(":")))
+        }
+        serde_json::Value::Array(arr) => Some(arr.iter().map(item).collect::<Vec<_>>().join(" ")),
+        _ => Some(item(value)),
+    }
+}
+
+/// An org file with `frontmatter` as `#+KEYWORD:` lines. A keyword the
+/// content already sets ahead of its body is updated in place, or dropped
+/// for an empty field; the rest go on top, after the file's property drawer.
+fn serialize_org_document(frontmatter: &HashMap<String, serde_json::Value>, content: &str) -> String {
+    let field_order = ["title", "type", "status", "created", "completed", "updated", "tags"];
+    let mut keys: Vec<&String> = frontmatter.keys().collect();
+    keys.sort_by_key(|k| (field_order.iter().position(|f| f == k).unwrap_or(field_order.len()), k.as_str()));
+
+    let head = metadata_head(content);
+    let mut head_lines: Vec<String> = head.split_inclusive('\n').map(|l| l.to_string()).collect();
+    let mut new_lines = Vec::new();
+    for key in keys {
+        let keyword = org_keyword(key);
+        let prefix = format!("#+{}:", keyword.to_lowercase());
+        let line = format_org_value(key, &frontmatter[key]).map(|v| format!("#+{}: {}\n", keyword, v));
+        match head_lines.iter().position(|l| l.trim().to_lowercase().starts_with(&prefix)) {
+            Some(i) => match line {
+                Some(line) => head_lines[i] = line,
+                None => {
+                    head_lines.remove(i);
+                }
+            },
+            None => new_lines.extend(line),
+        }
+    }
+    let at = match head_lines.first() {
+        Some(first) if first.trim().eq_ignore_ascii_case(":PROPERTIES:") => head_lines
+            .iter()
+            .position(|l| l.trim().eq_ignore_ascii_case(":END:"))
+            .map_or(0, |i| i + 1),
+        _ => 0,
+    };
+    head_lines.splice(at..at, new_lines);
+    head_lines.concat() + &content[head.len()..]
+}
+
 fn format_yaml_field(key: &str, value: &serde_json::Value) -> String {
     match value {
         serde_json::Value::Null => format!("{}: null\n", key),
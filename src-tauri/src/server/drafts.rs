@@ -0,0 +1,168 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+use crate::server::conditional::file_revision;
+use crate::server::{log_to_file, AppState};
+
+/// Drafts live here in the org root, one `<path>.json` per document. They
+/// aren't documents, so the watcher and the index pass them by.
+const DRAFTS_DIR: &str = ".org-viewer-drafts";
+
+#[derive(Deserialize)]
+pub struct SaveDraftRequest {
+    /// As for `PUT /api/files/{path}`
+    #[serde(default)]
+    frontmatter: Option<HashMap<String, serde_json::Value>>,
+    content: String,
+    /// Revision of the document the edit started from
+    #[serde(default)]
+    revision: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Draft {
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    frontmatter: Option<HashMap<String, serde_json::Value>>,
+    content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revision: Option<String>,
+    /// RFC 3339
+    #[serde(rename = "savedAt")]
+    saved_at: String,
+    /// Whether the document has changed since the edit started, so
+    /// restoring the draft as it is would undo that change
+    #[serde(default)]
+    stale: bool,
+}
+
+#[derive(Serialize)]
+pub struct DraftSummary {
+    path: String,
+    #[serde(rename = "savedAt")]
+    saved_at: String,
+}
+
+/// Where the draft of the document at `path` is kept, if `path` names a
+/// document inside its root
+fn draft_path(state: &AppState, path: &str) -> Result<PathBuf, StatusCode> {
+    if path.split(['/', '\\']).any(|s| s == ".." || s.is_empty()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let full_path = state.roots.resolve(path);
+    let canonical = full_path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(path, &canonical) {
+        log_to_file(&format!("[drafts] Rejected path traversal attempt: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(state.org_root.join(DRAFTS_DIR).join(format!("{}.json", path)))
+}
+
+/// Forget the draft of `path`, once it's saved for real
+pub async fn discard(state: &AppState, path: &str) {
+    if let Ok(draft) = draft_path(state, path) {
+        match tokio::fs::remove_file(&draft).await {
+            Ok(()) => log_to_file(&format!("[drafts] Discarded draft of {}", path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log_to_file(&format!("[drafts] Failed to discard draft of {}: {}", path, e)),
+        }
+    }
+}
+
+/// PUT /api/drafts/*path - Keep an unsaved edit of a document, e.g. every
+/// few seconds while typing, so it survives a client crash. The document
+/// itself is left alone.
+pub async fn put_draft(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Json(payload): Json<SaveDraftRequest>,
+) -> Result<Json<Draft>, StatusCode> {
+    let target = draft_path(&state, &path)?;
+    let draft = Draft {
+        path,
+        frontmatter: payload.frontmatter,
+        content: payload.content,
+        revision: payload.revision,
+        saved_at: chrono::Utc::now().to_rfc3339(),
+        stale: false,
+    };
+    let json = serde_json::to_string(&draft).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| {
+            log_to_file(&format!("[drafts] Failed to create {:?}: {}", parent, e));
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+    tokio::fs::write(&target, json).await.map_err(|e| {
+        log_to_file(&format!("[drafts] Failed to save draft of {}: {}", draft.path, e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(draft))
+}
+
+/// GET /api/drafts/*path - The unsaved edit of a document, to offer when
+/// it's next opened; 404 if there's none
+pub async fn get_draft(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<Draft>, StatusCode> {
+    let target = draft_path(&state, &path)?;
+    let json = tokio::fs::read_to_string(&target).await.map_err(|_| StatusCode::NOT_FOUND)?;
+    let mut draft: Draft = serde_json::from_str(&json).map_err(|e| {
+        log_to_file(&format!("[drafts] Invalid draft {:?}: {}", target, e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if draft.revision.is_some() {
+        draft.stale = draft.revision != file_revision(&state.roots.resolve(&path)).await;
+    }
+    Ok(Json(draft))
+}
+
+/// DELETE /api/drafts/*path - Throw away an unsaved edit
+pub async fn delete_draft(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let target = draft_path(&state, &path)?;
+    match tokio::fs::remove_file(&target).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            log_to_file(&format!("[drafts] Failed to delete draft of {}: {}", path, e));
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// GET /api/drafts - Documents with unsaved edits, most recent first
+pub async fn list_drafts(State(state): State<Arc<AppState>>) -> Json<Vec<DraftSummary>> {
+    let dir = state.org_root.join(DRAFTS_DIR);
+    let scan = move || {
+        WalkDir::new(&dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let draft: Draft = serde_json::from_str(&std::fs::read_to_string(e.path()).ok()?).ok()?;
+                Some(DraftSummary {
+                    path: draft.path,
+                    saved_at: draft.saved_at,
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut drafts = tokio::task::spawn_blocking(scan).await.unwrap_or_else(|e| {
+        log_to_file(&format!("[drafts] Listing drafts failed: {}", e));
+        Vec::new()
+    });
+    drafts.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+    Json(drafts)
+}
@@ -0,0 +1,115 @@
+use chrono::NaiveDateTime;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::server::org::{format_minutes, parse_duration_minutes, parse_headings};
+
+/// Column-view style effort/clock summary for one heading
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffortRollup {
+    pub line: usize,
+    pub level: usize,
+    pub title: String,
+    /// The heading's own `:Effort:` in minutes
+    pub effort: Option<i64>,
+    /// Minutes clocked directly on this heading
+    pub clocked: i64,
+    /// Effort summed over the whole subtree
+    #[serde(rename = "totalEffort")]
+    pub total_effort: i64,
+    /// Clocked minutes summed over the whole subtree
+    #[serde(rename = "totalClocked")]
+    pub total_clocked: i64,
+    /// `total_effort` formatted as H:MM
+    #[serde(rename = "totalEffortDisplay")]
+    pub total_effort_display: String,
+    /// `total_clocked` formatted as H:MM
+    #[serde(rename = "totalClockedDisplay")]
+    pub total_clocked_display: String,
+}
+
+/// Minutes of a single `CLOCK:` line, using the `=> H:MM` suffix when present
+/// and falling back to the difference between the two timestamps
+pub fn parse_clock_minutes(line: &str) -> Option<i64> {
+    // Runs for every CLOCK line of a file, so compiled once for the process
+    static CLOCK_RE: OnceLock<Regex> = OnceLock::new();
+    let clock_re = CLOCK_RE.get_or_init(|| {
+        Regex::new(r"^\s*CLOCK:\s*\[([^\]]+)\](?:--\[([^\]]+)\])?(?:\s*=>\s*(-?\d+:\d+))?").unwrap()
+    });
+    let caps = clock_re.captures(line)?;
+
+    if let Some(duration) = caps.get(3) {
+        return parse_duration_minutes(duration.as_str());
+    }
+
+    // Running clock (no end timestamp) doesn't count yet
+    let start = parse_org_timestamp(&caps[1])?;
+    let end = parse_org_timestamp(caps.get(2)?.as_str())?;
+    Some((end - start).num_minutes())
+}
+
+/// Parse the inside of an org timestamp like `2024-01-15 Mon 10:30`
+pub fn parse_org_timestamp(ts: &str) -> Option<NaiveDateTime> {
+    let ts_re = Regex::new(r"(\d{4}-\d{2}-\d{2})(?:\s+\w+)?(?:\s+(\d{1,2}:\d{2}))?").unwrap();
+    let caps = ts_re.captures(ts)?;
+    let time = caps.get(2).map(|m| m.as_str()).unwrap_or("00:00");
+    NaiveDateTime::parse_from_str(&format!("{} {}", &caps[1], time), "%Y-%m-%d %H:%M").ok()
+}
+
+/// Compute effort estimates vs clocked time per heading, rolled up over subtrees.
+/// Only headings with some effort or clock data in their subtree are returned.
+pub fn compute_rollups(content: &str) -> Vec<EffortRollup> {
+    let lines: Vec<&str> = content.lines().collect();
+    let headings = parse_headings(content);
+
+    // Own effort and clocked minutes per heading
+    let own: Vec<(Option<i64>, i64)> = headings
+        .iter()
+        .map(|h| {
+            let effort = h
+                .properties
+                .get("EFFORT")
+                .and_then(|e| parse_duration_minutes(e));
+            let clocked = lines[h.line..h.section_end]
+                .iter()
+                .filter(|l| l.trim_start().starts_with("CLOCK:"))
+                .filter_map(|l| parse_clock_minutes(l))
+                .sum();
+            (effort, clocked)
+        })
+        .collect();
+
+    headings
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, h)| {
+            // Headings are in document order, so the subtree is the run of
+            // following headings that start before this subtree ends
+            let subtree = headings[idx..]
+                .iter()
+                .zip(&own[idx..])
+                .take_while(|(sub, _)| sub.line <= h.subtree_end);
+
+            let (total_effort, total_clocked) = subtree.fold((0, 0), |(e, c), (_, (effort, clocked))| {
+                (e + effort.unwrap_or(0), c + clocked)
+            });
+
+            if total_effort == 0 && total_clocked == 0 {
+                return None;
+            }
+
+            Some(EffortRollup {
+                line: h.line,
+                level: h.level,
+                title: h.title.clone(),
+                effort: own[idx].0,
+                clocked: own[idx].1,
+                total_effort,
+                total_clocked,
+                total_effort_display: format_minutes(total_effort),
+                total_clocked_display: format_minutes(total_clocked),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,227 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Version of the event schema, sent as `v` with every event. Bumped when an
+/// event changes shape in a way older clients would misread.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Events queued for one client before it counts as fallen behind. Each
+/// connection has its own queue, so a slow one doesn't hold up the rest.
+const QUEUE_CAPACITY: usize = 64;
+
+/// Recent events kept for clients catching up after a reconnect
+const REPLAY_CAPACITY: usize = 256;
+
+/// What an event reports; serialized as its `type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    /// A document was added or edited
+    FileChanged,
+    /// A document was deleted; `linkedFrom` lists documents still linking to it
+    FileDeleted,
+    /// A document moved; `path` is the new path, `from` the old one
+    FileRenamed,
+    /// A large batch: `paths` changed, plus `deleted` and `renamed` like
+    /// the single-document events
+    FilesChanged,
+    /// Progress of a search reindex or index cache rebuild
+    IndexProgress,
+    /// Server state clients may show, like the watcher pausing
+    ServerStatus,
+    /// Files added or removed in a project under `projects/`
+    ProjectTreeChanged,
+    /// A file written in a project
+    ProjectFileChanged,
+    /// A document open for editing together changed; `heads` and `content`
+    /// are its merged state
+    CollabUpdate,
+    /// Which clients have which documents open; `clients` lists them all
+    Presence,
+    /// A timed SCHEDULED or DEADLINE entry is due in `minutes`
+    Reminder,
+}
+
+/// A message to WebSocket clients
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub v: u32,
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    /// Document path the event is about; absent for events about several
+    /// documents or the server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Goes up by one with every event, so a client seeing a jump knows it
+    /// missed some
+    pub revision: u64,
+    /// Details depending on the type
+    pub payload: serde_json::Value,
+    pub timestamp: i64,
+}
+
+impl Event {
+    /// Document paths the event is about: its own path, the ones in a
+    /// batch, a rename's old path, and documents whose links a deletion
+    /// broke. Project events give `projects/<name>/<file>`, or the
+    /// project's directory, ending in `/`, for tree changes.
+    pub fn documents(&self) -> Vec<String> {
+        let mut paths: Vec<&str> = self.path.iter().map(|p| p.as_str()).collect();
+        let payload = &self.payload;
+        match self.kind {
+            EventKind::FileDeleted => paths.extend(strings(&payload["linkedFrom"])),
+            EventKind::FileRenamed => paths.extend(payload["from"].as_str()),
+            EventKind::FilesChanged => {
+                paths.extend(strings(&payload["paths"]));
+                for deleted in payload["deleted"].as_array().into_iter().flatten() {
+                    paths.extend(deleted["path"].as_str());
+                    paths.extend(strings(&deleted["linkedFrom"]));
+                }
+                for renamed in payload["renamed"].as_array().into_iter().flatten() {
+                    paths.extend(renamed["from"].as_str());
+                    paths.extend(renamed["to"].as_str());
+                }
+            }
+            EventKind::ProjectFileChanged | EventKind::ProjectTreeChanged => {
+                let project = payload["project"].as_str().unwrap_or_default();
+                return match (self.kind, payload["path"].as_str()) {
+                    (EventKind::ProjectFileChanged, Some(path)) => vec![format!("projects/{}/{}", project, path)],
+                    _ => vec![format!("projects/{}/", project)],
+                };
+            }
+            _ => {}
+        }
+        paths.into_iter().map(|p| p.to_string()).collect()
+    }
+}
+
+fn strings(value: &serde_json::Value) -> impl Iterator<Item = &str> {
+    value.as_array().into_iter().flatten().filter_map(|v| v.as_str())
+}
+
+/// What a connection asked to hear about with a `subscribe` message. Until
+/// it sends one it gets every event; after, only events about the listed
+/// documents, plus ones about no document in particular like server status.
+/// A path ending in `/` covers everything below it.
+#[derive(Debug, Default)]
+pub struct Subscription {
+    paths: Option<Vec<String>>,
+}
+
+impl Subscription {
+    /// Apply a message from the client: `{"type": "subscribe", "paths": [...]}`
+    /// replaces the subscribed paths, `{"type": "unsubscribe"}` goes back to
+    /// every event. Returns false for other messages.
+    pub fn handle(&mut self, message: &serde_json::Value) -> bool {
+        match message["type"].as_str() {
+            Some("subscribe") => {
+                self.paths = Some(strings(&message["paths"]).map(|p| p.to_string()).collect());
+                true
+            }
+            Some("unsubscribe") => {
+                self.paths = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn wants(&self, event: &Event) -> bool {
+        let subscribed = match &self.paths {
+            Some(paths) => paths,
+            None => return true,
+        };
+        let documents = event.documents();
+        if documents.is_empty() {
+            return true;
+        }
+        documents.iter().any(|doc| subscribed.iter().any(|p| covers(p, doc) || covers(doc, p)))
+    }
+}
+
+/// Whether `path` is `other` or a directory (ending in `/`) containing it
+fn covers(path: &str, other: &str) -> bool {
+    path == other || (path.ends_with('/') && other.starts_with(path))
+}
+
+/// Numbers events and queues them for every WebSocket connection, keeping
+/// the latest few for clients that reconnect
+pub struct EventBus {
+    /// One queue per connection; a connection's is dropped when it closes or
+    /// falls behind
+    queues: Mutex<Vec<mpsc::Sender<Arc<Event>>>>,
+    revision: AtomicU64,
+    recent: Mutex<VecDeque<Arc<Event>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            queues: Mutex::new(Vec::new()),
+            revision: AtomicU64::new(0),
+            recent: Mutex::new(VecDeque::with_capacity(REPLAY_CAPACITY)),
+        }
+    }
+
+    /// Revision of the latest event sent
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    /// Events after the one a client saw last, given its revision and
+    /// timestamp. `None` if that event is no longer kept, or never was
+    /// because the server restarted and numbers events afresh, in which
+    /// case the client has to refetch what it shows. Without a timestamp
+    /// the revision is taken to be from this run of the server, and may be
+    /// the one before the oldest kept or the latest sent.
+    pub fn since(&self, revision: u64, timestamp: Option<i64>) -> Option<Vec<Arc<Event>>> {
+        let recent = self.recent.lock().unwrap();
+        let adjoins = revision == self.revision() || recent.front().is_some_and(|e| e.revision == revision + 1);
+        if timestamp.is_none() && adjoins {
+            return Some(recent.iter().filter(|e| e.revision > revision).cloned().collect());
+        }
+        let start = recent
+            .iter()
+            .position(|e| e.revision == revision && timestamp.is_none_or(|t| t == e.timestamp))?;
+        Some(recent.iter().skip(start + 1).cloned().collect())
+    }
+
+    /// A queue of every event from now on. It ends once the connection has
+    /// fallen `QUEUE_CAPACITY` events behind; the connection catches up from
+    /// the replay buffer with [`since`](Self::since) and subscribes again,
+    /// rather than everyone's events waiting on it.
+    pub fn subscribe(&self) -> mpsc::Receiver<Arc<Event>> {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        self.queues.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Send an event to every connected client
+    pub fn send(&self, kind: EventKind, path: Option<&str>, payload: serde_json::Value) {
+        // Numbered under the lock so the kept events stay in order
+        let mut recent = self.recent.lock().unwrap();
+        let event = Arc::new(Event {
+            v: PROTOCOL_VERSION,
+            kind,
+            path: path.map(|p| p.to_string()),
+            revision: self.revision.fetch_add(1, Ordering::SeqCst) + 1,
+            payload,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        });
+        if recent.len() == REPLAY_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(event.clone());
+        // Still under the lock, so every queue gets events in order
+        self.queues.lock().unwrap().retain(|queue| queue.try_send(event.clone()).is_ok());
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
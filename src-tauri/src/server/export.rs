@@ -0,0 +1,623 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
+};
+use base64::Engine;
+use gray_matter::{engine::YAML, Matter};
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::server::ids::rewrite_id_links;
+use crate::server::images::resolve_relative;
+use crate::server::includes::resolve_includes;
+use crate::server::macros::expand_macros;
+use crate::server::math::extract_math;
+use crate::server::org::{parse_document_headings, parse_todo_keywords, TodoKeywords};
+use crate::server::{log_to_file, AppState};
+
+/// Inline stylesheet for standalone HTML exports
+const EXPORT_CSS: &str = r#"
+body { max-width: 46rem; margin: 2rem auto; padding: 0 1rem; font: 16px/1.6 -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; color: #1f2328; }
+h1, h2, h3, h4, h5, h6 { line-height: 1.25; margin: 1.5em 0 0.5em; }
+h1 { border-bottom: 1px solid #d0d7de; padding-bottom: 0.3em; }
+a { color: #0969da; }
+code { font: 85% ui-monospace, Menlo, Consolas, monospace; background: #f6f8fa; padding: 0.2em 0.4em; border-radius: 4px; }
+pre { background: #f6f8fa; padding: 1em; overflow: auto; border-radius: 6px; }
+pre code { background: none; padding: 0; }
+blockquote { margin: 0; padding: 0 1em; color: #59636e; border-left: 0.25em solid #d0d7de; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: 6px 13px; }
+tr:nth-child(2n) { background: #f6f8fa; }
+img { max-width: 100%; }
+.todo { font: bold 80% ui-monospace, monospace; color: #cf222e; }
+.done { font: bold 80% ui-monospace, monospace; color: #1a7f37; }
+"#;
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn is_image(target: &str) -> bool {
+    target
+        .rsplit('.')
+        .next()
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Convert org links to Markdown: `[[url][desc]]` becomes `[desc](url)`, image
+/// file links become images, and wiki/ID links (which don't survive outside the
+/// vault) collapse to their label
+fn convert_links(line: &str) -> String {
+    let link_re = Regex::new(r"\[\[([^\]]+)\](?:\[([^\]]*)\])?\]").unwrap();
+    link_re
+        .replace_all(line, |caps: &Captures| {
+            let target = &caps[1];
+            let desc = caps.get(2).map(|m| m.as_str());
+
+            if let Some((_, label)) = target.split_once('|') {
+                return label.to_string();
+            }
+            if let Some(file) = target.strip_prefix("file:") {
+                let file = file.split("::").next().unwrap_or(file);
+                return if is_image(file) {
+                    format!("![{}]({})", desc.unwrap_or(""), file)
+                } else {
+                    format!("[{}]({})", desc.unwrap_or(file), file)
+                };
+            }
+            if target.contains("://") || target.starts_with("mailto:") {
+                return match desc {
+                    Some(d) => format!("[{}]({})", d, target),
+                    None if is_image(target) => format!("![]({})", target),
+                    None => format!("<{}>", target),
+                };
+            }
+            desc.unwrap_or(target).to_string()
+        })
+        .to_string()
+}
+
+/// Org footnote references and definitions to Markdown footnotes
+fn convert_footnotes(line: &str) -> String {
+    let def_re = Regex::new(r"^\[fn:([^\]:]+)\]\s*").unwrap();
+    let ref_re = Regex::new(r"\[fn:([^\]:]+)\]").unwrap();
+    let line = def_re.replace(line, "[^$1]: ");
+    ref_re.replace_all(&line, "[^$1]").to_string()
+}
+
+/// Convert a run of table lines: org `|---+---|` rules are dropped and a single
+/// Markdown header separator is placed after the first row
+fn convert_table(rows: &[&str], out: &mut Vec<String>) {
+    let is_rule = |l: &str| {
+        let t = l.trim();
+        t.starts_with("|-") || t.starts_with("|:-") || t.starts_with("| -") || t.starts_with("| :-")
+    };
+
+    // Already a Markdown table (separator right after the header, no `+` joins)
+    if rows.len() > 1 && is_rule(rows[1]) && !rows[1].contains('+') {
+        out.extend(rows.iter().map(|r| r.trim().to_string()));
+        return;
+    }
+
+    let data: Vec<&str> = rows.iter().copied().filter(|r| !is_rule(r)).collect();
+    for (i, row) in data.iter().enumerate() {
+        out.push(convert_links(row.trim()).replace("\\vert", "\\|"));
+        if i == 0 {
+            let columns = row.trim().trim_matches('|').split('|').count();
+            out.push(format!("|{}", "---|".repeat(columns)));
+        }
+    }
+}
+
+/// Ensure a block construct is separated from the previous paragraph, so
+/// tables aren't read as paragraph text and lines don't lazily continue a quote
+fn separate(out: &mut Vec<String>) {
+    if out.last().is_some_and(|l| !l.trim().is_empty()) {
+        out.push(String::new());
+    }
+}
+
+/// Convert org-flavoured document content to GitHub-flavoured Markdown.
+/// Headings are shifted so the shallowest one becomes `#`, or `##` when a
+/// `#+TITLE:` keyword supplies the top-level heading.
+pub fn org_to_markdown(content: &str) -> String {
+    let title_re = Regex::new(r"(?i)^\s*#\+TITLE:\s*(.+?)\s*$").unwrap();
+    let heading_re = Regex::new(r"^(\*+)\s+(.*?)(?:\s+:[\w@#%:]+:)?\s*$").unwrap();
+    let begin_re = Regex::new(r"(?i)^\s*#\+BEGIN_(\w+)\s*(\S*)").unwrap();
+    let end_re = Regex::new(r"(?i)^\s*#\+END_\w+").unwrap();
+    let keyword_re = Regex::new(r"^\s*#\+\w+:").unwrap();
+    let drawer_re = Regex::new(r"^\s*:[A-Za-z_]+:\s*$").unwrap();
+
+    let lines: Vec<&str> = content.lines().collect();
+    let min_level = lines
+        .iter()
+        .filter_map(|l| heading_re.captures(l).map(|c| c[1].len()))
+        .min()
+        .unwrap_or(1);
+    let offset = if lines.iter().any(|l| title_re.is_match(l)) { 1 } else { 0 };
+
+    let mut out: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+
+        if let Some(caps) = begin_re.captures(line) {
+            let kind = caps[1].to_uppercase();
+            let mut end = i + 1;
+            while end < lines.len() && !end_re.is_match(lines[end]) {
+                end += 1;
+            }
+            let body = &lines[i + 1..end.min(lines.len())];
+            separate(&mut out);
+            match kind.as_str() {
+                "SRC" | "EXAMPLE" => {
+                    out.push(format!("```{}", if kind == "SRC" { &caps[2] } else { "" }));
+                    out.extend(body.iter().map(|l| l.to_string()));
+                    out.push("```".to_string());
+                }
+                "QUOTE" => out.extend(body.iter().map(|l| format!("> {}", convert_links(l)))),
+                _ => out.extend(body.iter().map(|l| convert_links(l))),
+            }
+            separate(&mut out);
+            i = end + 1;
+            continue;
+        }
+
+        if drawer_re.is_match(line) && !line.trim().eq_ignore_ascii_case(":END:") {
+            // Skip property and logbook drawers entirely
+            let mut end = i + 1;
+            while end < lines.len() && !lines[end].trim().eq_ignore_ascii_case(":END:") {
+                end += 1;
+            }
+            if end < lines.len() {
+                i = end + 1;
+                continue;
+            }
+        }
+
+        if line.trim_start().starts_with('|') {
+            let start = i;
+            while i < lines.len() && lines[i].trim_start().starts_with('|') {
+                i += 1;
+            }
+            separate(&mut out);
+            convert_table(&lines[start..i], &mut out);
+            separate(&mut out);
+            continue;
+        }
+
+        if let Some(caps) = title_re.captures(line) {
+            separate(&mut out);
+            out.push(format!("# {}", &caps[1]));
+            i += 1;
+            continue;
+        }
+
+        if keyword_re.is_match(line) {
+            i += 1;
+            continue;
+        }
+
+        if let Some(caps) = heading_re.captures(line) {
+            let level = (caps[1].len() + 1 - min_level + offset).min(6);
+            separate(&mut out);
+            out.push(format!("{} {}", "#".repeat(level), convert_links(&caps[2])));
+            i += 1;
+            continue;
+        }
+
+        // Keep a blank line after headings before body text
+        if out.last().is_some_and(|l| l.starts_with('#')) && !line.trim().is_empty() {
+            out.push(String::new());
+        }
+        out.push(convert_footnotes(&convert_links(line)));
+        i += 1;
+    }
+
+    let mut markdown = out.join("\n").trim().to_string();
+    markdown.push('\n');
+    markdown
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    file: String,
+    /// Export only the subtree under this heading (matched by `:CUSTOM_ID:` or title)
+    heading: Option<String>,
+}
+
+/// Read a document for export: frontmatter stripped, includes and macros
+/// expanded, ID links resolved, optionally narrowed to one subtree.
+/// Returns (title, content, the file's TODO workflow).
+async fn load_export_source(
+    state: &AppState,
+    file: &str,
+    heading: Option<&str>,
+) -> Result<(String, String, TodoKeywords), StatusCode> {
+    // Validate path - prevent directory traversal
+    let canonical_path = state.roots.resolve(file).canonicalize()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(file, &canonical_path) {
+        log_to_file(&format!("[export] Rejected path traversal: {}", file));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let raw = tokio::fs::read_to_string(&canonical_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let body = Matter::<YAML>::new().parse(&raw).content;
+    let (root, relative) = state.roots.split(file);
+    let expanded = expand_macros(&resolve_includes(root, relative, &body).await);
+
+    let index = state.index.read().await;
+    let content = rewrite_id_links(&expanded, |id| index.resolve_id(id));
+    let keywords = parse_todo_keywords(&content);
+    let doc_title = index
+        .get_document(file)
+        .map(|d| d.title.clone())
+        .unwrap_or_else(|| file.to_string());
+
+    match heading {
+        Some(wanted) => {
+            let h = parse_document_headings(&canonical_path, &content)
+                .into_iter()
+                .find(|h| {
+                    h.properties.get("CUSTOM_ID").map(|v| v.as_str()) == Some(wanted)
+                        || h.title.eq_ignore_ascii_case(wanted)
+                })
+                .ok_or(StatusCode::NOT_FOUND)?;
+            let lines: Vec<&str> = content.lines().collect();
+            Ok((h.title, lines[h.line - 1..h.subtree_end].join("\n"), keywords))
+        }
+        None => Ok((doc_title, content, keywords)),
+    }
+}
+
+/// Render Markdown to an HTML fragment, inlining local images as data URIs
+/// relative to the exported document
+fn render_markdown_html(state: &AppState, doc_path: &str, markdown: &str, keywords: &TodoKeywords) -> String {
+    // Swap LaTeX for MathML up front; fragments we can't render stay as source
+    let mut markdown = markdown.to_string();
+    for fragment in extract_math(&markdown) {
+        if let Some(mathml) = fragment.mathml {
+            markdown = markdown.replacen(&fragment.source, &mathml, 1);
+        }
+    }
+
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+
+    let mut in_heading = false;
+    let events = Parser::new_ext(&markdown, options).map(|event| match event {
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+            let dest_url = match inline_image(state, doc_path, &dest_url) {
+                Some(data) => CowStr::from(data),
+                None => dest_url,
+            };
+            Event::Start(Tag::Image { link_type, dest_url, title, id })
+        }
+        Event::Start(Tag::Heading { .. }) => {
+            in_heading = true;
+            event
+        }
+        Event::End(TagEnd::Heading(_)) => {
+            in_heading = false;
+            event
+        }
+        // Badge TODO keywords at the start of headings, coloured by done state
+        Event::Text(text)
+            if in_heading && text.split_once(' ').is_some_and(|(k, _)| keywords.contains(k)) =>
+        {
+            let (keyword, rest) = text.split_once(' ').unwrap_or((&text, ""));
+            let class = if keywords.is_done(keyword) { "done" } else { "todo" };
+            Event::InlineHtml(CowStr::from(format!(
+                "<span class=\"{} {}\">{}</span> {}",
+                class,
+                keyword.to_lowercase(),
+                keyword,
+                escape_html(rest)
+            )))
+        }
+        other => other,
+    });
+
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output
+}
+
+/// Resolve an image URL relative to the exported document to a path relative
+/// to the document's root, provided it exists and stays inside that root
+fn local_image(state: &AppState, doc_path: &str, url: &str) -> Option<(String, PathBuf)> {
+    if url.contains("://") || url.starts_with("data:") {
+        return None;
+    }
+    let relative = resolve_relative(doc_path, url)?;
+    let (root, within_root) = state.roots.split(&relative);
+    if root != state.roots.root_of(doc_path) {
+        return None;
+    }
+    let path = root.join(within_root).canonicalize().ok()?;
+    if !state.roots.contains_relative(root, Path::new(within_root), &path) {
+        return None;
+    }
+    Some((within_root.to_string(), path))
+}
+
+/// Read a local image and encode it as a data URI
+fn inline_image(state: &AppState, doc_path: &str, url: &str) -> Option<String> {
+    let (_, path) = local_image(state, doc_path, url)?;
+    let bytes = std::fs::read(&path).ok()?;
+    let mime = mime_guess::from_path(&path).first_or_octet_stream();
+    Some(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// Escape characters that carry meaning in Typst markup
+fn escape_typst(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '#' | '*' | '_' | '`' | '$' | '<' | '>' | '@' | '[' | ']' | '~' | '/' | '=' | '-' | '+' | '"'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Quote a value as a Typst string literal
+fn typst_string(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "")
+    )
+}
+
+/// Translate Markdown into Typst markup for PDF rendering. Local images are
+/// referenced by root-relative path, so Typst must run with the org root as `--root`.
+fn markdown_to_typst(state: &AppState, doc_path: &str, title: &str, markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+
+    let mut out = format!(
+        "#set document(title: {})\n#set page(paper: \"a4\", margin: 2cm)\n#set text(size: 11pt)\n\
+         #show link: underline\n\n",
+        typst_string(title)
+    );
+    // Ordered flag per open list
+    let mut lists: Vec<bool> = Vec::new();
+    // Code block being collected: (language, text)
+    let mut code: Option<(String, String)> = None;
+    let mut in_image = false;
+    let mut in_table_head = false;
+
+    let line_start = |out: &mut String| {
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+    };
+
+    for event in Parser::new_ext(markdown, options) {
+        if let Some((_, buf)) = code.as_mut() {
+            match event {
+                Event::Text(text) => buf.push_str(&text),
+                Event::End(TagEnd::CodeBlock) => {
+                    let (lang, buf) = code.take().unwrap();
+                    let lang = if lang.is_empty() { String::new() } else { format!("lang: {}, ", typst_string(&lang)) };
+                    out.push_str(&format!("#raw(block: true, {}{})\n\n", lang, typst_string(buf.trim_end())));
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if in_image {
+            in_image = !matches!(event, Event::End(TagEnd::Image));
+            continue;
+        }
+
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                line_start(&mut out);
+                out.push_str(&format!("{} ", "=".repeat(level as usize)));
+            }
+            Event::End(TagEnd::Heading(_)) => out.push_str("\n\n"),
+            Event::End(TagEnd::Paragraph) => out.push_str(if lists.is_empty() { "\n\n" } else { " " }),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((lang, String::new()));
+            }
+            Event::Start(Tag::List(first)) => {
+                lists.push(first.is_some());
+                line_start(&mut out);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    out.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                line_start(&mut out);
+                let marker = if lists.last() == Some(&true) { "+" } else { "-" };
+                out.push_str(&format!("{}{} ", "  ".repeat(lists.len().saturating_sub(1)), marker));
+            }
+            Event::End(TagEnd::Item) => line_start(&mut out),
+            Event::Start(Tag::BlockQuote(_)) => out.push_str("#quote(block: true)["),
+            Event::End(TagEnd::BlockQuote(_)) => out.push_str("]\n\n"),
+            Event::Start(Tag::Emphasis) | Event::End(TagEnd::Emphasis) => out.push('_'),
+            Event::Start(Tag::Strong) | Event::End(TagEnd::Strong) => out.push('*'),
+            Event::Start(Tag::Strikethrough) => out.push_str("#strike["),
+            Event::End(TagEnd::Strikethrough) => out.push(']'),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                out.push_str(&format!("#link({})[", typst_string(&dest_url)));
+            }
+            Event::End(TagEnd::Link) => out.push(']'),
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                // Remote images can't be fetched by Typst; fall back to the alt text
+                if let Some((relative, _)) = local_image(state, doc_path, &dest_url) {
+                    out.push_str(&format!("#image({}, width: 100%)", typst_string(&format!("/{}", relative))));
+                    in_image = true;
+                }
+            }
+            Event::Start(Tag::Table(alignments)) => {
+                out.push_str(&format!("#table(columns: {},\n", alignments.len()));
+            }
+            Event::End(TagEnd::Table) => out.push_str(")\n\n"),
+            Event::Start(Tag::TableHead) => {
+                in_table_head = true;
+                out.push_str("  table.header(");
+            }
+            Event::End(TagEnd::TableHead) => {
+                in_table_head = false;
+                out.push_str("),\n");
+            }
+            Event::Start(Tag::TableCell) => out.push_str(if in_table_head { "[*" } else { "[" }),
+            Event::End(TagEnd::TableCell) => out.push_str(if in_table_head { "*], " } else { "], " }),
+            Event::End(TagEnd::TableRow) => out.push('\n'),
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                line_start(&mut out);
+                out.push_str(&format!("#super[{}] ", escape_typst(&label)));
+            }
+            Event::FootnoteReference(label) => out.push_str(&format!("#super[{}]", escape_typst(&label))),
+            Event::Code(text) => out.push_str(&format!("#raw({})", typst_string(&text))),
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => out.push_str(&escape_typst(&text)),
+            Event::SoftBreak => out.push(' '),
+            Event::HardBreak => out.push_str(" \\\n"),
+            Event::Rule => out.push_str("#line(length: 100%)\n\n"),
+            Event::TaskListMarker(done) => out.push_str(if done { "☑ " } else { "☐ " }),
+            _ => {}
+        }
+    }
+
+    out
+}
+
+/// GET /api/export/html?file=&heading= - Render a document or subtree to a
+/// self-contained HTML page
+pub async fn export_html(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Html<String>, StatusCode> {
+    log_to_file(&format!("[export] HTML export of {}", query.file));
+    let (title, content, keywords) = load_export_source(&state, &query.file, query.heading.as_deref()).await?;
+    let body = render_markdown_html(&state, &query.file, &org_to_markdown(&content), &keywords);
+
+    Ok(Html(format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(&title),
+        EXPORT_CSS,
+        body
+    )))
+}
+
+/// GET /api/export/markdown?file=&heading= - Convert a document or subtree to
+/// GitHub-flavoured Markdown
+pub async fn export_markdown(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    log_to_file(&format!("[export] Markdown export of {}", query.file));
+    let (_, content, _) = load_export_source(&state, &query.file, query.heading.as_deref()).await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        org_to_markdown(&content),
+    ))
+}
+
+/// POST /api/export/pdf - Render a document or subtree to PDF with the Typst CLI
+pub async fn export_pdf(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ExportQuery>,
+) -> Result<Response, StatusCode> {
+    log_to_file(&format!("[export] PDF export of {}", payload.file));
+    let (title, content, _) = load_export_source(&state, &payload.file, payload.heading.as_deref()).await?;
+    let markup = markdown_to_typst(&state, &payload.file, &title, &org_to_markdown(&content));
+
+    let output_path = std::env::temp_dir().join(format!("org-viewer-export-{}.pdf", uuid::Uuid::new_v4()));
+    let mut child = Command::new(&state.config.typst_path)
+        .arg("compile")
+        .arg("--root")
+        .arg(state.roots.root_of(&payload.file))
+        .arg("-")
+        .arg(&output_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            log_to_file(&format!("[export] Failed to start {}: {}", state.config.typst_path, e));
+            StatusCode::NOT_IMPLEMENTED
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(markup.as_bytes())
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let result = child
+        .wait_with_output()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !result.status.success() {
+        log_to_file(&format!(
+            "[export] typst failed for {}: {}",
+            payload.file,
+            String::from_utf8_lossy(&result.stderr).trim()
+        ));
+        let _ = tokio::fs::remove_file(&output_path).await;
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let pdf = tokio::fs::read(&output_path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let _ = tokio::fs::remove_file(&output_path).await;
+
+    let filename = std::path::Path::new(&payload.file)
+        .file_stem()
+        .map(|s| s.to_string_lossy().replace('"', ""))
+        .unwrap_or_else(|| "export".to_string());
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/pdf")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.pdf\"", filename),
+        )
+        .body(Body::from(pdf))
+        .unwrap())
+}
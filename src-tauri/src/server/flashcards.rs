@@ -0,0 +1,270 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::Duration;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::effort::parse_org_timestamp;
+use crate::server::org::{parse_document_headings, set_planning, set_properties, Heading};
+use crate::server::timezone::{self, TzQuery};
+use crate::server::{log_to_file, AppState};
+
+/// Heading tags that mark org-drill and org-fc cards
+const CARD_TAGS: &[&str] = &["drill", "fc"];
+
+/// Lowest ease factor SM-2 allows
+const MIN_EASE: f64 = 1.3;
+const DEFAULT_EASE: f64 = 2.5;
+
+#[derive(Serialize)]
+pub struct Flashcard {
+    file: String,
+    line: usize,
+    title: String,
+    /// Which tag marked the card (`drill` or `fc`)
+    kind: String,
+    question: String,
+    /// Text of the card's child headings (the usual "Answer" subtree)
+    answer: String,
+    /// Cloze deletions (`[text]` for org-drill, `{{text}}` for org-fc) when there is no answer subtree
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    clozes: Vec<String>,
+    /// SCHEDULED date of the next review; new cards have none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due: Option<String>,
+    ease: f64,
+    interval: i64,
+    repeats: u32,
+}
+
+/// Section body lines with property/logbook drawers and planning stripped
+fn body_text(lines: &[&str]) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    let mut in_drawer = false;
+    for line in lines {
+        let trimmed = line.trim();
+        if in_drawer {
+            in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+            continue;
+        }
+        if trimmed.len() > 1 && trimmed.starts_with(':') && trimmed.ends_with(':') && !trimmed.contains(' ') {
+            in_drawer = true;
+            continue;
+        }
+        if trimmed.starts_with("SCHEDULED:") || trimmed.starts_with("DEADLINE:") || trimmed.starts_with("CLOSED:") {
+            continue;
+        }
+        out.push(line);
+    }
+    out.join("\n").trim().to_string()
+}
+
+fn extract_clozes(text: &str) -> Vec<String> {
+    // `[text]` but not links, checkboxes, priorities, or timestamps
+    let drill_re = Regex::new(r"(?:^|[^\[])\[([^\[\]\d#][^\[\]]+)\](?:[^\[\]]|$)").unwrap();
+    let fc_re = Regex::new(r"\{\{([^}]+)\}").unwrap();
+    drill_re
+        .captures_iter(text)
+        .chain(fc_re.captures_iter(text))
+        .map(|c| c[1].split("||").next().unwrap_or(&c[1]).trim().to_string())
+        .collect()
+}
+
+fn to_card(path: &str, lines: &[&str], heading: &Heading) -> Option<Flashcard> {
+    let kind = heading.tags.iter().find(|t| CARD_TAGS.contains(&t.as_str()))?;
+
+    let question = body_text(&lines[heading.line..heading.section_end]);
+    // Drop generic "Answer"/"Back" subheadings, keeping their bodies
+    let answer_lines: Vec<&str> = lines[heading.section_end..heading.subtree_end]
+        .iter()
+        .copied()
+        .filter(|l| {
+            let title = l.trim_start_matches('*');
+            !(title.len() < l.len() && ["answer", "back"].contains(&title.trim().to_lowercase().as_str()))
+        })
+        .collect();
+    let answer = body_text(&answer_lines);
+    let clozes = if answer.is_empty() { extract_clozes(&question) } else { Vec::new() };
+    let prop = |key: &str| heading.properties.get(key);
+
+    Some(Flashcard {
+        file: path.to_string(),
+        line: heading.line,
+        title: heading.title.clone(),
+        kind: kind.clone(),
+        question,
+        answer,
+        clozes,
+        due: heading.scheduled.clone(),
+        ease: prop("DRILL_EASE").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_EASE),
+        interval: prop("DRILL_LAST_INTERVAL").and_then(|v| v.parse::<f64>().ok()).map(|v| v.round() as i64).unwrap_or(0),
+        repeats: prop("DRILL_TOTAL_REPEATS").and_then(|v| v.parse().ok()).unwrap_or(0),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct FlashcardQuery {
+    /// Only cards that are new or due today or earlier
+    #[serde(default)]
+    due: bool,
+    file: Option<String>,
+    /// IANA timezone deciding what "today" is for `due`
+    tz: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct FlashcardsResponse {
+    count: usize,
+    items: Vec<Flashcard>,
+}
+
+/// GET /api/flashcards?due=&file=&tz= - List `:drill:` / `:fc:` cards with question and answer text
+pub async fn list_flashcards(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FlashcardQuery>,
+) -> Result<Json<FlashcardsResponse>, StatusCode> {
+    let today = timezone::now(&state.config, query.tz.as_deref())?.date();
+    let index = state.index.read().await;
+
+    let mut items: Vec<Flashcard> = Vec::new();
+    for (doc, headings) in index.documents_with_headings() {
+        if query.file.as_ref().is_some_and(|f| f != &doc.path) {
+            continue;
+        }
+        if !headings.iter().any(|h| h.tags.iter().any(|t| CARD_TAGS.contains(&t.as_str()))) {
+            continue;
+        }
+
+        let full_path = state.roots.resolve(&doc.path);
+        let content = match tokio::fs::read_to_string(&full_path).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        // Re-parse so line numbers match the file as read
+        for heading in parse_document_headings(&full_path, &content) {
+            if let Some(card) = to_card(&doc.path, &lines, &heading) {
+                let due_date = card.due.as_deref().and_then(parse_org_timestamp).map(|d| d.date());
+                if query.due && due_date.is_some_and(|d| d > today) {
+                    continue;
+                }
+                items.push(card);
+            }
+        }
+    }
+    items.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+
+    Ok(Json(FlashcardsResponse {
+        count: items.len(),
+        items,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ReviewRequest {
+    file: String,
+    line: usize,
+    /// Heading title the client reviewed; guards against a stale line
+    title: String,
+    /// Recall quality, 0 (blackout) to 5 (perfect)
+    quality: u8,
+}
+
+#[derive(Serialize)]
+pub struct ReviewResponse {
+    due: String,
+    interval: i64,
+    ease: f64,
+}
+
+/// POST /api/flashcards/review?tz= - Record a review and reschedule the card with SM-2,
+/// writing org-drill's `DRILL_*` properties and a new SCHEDULED date
+pub async fn review_flashcard(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TzQuery>,
+    Json(payload): Json<ReviewRequest>,
+) -> Result<Json<ReviewResponse>, StatusCode> {
+    if payload.quality > 5 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Validate path - prevent directory traversal
+    let canonical_path = state.roots.resolve(&payload.file).canonicalize()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&payload.file, &canonical_path) {
+        log_to_file(&format!("[flashcards] Rejected path traversal: {}", payload.file));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let content = tokio::fs::read_to_string(&canonical_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let heading = parse_document_headings(&canonical_path, &content)
+        .into_iter()
+        .find(|h| h.line == payload.line && h.tags.iter().any(|t| CARD_TAGS.contains(&t.as_str())))
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if heading.title != payload.title {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let prop_f64 = |key: &str, default: f64| {
+        heading.properties.get(key).and_then(|v| v.parse::<f64>().ok()).unwrap_or(default)
+    };
+    let quality = payload.quality as f64;
+    let mut ease = prop_f64("DRILL_EASE", DEFAULT_EASE);
+    let last_interval = prop_f64("DRILL_LAST_INTERVAL", 0.0);
+    let mut since_fail = prop_f64("DRILL_REPEATS_SINCE_FAIL", 0.0) as u32;
+    let total = prop_f64("DRILL_TOTAL_REPEATS", 0.0) as u32 + 1;
+    let mut failures = prop_f64("DRILL_FAILURE_COUNT", 0.0) as u32;
+    let average = (prop_f64("DRILL_AVERAGE_QUALITY", 0.0) * (total - 1) as f64 + quality) / total as f64;
+
+    // SM-2: failed recalls restart the sequence; otherwise intervals grow by the ease factor
+    let interval: i64 = if payload.quality < 3 {
+        since_fail = 0;
+        failures += 1;
+        1
+    } else {
+        since_fail += 1;
+        match since_fail {
+            1 => 1,
+            2 => 6,
+            _ => (last_interval * ease).round().max(1.0) as i64,
+        }
+    };
+    ease = (ease + (0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02))).max(MIN_EASE);
+
+    let now = timezone::now(&state.config, query.tz.as_deref())?;
+    let due = (now.date() + Duration::days(interval)).format("%Y-%m-%d %a").to_string();
+    let updated = set_properties(
+        &canonical_path,
+        &content,
+        heading.line,
+        &[
+            ("DRILL_LAST_INTERVAL", format!("{:.4}", interval as f64)),
+            ("DRILL_REPEATS_SINCE_FAIL", since_fail.to_string()),
+            ("DRILL_TOTAL_REPEATS", total.to_string()),
+            ("DRILL_FAILURE_COUNT", failures.to_string()),
+            ("DRILL_AVERAGE_QUALITY", format!("{:.3}", average)),
+            ("DRILL_EASE", format!("{:.3}", ease)),
+            ("DRILL_LAST_QUALITY", payload.quality.to_string()),
+            ("DRILL_LAST_REVIEWED", now.format("[%Y-%m-%d %a %H:%M]").to_string()),
+        ],
+    );
+    let updated = set_planning(&updated, heading.line, "SCHEDULED", &due);
+
+    if let Err(e) = tokio::fs::write(&canonical_path, updated).await {
+        log_to_file(&format!("[flashcards] Failed to write: {}", e));
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    log_to_file(&format!(
+        "[flashcards] Reviewed {}:{} q={} next in {}d",
+        payload.file, payload.line, payload.quality, interval
+    ));
+    // File watcher will auto-refresh index
+    Ok(Json(ReviewResponse { due, interval, ease }))
+}
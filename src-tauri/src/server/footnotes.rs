@@ -0,0 +1,93 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Footnote {
+    pub label: String,
+    /// Definition text, if the footnote is defined anywhere in the document
+    pub definition: Option<String>,
+    /// True for inline footnotes (`[fn::text]` or `[fn:name:text]`)
+    pub inline: bool,
+    /// Line numbers (1-based) where the footnote is referenced
+    pub references: Vec<usize>,
+}
+
+/// Parse footnote definitions and references from document content
+pub fn parse_footnotes(content: &str) -> Vec<Footnote> {
+    let def_re = Regex::new(r"^\[fn:([\w-]+)\]\s*(.*)$").unwrap();
+    let ref_re = Regex::new(r"\[fn:([\w-]*)(?::([^\]]*))?\]").unwrap();
+    let heading_re = Regex::new(r"^\*+\s").unwrap();
+
+    let mut footnotes: Vec<Footnote> = Vec::new();
+    let mut anonymous = 0;
+    let lines: Vec<&str> = content.lines().collect();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+
+        // Footnote definition: starts at column 0, runs until a blank line,
+        // a heading, or the next definition
+        if let Some(caps) = def_re.captures(line) {
+            let label = caps[1].to_string();
+            let mut text = caps[2].trim().to_string();
+            let mut j = i + 1;
+            while j < lines.len() {
+                let next = lines[j];
+                if next.trim().is_empty() || heading_re.is_match(next) || def_re.is_match(next) {
+                    break;
+                }
+                text.push(' ');
+                text.push_str(next.trim());
+                j += 1;
+            }
+
+            match footnotes.iter_mut().find(|f| f.label == label) {
+                Some(f) => f.definition = Some(text),
+                None => footnotes.push(Footnote {
+                    label,
+                    definition: Some(text),
+                    inline: false,
+                    references: Vec::new(),
+                }),
+            }
+            i = j;
+            continue;
+        }
+
+        for caps in ref_re.captures_iter(line) {
+            let inline_def = caps.get(2).map(|m| m.as_str().trim().to_string());
+            let label = match &caps[1] {
+                "" => {
+                    // Anonymous inline footnote needs a definition to be meaningful
+                    if inline_def.is_none() {
+                        continue;
+                    }
+                    anonymous += 1;
+                    format!("anon-{}", anonymous)
+                }
+                l => l.to_string(),
+            };
+
+            match footnotes.iter_mut().find(|f| f.label == label) {
+                Some(f) => {
+                    f.references.push(i + 1);
+                    if f.definition.is_none() && inline_def.is_some() {
+                        f.definition = inline_def;
+                        f.inline = true;
+                    }
+                }
+                None => footnotes.push(Footnote {
+                    label,
+                    inline: inline_def.is_some(),
+                    definition: inline_def,
+                    references: vec![i + 1],
+                }),
+            }
+        }
+
+        i += 1;
+    }
+
+    footnotes
+}
@@ -0,0 +1,157 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use syntect::parsing::{ParseState, ScopeStack, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+/// Syntax definitions are expensive to load, so share one set for the process
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// A `#+BEGIN_SRC` block with server-computed highlight spans
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceBlock {
+    pub language: String,
+    /// 1-based line of the `#+BEGIN_SRC` marker
+    pub line: usize,
+    pub spans: Vec<HighlightSpan>,
+}
+
+/// A highlighted range within a source block. Columns are character offsets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighlightSpan {
+    /// 0-based line within the block body
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    /// Top-level TextMate scope, e.g. "keyword", "string", "comment"
+    pub class: String,
+}
+
+/// Map org-babel language names to tokens syntect knows
+fn syntax_token(lang: &str) -> &str {
+    match lang {
+        "emacs-lisp" | "elisp" => "lisp",
+        "sh" | "shell" | "zsh" => "bash",
+        "jupyter-python" | "ipython" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "C" => "c",
+        "C++" | "cpp" => "c++",
+        other => other,
+    }
+}
+
+/// Find org src blocks and tokenize each with syntect
+pub fn highlight_src_blocks(content: &str) -> Vec<SourceBlock> {
+    let begin_re = Regex::new(r"(?i)^\s*#\+BEGIN_SRC\s+(\S+)").unwrap();
+    let end_re = Regex::new(r"(?i)^\s*#\+END_SRC").unwrap();
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let caps = match begin_re.captures(lines[i]) {
+            Some(c) => c,
+            None => {
+                i += 1;
+                continue;
+            }
+        };
+
+        let start = i;
+        let mut end = i + 1;
+        while end < lines.len() && !end_re.is_match(lines[end]) {
+            end += 1;
+        }
+
+        let language = caps[1].to_string();
+        let code = lines[start + 1..end].join("\n");
+        if let Some(spans) = highlight_code(&code, &language) {
+            blocks.push(SourceBlock {
+                language,
+                line: start + 1,
+                spans,
+            });
+        }
+
+        i = end + 1;
+    }
+
+    blocks
+}
+
+/// Tokenize code into classed spans. Returns None for unknown languages.
+pub fn highlight_code(code: &str, language: &str) -> Option<Vec<HighlightSpan>> {
+    let ss = syntax_set();
+    let syntax = ss.find_syntax_by_token(syntax_token(language))?;
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut spans: Vec<HighlightSpan> = Vec::new();
+
+    for (line_no, line) in LinesWithEndings::from(code).enumerate() {
+        let ops = state.parse_line(line, ss).ok()?;
+        let text = line.trim_end_matches(['\n', '\r']);
+        let mut last = 0;
+
+        for (offset, op) in ops {
+            let offset = offset.min(text.len());
+            push_span(&mut spans, text, line_no, last, offset, &stack);
+            stack.apply(&op).ok()?;
+            last = offset;
+        }
+        push_span(&mut spans, text, line_no, last, text.len(), &stack);
+    }
+
+    Some(spans)
+}
+
+fn push_span(
+    spans: &mut Vec<HighlightSpan>,
+    text: &str,
+    line: usize,
+    from: usize,
+    to: usize,
+    stack: &ScopeStack,
+) {
+    if to <= from {
+        return;
+    }
+    let class = match scope_class(stack) {
+        Some(c) => c,
+        None => return,
+    };
+
+    let start = text[..from].chars().count();
+    let end = start + text[from..to].chars().count();
+
+    // Merge with the previous span when contiguous and identically classed
+    if let Some(prev) = spans.last_mut() {
+        if prev.line == line && prev.end == start && prev.class == class {
+            prev.end = end;
+            return;
+        }
+    }
+
+    spans.push(HighlightSpan {
+        line,
+        start,
+        end,
+        class,
+    });
+}
+
+/// Pick the most specific meaningful scope's top-level name
+fn scope_class(stack: &ScopeStack) -> Option<String> {
+    stack.as_slice().iter().rev().find_map(|scope| {
+        let name = scope.build_string();
+        let top = name.split('.').next().unwrap_or("").to_string();
+        match top.as_str() {
+            "" | "source" | "text" | "meta" => None,
+            _ => Some(top),
+        }
+    })
+}
@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::server::document::strip_document_extension;
+use crate::server::org::parse_properties;
+use crate::server::AppState;
+
+/// Where an org `:ID:` lives: a whole file or one heading within it
+#[derive(Debug, Clone, Serialize)]
+pub struct IdTarget {
+    pub id: String,
+    pub file: String,
+    /// Title of the owning document
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading: Option<IdHeading>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IdHeading {
+    pub line: usize,
+    pub level: usize,
+    pub title: String,
+}
+
+/// Properties from a drawer before the first heading (org-roam file nodes)
+pub fn file_properties(content: &str) -> HashMap<String, String> {
+    let preamble: Vec<&str> = content
+        .lines()
+        .take_while(|l| !(l.starts_with('*') && l.trim_start_matches('*').starts_with(' ')))
+        .collect();
+    parse_properties(&preamble)
+}
+
+/// File-level `:ID:` from a property drawer before the first heading
+pub fn file_id(content: &str) -> Option<String> {
+    file_properties(content).remove("ID")
+}
+
+/// Rewrite `[[id:...]]` / `[[id:...][desc]]` links to wikilinks on the owning file.
+/// Unknown IDs are left untouched so the viewer can flag them.
+pub fn rewrite_id_links<'a>(content: &str, resolve: impl Fn(&str) -> Option<&'a IdTarget>) -> String {
+    let id_re = Regex::new(r"\[\[id:([^\]]+)\](?:\[([^\]]*)\])?\]").unwrap();
+    id_re
+        .replace_all(content, |caps: &Captures| match resolve(caps[1].trim()) {
+            Some(target) => {
+                let label = caps
+                    .get(2)
+                    .map(|m| m.as_str().to_string())
+                    .or_else(|| target.heading.as_ref().map(|h| h.title.clone()))
+                    .unwrap_or_else(|| target.title.clone());
+                let file = strip_document_extension(&target.file);
+                format!("[[{}|{}]]", file, label)
+            }
+            None => caps[0].to_string(),
+        })
+        .to_string()
+}
+
+/// GET /api/resolve/id/{uuid} - Find the file and heading that own an org ID
+pub async fn resolve_id(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<IdTarget>, StatusCode> {
+    let index = state.index.read().await;
+    index.resolve_id(&id).cloned().map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Serialize)]
+pub struct TitleMatch {
+    path: String,
+    title: String,
+    /// `title` or `alias`
+    matched: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct TitleResolution {
+    title: String,
+    count: usize,
+    items: Vec<TitleMatch>,
+}
+
+/// GET /api/resolve/title/{title} - Find the documents a `[[Some Note Title]]`
+/// link points at, by `#+TITLE` or alias (case-insensitive). Several documents
+/// can share a title; all are listed, by path.
+pub async fn resolve_title(
+    State(state): State<Arc<AppState>>,
+    Path(title): Path<String>,
+) -> Result<Json<TitleResolution>, StatusCode> {
+    let index = state.index.read().await;
+    let wanted = title.trim().to_lowercase();
+    let items: Vec<TitleMatch> = index
+        .resolve_title(&title)
+        .into_iter()
+        .map(|doc| TitleMatch {
+            path: doc.path.clone(),
+            title: doc.title.clone(),
+            matched: if doc.title.trim().to_lowercase() == wanted { "title" } else { "alias" },
+        })
+        .collect();
+    if items.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(TitleResolution {
+        title,
+        count: items.len(),
+        items,
+    }))
+}
@@ -0,0 +1,70 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::server::log_to_file;
+
+/// Gitignore-syntax exclusions, read from the top of each root
+pub const IGNORE_FILENAME: &str = ".orgviewerignore";
+
+/// Parsed `.orgviewerignore` files by root directory. Clones share the rules,
+/// so reloading after the file changes updates every holder.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Arc<RwLock<HashMap<PathBuf, Gitignore>>>,
+}
+
+impl IgnoreRules {
+    pub fn load<'a>(roots: impl Iterator<Item = &'a Path>) -> Self {
+        let rules = Self::default();
+        for root in roots {
+            rules.reload(root);
+        }
+        rules
+    }
+
+    /// Re-read the ignore file of `root`; a missing file clears its rules
+    pub fn reload(&self, root: &Path) {
+        let path = root.join(IGNORE_FILENAME);
+        let mut rules = self.rules.write().unwrap();
+        if !path.is_file() {
+            rules.remove(root);
+            return;
+        }
+
+        let mut builder = GitignoreBuilder::new(root);
+        if let Some(e) = builder.add(&path) {
+            log_to_file(&format!("[ignore] Problem in {:?}: {}", path, e));
+        }
+        match builder.build() {
+            Ok(gitignore) => {
+                let patterns = gitignore.num_ignores() + gitignore.num_whitelists();
+                log_to_file(&format!("[ignore] Loaded {} patterns from {:?}", patterns, path));
+                rules.insert(root.to_path_buf(), gitignore);
+            }
+            Err(e) => {
+                log_to_file(&format!("[ignore] Failed to load {:?}: {}", path, e));
+                rules.remove(root);
+            }
+        }
+    }
+
+    /// `Some(true)` if the rules of `root` ignore `path` or a directory above
+    /// it, `Some(false)` if a `!` pattern re-includes it, `None` if no
+    /// pattern applies
+    pub fn matched(&self, root: &Path, path: &Path, is_dir: bool) -> Option<bool> {
+        if !path.starts_with(root) {
+            return None;
+        }
+        let rules = self.rules.read().unwrap();
+        let m = rules.get(root)?.matched_path_or_any_parents(path, is_dir);
+        if m.is_ignore() {
+            Some(true)
+        } else if m.is_whitelist() {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Response,
+};
+use regex::{Captures, Regex};
+use std::sync::Arc;
+
+use crate::server::attachments::serve_from_root;
+use crate::server::AppState;
+
+/// URL prefix that inline image links are rewritten to
+const IMAGE_ROUTE: &str = "/api/images/";
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "ico"];
+
+fn is_image_path(path: &str) -> bool {
+    path.rsplit('.')
+        .next()
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Resolve a link relative to a document's directory into an org-root-relative path.
+/// Returns None for absolute links or links that escape the org root.
+pub fn resolve_relative(doc_path: &str, link: &str) -> Option<String> {
+    if link.starts_with('/') || link.starts_with('~') || link.contains(':') {
+        return None;
+    }
+
+    let mut parts: Vec<&str> = doc_path.split('/').collect();
+    parts.pop(); // Drop the document filename
+
+    for segment in link.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            s => parts.push(s),
+        }
+    }
+
+    Some(parts.join("/"))
+}
+
+/// Rewrite `[[file:...]]` links to images so they point at the image route
+pub fn rewrite_image_links(doc_path: &str, content: &str) -> String {
+    let link_re = Regex::new(r"\[\[file:([^\]]+)\](\[[^\]]*\])?\]").unwrap();
+    link_re
+        .replace_all(content, |caps: &Captures| {
+            let target = &caps[1];
+            if !is_image_path(target) {
+                return caps[0].to_string();
+            }
+            match resolve_relative(doc_path, target) {
+                Some(resolved) => format!(
+                    "[[{}{}]{}]",
+                    IMAGE_ROUTE,
+                    resolved,
+                    caps.get(2).map(|m| m.as_str()).unwrap_or("")
+                ),
+                None => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// GET /api/images/*path - Serve an image referenced from an org document
+pub async fn get_image(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Response, StatusCode> {
+    if !is_image_path(&path) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    serve_from_root(&state.roots, &path).await
+}
@@ -0,0 +1,135 @@
+use regex::Regex;
+use std::path::Path;
+
+use crate::server::images::resolve_relative;
+use crate::server::log_to_file;
+
+/// Maximum nesting depth for recursive includes
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Expand `#+INCLUDE:` directives, inlining referenced files from inside the org root.
+///
+/// Supports plain includes plus `src <lang>` and `example` blocks, and the
+/// `:lines "from-to"` option. Directives that can't be resolved (missing file,
+/// outside the org root, include cycle) are left untouched. Included files
+/// are read on a blocking thread, off the async runtime.
+pub async fn resolve_includes(org_root: &Path, doc_path: &str, content: &str) -> String {
+    // Most documents include nothing, and needn't wait for a thread
+    if !Regex::new(r"(?im)^\s*#\+INCLUDE:").unwrap().is_match(content) {
+        return content.to_string();
+    }
+
+    let (root, path, text) = (org_root.to_path_buf(), doc_path.to_string(), content.to_string());
+    let expanded = tokio::task::spawn_blocking(move || {
+        let mut stack = vec![path.clone()];
+        expand(&root, &path, &text, &mut stack)
+    })
+    .await;
+    match expanded {
+        Ok(expanded) => expanded,
+        Err(e) => {
+            log_to_file(&format!("[includes] Expanding includes in {} failed: {}", doc_path, e));
+            content.to_string()
+        }
+    }
+}
+
+fn expand(org_root: &Path, doc_path: &str, content: &str, stack: &mut Vec<String>) -> String {
+    let include_re = Regex::new(r#"(?i)^\s*#\+INCLUDE:\s+"([^"]+)"\s*(.*)$"#).unwrap();
+    let lines_re = Regex::new(r#":lines\s+"(\d*)-(\d*)""#).unwrap();
+
+    let mut output = String::with_capacity(content.len());
+    for line in content.lines() {
+        let caps = match include_re.captures(line) {
+            Some(c) => c,
+            None => {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+        };
+
+        let target = match resolve_relative(doc_path, &caps[1]) {
+            Some(t) => t,
+            None => {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+        };
+
+        let included = match read_within_root(org_root, &target) {
+            Some(text) if !stack.contains(&target) && stack.len() < MAX_INCLUDE_DEPTH => text,
+            Some(_) => {
+                log_to_file(&format!("[includes] Include cycle or depth limit at {}", target));
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+            None => {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+        };
+
+        let options = caps[2].to_string();
+
+        // Apply :lines "from-to" (1-based, inclusive start, exclusive end like org)
+        let included = match lines_re.captures(&options) {
+            Some(l) => {
+                let from = l[1].parse::<usize>().unwrap_or(1).max(1);
+                let to = l[2].parse::<usize>().ok();
+                included
+                    .lines()
+                    .enumerate()
+                    .filter(|(i, _)| *i + 1 >= from && to.is_none_or(|t| *i + 1 < t))
+                    .map(|(_, l)| l)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            None => included,
+        };
+
+        let mut words = options.split_whitespace();
+        match words.next().map(|w| w.to_lowercase()) {
+            Some(kind) if kind == "src" => {
+                let lang = words.next().filter(|w| !w.starts_with(':')).unwrap_or("");
+                output.push_str(&format!("#+BEGIN_SRC {}\n", lang));
+                output.push_str(included.trim_end_matches('\n'));
+                output.push_str("\n#+END_SRC\n");
+            }
+            Some(kind) if kind == "example" => {
+                output.push_str("#+BEGIN_EXAMPLE\n");
+                output.push_str(included.trim_end_matches('\n'));
+                output.push_str("\n#+END_EXAMPLE\n");
+            }
+            _ => {
+                stack.push(target.clone());
+                let nested = expand(org_root, &target, &included, stack);
+                stack.pop();
+                output.push_str(&nested);
+                if !nested.ends_with('\n') {
+                    output.push('\n');
+                }
+            }
+        }
+    }
+
+    // Preserve the original trailing-newline behaviour
+    if !content.ends_with('\n') && output.ends_with('\n') {
+        output.pop();
+    }
+    output
+}
+
+/// Read a file by org-root-relative path, rejecting anything that escapes the root
+fn read_within_root(org_root: &Path, relative: &str) -> Option<String> {
+    let canonical_root = org_root.canonicalize().ok()?;
+    let canonical_path = org_root.join(relative).canonicalize().ok()?;
+    if !canonical_path.starts_with(&canonical_root) {
+        log_to_file(&format!("[includes] Rejected include outside org root: {}", relative));
+        return None;
+    }
+    std::fs::read_to_string(canonical_path).ok()
+}
@@ -1,11 +1,42 @@
-use crate::server::document::{parse_document, OrgDocument};
+use crate::server::attachments::list_attachments;
+use crate::server::crypt::{find_encrypted, EncryptedHeading};
+use crate::server::document::{
+    is_document_file, is_org_file, metadata_head, parse_document, strip_document_extension, OrgDocument,
+};
+use crate::server::effort::{compute_rollups, EffortRollup};
+use crate::server::footnotes::{parse_footnotes, Footnote};
+use crate::server::highlight::{highlight_src_blocks, SourceBlock};
+use crate::server::ids::{IdHeading, IdTarget};
+use crate::server::images::resolve_relative;
+use crate::server::instant::{InstantIndex, InstantMatch};
+use crate::server::logbook::{parse_history, TaskHistory};
+use crate::server::math::{extract_math, MathFragment};
+use crate::server::org::{
+    custom_id_anchors, parse_document_headings, parse_todo_keywords, reparse_headings, Heading, OutlineSnapshot,
+    TodoKeywords,
+};
+use crate::server::search::{uses_query_syntax, SearchIndex};
+use crate::server::roots::Roots;
+use crate::server::store::IndexStore;
+use lru::LruCache;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
 use walkdir::WalkDir;
 
-const INDEX_FILENAME: &str = ".org-viewer-index.json";
+/// Maximum documents returned by a search
+const SEARCH_LIMIT: usize = 50;
+
+/// Documents between progress lines while parsing at startup
+const PARSE_PROGRESS_INTERVAL: usize = 500;
+
+/// Memory for parsed bodies when `bodyCacheMb` isn't configured
+pub const DEFAULT_BODY_BUDGET_MB: usize = 64;
 
 /// Cached entry with modification time for incremental updates
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,101 +44,340 @@ pub struct CachedEntry {
     pub document: OrgDocument,
     /// Unix timestamp (seconds since epoch) of file modification
     pub mtime_secs: u64,
+    /// Org headings in document order
+    #[serde(default)]
+    pub headings: Vec<Heading>,
+    /// `content_hash` of the file when parsed; 0 when unknown
+    #[serde(default)]
+    pub hash: u64,
 }
 
-/// Persisted index structure for serialization
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PersistedIndex {
-    /// Version for future compatibility
-    pub version: u32,
-    /// Cached document entries keyed by relative path
-    pub entries: HashMap<String, CachedEntry>,
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, whose algorithm may change between
+/// Rust releases, it gives the same value on every build, so it's safe to
+/// persist.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// `(from, to)` paths of files that moved, pairing removed and added files by
+/// content hash. A hash more than one removed or added file has, like copies
+/// of a template, doesn't say which went where, so it pairs nothing.
+fn unique_moves(
+    removed: impl IntoIterator<Item = (u64, String)>,
+    added: impl IntoIterator<Item = (u64, String)>,
+) -> Vec<(String, String)> {
+    // Each hash maps to its one path, or None once a second turns up
+    fn by_hash(paths: impl IntoIterator<Item = (u64, String)>) -> HashMap<u64, Option<String>> {
+        let mut by_hash = HashMap::new();
+        for (hash, path) in paths.into_iter().filter(|(hash, _)| *hash != 0) {
+            by_hash.entry(hash).and_modify(|p| *p = None).or_insert(Some(path));
+        }
+        by_hash
+    }
+    let removed = by_hash(removed);
+    let mut moves: Vec<(String, String)> = by_hash(added)
+        .into_iter()
+        .filter_map(|(hash, to)| Some((removed.get(&hash)?.clone()?, to?)))
+        .collect();
+    moves.sort();
+    moves
+}
+
+/// Fingerprint of a file's content, so a new mtime over the same bytes (sync
+/// tools rewriting files) doesn't cost a reparse. Stored with the cached
+/// entry, so it has to be stable.
+pub fn content_hash(content: &str) -> u64 {
+    stable_hash(content.as_bytes())
+}
+
+/// A document's content and the fields derived from it, which are only
+/// computed when the document is served
+#[derive(Clone)]
+struct LoadedBody {
+    /// File modification time when loaded; a different one means a reload
+    modified: SystemTime,
+    content: String,
+    footnotes: Vec<Footnote>,
+    math: Vec<MathFragment>,
+    src_blocks: Vec<SourceBlock>,
+    rollups: Vec<EffortRollup>,
+    history: Vec<TaskHistory>,
+    encrypted: Vec<EncryptedHeading>,
+    todo_keywords: TodoKeywords,
 }
 
-impl Default for PersistedIndex {
-    fn default() -> Self {
+impl LoadedBody {
+    /// Derive the fields of the file at `path`. Effort, logbook and crypt
+    /// subtrees hang off org headings, so Markdown files have none.
+    fn parse(path: &Path, content: String, modified: SystemTime) -> Self {
+        let org = is_org_file(path);
         Self {
-            version: 1,
-            entries: HashMap::new(),
+            modified,
+            footnotes: parse_footnotes(&content),
+            math: extract_math(&content),
+            src_blocks: highlight_src_blocks(&content),
+            rollups: if org { compute_rollups(&content) } else { Vec::new() },
+            history: if org { parse_history(&content) } else { Vec::new() },
+            encrypted: find_encrypted(path, &content),
+            todo_keywords: parse_todo_keywords(&content),
+            content,
+        }
+    }
+
+    /// Approximate heap size in bytes
+    fn size_estimate(&self) -> usize {
+        let derived = serde_json::to_vec(&(
+            &self.footnotes,
+            &self.math,
+            &self.src_blocks,
+            &self.rollups,
+            &self.history,
+            &self.encrypted,
+        ))
+        .map(|v| v.len())
+        .unwrap_or(0);
+        self.content.len() + derived
+    }
+}
+
+/// Recently served bodies, evicted least recently used first once their
+/// estimated size exceeds the budget
+struct BodyCache {
+    entries: LruCache<String, (LoadedBody, usize)>,
+    bytes: usize,
+    budget: usize,
+}
+
+impl BodyCache {
+    fn new(budget: usize) -> Self {
+        Self {
+            entries: LruCache::unbounded(),
+            bytes: 0,
+            budget,
+        }
+    }
+
+    fn get(&mut self, path: &str) -> Option<&LoadedBody> {
+        self.entries.get(path).map(|(body, _)| body)
+    }
+
+    fn put(&mut self, path: String, body: LoadedBody) {
+        self.pop(&path);
+        let size = body.size_estimate();
+        // A body over the whole budget would only evict everything else
+        if size > self.budget {
+            return;
+        }
+        while self.bytes + size > self.budget {
+            match self.entries.pop_lru() {
+                Some((_, (_, evicted))) => self.bytes -= evicted,
+                None => break,
+            }
         }
+        self.bytes += size;
+        self.entries.put(path, (body, size));
+    }
+
+    fn pop(&mut self, path: &str) {
+        if let Some((_, size)) = self.entries.pop(path) {
+            self.bytes -= size;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
     }
 }
 
+/// Lookups links resolve through, borrowed from the index
+struct LinkTables<'a> {
+    ids: &'a HashMap<String, IdTarget>,
+    titles: &'a HashMap<String, Vec<String>>,
+    moved_from: &'a HashMap<String, Vec<String>>,
+}
+
+/// Meta key under which former paths of renamed documents are cached
+const MOVED_FROM_KEY: &str = "moved_from";
+
+/// Meta key under which the `metadataOnly` directories of the cache are kept
+const METADATA_ONLY_KEY: &str = "metadata_only";
+
+/// Most of a metadata-only document that's read: plenty for its front
+/// matter and keywords
+const METADATA_HEAD_BYTES: u64 = 16 * 1024;
+
 pub struct DocumentIndex {
-    org_root: PathBuf,
+    roots: Roots,
     documents: HashMap<String, OrgDocument>,
     /// Modification times for incremental updates
     mtimes: HashMap<String, u64>,
+    /// Content hashes, to tell a touched file from an edited one
+    hashes: HashMap<String, u64>,
+    /// Parsed org headings per document
+    headings: HashMap<String, Vec<Heading>>,
+    /// Line hashes of documents changed since startup, so the next change
+    /// only reparses the sections it touched
+    outlines: HashMap<String, OutlineSnapshot>,
+    /// Org `:ID:` properties (file and heading level) to their owners
+    ids: HashMap<String, IdTarget>,
+    /// Lowercased titles and aliases to the documents carrying them, so
+    /// `[[Some Note Title]]` links resolve
+    titles: HashMap<String, Vec<String>>,
+    /// Former paths of renamed documents, so links written against the old
+    /// name keep resolving
+    moved_from: HashMap<String, Vec<String>>,
+    /// Full-text search over titles, tags and bodies
+    search: SearchIndex,
+    /// Trigram index over titles and headings for search-as-you-type
+    instant: InstantIndex,
+    /// On-disk cache of parsed entries, so restarts only reparse changed files
+    store: IndexStore,
+    /// Only metadata stays resident for every document; bodies are loaded when
+    /// served and the most recent ones kept here
+    bodies: Mutex<BodyCache>,
+    /// How the index was last loaded at startup
+    last_load: Option<LoadReport>,
 }
 
 impl DocumentIndex {
-    pub fn new(org_root: &Path) -> Self {
+    /// `body_budget` is the memory, in bytes, for parsed bodies of recently
+    /// served documents
+    pub fn new(roots: Roots, body_budget: usize) -> Self {
+        let org_root = roots.primary().to_path_buf();
         Self {
-            org_root: org_root.to_path_buf(),
+            roots,
             documents: HashMap::new(),
             mtimes: HashMap::new(),
+            hashes: HashMap::new(),
+            headings: HashMap::new(),
+            outlines: HashMap::new(),
+            ids: HashMap::new(),
+            titles: HashMap::new(),
+            moved_from: HashMap::new(),
+            search: SearchIndex::open(&org_root),
+            instant: InstantIndex::default(),
+            store: IndexStore::open(&org_root),
+            bodies: Mutex::new(BodyCache::new(body_budget)),
+            last_load: None,
         }
     }
 
-    /// Get path to the persisted index file
-    fn index_path(&self) -> PathBuf {
-        self.org_root.join(INDEX_FILENAME)
+    /// The cache entry for a loaded document
+    fn entry(&self, path: &str) -> Option<CachedEntry> {
+        Some(CachedEntry {
+            document: self.documents.get(path)?.clone(),
+            mtime_secs: *self.mtimes.get(path)?,
+            headings: self.headings.get(path).cloned().unwrap_or_default(),
+            hash: self.hashes.get(path).copied().unwrap_or(0),
+        })
     }
 
-    /// Load persisted index from disk, or return None if not found/invalid
-    fn load_persisted(&self) -> Option<PersistedIndex> {
-        let path = self.index_path();
-        if !path.exists() {
-            return None;
-        }
+    /// Write the current entries for `paths` to the cache and drop `removed`
+    fn persist(&self, paths: &[&str], removed: &[String]) {
+        let entries: Vec<(&str, CachedEntry)> = paths
+            .iter()
+            .filter_map(|path| Some((*path, self.entry(path)?)))
+            .collect();
+        self.store.write(&entries, removed);
+    }
 
-        match std::fs::read_to_string(&path) {
-            Ok(content) => match serde_json::from_str(&content) {
-                Ok(index) => Some(index),
-                Err(e) => {
-                    println!("Failed to parse index cache: {}", e);
-                    None
-                }
-            },
-            Err(e) => {
-                println!("Failed to read index cache: {}", e);
-                None
-            }
-        }
+    /// Check the on-disk cache is intact and matches what's loaded
+    pub fn verify_cache(&self) -> Result<(), String> {
+        self.store.verify(self.documents.len())
     }
 
-    /// Save current index to disk
-    pub fn save_to_disk(&self) {
-        let entries: HashMap<String, CachedEntry> = self
-            .documents
-            .iter()
-            .filter_map(|(path, doc)| {
-                self.mtimes.get(path).map(|&mtime_secs| {
-                    (
-                        path.clone(),
-                        CachedEntry {
-                            document: doc.clone(),
-                            mtime_secs,
-                        },
-                    )
-                })
-            })
-            .collect();
+    /// Replace the on-disk cache with the loaded documents
+    pub fn rewrite_cache(&self) {
+        self.store.reset();
+        let paths: Vec<&str> = self.documents.keys().map(|p| p.as_str()).collect();
+        self.persist(&paths, &[]);
+        self.save_moves();
+    }
 
-        let persisted = PersistedIndex {
-            version: 1,
-            entries,
-        };
+    /// Every loaded document's entry, keyed by document path
+    pub fn entries(&self) -> HashMap<String, CachedEntry> {
+        self.documents
+            .keys()
+            .filter_map(|path| Some((path.clone(), self.entry(path)?)))
+            .collect()
+    }
 
-        match serde_json::to_string_pretty(&persisted) {
-            Ok(json) => {
-                if let Err(e) = std::fs::write(self.index_path(), json) {
-                    println!("Failed to save index cache: {}", e);
-                } else {
-                    println!("Saved index cache ({} entries)", persisted.entries.len());
+    /// Adopt entries parsed elsewhere, e.g. exported from another machine.
+    /// Entries are trusted for any document whose content here hashes the
+    /// same, and take its local mtime, since copying files rarely preserves
+    /// them; documents that differ are parsed from the local file instead,
+    /// and entries for missing or out-of-root files are skipped.
+    pub fn import_entries(&mut self, entries: HashMap<String, CachedEntry>) -> ImportReport {
+        let mut imported: Vec<String> = Vec::new();
+        let mut differing: Vec<PathBuf> = Vec::new();
+        let mut missing = 0;
+
+        for (path, mut entry) in entries {
+            let full_path = self.roots.resolve(&path);
+            let inside_root = match (full_path.canonicalize(), self.roots.root_of(&path).canonicalize()) {
+                (Ok(full), Ok(root)) => full.starts_with(root),
+                _ => false,
+            };
+            if !inside_root || !full_path.is_file() || !is_document_file(&full_path) {
+                missing += 1;
+                continue;
+            }
+            let mtime = match Self::get_mtime(&full_path) {
+                Some(m) => m,
+                None => {
+                    missing += 1;
+                    continue;
+                }
+            };
+            match Self::read_indexed(&self.roots, &full_path) {
+                Ok(content) if content_hash(&content) == entry.hash => {}
+                Ok(_) => {
+                    differing.push(full_path);
+                    continue;
+                }
+                Err(_) => {
+                    missing += 1;
+                    continue;
                 }
             }
-            Err(e) => println!("Failed to serialize index: {}", e),
+
+            entry.document.path = path.clone();
+            entry.document.backlinks.clear();
+            self.mtimes.insert(path.clone(), mtime);
+            self.hashes.insert(path.clone(), entry.hash);
+            self.headings.insert(path.clone(), entry.headings);
+            self.outlines.remove(&path);
+            self.bodies.lock().unwrap().pop(&path);
+            self.documents.insert(path.clone(), entry.document);
+            imported.push(path);
+        }
+        let mut reparsed: Vec<String> = Vec::new();
+        for full_path in &differing {
+            if let Some((relative, _)) = self.load_file(full_path) {
+                reparsed.push(relative);
+            }
+        }
+
+        self.rebuild_backlinks();
+        self.sync_search(&HashSet::new());
+        self.rebuild_instant();
+
+        let paths: Vec<&str> = imported.iter().chain(&reparsed).map(|p| p.as_str()).collect();
+        self.persist(&paths, &[]);
+        println!(
+            "Imported {} index entries ({} differing from the local file, {} without one)",
+            imported.len(),
+            reparsed.len(),
+            missing
+        );
+
+        ImportReport {
+            imported: imported.len(),
+            reparsed: reparsed.len(),
+            missing,
         }
     }
 
@@ -123,27 +393,23 @@ impl DocumentIndex {
     /// Load from cache and incrementally update only changed files
     /// Returns (total_docs, cached_count, parsed_count, removed_count)
     pub async fn load_or_build(&mut self) -> (usize, usize, usize, usize) {
-        let cached = self.load_persisted();
+        let started = Instant::now();
+        // A cache that fails validation is dropped whole and every file
+        // parsed again, rather than trusting whichever entries look intact
+        let (cached, corruption) = match self.store.load() {
+            Ok(entries) => (entries, None),
+            Err(reason) => {
+                println!("Index cache is corrupt ({}); rebuilding from the files", reason);
+                self.store.reset();
+                (HashMap::new(), Some(reason))
+            }
+        };
 
         // Collect all current markdown files with their mtimes
         let mut current_files: HashMap<String, u64> = HashMap::new();
-        for entry in WalkDir::new(&self.org_root)
-            .follow_links(false)
-            .into_iter()
-            .filter_entry(|e| !Self::should_exclude(e.path(), &self.org_root))
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            if path.is_file() && path.extension().map(|e| e == "md").unwrap_or(false) {
-                let relative = path
-                    .strip_prefix(&self.org_root)
-                    .unwrap_or(path)
-                    .to_string_lossy()
-                    .replace('\\', "/");
-
-                if let Some(mtime) = Self::get_mtime(path) {
-                    current_files.insert(relative, mtime);
-                }
+        for (path, relative) in Self::markdown_files(&self.roots) {
+            if let Some(mtime) = Self::get_mtime(&path) {
+                current_files.insert(relative, mtime);
             }
         }
 
@@ -151,22 +417,37 @@ impl DocumentIndex {
         let mut parsed_count = 0;
         let mut docs_to_parse: Vec<(PathBuf, String, u64)> = Vec::new();
 
+        // Documents moved into or out of a metadataOnly directory since the
+        // cache was written are parsed again, though their files didn't change
+        let metadata_only: Vec<PathBuf> = self
+            .store
+            .load_meta(METADATA_ONLY_KEY)
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let mut regrouped_paths = HashSet::new();
+
         // Check each current file against cache
         for (rel_path, current_mtime) in &current_files {
-            let full_path = self.org_root.join(rel_path);
+            let full_path = self.roots.resolve(rel_path);
+            let regrouped =
+                metadata_only.iter().any(|dir| full_path.starts_with(dir)) != self.roots.is_metadata_only(&full_path);
+            if regrouped {
+                regrouped_paths.insert(rel_path.clone());
+            }
 
             // Check if we have a valid cached entry
-            let use_cache = cached.as_ref().map_or(false, |c| {
-                c.entries.get(rel_path).map_or(false, |entry| {
-                    entry.mtime_secs == *current_mtime
-                })
-            });
+            let use_cache = !regrouped
+                && cached
+                    .get(rel_path)
+                    .is_some_and(|entry| entry.mtime_secs == *current_mtime);
 
             if use_cache {
                 // Use cached document
-                if let Some(entry) = cached.as_ref().and_then(|c| c.entries.get(rel_path)) {
+                if let Some(entry) = cached.get(rel_path) {
                     self.documents.insert(rel_path.clone(), entry.document.clone());
                     self.mtimes.insert(rel_path.clone(), entry.mtime_secs);
+                    self.hashes.insert(rel_path.clone(), entry.hash);
+                    self.headings.insert(rel_path.clone(), entry.headings.clone());
                     cached_count += 1;
                 }
             } else {
@@ -175,29 +456,91 @@ impl DocumentIndex {
             }
         }
 
-        // Parse files that weren't in cache or were modified
-        let mut newly_parsed: Vec<OrgDocument> = Vec::new();
-        for (full_path, rel_path, mtime) in docs_to_parse {
-            if let Ok(content) = tokio::fs::read_to_string(&full_path).await {
-                let doc = parse_document(&full_path, &self.org_root, &content);
-                self.mtimes.insert(rel_path.clone(), mtime);
-                newly_parsed.push(doc);
+        // Parse files that weren't in cache or were modified, spread across
+        // cores; parsing is CPU-bound and dominates a cold start
+        let roots = &self.roots;
+        let to_parse = docs_to_parse.len();
+        let progress = AtomicUsize::new(0);
+        let parse_started = Instant::now();
+        let cached_ref = &cached;
+        let parsed: Vec<(String, u64, CachedEntry, bool)> = docs_to_parse
+            .into_par_iter()
+            .filter_map(|(full_path, rel_path, mtime)| {
+                // Report progress on large rebuilds, e.g. after a cache format change
+                let done = progress.fetch_add(1, Ordering::Relaxed) + 1;
+                if done.is_multiple_of(PARSE_PROGRESS_INTERVAL) {
+                    println!("Parsing documents: {}/{}", done, to_parse);
+                }
+                let content = Self::read_indexed(roots, &full_path).ok()?;
+                let hash = content_hash(&content);
+
+                // Touched but not edited: the cached entry still holds
+                if let Some(entry) = cached_ref.get(&rel_path).filter(|e| e.hash != 0 && e.hash == hash) {
+                    return Some((rel_path, mtime, entry.clone(), false));
+                }
+
+                let entry = CachedEntry {
+                    document: Self::parse_file(roots, &full_path, &content),
+                    mtime_secs: mtime,
+                    headings: parse_document_headings(&full_path, &content),
+                    hash,
+                };
+                Some((rel_path, mtime, entry, true))
+            })
+            .collect();
+        let parse_time = parse_started.elapsed();
+
+        // Both parsed and touched entries changed and need writing back
+        let mut parsed_paths: Vec<String> = Vec::new();
+        for (rel_path, mtime, entry, reparsed) in parsed {
+            if reparsed {
                 parsed_count += 1;
+            } else {
+                cached_count += 1;
             }
-        }
-
-        // Add newly parsed documents
-        for doc in newly_parsed {
-            self.documents.insert(doc.path.clone(), doc);
+            self.mtimes.insert(rel_path.clone(), mtime);
+            self.hashes.insert(rel_path.clone(), entry.hash);
+            self.headings.insert(rel_path.clone(), entry.headings);
+            self.documents.insert(rel_path.clone(), entry.document);
+            parsed_paths.push(rel_path);
         }
 
         // Count removed (files in cache but not on disk)
-        let removed_count = cached.as_ref().map_or(0, |c| {
-            c.entries.keys().filter(|p| !current_files.contains_key(*p)).count()
-        });
+        let removed: Vec<String> = cached
+            .keys()
+            .filter(|p| !current_files.contains_key(*p))
+            .cloned()
+            .collect();
+        let removed_count = removed.len();
+
+        // Files moved while we weren't running: a new file with a removed
+        // one's content keeps answering to the old path
+        self.moved_from = self
+            .store
+            .load_meta(MOVED_FROM_KEY)
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let moves = unique_moves(
+            removed.iter().filter_map(|path| Some((cached.get(path)?.hash, path.clone()))),
+            parsed_paths
+                .iter()
+                .filter(|path| !cached.contains_key(*path))
+                .filter_map(|path| Some((*self.hashes.get(path)?, path.clone()))),
+        );
+        for (from, to) in &moves {
+            println!("Detected rename while stopped: {} -> {}", from, to);
+            self.record_move(from, to);
+        }
+        self.moved_from.retain(|path, _| self.documents.contains_key(path));
+        self.save_moves();
+        if let Ok(json) = serde_json::to_string(self.roots.metadata_only()) {
+            self.store.save_meta(METADATA_ONLY_KEY, &json);
+        }
 
         // Rebuild backlinks for all documents
         self.rebuild_backlinks();
+        self.sync_search(&regrouped_paths);
+        self.rebuild_instant();
 
         println!(
             "Index loaded: {} total ({} cached, {} parsed, {} removed)",
@@ -207,14 +550,139 @@ impl DocumentIndex {
             removed_count
         );
 
-        // Save updated index
-        self.save_to_disk();
+        // Only parsed and removed files differ from the cache; backlinks are
+        // derived on load, so unchanged entries needn't be rewritten
+        let parsed: Vec<&str> = parsed_paths.iter().map(|p| p.as_str()).collect();
+        self.persist(&parsed, &removed);
+
+        self.last_load = Some(LoadReport {
+            cache_hits: cached_count,
+            cache_misses: parsed_count,
+            removed: removed_count,
+            parse_ms: parse_time.as_millis() as u64,
+            total_ms: started.elapsed().as_millis() as u64,
+            finished_at: chrono::Utc::now().to_rfc3339(),
+            corruption,
+        });
 
         (self.documents.len(), cached_count, parsed_count, removed_count)
     }
 
+    /// Bring the full-text index in line with the loaded documents, re-reading
+    /// only files whose mtime differs from what was indexed, and `stale` ones
+    fn sync_search(&mut self, stale: &HashSet<String>) {
+        let indexed = self.search.indexed_mtimes();
+        let mut staged = 0;
+
+        for (path, doc) in &self.documents {
+            let mtime = self.mtimes.get(path).copied().unwrap_or(0);
+            if indexed.get(path) == Some(&mtime) && !stale.contains(path) {
+                continue;
+            }
+            if let Ok(content) = Self::read_indexed(&self.roots, &self.roots.resolve(path)) {
+                let headings = self.headings.get(path).map(|h| h.as_slice()).unwrap_or(&[]);
+                self.search.stage(doc, headings, &content, mtime);
+                staged += 1;
+            }
+        }
+        for path in indexed.keys().filter(|p| !self.documents.contains_key(*p)) {
+            self.search.stage_removal(path);
+            staged += 1;
+        }
+
+        if staged > 0 {
+            self.search.commit();
+            println!("Search index updated: {} documents", staged);
+        }
+    }
+
+    /// Re-index one document's text during a full rebuild; searchable after
+    /// the next `commit_search`
+    pub fn restage_search(&mut self, path: &str, content: &str) {
+        let doc = match self.documents.get(path) {
+            Some(d) => d,
+            None => return,
+        };
+        let mtime = self.mtimes.get(path).copied().unwrap_or(0);
+        let headings = self.headings.get(path).map(|h| h.as_slice()).unwrap_or(&[]);
+        self.search.stage(doc, headings, content, mtime);
+    }
+
+    pub fn commit_search(&mut self) {
+        self.search.commit();
+    }
+
+    /// End a full rebuild: drop entries for documents that no longer exist and
+    /// rebuild the title/heading index too
+    pub fn finish_search_rebuild(&mut self) {
+        let indexed = self.search.indexed_mtimes();
+        for path in indexed.keys().filter(|p| !self.documents.contains_key(*p)) {
+            self.search.stage_removal(path);
+        }
+        self.search.commit();
+        self.rebuild_instant();
+    }
+
+    fn rebuild_instant(&mut self) {
+        self.instant.clear();
+        for (path, doc) in &self.documents {
+            self.instant.update(doc, self.headings.get(path).map(|h| h.as_slice()).unwrap_or(&[]));
+        }
+    }
+
+    /// Rebuild the org ID map from file-level IDs and heading properties
+    fn rebuild_ids(&mut self) {
+        self.ids.clear();
+        for (path, doc) in &self.documents {
+            if let Some(id) = &doc.id {
+                self.ids.insert(
+                    id.clone(),
+                    IdTarget {
+                        id: id.clone(),
+                        file: path.clone(),
+                        title: doc.title.clone(),
+                        heading: None,
+                    },
+                );
+            }
+            for heading in self.headings.get(path).into_iter().flatten() {
+                if let Some(id) = heading.properties.get("ID") {
+                    self.ids.insert(
+                        id.clone(),
+                        IdTarget {
+                            id: id.clone(),
+                            file: path.clone(),
+                            title: doc.title.clone(),
+                            heading: Some(IdHeading {
+                                line: heading.line,
+                                level: heading.level,
+                                title: heading.title.clone(),
+                            }),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Rebuild the title and alias map
+    fn rebuild_titles(&mut self) {
+        self.titles.clear();
+        for (path, doc) in &self.documents {
+            for name in std::iter::once(&doc.title).chain(doc.aliases.iter()) {
+                let paths = self.titles.entry(name.trim().to_lowercase()).or_default();
+                if !paths.contains(path) {
+                    paths.push(path.clone());
+                }
+            }
+        }
+    }
+
     /// Rebuild backlinks across all documents
     fn rebuild_backlinks(&mut self) {
+        self.rebuild_ids();
+        self.rebuild_titles();
+
         // First, collect all links
         let links_map: HashMap<String, Vec<String>> = self
             .documents
@@ -228,132 +696,320 @@ impl DocumentIndex {
         }
 
         // Rebuild backlinks
+        let tables = LinkTables {
+            ids: &self.ids,
+            titles: &self.titles,
+            moved_from: &self.moved_from,
+        };
         for (doc_path, doc) in self.documents.iter_mut() {
-            // Get filename stem (e.g., "my-task" from "tasks/my-task.md")
-            let doc_name = Path::new(doc_path)
-                .file_stem()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_default();
-
-            // Get path without .md extension (e.g., "tasks/my-task")
-            let doc_path_no_ext = doc_path.strip_suffix(".md").unwrap_or(doc_path);
-
-            // For project files, also match the project folder name
-            // e.g., "projects/org-viewer/README.md" should match [[org-viewer]]
-            let project_name = if doc_path.starts_with("projects/") {
-                doc_path
-                    .strip_prefix("projects/")
-                    .and_then(|p| p.split('/').next())
-                    .map(|s| s.to_string())
-            } else {
-                None
-            };
-
             for (other_path, other_links) in &links_map {
-                if other_path != doc_path {
-                    let has_link = other_links.iter().any(|link| {
-                        let link_lower = link.to_lowercase();
-                        let doc_name_lower = doc_name.to_lowercase();
+                if other_path != doc_path
+                    && other_links
+                        .iter()
+                        .any(|link| Self::link_matches(link, other_path, doc_path, &tables))
+                {
+                    doc.backlinks.push(other_path.clone());
+                }
+            }
+        }
+    }
 
-                        // Skip generic names like README and CLAUDE for stem matching
-                        let is_generic = doc_name_lower == "readme" || doc_name_lower == "claude";
+    /// Whether a link found in `source_path` points at `doc_path`
+    fn link_matches(link: &str, source_path: &str, doc_path: &str, tables: &LinkTables) -> bool {
+        // Org ID links resolve through the ID map
+        if let Some(id) = link.strip_prefix("id:") {
+            return tables.ids.get(id.trim()).map(|t| t.file.as_str()) == Some(doc_path);
+        }
 
-                        // Match by full path (without .md)
-                        if link_lower == doc_path_no_ext.to_lowercase() {
-                            return true;
-                        }
+        // Links to a renamed document's old paths still reach it
+        let previous = tables.moved_from.get(doc_path).map(|p| p.as_slice()).unwrap_or(&[]);
+        if std::iter::once(doc_path)
+            .chain(previous.iter().map(|p| p.as_str()))
+            .any(|path| Self::path_matches(link, source_path, path))
+        {
+            return true;
+        }
 
-                        // Match by filename stem (but not for generic names)
-                        if !is_generic && link_lower == doc_name_lower {
-                            return true;
-                        }
+        // Match by #+TITLE or alias
+        tables
+            .titles
+            .get(link.to_lowercase().trim())
+            .is_some_and(|paths| paths.iter().any(|p| p == doc_path))
+    }
 
-                        // Match project folder name for project files
-                        if let Some(ref proj) = project_name {
-                            if link_lower == proj.to_lowercase() {
-                                return true;
-                            }
-                        }
+    /// Whether a file or wiki link from `source_path` names `doc_path`
+    fn path_matches(link: &str, source_path: &str, doc_path: &str) -> bool {
+        // Org file links are relative to the linking document; drop any `::search` part
+        if let Some(target) = link.strip_prefix("file:") {
+            let target = target.split("::").next().unwrap_or(target);
+            return resolve_relative(source_path, target).as_deref() == Some(doc_path);
+        }
 
-                        false
-                    });
+        // Get filename stem (e.g., "my-task" from "tasks/my-task.md" or "tasks/my-task.org")
+        let doc_name = Path::new(doc_path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
 
-                    if has_link {
-                        doc.backlinks.push(other_path.clone());
-                    }
+        // Get path without .md/.org extension (e.g., "tasks/my-task")
+        let doc_path_no_ext = strip_document_extension(doc_path);
+
+        let link_lower = link.to_lowercase();
+        let doc_name_lower = doc_name.to_lowercase();
+
+        // Skip generic names like README and CLAUDE for stem matching
+        let is_generic = doc_name_lower == "readme" || doc_name_lower == "claude";
+
+        // Match by full path (without extension)
+        if link_lower == doc_path_no_ext.to_lowercase() {
+            return true;
+        }
+
+        // Match by filename stem (but not for generic names)
+        if !is_generic && link_lower == doc_name_lower {
+            return true;
+        }
+
+        // For project files, also match the project folder name
+        // e.g., "projects/org-viewer/README.md" should match [[org-viewer]]
+        if let Some(proj) = doc_path
+            .strip_prefix("projects/")
+            .and_then(|p| p.split('/').next())
+        {
+            if link_lower == proj.to_lowercase() {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Links that pointed at a renamed document by its old path and don't
+    /// name the new one, plus the moved document's own relative `file:`
+    /// links that no longer reach their target. Links by ID, title or a file
+    /// name that still matches survive the move and aren't included.
+    pub fn stale_links(&self, from: &str, to: &str) -> Vec<StaleLink> {
+        let mut stale: Vec<StaleLink> = Vec::new();
+        for (source, doc) in &self.documents {
+            for link in &doc.links {
+                if link.starts_with("id:") {
+                    continue;
+                }
+                let broken = if source == to {
+                    // Relative links from the moved document itself
+                    link.strip_prefix("file:").is_some_and(|target| {
+                        let target = target.split("::").next().unwrap_or(target);
+                        let before = resolve_relative(from, target);
+                        before.is_some_and(|p| self.documents.contains_key(&p) && p != to)
+                            && resolve_relative(to, target).is_none_or(|p| !self.documents.contains_key(&p))
+                    })
+                } else {
+                    Self::path_matches(link, source, from) && !Self::path_matches(link, source, to)
+                };
+                if broken {
+                    stale.push(StaleLink {
+                        source: source.clone(),
+                        link: link.clone(),
+                    });
                 }
             }
         }
+        stale.sort_by(|a, b| a.source.cmp(&b.source).then_with(|| a.link.cmp(&b.link)));
+        stale
+    }
+
+    /// Whether a link written in `source_path` resolves to `doc_path`
+    pub fn link_resolves_to(&self, link: &str, source_path: &str, doc_path: &str) -> bool {
+        let tables = LinkTables {
+            ids: &self.ids,
+            titles: &self.titles,
+            moved_from: &self.moved_from,
+        };
+        Self::link_matches(link, source_path, doc_path, &tables)
     }
 
     /// Full rebuild - clears everything and re-parses all files
     pub async fn build_index(&mut self) {
         self.documents.clear();
         self.mtimes.clear();
+        self.hashes.clear();
+        self.headings.clear();
+        self.outlines.clear();
+        self.bodies.lock().unwrap().clear();
+        self.search.stage_clear();
         let mut docs: Vec<OrgDocument> = Vec::new();
 
-        // Walk the directory
-        for entry in WalkDir::new(&self.org_root)
-            .follow_links(false)
-            .into_iter()
-            .filter_entry(|e| !Self::should_exclude(e.path(), &self.org_root))
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            if path.is_file() && path.extension().map(|e| e == "md").unwrap_or(false) {
-                if let Ok(content) = tokio::fs::read_to_string(path).await {
-                    let doc = parse_document(path, &self.org_root, &content);
-
-                    // Track mtime
-                    let relative = path
-                        .strip_prefix(&self.org_root)
-                        .unwrap_or(path)
-                        .to_string_lossy()
-                        .replace('\\', "/");
-                    if let Some(mtime) = Self::get_mtime(path) {
-                        self.mtimes.insert(relative, mtime);
-                    }
+        // Walk every root
+        for (path, relative) in Self::markdown_files(&self.roots) {
+            if let Ok(content) = Self::read_indexed(&self.roots, &path) {
+                let doc = Self::parse_file(&self.roots, &path, &content);
 
-                    docs.push(doc);
+                // Track mtime
+                let mtime = Self::get_mtime(&path);
+                if let Some(mtime) = mtime {
+                    self.mtimes.insert(relative.clone(), mtime);
                 }
+                let headings = parse_document_headings(&path, &content);
+                self.search.stage(&doc, &headings, &content, mtime.unwrap_or(0));
+                self.hashes.insert(relative.clone(), content_hash(&content));
+                self.headings.insert(relative, headings);
+
+                docs.push(doc);
             }
         }
 
+        // Store in hashmap
+        for doc in docs {
+            self.documents.insert(doc.path.clone(), doc);
+        }
+
         // Build backlinks
-        let links_map: HashMap<String, Vec<String>> = docs
-            .iter()
-            .map(|d| (d.path.clone(), d.links.clone()))
-            .collect();
+        self.rebuild_backlinks();
+        self.search.commit();
+        self.rebuild_instant();
+
+        println!("Full index built: {} documents", self.documents.len());
 
-        for doc in &mut docs {
-            let doc_name = Path::new(&doc.path)
-                .file_stem()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_default();
+        // Replace the cache wholesale
+        self.store.clear();
+        let paths: Vec<&str> = self.documents.keys().map(|p| p.as_str()).collect();
+        self.persist(&paths, &[]);
+    }
 
-            for (other_path, other_links) in &links_map {
-                if other_path != &doc.path
-                    && other_links
-                        .iter()
-                        .any(|link| link.to_lowercase() == doc_name.to_lowercase())
-                {
-                    doc.backlinks.push(other_path.clone());
+    /// Every document file to index across the roots, with its document path
+    fn markdown_files(roots: &Roots) -> Vec<(PathBuf, String)> {
+        let mut files = Vec::new();
+        for (prefix, root) in roots.iter() {
+            for entry in WalkDir::new(root)
+                .follow_links(roots.follow_symlinks())
+                .into_iter()
+                .filter_entry(|e| {
+                    // Roots nested inside this one are walked on their own
+                    !Self::should_exclude(roots, e.path(), root)
+                        && roots.locate(e.path()).is_some_and(|(p, _)| p == prefix)
+                })
+                .filter_map(|e| match e {
+                    Ok(e) => Some(e),
+                    Err(e) => {
+                        if e.loop_ancestor().is_some() {
+                            println!("Skipping symlink loop at {:?}", e.path());
+                        }
+                        None
+                    }
+                })
+            {
+                let path = entry.path();
+                if path.is_file() && is_document_file(path) && !Self::is_link_alias(roots, path) {
+                    if let Some(relative) = roots.relativize(path) {
+                        files.push((path.to_path_buf(), relative));
+                    }
                 }
             }
         }
 
-        // Store in hashmap
-        for doc in docs {
-            self.documents.insert(doc.path.clone(), doc);
+        // Several links to the same file outside the roots: keep the first path
+        if roots.follow_symlinks() {
+            files.sort_by(|a, b| a.1.cmp(&b.1));
+            let mut seen = HashSet::new();
+            files.retain(|(path, _)| seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())));
         }
+        files
+    }
 
-        println!("Full index built: {} documents", self.documents.len());
+    /// Whether a walk of the roots would index the file at `path`: an
+    /// existing document outside every excluded or ignored directory. Paths
+    /// that fail this are treated as removed.
+    pub fn is_indexable(roots: &Roots, path: &Path) -> bool {
+        Self::is_walked(roots, path) && !Self::is_link_alias(roots, path)
+    }
 
-        // Save to disk
-        self.save_to_disk();
+    fn is_walked(roots: &Roots, path: &Path) -> bool {
+        let root = match roots.locate(path) {
+            Some((_, root)) => root,
+            None => return false,
+        };
+        path.is_file()
+            && is_document_file(path)
+            && path
+                .ancestors()
+                .take_while(|p| *p != root)
+                .all(|p| !Self::should_exclude(roots, p, root))
     }
 
-    fn should_exclude(path: &Path, org_root: &Path) -> bool {
+    /// With `followSymlinks`, whether `path` leads through a link to a file
+    /// that is also indexed at its real location in a root, so it's left out
+    fn is_link_alias(roots: &Roots, path: &Path) -> bool {
+        if !roots.follow_symlinks() {
+            return false;
+        }
+        match path.canonicalize() {
+            Ok(real) => real != path && Self::is_walked(roots, &real),
+            Err(_) => false,
+        }
+    }
+
+    /// Every document file under the roots with its mtime. The walk is slow on
+    /// large or networked trees, so it doesn't touch the index and callers can
+    /// run it before taking the lock.
+    pub fn scan(roots: &Roots) -> HashMap<String, (PathBuf, u64)> {
+        Self::markdown_files(roots)
+            .into_iter()
+            .filter_map(|(path, relative)| {
+                let mtime = Self::get_mtime(&path)?;
+                Some((relative, (path, mtime)))
+            })
+            .collect()
+    }
+
+    /// Documents whose file differs from the index per a `scan`: (changed or
+    /// new files, document paths whose file is gone)
+    pub fn diff_scan(&self, scanned: &HashMap<String, (PathBuf, u64)>) -> (Vec<(PathBuf, String)>, Vec<String>) {
+        let changed = scanned
+            .iter()
+            .filter(|(relative, (_, mtime))| self.mtimes.get(*relative) != Some(mtime))
+            .map(|(relative, (path, _))| (path.clone(), relative.clone()))
+            .collect();
+        let removed = self
+            .documents
+            .keys()
+            .filter(|p| !scanned.contains_key(*p))
+            .cloned()
+            .collect();
+        (changed, removed)
+    }
+
+    /// Text of a document file to index. Documents in `metadataOnly`
+    /// directories are cut off where their body starts, so only their title
+    /// and file-level metadata are parsed, searched and held in memory.
+    fn read_indexed(roots: &Roots, path: &Path) -> std::io::Result<String> {
+        if !roots.is_metadata_only(path) {
+            return std::fs::read_to_string(path);
+        }
+        let mut bytes = Vec::new();
+        std::fs::File::open(path)?
+            .take(METADATA_HEAD_BYTES)
+            .read_to_end(&mut bytes)?;
+        let content = String::from_utf8_lossy(&bytes);
+        Ok(metadata_head(&content).to_string())
+    }
+
+    /// Parse a file under any root, namespacing its path by root
+    fn parse_file(roots: &Roots, full_path: &Path, content: &str) -> OrgDocument {
+        let (prefix, root) = roots
+            .locate(full_path)
+            .unwrap_or_else(|| (String::new(), roots.primary()));
+        let mut doc = parse_document(full_path, root, content);
+        doc.path.insert_str(0, &prefix);
+        doc
+    }
+
+    fn should_exclude(roots: &Roots, path: &Path, org_root: &Path) -> bool {
+        // `.orgviewerignore` comes first, and its `!` patterns can bring back
+        // anything the built-in rules below skip
+        if let Some(ignored) = roots.ignore_match(path, path.is_dir()) {
+            return ignored;
+        }
+
         let relative = path.strip_prefix(org_root).unwrap_or(path);
         let components: Vec<_> = relative.components().collect();
 
@@ -423,19 +1079,139 @@ impl DocumentIndex {
         self.documents.get(path)
     }
 
+    /// Org headings for one document
+    pub fn get_headings(&self, path: &str) -> &[Heading] {
+        self.headings.get(path).map(|h| h.as_slice()).unwrap_or(&[])
+    }
+
+    /// All documents paired with their headings
+    pub fn documents_with_headings(&self) -> impl Iterator<Item = (&OrgDocument, &[Heading])> {
+        self.documents
+            .iter()
+            .map(move |(path, doc)| (doc, self.get_headings(path)))
+    }
+
+    /// Look up the file and heading that own an org ID
+    pub fn resolve_id(&self, id: &str) -> Option<&IdTarget> {
+        self.ids.get(id)
+    }
+
+    /// Documents titled or aliased `title` (case-insensitive), by path
+    pub fn resolve_title(&self, title: &str) -> Vec<&OrgDocument> {
+        let mut docs: Vec<&OrgDocument> = self
+            .titles
+            .get(&title.trim().to_lowercase())
+            .into_iter()
+            .flatten()
+            .filter_map(|p| self.documents.get(p))
+            .collect();
+        docs.sort_by(|a, b| a.path.cmp(&b.path));
+        docs
+    }
+
+    pub fn roots(&self) -> &Roots {
+        &self.roots
+    }
+
     pub async fn get_document_with_content(&self, path: &str) -> Option<OrgDocument> {
         let doc = self.documents.get(path)?;
         let mut doc = doc.clone();
 
-        let full_path = self.org_root.join(path);
-        if let Ok(content) = tokio::fs::read_to_string(&full_path).await {
-            doc.content = Some(content);
-        }
+        let full_path = self.roots.resolve(path);
+        let modified = match tokio::fs::metadata(&full_path).await.and_then(|m| m.modified()) {
+            Ok(m) => m,
+            Err(_) => return Some(doc),
+        };
+        let cached = self
+            .bodies
+            .lock()
+            .unwrap()
+            .get(path)
+            .filter(|b| b.modified == modified)
+            .cloned();
+        let body = match cached {
+            Some(body) => body,
+            None => match tokio::fs::read_to_string(&full_path).await {
+                Ok(content) => {
+                    // Deriving footnotes, math and highlighting is CPU-bound and
+                    // takes a while on multi-megabyte files, so keep it off the
+                    // async workers
+                    let file = full_path.clone();
+                    let parse = move || LoadedBody::parse(&file, content, modified);
+                    let body = match tokio::task::spawn_blocking(parse).await {
+                        Ok(b) => b,
+                        Err(_) => return Some(doc),
+                    };
+                    self.bodies.lock().unwrap().put(path.to_string(), body.clone());
+                    body
+                }
+                Err(_) => return Some(doc),
+            },
+        };
+
+        // Attachment directories can change without the document changing
+        let prefix = self.roots.prefix_of(path);
+        doc.attachments = list_attachments(&self.roots, path, &body.content)
+            .into_iter()
+            .map(|a| format!("{}{}", prefix, a))
+            .collect();
+        doc.anchors = custom_id_anchors(self.get_headings(path));
+        doc.footnotes = body.footnotes;
+        doc.math = body.math;
+        doc.src_blocks = body.src_blocks;
+        doc.rollups = body.rollups;
+        doc.history = body.history;
+        doc.encrypted = body.encrypted;
+        doc.todo_keywords = Some(body.todo_keywords);
+        doc.content = Some(body.content);
 
         Some(doc)
     }
 
+    /// Title and heading matches for search-as-you-type
+    pub fn instant_search(&self, query: &str, limit: usize) -> Vec<InstantMatch> {
+        self.instant.search(query, limit)
+    }
+
+    /// Modification time (Unix seconds) recorded for a document
+    pub fn get_mtime_secs(&self, path: &str) -> Option<u64> {
+        self.mtimes.get(path).copied()
+    }
+
+    /// Ranked full-text search; falls back to fuzzy title/path/tag matching
+    /// when nothing matches (typos, partial words)
     pub fn search(&self, query: &str) -> Vec<&OrgDocument> {
+        let mut results = self.search_filtered(query, |_| true);
+        results.truncate(SEARCH_LIMIT);
+        results
+    }
+
+    /// Every hit for `query` passing `filter`, best first and untruncated so
+    /// callers can page through them. An empty query lists every passing
+    /// document, most recently modified first.
+    pub fn search_filtered<F: Fn(&OrgDocument) -> bool>(&self, query: &str, filter: F) -> Vec<&OrgDocument> {
+        if query.trim().is_empty() {
+            let mut docs: Vec<&OrgDocument> = self.documents.values().filter(|d| filter(d)).collect();
+            docs.sort_by_key(|d| std::cmp::Reverse(self.mtimes.get(&d.path).copied().unwrap_or(0)));
+            return docs;
+        }
+
+        let ranked: Vec<&OrgDocument> = self
+            .search
+            .search(query, self.documents.len().max(1))
+            .into_iter()
+            .filter_map(|(path, _)| self.documents.get(&path))
+            .filter(|d| filter(d))
+            .collect();
+        // A structured query that matched nothing means no results, not a typo
+        if !ranked.is_empty() || uses_query_syntax(query) {
+            return ranked;
+        }
+
+        self.fuzzy_search(query, &filter)
+    }
+
+    fn fuzzy_search<F: Fn(&OrgDocument) -> bool>(&self, query: &str, filter: &F) -> Vec<&OrgDocument> {
         use fuzzy_matcher::skim::SkimMatcherV2;
         use fuzzy_matcher::FuzzyMatcher;
 
@@ -445,6 +1221,7 @@ impl DocumentIndex {
         let mut results: Vec<(&OrgDocument, i64)> = self
             .documents
             .values()
+            .filter(|doc| filter(doc))
             .filter_map(|doc| {
                 // Search in title
                 let title_score = matcher.fuzzy_match(&doc.title, &query_lower).unwrap_or(0);
@@ -471,7 +1248,35 @@ impl DocumentIndex {
             .collect();
 
         results.sort_by(|a, b| b.1.cmp(&a.1));
-        results.into_iter().map(|(doc, _)| doc).take(50).collect()
+        results.into_iter().map(|(doc, _)| doc).collect()
+    }
+
+    pub fn last_load(&self) -> Option<&LoadReport> {
+        self.last_load.as_ref()
+    }
+
+    /// Rough heap usage of resident metadata and cached bodies, measured as
+    /// serialized size
+    pub fn memory_estimate(&self) -> MemoryEstimate {
+        let documents = self
+            .documents
+            .values()
+            .map(|d| serde_json::to_vec(d).map(|v| v.len()).unwrap_or(0))
+            .sum();
+        let headings = self
+            .headings
+            .values()
+            .map(|h| serde_json::to_vec(h).map(|v| v.len()).unwrap_or(0))
+            .sum();
+        let bodies = self.bodies.lock().unwrap();
+        MemoryEstimate {
+            documents,
+            headings,
+            bodies: bodies.bytes,
+            body_budget: bodies.budget,
+            cached_bodies: bodies.entries.len(),
+            total: documents + headings + bodies.bytes,
+        }
     }
 
     pub fn get_stats(&self) -> IndexStats {
@@ -493,46 +1298,238 @@ impl DocumentIndex {
     }
 
     pub fn refresh_document(&mut self, path: &Path) {
-        let relative = path
-            .strip_prefix(&self.org_root)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .replace('\\', "/");
-
-        if let Ok(content) = std::fs::read_to_string(path) {
-            let doc = parse_document(path, &self.org_root, &content);
-
-            // Update mtime
-            if let Some(mtime) = Self::get_mtime(path) {
-                self.mtimes.insert(relative.clone(), mtime);
+        if let Some((relative, changed)) = self.load_file(path) {
+            if changed {
+                self.search.commit();
+
+                // Rebuild backlinks since links may have changed
+                self.rebuild_backlinks();
             }
+            self.persist(&[relative.as_str()], &[]);
+        }
+    }
 
-            self.documents.insert(relative, doc);
+    pub fn remove_document(&mut self, path: &Path) {
+        if let Some(relative) = self.unload_file(path) {
+            self.search.commit();
 
-            // Rebuild backlinks since links may have changed
+            // Rebuild backlinks since a document was removed
             self.rebuild_backlinks();
 
-            // Save updated index (debounce this in production)
-            self.save_to_disk();
+            self.persist(&[], &[relative]);
         }
     }
 
-    pub fn remove_document(&mut self, path: &Path) {
-        let relative = path
-            .strip_prefix(&self.org_root)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .replace('\\', "/");
+    /// Apply a batch of file changes at once: one search commit, backlink
+    /// rebuild and cache write however many files changed. A new file with
+    /// the same content as a removed one is reported as a rename rather than
+    /// a removal plus an addition; files whose content didn't change aren't
+    /// reported at all.
+    pub fn apply_changes(&mut self, changed: &[PathBuf], removed: &[PathBuf]) -> AppliedChanges {
+        let renamed = self.detect_renames(changed, removed);
+
+        let mut updated: Vec<String> = Vec::new();
+        let mut touched: Vec<String> = Vec::new();
+        let mut previous: HashMap<String, String> = HashMap::new();
+        for path in changed {
+            // A cached body is what the document's viewers were last served
+            let cached = self
+                .roots
+                .relativize(path)
+                .and_then(|relative| Some(self.bodies.lock().unwrap().get(&relative)?.content.clone()));
+            match self.load_file(path) {
+                Some((relative, true)) => {
+                    if let Some(content) = cached {
+                        previous.insert(relative.clone(), content);
+                    }
+                    updated.push(relative);
+                }
+                Some((relative, false)) => touched.push(relative),
+                None => {}
+            }
+        }
+        // Who linked to each removed document, before its backlinks go with it
+        let mut linked_from: HashMap<String, Vec<String>> = removed
+            .iter()
+            .filter_map(|p| self.roots.relativize(p))
+            .filter_map(|relative| {
+                let backlinks = self.documents.get(&relative)?.backlinks.clone();
+                Some((relative, backlinks))
+            })
+            .collect();
+        let gone: Vec<String> = removed.iter().filter_map(|p| self.unload_file(p)).collect();
 
-        self.documents.remove(&relative);
-        self.mtimes.remove(&relative);
+        if !updated.is_empty() || !gone.is_empty() {
+            self.search.commit();
+            self.rebuild_backlinks();
+        }
+        let paths: Vec<&str> = updated.iter().chain(touched.iter()).map(|p| p.as_str()).collect();
+        self.persist(&paths, &gone);
 
-        // Rebuild backlinks since a document was removed
-        self.rebuild_backlinks();
+        // Renames are reported on their own, not as an update plus a removal
+        updated.retain(|path| !renamed.iter().any(|(_, to)| to == path));
+        let gone: Vec<String> = gone
+            .into_iter()
+            .filter(|path| !renamed.iter().any(|(from, _)| from == path))
+            .collect();
+        previous.retain(|path, _| updated.contains(path));
+        linked_from.retain(|path, _| gone.contains(path));
+        for sources in linked_from.values_mut() {
+            sources.retain(|source| self.documents.contains_key(source));
+        }
+        AppliedChanges {
+            updated,
+            removed: gone,
+            renamed,
+            linked_from,
+            previous,
+        }
+    }
 
-        // Save updated index
-        self.save_to_disk();
+    /// Pair new files in `changed` one to one with files in `removed` that
+    /// had the same content, and carry each old path's parsed outline over to
+    /// the new one so loading it doesn't start from scratch
+    fn detect_renames(&mut self, changed: &[PathBuf], removed: &[PathBuf]) -> Vec<(String, String)> {
+        let removed_hashes: Vec<(u64, String)> = removed
+            .iter()
+            .filter_map(|path| self.roots.relativize(path))
+            .filter_map(|relative| Some((*self.hashes.get(&relative)?, relative)))
+            .collect();
+        if removed_hashes.is_empty() {
+            return Vec::new();
+        }
+        let added: Vec<(u64, String)> = changed
+            .iter()
+            .filter_map(|path| Some((path, self.roots.relativize(path)?)))
+            .filter(|(_, relative)| !self.documents.contains_key(relative))
+            .filter_map(|(path, relative)| {
+                let content = Self::read_indexed(&self.roots, path).ok()?;
+                Some((content_hash(&content), relative))
+            })
+            .collect();
+
+        let mut renamed = Vec::new();
+        for (from, relative) in unique_moves(removed_hashes, added) {
+            if let Some(headings) = self.headings.remove(&from) {
+                self.headings.insert(relative.clone(), headings);
+            }
+            if let Some(outline) = self.outlines.remove(&from) {
+                self.outlines.insert(relative.clone(), outline);
+            }
+            println!("Detected rename: {} -> {}", from, relative);
+            self.record_move(&from, &relative);
+            renamed.push((from, relative));
+        }
+        if !renamed.is_empty() {
+            self.save_moves();
+        }
+        renamed
+    }
+
+    /// Remember that the document at `to` used to live at `from`, along with
+    /// any paths `from` had itself been moved from
+    fn record_move(&mut self, from: &str, to: &str) {
+        let mut previous = self.moved_from.remove(from).unwrap_or_default();
+        previous.push(from.to_string());
+        // A document now lives at `to` again, so it stops being an old name
+        previous.retain(|path| path != to);
+        for paths in self.moved_from.values_mut() {
+            paths.retain(|path| path != to);
+        }
+        self.moved_from.retain(|_, paths| !paths.is_empty());
+        self.moved_from.insert(to.to_string(), previous);
+    }
+
+    fn save_moves(&self) {
+        match serde_json::to_string(&self.moved_from) {
+            Ok(json) => self.store.save_meta(MOVED_FROM_KEY, &json),
+            Err(e) => println!("Failed to serialize moved paths: {}", e),
+        }
     }
+
+    /// Reparse a file into the index and stage it for search, without
+    /// committing or rebuilding backlinks. Returns its document path and
+    /// whether its content changed; if not, only the mtime is updated.
+    fn load_file(&mut self, path: &Path) -> Option<(String, bool)> {
+        let relative = self.roots.relativize(path)?;
+        let content = Self::read_indexed(&self.roots, path).ok()?;
+
+        // Update mtime
+        let mtime = Self::get_mtime(path);
+        if let Some(mtime) = mtime {
+            self.mtimes.insert(relative.clone(), mtime);
+        }
+
+        let hash = content_hash(&content);
+        if self.documents.contains_key(&relative) && self.hashes.get(&relative) == Some(&hash) {
+            return Some((relative, false));
+        }
+        self.hashes.insert(relative.clone(), hash);
+        let doc = Self::parse_file(&self.roots, path, &content);
+        let headings = match (self.headings.get(&relative), self.outlines.get(&relative)) {
+            (Some(previous), Some(snapshot)) if is_org_file(path) => reparse_headings(previous, snapshot, &content),
+            _ => parse_document_headings(path, &content),
+        };
+        self.outlines.insert(relative.clone(), OutlineSnapshot::new(&content));
+        self.bodies.lock().unwrap().pop(&relative);
+        self.search.stage(&doc, &headings, &content, mtime.unwrap_or(0));
+        self.headings.insert(relative.clone(), headings);
+        self.instant.update(&doc, self.headings.get(&relative).map(|h| h.as_slice()).unwrap_or(&[]));
+
+        self.documents.insert(relative.clone(), doc);
+        Some((relative, true))
+    }
+
+    /// Drop a file from the index and stage its removal from search. Files
+    /// that were never indexed, like an editor's short-lived temp file, are
+    /// ignored.
+    fn unload_file(&mut self, path: &Path) -> Option<String> {
+        let relative = self.roots.relativize(path)?;
+
+        self.documents.remove(&relative)?;
+        self.mtimes.remove(&relative);
+        self.hashes.remove(&relative);
+        self.headings.remove(&relative);
+        self.outlines.remove(&relative);
+        self.bodies.lock().unwrap().pop(&relative);
+        self.search.stage_removal(&relative);
+        self.instant.remove(&relative);
+        Some(relative)
+    }
+}
+
+/// Documents an `apply_changes` batch touched
+#[derive(Debug, Clone, Default)]
+pub struct AppliedChanges {
+    /// Documents added or edited
+    pub updated: Vec<String>,
+    /// Documents deleted
+    pub removed: Vec<String>,
+    /// Documents moved, as (from, to)
+    pub renamed: Vec<(String, String)>,
+    /// Documents that still link to each removed one
+    pub linked_from: HashMap<String, Vec<String>>,
+    /// Content before the change of updated documents that had their body
+    /// cached, i.e. ones recently viewed, so viewers can be sent a patch
+    pub previous: HashMap<String, String>,
+}
+
+/// A link left pointing at a path a document moved away from
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleLink {
+    /// Document containing the link
+    pub source: String,
+    pub link: String,
+}
+
+/// Outcome of `import_entries`
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Entries whose file differs here, so it was parsed locally instead
+    pub reparsed: usize,
+    /// Entries whose file doesn't exist under the roots here
+    pub missing: usize,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -541,3 +1538,38 @@ pub struct IndexStats {
     pub by_type: HashMap<String, usize>,
     pub by_status: HashMap<String, usize>,
 }
+
+/// Outcome of the startup load
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LoadReport {
+    /// Documents reused from the on-disk cache
+    #[serde(rename = "cacheHits")]
+    pub cache_hits: usize,
+    /// Documents that were new or changed and had to be parsed
+    #[serde(rename = "cacheMisses")]
+    pub cache_misses: usize,
+    /// Cached documents no longer on disk
+    pub removed: usize,
+    #[serde(rename = "parseMs")]
+    pub parse_ms: u64,
+    #[serde(rename = "totalMs")]
+    pub total_ms: u64,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: String,
+    /// Why the cache was discarded, if it failed validation
+    #[serde(rename = "cacheCorruption")]
+    pub corruption: Option<String>,
+}
+
+/// Approximate bytes held by the index
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MemoryEstimate {
+    pub documents: usize,
+    pub headings: usize,
+    pub bodies: usize,
+    #[serde(rename = "bodyBudget")]
+    pub body_budget: usize,
+    #[serde(rename = "cachedBodies")]
+    pub cached_bodies: usize,
+    pub total: usize,
+}
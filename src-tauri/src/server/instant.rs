@@ -0,0 +1,215 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::server::document::OrgDocument;
+use crate::server::org::Heading;
+use crate::server::AppState;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+/// A document title or heading, kept lowercased for matching
+struct Entry {
+    path: String,
+    /// Heading line; `None` for the document title
+    line: Option<usize>,
+    title: String,
+    lower: String,
+}
+
+/// In-memory trigram index over document titles and headings, for per-keystroke
+/// lookups that shouldn't wait on the full-text index
+#[derive(Default)]
+pub struct InstantIndex {
+    /// Slots freed by removals are reused through `free`
+    entries: Vec<Option<Entry>>,
+    free: Vec<usize>,
+    by_path: HashMap<String, Vec<usize>>,
+    trigrams: HashMap<[char; 3], Vec<usize>>,
+}
+
+#[derive(Serialize)]
+pub struct InstantMatch {
+    path: String,
+    title: String,
+    /// Heading line, absent for document title matches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+}
+
+fn trigrams(text: &str) -> Vec<[char; 3]> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut grams: Vec<[char; 3]> = chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect();
+    grams.sort_unstable();
+    grams.dedup();
+    grams
+}
+
+/// Higher is better: whole-title and prefix matches beat word starts, which beat substrings
+fn score(lower: &str, query: &str, terms: &[&str]) -> i64 {
+    let base = if lower == query {
+        1000
+    } else if lower.starts_with(query) {
+        800
+    } else if lower.split(|c: char| !c.is_alphanumeric()).any(|w| w.starts_with(query)) {
+        600
+    } else if lower.contains(query) {
+        400
+    } else {
+        // Terms matched out of order
+        200
+    };
+    let word_starts = terms
+        .iter()
+        .filter(|t| lower.split(|c: char| !c.is_alphanumeric()).any(|w| w.starts_with(*t)))
+        .count() as i64;
+    base + word_starts * 50
+}
+
+impl InstantIndex {
+    /// Replace everything indexed for `doc` with its title and headings
+    pub fn update(&mut self, doc: &OrgDocument, headings: &[Heading]) {
+        self.remove(&doc.path);
+        let items = std::iter::once((None, doc.title.clone()))
+            .chain(headings.iter().map(|h| (Some(h.line), h.title.clone())));
+
+        let mut ids = Vec::new();
+        for (line, title) in items {
+            let lower = title.to_lowercase();
+            let entry = Entry {
+                path: doc.path.clone(),
+                line,
+                title,
+                lower,
+            };
+            let id = match self.free.pop() {
+                Some(id) => {
+                    self.entries[id] = Some(entry);
+                    id
+                }
+                None => {
+                    self.entries.push(Some(entry));
+                    self.entries.len() - 1
+                }
+            };
+            if let Some(entry) = &self.entries[id] {
+                for gram in trigrams(&entry.lower) {
+                    self.trigrams.entry(gram).or_default().push(id);
+                }
+            }
+            ids.push(id);
+        }
+        self.by_path.insert(doc.path.clone(), ids);
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        let ids = match self.by_path.remove(path) {
+            Some(ids) => ids,
+            None => return,
+        };
+        for id in ids {
+            if let Some(entry) = self.entries[id].take() {
+                for gram in trigrams(&entry.lower) {
+                    if let Some(postings) = self.trigrams.get_mut(&gram) {
+                        postings.retain(|&p| p != id);
+                        if postings.is_empty() {
+                            self.trigrams.remove(&gram);
+                        }
+                    }
+                }
+            }
+            self.free.push(id);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Titles and headings containing every whitespace-separated term, best first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<InstantMatch> {
+        let query = query.trim().to_lowercase();
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        // Narrow to entries holding every trigram of the query terms; queries
+        // shorter than a trigram fall back to a scan, which is cheap for titles
+        let mut candidates: Option<Vec<usize>> = None;
+        for gram in terms.iter().flat_map(|t| trigrams(t)) {
+            let postings = match self.trigrams.get(&gram) {
+                Some(p) => p,
+                None => return Vec::new(),
+            };
+            candidates = Some(match candidates {
+                None => postings.clone(),
+                Some(current) => current.into_iter().filter(|id| postings.contains(id)).collect(),
+            });
+        }
+        let candidates = candidates.unwrap_or_else(|| (0..self.entries.len()).collect());
+
+        let mut matches: Vec<(i64, &Entry)> = candidates
+            .into_iter()
+            .filter_map(|id| self.entries.get(id)?.as_ref())
+            .filter(|e| terms.iter().all(|t| e.lower.contains(t)))
+            .map(|e| {
+                // Document titles rank above headings with the same match
+                let bonus = if e.line.is_none() { 25 } else { 0 };
+                (score(&e.lower, &query, &terms) + bonus, e)
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then(a.1.title.len().cmp(&b.1.title.len()))
+                .then(a.1.path.cmp(&b.1.path))
+                .then(a.1.line.cmp(&b.1.line))
+        });
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, e)| InstantMatch {
+                path: e.path.clone(),
+                title: e.title.clone(),
+                line: e.line,
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct InstantQuery {
+    #[serde(default)]
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct InstantResponse {
+    query: String,
+    count: usize,
+    items: Vec<InstantMatch>,
+}
+
+/// GET /api/search/instant?q=&limit= - Search-as-you-type over document titles
+/// and headings only; use /api/search for body text
+pub async fn instant_search(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<InstantQuery>,
+) -> Json<InstantResponse> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let index = state.index.read().await;
+    let items = index.instant_search(&query.q, limit);
+
+    Json(InstantResponse {
+        query: query.q,
+        count: items.len(),
+        items,
+    })
+}
@@ -0,0 +1,135 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::document::is_org_file;
+use crate::server::timezone::{self, TzQuery};
+use crate::server::{dirty, log_to_file, AppState};
+
+/// Used when no `journalTemplate` is configured
+const DEFAULT_TEMPLATE: &str = "---\ntype: journal\ncreated: {{date}}\n---\n# {{title}}\n\n";
+
+/// As `DEFAULT_TEMPLATE`, for a journal pattern naming `.org` files
+const DEFAULT_ORG_TEMPLATE: &str = "#+TITLE: {{title}}\n#+TYPE: journal\n#+CREATED: {{date}}\n\n";
+
+/// Fill `{{date}}`, `{{weekday}}` and `{{title}}` placeholders for a journal day
+fn render_template(template: &str, date: NaiveDate) -> String {
+    template
+        .replace("{{date}}", &date.format("%Y-%m-%d").to_string())
+        .replace("{{weekday}}", &date.format("%A").to_string())
+        .replace("{{title}}", &date.format("%A, %B %-d, %Y").to_string())
+}
+
+#[derive(Serialize)]
+pub struct JournalEntry {
+    date: String,
+    path: String,
+    title: String,
+}
+
+#[derive(Serialize)]
+pub struct TodayResponse {
+    path: String,
+    date: String,
+    /// False when today's entry already existed
+    created: bool,
+}
+
+/// POST /api/journal/today?tz= - Create today's journal file if needed and return its path
+pub async fn today(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TzQuery>,
+) -> Result<Json<TodayResponse>, StatusCode> {
+    let date = timezone::now(&state.config, query.tz.as_deref())?.date();
+    let relative = date.format(&state.config.journal_pattern).to_string();
+    let full_path = state.org_root.join(&relative);
+
+    // Validate the configured pattern can't escape the org root
+    let canonical_root = state.org_root.canonicalize()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if relative.split('/').any(|segment| segment == "..") || !full_path.starts_with(&state.org_root) {
+        log_to_file(&format!("[journal] Pattern resolves outside org root: {}", relative));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut created = false;
+    if !full_path.exists() {
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                log_to_file(&format!("[journal] Failed to create directory: {}", e));
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        }
+
+        let default = if is_org_file(&full_path) { DEFAULT_ORG_TEMPLATE } else { DEFAULT_TEMPLATE };
+        let template = match &state.config.journal_template {
+            Some(t) => std::fs::read_to_string(canonical_root.join(t)).unwrap_or_else(|e| {
+                log_to_file(&format!("[journal] Failed to read template {}: {}", t, e));
+                default.to_string()
+            }),
+            None => default.to_string(),
+        };
+
+        std::fs::write(&full_path, render_template(&template, date)).map_err(|e| {
+            log_to_file(&format!("[journal] Failed to write entry: {}", e));
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        created = true;
+        log_to_file(&format!("[journal] Created {}", relative));
+
+        // Index right away so the client can open it without waiting for the watcher
+        dirty::apply_now(&state, vec![full_path]).await;
+    }
+
+    Ok(Json(TodayResponse {
+        path: relative,
+        date: date.format("%Y-%m-%d").to_string(),
+        created,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct JournalQuery {
+    /// Inclusive `YYYY-MM-DD` bounds
+    from: Option<String>,
+    to: Option<String>,
+}
+
+/// GET /api/journal?from=&to= - List journal entries chronologically
+pub async fn list_entries(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<JournalQuery>,
+) -> Json<Vec<JournalEntry>> {
+    let parse = |d: &Option<String>| d.as_deref().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    let (from, to) = (parse(&query.from), parse(&query.to));
+
+    let index = state.index.read().await;
+
+    // A document is a journal entry if its path parses with the journal pattern
+    let mut entries: Vec<(NaiveDate, JournalEntry)> = index
+        .get_documents()
+        .into_iter()
+        .filter_map(|doc| {
+            let date = NaiveDate::parse_from_str(&doc.path, &state.config.journal_pattern).ok()?;
+            if from.is_some_and(|from| date < from) || to.is_some_and(|to| date > to) {
+                return None;
+            }
+            Some((
+                date,
+                JournalEntry {
+                    date: date.format("%Y-%m-%d").to_string(),
+                    path: doc.path.clone(),
+                    title: doc.title.clone(),
+                },
+            ))
+        })
+        .collect();
+
+    entries.sort_by_key(|(date, _)| *date);
+    Json(entries.into_iter().map(|(_, entry)| entry).collect())
+}
@@ -0,0 +1,100 @@
+use axum::{extract::State, response::Json};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::server::document::extract_wikilinks;
+use crate::server::images::resolve_relative;
+use crate::server::AppState;
+
+#[derive(Serialize)]
+pub struct BrokenLink {
+    /// Document containing the link
+    file: String,
+    /// 1-based line of the link
+    line: usize,
+    /// Link target as written, e.g. `id:1234` or `notes/missing`
+    link: String,
+    /// `id`, `file` or `wiki`
+    kind: String,
+    context: String,
+}
+
+#[derive(Serialize)]
+pub struct BrokenLinksResponse {
+    count: usize,
+    items: Vec<BrokenLink>,
+}
+
+/// GET /api/links/broken - ID, file and wiki links whose targets don't exist
+pub async fn get_broken_links(State(state): State<Arc<AppState>>) -> Json<BrokenLinksResponse> {
+    let index = state.index.read().await;
+    let mut paths: Vec<String> = index.get_documents().iter().map(|d| d.path.clone()).collect();
+    paths.sort();
+
+    // Wiki links resolve the same way from every file, so check each target once
+    let mut wiki_cache: HashMap<String, bool> = HashMap::new();
+    let mut items = Vec::new();
+
+    for path in &paths {
+        let content = match tokio::fs::read_to_string(state.roots.resolve(path)).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+
+        let mut in_block = false;
+        for (i, line) in content.lines().enumerate() {
+            // Links inside code are examples, not references
+            let trimmed = line.trim_start().to_lowercase();
+            if trimmed.starts_with("#+begin_src") || trimmed.starts_with("#+begin_example") || trimmed.starts_with("```") {
+                in_block = !trimmed.starts_with("```") || !in_block;
+                continue;
+            }
+            if trimmed.starts_with("#+end_src") || trimmed.starts_with("#+end_example") {
+                in_block = false;
+                continue;
+            }
+            if in_block {
+                continue;
+            }
+
+            for link in extract_wikilinks(line) {
+                let link = link.trim().to_string();
+                let (kind, exists) = if let Some(id) = link.strip_prefix("id:") {
+                    ("id", index.resolve_id(id.trim()).is_some())
+                } else if let Some(target) = link.strip_prefix("file:") {
+                    let target = target.split("::").next().unwrap_or(target);
+                    match resolve_relative(path, target) {
+                        Some(relative) => ("file", state.roots.resolve(&relative).exists()),
+                        // Absolute and home-relative paths point outside the vault
+                        None => continue,
+                    }
+                } else if link.contains(':') || link.starts_with('#') || link.starts_with('*') {
+                    // URLs, other link types, and in-file targets
+                    continue;
+                } else {
+                    let target = link.split('#').next().unwrap_or(&link).to_string();
+                    let exists = *wiki_cache.entry(target.clone()).or_insert_with(|| {
+                        paths.iter().any(|doc| index.link_resolves_to(&target, path, doc))
+                    });
+                    ("wiki", exists)
+                };
+
+                if !exists {
+                    items.push(BrokenLink {
+                        file: path.clone(),
+                        line: i + 1,
+                        link,
+                        kind: kind.to_string(),
+                        context: line.trim().chars().take(200).collect(),
+                    });
+                }
+            }
+        }
+    }
+
+    Json(BrokenLinksResponse {
+        count: items.len(),
+        items,
+    })
+}
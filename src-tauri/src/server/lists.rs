@@ -0,0 +1,373 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::{log_to_file, AppState};
+
+/// One list item: its bullet line plus continuation lines up to the next item
+#[derive(Debug, Clone)]
+struct Item {
+    lines: Vec<String>,
+}
+
+/// A plain list found in a document, as 0-based line range (end exclusive)
+#[derive(Debug, Clone)]
+struct List {
+    start: usize,
+    end: usize,
+    items: Vec<Item>,
+}
+
+fn item_re() -> Regex {
+    Regex::new(r"^(\s*)([-+*]|\d+[.)])(\s+)(?:\[([ xX-])\](\s+|$))?").unwrap()
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+impl Item {
+    fn indent(&self) -> usize {
+        indent_of(&self.lines[0])
+    }
+
+    /// Column where the item's text starts, i.e. where children are indented to
+    fn content_column(&self, re: &Regex) -> usize {
+        re.captures(&self.lines[0])
+            .map(|c| c[1].len() + c[2].len() + c[3].len())
+            .unwrap_or(self.indent() + 2)
+    }
+
+    fn checkbox(&self, re: &Regex) -> Option<char> {
+        re.captures(&self.lines[0])?.get(4)?.as_str().chars().next()
+    }
+
+    /// Shift every line right (positive) or left (negative) by `delta` columns
+    fn shift(&mut self, delta: isize) {
+        for line in &mut self.lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if delta >= 0 {
+                *line = format!("{}{}", " ".repeat(delta as usize), line);
+            } else {
+                let remove = (-delta as usize).min(indent_of(line));
+                *line = line[remove..].to_string();
+            }
+        }
+    }
+}
+
+fn is_item_line(line: &str, re: &Regex) -> bool {
+    // A `*` in column 0 is a heading, not a bullet
+    re.is_match(line) && !line.starts_with('*')
+}
+
+fn is_ordered(line: &str) -> bool {
+    line.trim_start().starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Find all plain lists in document order, skipping src/example blocks and code fences
+fn find_lists(lines: &[&str]) -> Vec<List> {
+    let re = item_re();
+    let mut lists = Vec::new();
+    let mut in_block = false;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let trimmed = lines[i].trim_start();
+        let lower = trimmed.to_lowercase();
+        if lower.starts_with("#+begin_") || trimmed.starts_with("```") {
+            in_block = !trimmed.starts_with("```") || !in_block;
+            i += 1;
+            continue;
+        }
+        if lower.starts_with("#+end_") {
+            in_block = false;
+            i += 1;
+            continue;
+        }
+        if in_block || !is_item_line(lines[i], &re) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let base = indent_of(lines[i]);
+        let ordered = is_ordered(lines[i]);
+        let mut items: Vec<Item> = Vec::new();
+        let mut end = i;
+        while i < lines.len() {
+            let line = lines[i];
+            let item_line = is_item_line(line, &re);
+            if item_line && indent_of(line) == base && is_ordered(line) != ordered {
+                // Switching between bullets and numbers starts a new list
+                break;
+            }
+            if item_line && indent_of(line) >= base {
+                items.push(Item { lines: vec![line.to_string()] });
+            } else if !line.trim().is_empty() && indent_of(line) > base {
+                items.last_mut().unwrap().lines.push(line.to_string());
+            } else if line.trim().is_empty() && lines.get(i + 1).is_some_and(|next| {
+                !next.trim().is_empty() && indent_of(next) >= base && (indent_of(next) > base || is_item_line(next, &re))
+            }) {
+                // A single blank line doesn't end the list
+                items.last_mut().unwrap().lines.push(line.to_string());
+            } else {
+                break;
+            }
+            i += 1;
+            end = i;
+        }
+
+        // A blank line before a list of the other kind belongs to neither
+        while items.last().is_some_and(|item| item.lines.len() > 1 && item.lines.last().unwrap().trim().is_empty()) {
+            items.last_mut().unwrap().lines.pop();
+            end -= 1;
+        }
+
+        lists.push(List { start, end, items });
+    }
+
+    lists
+}
+
+/// Index one past the last item of `idx`'s subtree
+fn subtree_end(items: &[Item], idx: usize) -> usize {
+    let indent = items[idx].indent();
+    items[idx + 1..]
+        .iter()
+        .position(|i| i.indent() <= indent)
+        .map(|p| idx + 1 + p)
+        .unwrap_or(items.len())
+}
+
+/// Indices of the items sharing `idx`'s parent, in order
+fn siblings(items: &[Item], idx: usize) -> Vec<usize> {
+    let indent = items[idx].indent();
+    let parent_start = items[..idx]
+        .iter()
+        .rposition(|i| i.indent() < indent)
+        .map(|p| p + 1)
+        .unwrap_or(0);
+
+    let mut result = Vec::new();
+    let mut j = parent_start;
+    while j < items.len() && items[j].indent() >= indent {
+        if items[j].indent() == indent {
+            result.push(j);
+        }
+        j = subtree_end(items, j);
+    }
+    result
+}
+
+/// Renumber ordered bullets per sibling group and refresh checkbox state and
+/// `[n/m]` / `[n%]` cookies from each item's direct children
+fn normalize(items: &mut [Item]) {
+    let re = item_re();
+    let number_re = Regex::new(r"^(\s*)\d+([.)])").unwrap();
+    let cookie_re = Regex::new(r"\[(\d*%|\d*/\d*)\]").unwrap();
+
+    // (indent, counter) for each open nesting level
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    for item in items.iter_mut() {
+        let indent = item.indent();
+        while stack.last().is_some_and(|(d, _)| *d > indent) {
+            stack.pop();
+        }
+        if stack.last().is_none_or(|(d, _)| *d != indent) {
+            stack.push((indent, 0));
+        }
+        let counter = &mut stack.last_mut().unwrap().1;
+        // Only numbered siblings count; a bullet item restarts the numbering
+        *counter = if is_ordered(&item.lines[0]) { *counter + 1 } else { 0 };
+        let count = *counter;
+        item.lines[0] = number_re
+            .replace(&item.lines[0], |c: &regex::Captures| format!("{}{}{}", &c[1], count, &c[2]))
+            .to_string();
+    }
+
+    // Children before parents, so nested parents see updated states
+    for idx in (0..items.len()).rev() {
+        let indent = items[idx].indent();
+        let end = subtree_end(items, idx);
+        let mut done = 0;
+        let mut total = 0;
+        let mut j = idx + 1;
+        while j < end {
+            if items[j].indent() > indent {
+                if let Some(state) = items[j].checkbox(&re) {
+                    total += 1;
+                    if state == 'X' || state == 'x' {
+                        done += 1;
+                    }
+                }
+            }
+            j = subtree_end(items, j);
+        }
+        if total == 0 {
+            continue;
+        }
+
+        let line = items[idx].lines[0].clone();
+        let line = cookie_re
+            .replace(&line, |c: &regex::Captures| {
+                if c[1].ends_with('%') {
+                    format!("[{}%]", done * 100 / total)
+                } else {
+                    format!("[{}/{}]", done, total)
+                }
+            })
+            .to_string();
+        let state = if done == total { 'X' } else if done == 0 { ' ' } else { '-' };
+        items[idx].lines[0] = match re.captures(&line).and_then(|c| c.get(4)) {
+            Some(m) => format!("{}{}{}", &line[..m.start()], state, &line[m.end()..]),
+            None => line,
+        };
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateListRequest {
+    /// 0-based index of the list within the document
+    list: usize,
+    /// 0-based item, counting nested items in document order
+    item: usize,
+    /// `check`, `uncheck`, `toggle`, `indent`, `outdent` or `move`
+    action: String,
+    /// New 0-based position among the item's siblings, for `move`
+    to: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateListResponse {
+    /// List text as written to disk
+    list: Vec<String>,
+}
+
+/// Apply one structural edit to a list, returning the new content and the rendered list
+fn apply_list_update(content: &str, req: &UpdateListRequest) -> Result<(String, Vec<String>), StatusCode> {
+    let re = item_re();
+    let lines: Vec<&str> = content.lines().collect();
+    let list = find_lists(&lines).into_iter().nth(req.list).ok_or(StatusCode::NOT_FOUND)?;
+    let mut items = list.items.clone();
+    let idx = req.item;
+    if idx >= items.len() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    match req.action.as_str() {
+        "check" | "uncheck" | "toggle" => {
+            let checked = match req.action.as_str() {
+                "check" => true,
+                "uncheck" => false,
+                _ => !matches!(items[idx].checkbox(&re), Some('X') | Some('x')),
+            };
+            let mark = if checked { 'X' } else { ' ' };
+            let line = items[idx].lines[0].clone();
+            let caps = re.captures(&line).ok_or(StatusCode::NOT_FOUND)?;
+            items[idx].lines[0] = match caps.get(4) {
+                Some(m) => format!("{}{}{}", &line[..m.start()], mark, &line[m.end()..]),
+                // No checkbox yet: add one after the bullet
+                None => {
+                    let at = caps.get(3).map(|m| m.end()).unwrap_or(line.len());
+                    format!("{}[{}] {}", &line[..at], mark, &line[at..])
+                }
+            };
+        }
+        "indent" => {
+            // Nest under the previous sibling
+            let sibs = siblings(&items, idx);
+            let pos = sibs.iter().position(|&s| s == idx).unwrap_or(0);
+            let prev = pos
+                .checked_sub(1)
+                .and_then(|p| sibs.get(p).copied())
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            let delta = items[prev].content_column(&re) as isize - items[idx].indent() as isize;
+            let end = subtree_end(&items, idx);
+            for item in &mut items[idx..end] {
+                item.shift(delta);
+            }
+        }
+        "outdent" => {
+            let indent = items[idx].indent();
+            let parent = items[..idx]
+                .iter()
+                .rposition(|i| i.indent() < indent)
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            let delta = items[parent].indent() as isize - indent as isize;
+            let end = subtree_end(&items, idx);
+            for item in &mut items[idx..end] {
+                item.shift(delta);
+            }
+        }
+        "move" => {
+            let to = req.to.ok_or(StatusCode::BAD_REQUEST)?;
+            let sibs = siblings(&items, idx);
+            let target = *sibs.get(to).ok_or(StatusCode::BAD_REQUEST)?;
+            let end = subtree_end(&items, idx);
+            let moved: Vec<Item> = items.drain(idx..end).collect();
+            // Insert before the target sibling, or after it when moving down
+            let insert_at = if target < idx {
+                target
+            } else {
+                subtree_end(&items, target - moved.len())
+            };
+            items.splice(insert_at..insert_at, moved);
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    }
+
+    normalize(&mut items);
+
+    let rendered: Vec<String> = items.into_iter().flat_map(|i| i.lines).collect();
+    let mut output: Vec<String> = lines[..list.start].iter().map(|l| l.to_string()).collect();
+    output.extend(rendered.iter().cloned());
+    output.extend(lines[list.end..].iter().map(|l| l.to_string()));
+
+    let mut new_content = output.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    Ok((new_content, rendered))
+}
+
+/// POST /api/files/*path/list - Check, indent/outdent or reorder one plain-list item
+pub async fn update_list(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Json(payload): Json<UpdateListRequest>,
+) -> Result<Json<UpdateListResponse>, StatusCode> {
+    log_to_file(&format!(
+        "[lists] {} item {} of list {} in {}",
+        payload.action, payload.item, payload.list, path
+    ));
+
+    // Validate path - prevent directory traversal
+    let full_path = state.roots.resolve(&path);
+    let canonical_path = full_path.canonicalize()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&path, &canonical_path) {
+        log_to_file(&format!("[lists] Rejected path traversal: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let content = tokio::fs::read_to_string(&canonical_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let (new_content, list) = apply_list_update(&content, &payload)?;
+
+    if let Err(e) = tokio::fs::write(&canonical_path, new_content).await {
+        log_to_file(&format!("[lists] Failed to write: {}", e));
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // File watcher will auto-refresh index
+    Ok(Json(UpdateListResponse { list }))
+}
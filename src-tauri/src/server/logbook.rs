@@ -0,0 +1,157 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::server::effort::{parse_clock_minutes, parse_org_timestamp};
+use crate::server::org::{parse_headings, parse_todo_keywords};
+
+/// A TODO state transition recorded by org's logging
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateChange {
+    pub to: String,
+    pub from: Option<String>,
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// A `- Note taken on [...]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogNote {
+    pub timestamp: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockEntry {
+    pub start: String,
+    pub end: Option<String>,
+    pub minutes: Option<i64>,
+}
+
+/// Structured logbook history for one heading
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskHistory {
+    pub line: usize,
+    pub title: String,
+    pub todo: Option<String>,
+    pub transitions: Vec<StateChange>,
+    pub notes: Vec<LogNote>,
+    pub clocks: Vec<ClockEntry>,
+    /// Timestamp of the most recent transition into a done state
+    pub completed: Option<String>,
+    /// Minutes spent in each state, derived from consecutive transitions
+    #[serde(rename = "timeInState")]
+    pub time_in_state: HashMap<String, i64>,
+}
+
+/// Parse logbook entries (state changes, notes, clocks) for every heading that has any.
+///
+/// Entries are read from the heading's whole section so both `:LOGBOOK:` drawers
+/// and inline logging (`org-log-into-drawer` unset) are picked up.
+pub fn parse_history(content: &str) -> Vec<TaskHistory> {
+    let state_re = Regex::new(
+        r#"^\s*-\s+State\s+"([^"]+)"\s+(?:from\s+(?:"([^"]*)"\s+)?)?\[([^\]]+)\]\s*(?:\\\\)?\s*$"#,
+    )
+    .unwrap();
+    let note_re = Regex::new(r"^\s*-\s+Note taken on\s+\[([^\]]+)\]\s*(?:\\\\)?\s*$").unwrap();
+    let clock_re = Regex::new(r"^\s*CLOCK:\s*\[([^\]]+)\](?:--\[([^\]]+)\])?").unwrap();
+    let item_re = Regex::new(r"^\s*(-\s|:END:|CLOCK:)").unwrap();
+
+    let lines: Vec<&str> = content.lines().collect();
+    let keywords = parse_todo_keywords(content);
+    let mut histories = Vec::new();
+
+    for heading in parse_headings(content) {
+        let section = &lines[heading.line..heading.section_end];
+        let mut transitions: Vec<StateChange> = Vec::new();
+        let mut notes: Vec<LogNote> = Vec::new();
+        let mut clocks: Vec<ClockEntry> = Vec::new();
+
+        let mut i = 0;
+        while i < section.len() {
+            let line = section[i];
+
+            if let Some(caps) = clock_re.captures(line) {
+                clocks.push(ClockEntry {
+                    start: caps[1].to_string(),
+                    end: caps.get(2).map(|m| m.as_str().to_string()),
+                    minutes: parse_clock_minutes(line),
+                });
+                i += 1;
+                continue;
+            }
+
+            // Notes continue on following indented lines until the next item
+            let continuation = |start: usize| -> (String, usize) {
+                let mut text: Vec<&str> = Vec::new();
+                let mut j = start;
+                while j < section.len() && !item_re.is_match(section[j]) && !section[j].trim().is_empty() {
+                    text.push(section[j].trim());
+                    j += 1;
+                }
+                (text.join(" "), j)
+            };
+
+            if let Some(caps) = state_re.captures(line) {
+                let (note, next) = continuation(i + 1);
+                transitions.push(StateChange {
+                    to: caps[1].to_string(),
+                    from: caps.get(2).map(|m| m.as_str().to_string()).filter(|s| !s.is_empty()),
+                    timestamp: caps[3].to_string(),
+                    note: Some(note).filter(|n| !n.is_empty()),
+                });
+                i = next;
+                continue;
+            }
+
+            if let Some(caps) = note_re.captures(line) {
+                let (text, next) = continuation(i + 1);
+                notes.push(LogNote {
+                    timestamp: caps[1].to_string(),
+                    text,
+                });
+                i = next;
+                continue;
+            }
+
+            i += 1;
+        }
+
+        if transitions.is_empty() && notes.is_empty() && clocks.is_empty() {
+            continue;
+        }
+
+        // Org logs newest first; sort oldest first for duration math
+        transitions.sort_by_key(|t| parse_org_timestamp(&t.timestamp));
+
+        let mut time_in_state: HashMap<String, i64> = HashMap::new();
+        for pair in transitions.windows(2) {
+            if let (Some(start), Some(end)) = (
+                parse_org_timestamp(&pair[0].timestamp),
+                parse_org_timestamp(&pair[1].timestamp),
+            ) {
+                *time_in_state.entry(pair[0].to.clone()).or_insert(0) += (end - start).num_minutes();
+            }
+        }
+
+        let completed = transitions
+            .iter()
+            .rev()
+            .find(|t| keywords.is_done(&t.to))
+            .map(|t| t.timestamp.clone());
+
+        histories.push(TaskHistory {
+            line: heading.line,
+            title: heading.title,
+            todo: heading.todo,
+            transitions,
+            notes,
+            clocks,
+            completed,
+            time_in_state,
+        });
+    }
+
+    histories
+}
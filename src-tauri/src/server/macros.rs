@@ -0,0 +1,102 @@
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+/// Maximum passes when expanding macros that produce further macro calls
+const MAX_EXPANSION_PASSES: usize = 5;
+
+/// Collect `#+MACRO:` definitions plus the keyword-backed built-ins
+/// (`title`, `author`, `date`, `email`).
+fn collect_macros(content: &str) -> (HashMap<String, String>, HashMap<String, String>) {
+    let macro_re = Regex::new(r"(?im)^\s*#\+MACRO:\s+(\S+)\s?(.*)$").unwrap();
+    let keyword_re = Regex::new(r"(?m)^\s*#\+([A-Za-z_]+):\s*(.*)$").unwrap();
+
+    let mut macros = HashMap::new();
+    for caps in macro_re.captures_iter(content) {
+        macros.insert(caps[1].to_lowercase(), caps[2].trim_end().to_string());
+    }
+
+    let mut keywords = HashMap::new();
+    for caps in keyword_re.captures_iter(content) {
+        keywords
+            .entry(caps[1].to_uppercase())
+            .or_insert_with(|| caps[2].trim().to_string());
+    }
+
+    for builtin in ["title", "author", "date", "email"] {
+        if let Some(value) = keywords.get(&builtin.to_uppercase()) {
+            macros.entry(builtin.to_string()).or_insert_with(|| value.clone());
+        }
+    }
+
+    (macros, keywords)
+}
+
+/// Split macro arguments on unescaped commas
+fn split_args(args: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut chars = args.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&',') => {
+                current.push(',');
+                chars.next();
+            }
+            ',' => result.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    result.push(current.trim().to_string());
+    result
+}
+
+/// Expand `{{{name(args)}}}` invocations using the document's `#+MACRO:` definitions.
+/// Unknown macros are left untouched.
+pub fn expand_macros(content: &str) -> String {
+    let (macros, keywords) = collect_macros(content);
+    let call_re = Regex::new(r"\{\{\{([\w-]+)(?:\((.*?)\))?\}\}\}").unwrap();
+    let placeholder_re = Regex::new(r"\$(\d+)").unwrap();
+
+    let mut text = content.to_string();
+    for _ in 0..MAX_EXPANSION_PASSES {
+        let mut changed = false;
+        let next = call_re
+            .replace_all(&text, |caps: &Captures| {
+                let name = caps[1].to_lowercase();
+                let args = caps.get(2).map(|m| split_args(m.as_str())).unwrap_or_default();
+
+                if name == "keyword" {
+                    if let Some(value) = args.first().and_then(|k| keywords.get(&k.to_uppercase())) {
+                        changed = true;
+                        return value.clone();
+                    }
+                    return caps[0].to_string();
+                }
+
+                match macros.get(&name) {
+                    Some(body) => {
+                        changed = true;
+                        placeholder_re
+                            .replace_all(body, |p: &Captures| {
+                                let n: usize = p[1].parse().unwrap_or(0);
+                                if n == 0 {
+                                    return String::new();
+                                }
+                                args.get(n - 1).cloned().unwrap_or_default()
+                            })
+                            .to_string()
+                    }
+                    None => caps[0].to_string(),
+                }
+            })
+            .to_string();
+
+        text = next;
+        if !changed {
+            break;
+        }
+    }
+
+    text
+}
@@ -0,0 +1,438 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A LaTeX fragment found in a document, pre-rendered to MathML when possible
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MathFragment {
+    /// Original LaTeX source including delimiters
+    pub source: String,
+    /// Display (block) math vs inline math
+    pub display: bool,
+    /// 1-based line where the fragment starts
+    pub line: usize,
+    /// Rendered MathML, or None if the fragment uses unsupported commands
+    /// (the client should fall back to its own renderer)
+    pub mathml: Option<String>,
+}
+
+/// Find `\( ... \)`, `\[ ... \]`, `$$ ... $$` and `\begin{equation} ... \end{equation}`
+/// fragments and render each to MathML
+pub fn extract_math(content: &str) -> Vec<MathFragment> {
+    let fragment_re = Regex::new(
+        r"(?s)\\\((.+?)\\\)|\\\[(.+?)\\\]|\$\$(.+?)\$\$|\\begin\{(equation\*?|displaymath)\}(.+?)\\end\{(?:equation\*?|displaymath)\}",
+    )
+    .unwrap();
+
+    fragment_re
+        .captures_iter(content)
+        .map(|caps| {
+            let whole = caps.get(0).unwrap();
+            let (tex, display) = if let Some(m) = caps.get(1) {
+                (m.as_str(), false)
+            } else if let Some(m) = caps.get(2).or_else(|| caps.get(3)) {
+                (m.as_str(), true)
+            } else {
+                (caps.get(5).map(|m| m.as_str()).unwrap_or(""), true)
+            };
+
+            MathFragment {
+                source: whole.as_str().to_string(),
+                display,
+                line: content[..whole.start()].matches('\n').count() + 1,
+                mathml: render_mathml(tex, display),
+            }
+        })
+        .collect()
+}
+
+/// Render a LaTeX math expression to MathML. Supports a common subset of
+/// LaTeX (scripts, fractions, roots, Greek letters, operators, text); returns
+/// None when anything outside that subset is encountered, or when it nests
+/// deeper than `MAX_DEPTH`.
+pub fn render_mathml(tex: &str, display: bool) -> Option<String> {
+    let tokens = tokenize(tex);
+    let mut parser = Parser { tokens, pos: 0, depth: 0 };
+    let body = parser.parse_sequence(None)?;
+    if parser.peek().is_some() {
+        // Unbalanced closing brace
+        return None;
+    }
+    Some(format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"{}\"><mrow>{}</mrow></math>",
+        if display { "block" } else { "inline" },
+        body
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Command(String),
+    Char(char),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Space,
+}
+
+fn tokenize(tex: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = tex.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut name = String::new();
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_alphabetic() {
+                        name.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if name.is_empty() {
+                    // Single-character command like \, \{ \\ \|
+                    if let Some(n) = chars.next() {
+                        name.push(n);
+                    }
+                }
+                tokens.push(Token::Command(name));
+            }
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '^' => tokens.push(Token::Sup),
+            '_' => tokens.push(Token::Sub),
+            c if c.is_whitespace() => {
+                if tokens.last() != Some(&Token::Space) {
+                    tokens.push(Token::Space);
+                }
+            }
+            c => tokens.push(Token::Char(c)),
+        }
+    }
+
+    tokens
+}
+
+/// Deepest nesting of groups and arguments rendered. The parser recurses
+/// once per level, so past this an expression is left as TeX rather than
+/// risk the stack.
+const MAX_DEPTH: usize = 64;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Sequences and groups currently being parsed
+    depth: usize,
+}
+
+impl Parser {
+    /// Whitespace is insignificant in math mode except inside \text{}
+    fn skip_space(&mut self) {
+        while self.tokens.get(self.pos) == Some(&Token::Space) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        self.skip_space();
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.skip_space();
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    /// Go a level deeper, or None at `MAX_DEPTH`. Any None abandons the
+    /// whole expression, so levels an early return leaves entered don't matter.
+    fn enter(&mut self) -> Option<()> {
+        (self.depth < MAX_DEPTH).then(|| self.depth += 1)
+    }
+
+    /// Parse atoms until the end of input or a closing token
+    fn parse_sequence(&mut self, until: Option<&Token>) -> Option<String> {
+        self.enter()?;
+        let mut out = String::new();
+        while let Some(tok) = self.peek() {
+            if Some(tok) == until || *tok == Token::Close {
+                break;
+            }
+            out.push_str(&self.parse_scripted()?);
+        }
+        self.depth -= 1;
+        Some(out)
+    }
+
+    /// Parse an atom with optional sub/superscripts
+    fn parse_scripted(&mut self) -> Option<String> {
+        let base = self.parse_atom()?;
+        let mut sub = None;
+        let mut sup = None;
+
+        loop {
+            match self.peek() {
+                Some(Token::Sub) if sub.is_none() => {
+                    self.next();
+                    sub = Some(self.parse_atom()?);
+                }
+                Some(Token::Sup) if sup.is_none() => {
+                    self.next();
+                    sup = Some(self.parse_atom()?);
+                }
+                _ => break,
+            }
+        }
+
+        Some(match (sub, sup) {
+            (Some(b), Some(p)) => format!("<msubsup>{}{}{}</msubsup>", base, b, p),
+            (Some(b), None) => format!("<msub>{}{}</msub>", base, b),
+            (None, Some(p)) => format!("<msup>{}{}</msup>", base, p),
+            (None, None) => base,
+        })
+    }
+
+    /// Parse a braced group into an mrow
+    fn parse_group(&mut self) -> Option<String> {
+        self.enter()?;
+        let group = match self.next()? {
+            Token::Open => {
+                let inner = self.parse_sequence(None)?;
+                if self.next()? != Token::Close {
+                    return None;
+                }
+                Some(format!("<mrow>{}</mrow>", inner))
+            }
+            // Unbraced single-token argument, e.g. \frac12
+            Token::Char(c) => Some(char_element(c)),
+            Token::Command(name) => self.parse_command(&name),
+            _ => None,
+        };
+        self.depth -= 1;
+        group
+    }
+
+    /// Read the raw text of a braced group (for \text and friends)
+    fn parse_raw_group(&mut self) -> Option<String> {
+        if self.next()? != Token::Open {
+            return None;
+        }
+        let mut text = String::new();
+        loop {
+            let tok = self.tokens.get(self.pos).cloned()?;
+            self.pos += 1;
+            match tok {
+                Token::Close => break,
+                Token::Space => text.push(' '),
+                Token::Char(c) => text.push(c),
+                Token::Command(name) => text.push_str(&name),
+                _ => return None,
+            }
+        }
+        Some(text)
+    }
+
+    fn parse_atom(&mut self) -> Option<String> {
+        match self.peek()? {
+            Token::Open => self.parse_group(),
+            Token::Char(c) if c.is_ascii_digit() || *c == '.' => {
+                let mut number = String::new();
+                while let Some(Token::Char(d)) = self.peek() {
+                    if d.is_ascii_digit() || (*d == '.' && !number.contains('.')) {
+                        number.push(*d);
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+                Some(format!("<mn>{}</mn>", number))
+            }
+            Token::Char(_) => match self.next()? {
+                Token::Char(c) => Some(char_element(c)),
+                _ => None,
+            },
+            Token::Command(_) => match self.next()? {
+                Token::Command(name) => self.parse_command(&name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn parse_command(&mut self, name: &str) -> Option<String> {
+        if let Some(symbol) = greek(name) {
+            return Some(format!("<mi>{}</mi>", symbol));
+        }
+        if let Some(op) = operator(name) {
+            return Some(format!("<mo>{}</mo>", op));
+        }
+        if FUNCTIONS.contains(&name) {
+            return Some(format!("<mi>{}</mi>", name));
+        }
+
+        match name {
+            "frac" | "dfrac" | "tfrac" => {
+                let num = self.parse_group()?;
+                let den = self.parse_group()?;
+                Some(format!("<mfrac>{}{}</mfrac>", num, den))
+            }
+            "sqrt" => {
+                // Optional index: \sqrt[n]{x}
+                if self.peek() == Some(&Token::Char('[')) {
+                    self.next();
+                    let index = self.parse_sequence(Some(&Token::Char(']')))?;
+                    if self.next()? != Token::Char(']') {
+                        return None;
+                    }
+                    let radicand = self.parse_group()?;
+                    Some(format!("<mroot>{}<mrow>{}</mrow></mroot>", radicand, index))
+                } else {
+                    Some(format!("<msqrt>{}</msqrt>", self.parse_group()?))
+                }
+            }
+            "text" | "textrm" | "mathrm" | "operatorname" => {
+                Some(format!("<mtext>{}</mtext>", escape(&self.parse_raw_group()?)))
+            }
+            "mathbf" => Some(format!(
+                "<mi mathvariant=\"bold\">{}</mi>",
+                escape(&self.parse_raw_group()?)
+            )),
+            "mathit" => Some(format!(
+                "<mi mathvariant=\"italic\">{}</mi>",
+                escape(&self.parse_raw_group()?)
+            )),
+            "mathbb" => Some(format!(
+                "<mi mathvariant=\"double-struck\">{}</mi>",
+                escape(&self.parse_raw_group()?)
+            )),
+            "left" | "right" => match self.next()? {
+                Token::Char('.') => Some(String::new()),
+                Token::Char(c) => Some(format!("<mo>{}</mo>", escape(&c.to_string()))),
+                Token::Command(n) => operator(&n).map(|op| format!("<mo>{}</mo>", op)),
+                _ => None,
+            },
+            // Spacing commands
+            "," | ";" | ":" | "!" | "quad" | "qquad" => Some("<mspace width=\"0.2em\"/>".to_string()),
+            "{" => Some("<mo>{</mo>".to_string()),
+            "}" => Some("<mo>}</mo>".to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn char_element(c: char) -> String {
+    if c.is_alphabetic() {
+        format!("<mi>{}</mi>", c)
+    } else {
+        format!("<mo>{}</mo>", escape(&c.to_string()))
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh",
+    "tanh", "log", "ln", "exp", "lim", "max", "min", "sup", "inf", "det", "gcd", "deg",
+];
+
+fn greek(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" => "ϵ",
+        "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "vartheta" => "ϑ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "rho" => "ρ",
+        "sigma" => "σ",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" => "ϕ",
+        "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        _ => return None,
+    })
+}
+
+fn operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "cdot" => "⋅",
+        "times" => "×",
+        "div" => "÷",
+        "pm" => "±",
+        "mp" => "∓",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "propto" => "∝",
+        "infty" => "∞",
+        "sum" => "∑",
+        "prod" => "∏",
+        "int" => "∫",
+        "oint" => "∮",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "in" => "∈",
+        "notin" => "∉",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "cup" => "∪",
+        "cap" => "∩",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" => "¬",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "to" | "rightarrow" => "→",
+        "leftarrow" => "←",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔",
+        "mapsto" => "↦",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "|" => "‖",
+        "circ" => "∘",
+        "emptyset" => "∅",
+        _ => return None,
+    })
+}
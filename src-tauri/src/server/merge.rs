@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use similar::{Algorithm, DiffTag};
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::server::conditional::etag;
+use crate::server::patch::DIFF_DEADLINE;
+use crate::server::{log_to_file, AppState};
+
+#[derive(Deserialize)]
+pub struct MergeRequest {
+    /// The text both sides started from
+    base: String,
+    /// This client's edit of it
+    ours: String,
+    /// The other edit; the file as stored when absent
+    theirs: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MergeResponse {
+    /// Merged text, with conflict markers where both sides changed the same lines
+    content: String,
+    /// How many conflict regions are marked in it
+    conflicts: usize,
+    /// Revision of the file `theirs` was read from, to save the merge over
+    /// it with `If-Match`; absent when `theirs` was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revision: Option<String>,
+}
+
+/// One side's replacement of a run of base lines by a run of its own
+struct Change {
+    base: Range<usize>,
+    side: Range<usize>,
+}
+
+/// Runs of `base` lines that `side` replaced, in order
+fn changes(base: &[&str], side: &[&str]) -> Vec<Change> {
+    let ops = similar::capture_diff_slices_deadline(
+        Algorithm::Myers,
+        base,
+        side,
+        Some(Instant::now() + DIFF_DEADLINE),
+    );
+    let mut changes: Vec<Change> = Vec::new();
+    for op in ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        // A replacement comes as a deletion next to an insertion
+        match changes.last_mut() {
+            Some(last) if last.base.end == old_range.start => {
+                last.base.end = old_range.end;
+                last.side.end = new_range.end;
+            }
+            _ => changes.push(Change {
+                base: old_range,
+                side: new_range,
+            }),
+        }
+    }
+    changes
+}
+
+/// `base[range]` with the changes `side` made inside it applied
+fn apply<'a>(base: &[&'a str], side: &[&'a str], range: &Range<usize>, changes: &[Change]) -> Vec<&'a str> {
+    let mut out = Vec::new();
+    let mut pos = range.start;
+    for change in changes {
+        out.extend_from_slice(&base[pos..change.base.start]);
+        out.extend_from_slice(&side[change.side.clone()]);
+        pos = change.base.end;
+    }
+    out.extend_from_slice(&base[pos..range.end]);
+    out
+}
+
+/// Three-way merge of two edits of `base`, line by line as in diff3. Lines
+/// changed on one side only take that side's version, as do lines both
+/// sides changed alike; where they changed overlapping or adjacent lines
+/// differently, both versions are kept between conflict markers, with the
+/// base version between them. Returns the text and the number of conflicts.
+pub fn merge_text(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+    let base: Vec<&str> = base.split('\n').collect();
+    let ours: Vec<&str> = ours.split('\n').collect();
+    let theirs: Vec<&str> = theirs.split('\n').collect();
+    let ours_changes = changes(&base, &ours);
+    let theirs_changes = changes(&base, &theirs);
+
+    let mut out: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let mut pos = 0;
+    let (mut a, mut b) = (0, 0);
+    while a < ours_changes.len() || b < theirs_changes.len() {
+        // A region starts at the next change on either side and grows while
+        // a change on either side overlaps or touches it
+        let start = match (ours_changes.get(a), theirs_changes.get(b)) {
+            (Some(x), Some(y)) => x.base.start.min(y.base.start),
+            (Some(x), None) => x.base.start,
+            (None, Some(y)) => y.base.start,
+            (None, None) => break,
+        };
+        let mut region = start..start;
+        let (a_start, b_start) = (a, b);
+        loop {
+            if let Some(x) = ours_changes.get(a).filter(|x| x.base.start <= region.end) {
+                region.end = region.end.max(x.base.end);
+                a += 1;
+            } else if let Some(y) = theirs_changes.get(b).filter(|y| y.base.start <= region.end) {
+                region.end = region.end.max(y.base.end);
+                b += 1;
+            } else {
+                break;
+            }
+        }
+
+        out.extend_from_slice(&base[pos..region.start]);
+        let ours_part = apply(&base, &ours, &region, &ours_changes[a_start..a]);
+        let theirs_part = apply(&base, &theirs, &region, &theirs_changes[b_start..b]);
+        if a == a_start {
+            out.extend(theirs_part);
+        } else if b == b_start || ours_part == theirs_part {
+            out.extend(ours_part);
+        } else {
+            conflicts += 1;
+            out.push("<<<<<<< ours");
+            out.extend(ours_part);
+            out.push("||||||| base");
+            out.extend_from_slice(&base[region.clone()]);
+            out.push("=======");
+            out.extend(theirs_part);
+            out.push(">>>>>>> theirs");
+        }
+        pos = region.end;
+    }
+    out.extend_from_slice(&base[pos..]);
+    (out.join("\n"), conflicts)
+}
+
+/// POST /api/files/*path/merge - Merge an edit that failed to save with a
+/// 412 into the document as it is now, for resolving in the editor rather
+/// than overwriting. Without `theirs`, merges with the file as stored,
+/// front matter and all, and returns its revision to save over.
+pub async fn merge_document(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Json(payload): Json<MergeRequest>,
+) -> Result<Json<MergeResponse>, StatusCode> {
+    log_to_file(&format!("[server] POST /api/files/{}/merge", path));
+    let full_path = state.roots.resolve(&path);
+    let canonical = full_path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&path, &canonical) {
+        log_to_file(&format!("[server] Merge rejected - path traversal attempt: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let (theirs, revision) = match payload.theirs {
+        Some(theirs) => (theirs, None),
+        None => {
+            let stored = std::fs::read_to_string(&canonical).map_err(|_| StatusCode::NOT_FOUND)?;
+            let revision = etag(stored.as_bytes());
+            (stored, Some(revision))
+        }
+    };
+    let (content, conflicts) = merge_text(&payload.base, &payload.ours, &theirs);
+    Ok(Json(MergeResponse {
+        content,
+        conflicts,
+        revision,
+    }))
+}
@@ -0,0 +1,97 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::server::org::parse_todo_keywords;
+use crate::server::AppState;
+
+#[derive(Serialize)]
+pub struct TodoCounts {
+    open: usize,
+    done: usize,
+}
+
+#[derive(Serialize)]
+pub struct DocumentMeta {
+    path: String,
+    title: String,
+    #[serde(rename = "type")]
+    doc_type: String,
+    status: Option<String>,
+    tags: Vec<String>,
+    #[serde(rename = "fileTags")]
+    file_tags: Vec<String>,
+    headings: usize,
+    todo: TodoCounts,
+    words: usize,
+    /// Outgoing links
+    links: usize,
+    backlinks: usize,
+    /// Unix seconds
+    mtime: Option<u64>,
+    /// File size in bytes
+    size: u64,
+}
+
+/// Words of prose, leaving out YAML frontmatter and `#+KEYWORD:` lines
+fn count_words(content: &str) -> usize {
+    let mut lines = content.lines().peekable();
+    if lines.peek().is_some_and(|l| l.trim() == "---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line.trim() == "---" {
+                break;
+            }
+        }
+    }
+    lines
+        .filter(|l| !l.trim_start().starts_with("#+"))
+        .map(|l| l.split_whitespace().count())
+        .sum()
+}
+
+/// GET /api/files/*path/meta - Summary of a document without its body, for
+/// hover cards and list views
+pub async fn get_meta(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<DocumentMeta>, StatusCode> {
+    let index = state.index.read().await;
+    let doc = index.get_document(&path).ok_or(StatusCode::NOT_FOUND)?;
+
+    let full_path = state.roots.resolve(&path);
+    let content = tokio::fs::read_to_string(&full_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let keywords = parse_todo_keywords(&content);
+    let headings = index.get_headings(&path);
+    let mut todo = TodoCounts { open: 0, done: 0 };
+    for keyword in headings.iter().filter_map(|h| h.todo.as_deref()) {
+        if keywords.is_done(keyword) {
+            todo.done += 1;
+        } else {
+            todo.open += 1;
+        }
+    }
+
+    Ok(Json(DocumentMeta {
+        path: doc.path.clone(),
+        title: doc.title.clone(),
+        doc_type: doc.doc_type.clone(),
+        status: doc.status.clone(),
+        tags: doc.tags.clone(),
+        file_tags: doc.file_tags.clone(),
+        headings: headings.len(),
+        todo,
+        words: count_words(&content),
+        links: doc.links.len(),
+        backlinks: doc.backlinks.len(),
+        mtime: index.get_mtime_secs(&path),
+        size: content.len() as u64,
+    }))
+}
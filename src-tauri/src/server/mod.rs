@@ -1,31 +1,119 @@
+pub mod agenda;
+pub mod attachments;
+pub mod auth;
+pub mod backlinks;
+pub mod backup;
+pub mod batch;
+pub mod board;
+pub mod capture;
+pub mod collab;
+pub mod commands;
+pub mod conditional;
+pub mod config;
+pub mod create;
+pub mod crypt;
+pub mod dblocks;
+pub mod diagnostics;
+pub mod diary;
+pub mod dirty;
+pub mod drafts;
 pub mod document;
+pub mod effort;
+pub mod events;
+pub mod export;
+pub mod flashcards;
+pub mod footnotes;
+pub mod highlight;
+pub mod ids;
+pub mod ignore_file;
+pub mod images;
+pub mod includes;
 pub mod index;
+pub mod instant;
+pub mod journal;
+pub mod links;
+pub mod lists;
+pub mod logbook;
+pub mod macros;
+pub mod math;
+pub mod merge;
+pub mod meta;
+pub mod occurrences;
+pub mod openapi;
+pub mod org;
+pub mod outline;
+pub mod patch;
+pub mod presence;
 pub mod projects;
+pub mod query;
+pub mod quickswitch;
+pub mod raw;
+pub mod recent;
+pub mod reconcile;
+pub mod related;
+pub mod reminders;
+pub mod rename;
+pub mod roots;
 pub mod routes;
+pub mod saved_searches;
+pub mod search;
+pub mod search_history;
+pub mod semantic;
 pub mod static_files;
+pub mod stats;
+pub mod store;
+pub mod streaming;
+pub mod tables;
+pub mod timezone;
+pub mod tls;
+pub mod trash;
 pub mod watcher;
 
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+        ConnectInfo, DefaultBodyLimit, State, WebSocketUpgrade,
     },
+    http::{Extensions, HeaderMap, StatusCode, Uri, Version},
+    middleware,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{get, post, put},
     Router,
 };
 use axum_server::tls_rustls::RustlsConfig;
+use std::collections::HashMap;
 use std::env;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{mpsc, RwLock};
+use tower_http::compression::{predicate::DefaultPredicate, CompressionLayer, Predicate};
 use tower_http::cors::{Any, CorsLayer};
 
+use collab::CollabSessions;
+use config::ServerConfig;
+use dirty::DirtyQueue;
+use events::{EventBus, Subscription};
 use index::DocumentIndex;
-use watcher::FileWatcher;
+use presence::Presence;
+use roots::Roots;
+use semantic::SemanticIndex;
+use watcher::{FileWatcher, WatcherStatus};
+
+/// Where the current version of the HTTP API is served. A change to a
+/// response's shape that would break existing clients goes under a new
+/// version, leaving this one as it is.
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Seconds between pings to WebSocket clients when `wsPingSeconds` isn't configured
+pub const DEFAULT_WS_PING_SECONDS: u64 = 30;
+
+/// Seconds of silence after which a WebSocket client is taken for gone when
+/// `wsIdleTimeoutSeconds` isn't configured; a few missed pings
+pub const DEFAULT_WS_IDLE_TIMEOUT_SECONDS: u64 = 90;
 
 pub fn log_to_file(msg: &str) {
     let log_path = env::temp_dir().join("org-viewer.log");
@@ -41,48 +129,175 @@ pub fn log_to_file(msg: &str) {
 
 pub struct AppState {
     pub index: Arc<RwLock<DocumentIndex>>,
+    /// Primary root; holds the config and caches
     pub org_root: PathBuf,
+    /// Every directory documents are indexed from, the org root included
+    pub roots: Roots,
+    pub config: ServerConfig,
     pub start_time: std::time::Instant,
-    pub ws_tx: broadcast::Sender<String>,
+    /// Events for WebSocket clients
+    pub events: EventBus,
+    /// org-crypt passphrases keyed by session token — memory only, never persisted
+    pub crypt_sessions: RwLock<HashMap<String, String>>,
+    /// Heading embeddings for semantic search (empty unless configured)
+    pub semantic: SemanticIndex,
+    /// Set while POST /api/search/reindex is rebuilding the text index
+    pub reindexing: AtomicBool,
+    /// Changed files waiting to be reindexed in a batch
+    pub dirty: DirtyQueue,
+    /// Which file watcher is running
+    pub watcher: RwLock<WatcherStatus>,
+    /// Documents being edited together over WebSockets
+    pub collab: CollabSessions,
+    /// Which WebSocket clients have which documents open
+    pub presence: Presence,
+    /// Open WebSocket connections
+    pub connections: AtomicUsize,
+    /// Token remote clients must present (`ORG_VIEWER_TOKEN`); unset leaves
+    /// them unauthenticated
+    pub token: Option<String>,
 }
 
-/// WebSocket upgrade handler
+/// WebSocket upgrade handler. Remote clients authenticate with the token in
+/// the URL or cookie like HTTP requests, or else in their first message.
 async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    uri: Uri,
 ) -> impl IntoResponse {
-    log_to_file("[ws] Client connecting...");
-    ws.on_upgrade(move |socket| handle_ws_connection(socket, state))
+    log_to_file(&format!("[ws] Client connecting from {}...", peer));
+    let authenticated = !auth::required(&state, &peer)
+        || auth::presented(&headers, &uri).is_some_and(|t| auth::matches(&state, &t));
+    ws.on_upgrade(move |socket| handle_ws_connection(socket, state, peer, authenticated))
 }
 
 /// Handle an individual WebSocket connection
-async fn handle_ws_connection(mut socket: WebSocket, state: Arc<AppState>) {
+async fn handle_ws_connection(mut socket: WebSocket, state: Arc<AppState>, peer: SocketAddr, authenticated: bool) {
+    if !authenticated && !auth::handshake(&mut socket, &state, &peer).await {
+        return;
+    }
+    static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(1);
+    let conn = NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed);
+    state.connections.fetch_add(1, Ordering::Relaxed);
     log_to_file("[ws] Client connected");
-    let mut rx = state.ws_tx.subscribe();
+    let mut rx = state.events.subscribe();
+    let mut subscription = Subscription::default();
+    // Events up to here were already sent by a replay
+    let mut replayed_to = 0;
+    // Latest event this connection has dealt with, sent or not; where it
+    // catches up from when it falls behind
+    let mut handled = state.events.revision();
+    // Results of commands, which run alongside the connection
+    let (replies, mut results) = mpsc::unbounded_channel::<String>();
+
+    // Pings keep the connection alive through proxies and tell a dead one,
+    // which never answers, from a quiet one
+    let ping_seconds = state.config.ws_ping_seconds;
+    let idle_timeout = std::time::Duration::from_secs(state.config.ws_idle_timeout_seconds);
+    let mut heartbeat = tokio::time::interval(std::time::Duration::from_secs(ping_seconds.max(1)));
+    let mut last_seen = tokio::time::Instant::now();
 
     loop {
         tokio::select! {
-            // Forward broadcast messages to this client
+            _ = heartbeat.tick(), if ping_seconds > 0 => {
+                if !idle_timeout.is_zero() && last_seen.elapsed() > idle_timeout {
+                    log_to_file(&format!("[ws] Closing connection idle for {}s", last_seen.elapsed().as_secs()));
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                }
+                if socket.send(Message::Ping(Default::default())).await.is_err() {
+                    log_to_file("[ws] Client disconnected (ping failed)");
+                    break;
+                }
+            }
+            Some(reply) = results.recv() => {
+                if socket.send(Message::Text(reply.into())).await.is_err() {
+                    log_to_file("[ws] Client disconnected (send failed)");
+                    break;
+                }
+            }
+            // Forward events to this client
             msg = rx.recv() => {
                 match msg {
-                    Ok(text) => {
+                    Some(event) => {
+                        handled = handled.max(event.revision);
+                        if event.revision <= replayed_to || !subscription.wants(&event) {
+                            continue;
+                        }
+                        let text = match serde_json::to_string(&*event) {
+                            Ok(t) => t,
+                            Err(_) => continue,
+                        };
                         if socket.send(Message::Text(text.into())).await.is_err() {
                             log_to_file("[ws] Client disconnected (send failed)");
                             break;
                         }
                     }
-                    Err(broadcast::error::RecvError::Lagged(n)) => {
-                        log_to_file(&format!("[ws] Client lagged by {} messages", n));
-                    }
-                    Err(broadcast::error::RecvError::Closed) => {
-                        log_to_file("[ws] Broadcast channel closed");
-                        break;
+                    // Its queue filled up and was dropped: queue afresh, then
+                    // send what was missed from the replay buffer
+                    None => {
+                        log_to_file(&format!("[ws] Client fell behind after revision {}, catching up", handled));
+                        rx = state.events.subscribe();
+                        match replay(&mut socket, &state, &subscription, handled, None).await {
+                            Some(revision) => {
+                                replayed_to = revision;
+                                handled = handled.max(revision);
+                            }
+                            None => {
+                                log_to_file("[ws] Client disconnected (send failed)");
+                                break;
+                            }
+                        }
                     }
                 }
             }
-            // Handle incoming messages from client (ping/pong, close)
+            // Handle incoming messages from client (subscriptions, ping/pong, close)
             msg = socket.recv() => {
+                if let Some(Ok(_)) = msg {
+                    last_seen = tokio::time::Instant::now();
+                }
                 match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        let message = match serde_json::from_str::<serde_json::Value>(&text) {
+                            Ok(m) => m,
+                            Err(_) => {
+                                log_to_file(&format!("[ws] Ignoring client message: {}", text.as_str()));
+                                continue;
+                            }
+                        };
+                        if subscription.handle(&message)
+                            || presence::handle(&state, conn, &message).await
+                            || commands::spawn(&state, &message, &replies)
+                        {
+                            continue;
+                        }
+                        if message["type"] == "replay" {
+                            let since = message["since"].as_u64().unwrap_or(0);
+                            let timestamp = message["timestamp"].as_i64();
+                            match replay(&mut socket, &state, &subscription, since, timestamp).await {
+                                Some(revision) => {
+                                    replayed_to = revision;
+                                    handled = handled.max(revision);
+                                }
+                                None => {
+                                    log_to_file("[ws] Client disconnected (send failed)");
+                                    break;
+                                }
+                            }
+                            continue;
+                        }
+                        match collab::handle(&state, conn, &message).await {
+                            Some(reply) => {
+                                if socket.send(Message::Text(reply.to_string().into())).await.is_err() {
+                                    log_to_file("[ws] Client disconnected (send failed)");
+                                    break;
+                                }
+                            }
+                            None => log_to_file(&format!("[ws] Ignoring client message: {}", text.as_str())),
+                        }
+                    }
                     Some(Ok(Message::Close(_))) | None => {
                         log_to_file("[ws] Client disconnected");
                         break;
@@ -101,6 +316,68 @@ async fn handle_ws_connection(mut socket: WebSocket, state: Arc<AppState>) {
             }
         }
     }
+    state.connections.fetch_sub(1, Ordering::Relaxed);
+    collab::leave_all(&state, conn).await;
+    presence::leave(&state, conn).await;
+}
+
+/// Send a client the events it missed: after reconnecting, as asked for with
+/// `{"type": "replay", "since": <revision>, "timestamp": <its timestamp>}` of
+/// the last event it got, or after falling behind. Followed by `{"type":
+/// "replay", "payload": {"complete", "revision"}}`; not complete means the
+/// events are no longer kept and the client has to refetch what it shows.
+/// Returns the revision replayed up to, or `None` if the socket failed.
+async fn replay(
+    socket: &mut WebSocket,
+    state: &AppState,
+    subscription: &Subscription,
+    since: u64,
+    timestamp: Option<i64>,
+) -> Option<u64> {
+    let missed = state.events.since(since, timestamp);
+    let complete = missed.is_some();
+    let missed = missed.unwrap_or_default();
+    let revision = match missed.last() {
+        Some(event) => event.revision,
+        None if complete => since,
+        None => state.events.revision(),
+    };
+
+    let mut sent = 0;
+    for event in missed.iter().filter(|e| subscription.wants(e)) {
+        let text = serde_json::to_string(&**event).ok()?;
+        socket.send(Message::Text(text.into())).await.ok()?;
+        sent += 1;
+    }
+    log_to_file(&format!(
+        "[ws] Replayed {} events since revision {}{}",
+        sent,
+        since,
+        if complete { "" } else { " (too old, client refetches)" }
+    ));
+
+    let reply = serde_json::json!({
+        "v": events::PROTOCOL_VERSION,
+        "type": "replay",
+        "payload": { "complete": complete, "revision": revision }
+    });
+    socket.send(Message::Text(reply.to_string().into())).await.ok()?;
+    Some(revision)
+}
+
+/// Whether a response is worth compressing: text and JSON, such as large
+/// documents, but not media and archives, which are compressed already, nor
+/// part of a file sent for a `Range`, whose offsets are into the file as is
+fn compressible(status: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions) -> bool {
+    if status == StatusCode::PARTIAL_CONTENT {
+        return false;
+    }
+    let content_type = headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    content_type.starts_with("text/")
+        || ["json", "javascript", "xml", "wasm"].iter().any(|t| content_type.contains(t))
 }
 
 pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -111,33 +388,60 @@ pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn st
 
     let start_time = std::time::Instant::now();
 
+    // Config first: the default TODO workflow affects heading parsing
+    let config = ServerConfig::load(&org_root);
+    org::set_default_todo_keywords(config.todo_keywords.as_deref().map(org::parse_todo_sequence));
+
     // Load index from cache or build incrementally
     log_to_file("Loading document index...");
-    let mut index = DocumentIndex::new(&org_root);
+    let roots = Roots::new(&org_root, &config.roots)
+        .with_follow_symlinks(config.follow_symlinks)
+        .with_metadata_only(&config.metadata_only);
+    let mut index = DocumentIndex::new(roots.clone(), config.body_cache_mb * 1024 * 1024);
     let (total, cached, parsed, removed) = index.load_or_build().await;
     log_to_file(&format!(
         "Index loaded: {} total ({} cached, {} parsed, {} removed)",
         total, cached, parsed, removed
     ));
 
-    // Create broadcast channel for WebSocket live reload
-    let (ws_tx, _) = broadcast::channel::<String>(64);
+    let semantic = SemanticIndex::load(&org_root, config.embeddings.as_ref());
+
+    let (dirty, dirty_rx) = DirtyQueue::new();
+    let token = auth::load_token();
 
     let state = Arc::new(AppState {
         index: Arc::new(RwLock::new(index)),
         org_root: org_root.clone(),
+        roots,
+        config,
         start_time,
-        ws_tx,
+        events: EventBus::new(),
+        crypt_sessions: RwLock::new(HashMap::new()),
+        semantic,
+        reindexing: AtomicBool::new(false),
+        dirty,
+        watcher: RwLock::new(WatcherStatus::default()),
+        collab: CollabSessions::default(),
+        presence: Presence::default(),
+        connections: AtomicUsize::new(0),
+        token,
     });
 
-    // Start file watcher
+    // Reindex changed files in batches as the watcher reports them
+    tokio::spawn(dirty::run(state.clone(), dirty_rx));
+
+    // Start file watcher, restarting it if it dies
     log_to_file("Starting file watcher...");
-    let watcher_state = state.clone();
-    tokio::spawn(async move {
-        if let Err(e) = FileWatcher::watch(watcher_state).await {
-            log_to_file(&format!("File watcher error: {}", e));
-        }
-    });
+    tokio::spawn(FileWatcher::supervise(state.clone()));
+
+    // Catch embeddings up with edits made while the server was down
+    tokio::spawn(semantic::refresh(state.clone(), None));
+
+    // Catch changes the watcher missed
+    tokio::spawn(reconcile::run(state.clone()));
+
+    // Announce agenda entries coming up
+    tokio::spawn(reminders::run(state.clone()));
 
     // CORS configuration
     let cors = CorsLayer::new()
@@ -145,21 +449,101 @@ pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn st
         .allow_methods(Any)
         .allow_headers(Any);
 
-    // Build router — API routes first, then static file fallback
+    // API routes, relative to the prefix they're served under
+    let api = Router::new()
+        .route("/health", get(routes::health))
+        .route("/status", get(routes::status))
+        .route("/openapi.json", get(openapi::get_spec))
+        .route("/docs", get(openapi::get_docs))
+        .route("/files", get(routes::list_files))
+        .route("/files/batch", post(batch::get_files))
+        .route(
+            "/files/{*path}",
+            // Only POST takes uploads, so only it gets the larger limit
+            post(routes::post_file)
+                .layer(DefaultBodyLimit::max(attachments::UPLOAD_LIMIT_BYTES))
+                .get(routes::get_file)
+                .put(routes::put_file)
+                .delete(trash::delete_file),
+        )
+        .route("/drafts", get(drafts::list_drafts))
+        .route(
+            "/drafts/{*path}",
+            get(drafts::get_draft).put(drafts::put_draft).delete(drafts::delete_draft),
+        )
+        .route("/trash", get(trash::list_trash))
+        .route("/trash/restore", post(trash::restore))
+        .route("/attachments/{*path}", get(attachments::get_attachment))
+        .route("/crypt/unlock", post(crypt::unlock))
+        .route("/crypt/lock", post(crypt::lock))
+        .route("/images/{*path}", get(images::get_image))
+        .route("/raw/{*path}", get(raw::get_raw))
+        .route("/search", get(routes::search))
+        .route(
+            "/search/history",
+            get(search_history::get_history)
+                .post(search_history::post_history)
+                .delete(search_history::delete_history),
+        )
+        .route("/search/instant", get(instant::instant_search))
+        .route("/search/reindex", post(search::reindex))
+        .route("/watcher/pause", post(watcher::pause))
+        .route("/watcher/resume", post(watcher::resume))
+        .route("/search/semantic", get(semantic::semantic_search))
+        .route("/quickswitch", get(quickswitch::quickswitch))
+        .route("/recent", get(recent::get_recent))
+        .route("/searches", get(saved_searches::list_searches))
+        .route(
+            "/searches/{name}",
+            get(saved_searches::get_search)
+                .put(saved_searches::put_search)
+                .delete(saved_searches::delete_search),
+        )
+        .route("/stats", get(stats::get_stats))
+        .route("/index/stats", get(diagnostics::get_index_stats))
+        .route("/index/export", get(backup::export_index))
+        .route(
+            "/index/import",
+            post(backup::import_index).layer(DefaultBodyLimit::max(backup::IMPORT_LIMIT_BYTES)),
+        )
+        .route("/graph", get(routes::graph))
+        .route("/links/broken", get(links::get_broken_links))
+        .route("/flashcards", get(flashcards::list_flashcards))
+        .route("/flashcards/review", post(flashcards::review_flashcard))
+        .route("/resolve/id/{id}", get(ids::resolve_id))
+        .route("/resolve/title/{title}", get(ids::resolve_title))
+        .route("/export/html", get(export::export_html))
+        .route("/export/markdown", get(export::export_markdown))
+        .route("/export/pdf", post(export::export_pdf))
+        .route("/query", get(query::query))
+        .route("/agenda", get(agenda::get_agenda))
+        .route("/board", get(board::get_board))
+        .route("/board/move", post(board::move_card))
+        .route("/capture", post(capture::capture))
+        .route("/capture/templates", get(capture::list_templates))
+        .route(
+            "/capture/templates/{name}",
+            put(capture::put_template).delete(capture::delete_template),
+        )
+        .route("/journal", get(journal::list_entries))
+        .route("/journal/today", post(journal::today))
+        .route("/projects", get(projects::list_projects))
+        .route("/projects/{name}/tree", get(projects::get_tree))
+        .route("/projects/{name}/file/{*path}", get(projects::get_file).put(projects::put_file))
+        .route("/debug-log", post(routes::debug_log));
+
+    // Build router — API routes first, then static file fallback. The API is
+    // served under its version and, for clients written before it had one,
+    // under plain `/api` too.
     let app = Router::new()
-        .route("/api/health", get(routes::health))
-        .route("/api/status", get(routes::status))
-        .route("/api/files", get(routes::list_files))
-        .route("/api/files/{*path}", get(routes::get_file).put(routes::put_file))
-        .route("/api/search", get(routes::search))
-        .route("/api/graph", get(routes::graph))
-        .route("/api/projects", get(projects::list_projects))
-        .route("/api/projects/{name}/tree", get(projects::get_tree))
-        .route("/api/projects/{name}/file/{*path}", get(projects::get_file).put(projects::put_file))
-        .route("/api/debug-log", post(routes::debug_log))
+        .nest(API_PREFIX, api.clone())
+        .nest("/api", api)
         .route("/ws", get(ws_handler))
         // Static file serving (embedded client dist) — enables remote/Tailscale access
         .fallback(static_files::static_handler)
+        .layer(middleware::from_fn_with_state(state.clone(), auth::require_token))
+        // gzip or brotli, as the client accepts
+        .layer(CompressionLayer::new().compress_when(DefaultPredicate::new().and(compressible)))
         .layer(cors)
         .with_state(state);
 
@@ -183,9 +567,12 @@ pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn st
                 }
             };
 
+            // Pick up renewed certificates without a restart
+            tokio::spawn(tls::watch_certificates(config.clone(), cert_path.into(), key_path.into()));
+
             // Spawn HTTP listener on localhost only (for Tauri WebView IPC)
             let local_addr = SocketAddr::from(([127, 0, 0, 1], port));
-            let local_app = app.clone();
+            let local_app = app.clone().into_make_service_with_connect_info::<SocketAddr>();
             tokio::spawn(async move {
                 match tokio::net::TcpListener::bind(local_addr).await {
                     Ok(listener) => {
@@ -207,7 +594,7 @@ pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn st
             log_to_file(&format!("SUCCESS: HTTPS listener on https://0.0.0.0:{} (Tailscale)", tls_port));
 
             if let Err(e) = axum_server::bind_rustls(tls_addr, config)
-                .serve(app.into_make_service())
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
                 .await
             {
                 log_to_file(&format!("Axum TLS serve error: {}", e));
@@ -235,7 +622,8 @@ pub async fn start_server(org_root: PathBuf, port: u16) -> Result<(), Box<dyn st
             };
 
             log_to_file("Starting axum serve loop...");
-            if let Err(e) = axum::serve(listener, app).await {
+            let service = app.into_make_service_with_connect_info::<SocketAddr>();
+            if let Err(e) = axum::serve(listener, service).await {
                 log_to_file(&format!("Axum serve error: {}", e));
                 return Err(e.into());
             }
@@ -0,0 +1,97 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::AppState;
+
+#[derive(Deserialize)]
+pub struct OccurrencesQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+#[derive(Serialize)]
+pub struct HeadingRef {
+    line: usize,
+    title: String,
+}
+
+#[derive(Serialize)]
+pub struct Occurrence {
+    /// 1-based line
+    line: usize,
+    /// 1-based character column where the match starts
+    column: usize,
+    /// Match length in characters
+    length: usize,
+    /// Enclosing headings, outermost first, for building a sparse tree
+    #[serde(rename = "headingPath")]
+    heading_path: Vec<HeadingRef>,
+    context: String,
+}
+
+#[derive(Serialize)]
+pub struct OccurrencesResponse {
+    path: String,
+    query: String,
+    count: usize,
+    items: Vec<Occurrence>,
+}
+
+/// GET /api/files/*path/occurrences?q= - Every match of `q` in one document.
+/// Case-insensitive unless `q` contains an uppercase letter.
+pub async fn get_occurrences(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Query(query): Query<OccurrencesQuery>,
+) -> Result<Json<OccurrencesResponse>, StatusCode> {
+    if query.q.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let index = state.index.read().await;
+    if index.get_document(&path).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let content = tokio::fs::read_to_string(state.roots.resolve(&path))
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let headings = index.get_headings(&path);
+
+    let case_sensitive = query.q.chars().any(|c| c.is_uppercase());
+    let needle = if case_sensitive { query.q.clone() } else { query.q.to_lowercase() };
+    let length = needle.chars().count();
+
+    let mut items = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let haystack = if case_sensitive { line.to_string() } else { line.to_lowercase() };
+        // Lowercasing can change byte lengths, so columns come from the haystack
+        for (at, _) in haystack.match_indices(&needle) {
+            let number = i + 1;
+            items.push(Occurrence {
+                line: number,
+                column: haystack[..at].chars().count() + 1,
+                length,
+                heading_path: headings
+                    .iter()
+                    .filter(|h| h.line <= number && number <= h.subtree_end)
+                    .map(|h| HeadingRef {
+                        line: h.line,
+                        title: h.title.clone(),
+                    })
+                    .collect(),
+                context: line.trim().chars().take(200).collect(),
+            });
+        }
+    }
+
+    Ok(Json(OccurrencesResponse {
+        path,
+        query: query.q,
+        count: items.len(),
+        items,
+    }))
+}
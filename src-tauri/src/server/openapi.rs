@@ -0,0 +1,308 @@
+use axum::response::{Html, Json};
+use utoipa::openapi::{
+    path::{
+        HttpMethod::{self, Delete, Get, Post, Put},
+        OperationBuilder, ParameterBuilder, ParameterIn, PathItem,
+    },
+    request_body::RequestBodyBuilder,
+    security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme},
+    ComponentsBuilder, ContentBuilder, InfoBuilder, ObjectBuilder, OpenApi, OpenApiBuilder, PathsBuilder, Required,
+    ResponseBuilder, ResponsesBuilder, Type,
+};
+
+use crate::server::auth::{TOKEN_COOKIE, TOKEN_PARAM};
+use crate::server::API_PREFIX;
+
+/// One documented operation, its path relative to `API_PREFIX`. Path
+/// parameters come from the `{name}` parts of the path; `{path}` is a
+/// document path and may contain slashes.
+struct Op {
+    method: HttpMethod,
+    path: &'static str,
+    tag: &'static str,
+    summary: &'static str,
+    /// Query parameters as (name, description)
+    query: &'static [(&'static str, &'static str)],
+    /// What the JSON body holds, for operations that take one
+    body: Option<&'static str>,
+}
+
+const fn op(method: HttpMethod, path: &'static str, tag: &'static str, summary: &'static str) -> Op {
+    Op {
+        method,
+        path,
+        tag,
+        summary,
+        query: &[],
+        body: None,
+    }
+}
+
+impl Op {
+    const fn query(mut self, query: &'static [(&'static str, &'static str)]) -> Op {
+        self.query = query;
+        self
+    }
+
+    const fn body(mut self, body: &'static str) -> Op {
+        self.body = Some(body);
+        self
+    }
+}
+
+/// Every route the server answers. Keep in step with the router in `mod.rs`
+/// and the sub-resource actions in `routes.rs`.
+const OPERATIONS: &[Op] = &[
+    op(Get, "/health", "server", "Liveness check; open without a token"),
+    op(Get, "/status", "server", "Index and watcher status, document counts, top tags and recent documents"),
+    op(Get, "/openapi.json", "server", "This API description"),
+    op(Get, "/docs", "server", "This API description, rendered for browsing"),
+    op(Post, "/debug-log", "server", "Append a client message to the server log").body("`{msg}`"),
+    op(Get, "/files", "documents", "Document metadata, filtered, sorted and paged").query(&[
+        ("type", "Document type, e.g. `task`"),
+        ("tag", "Frontmatter tag or `#+FILETAGS` entry, case-insensitive"),
+        ("category", "`#+CATEGORY` value, case-insensitive"),
+        ("dir", "Only documents under this directory"),
+        ("modified_after", "Modified after this instant (RFC 3339), or on or after this day (`YYYY-MM-DD`)"),
+        ("tz", "IANA timezone a `modified_after` day is in"),
+        ("sort", "`path` (default), `title` or `modified`; a leading `-` reverses it"),
+        ("limit", "Page size; all matching documents when absent"),
+        ("offset", "Documents to skip before the page"),
+    ]),
+    op(Post, "/files/batch", "documents", "Several documents, or their summaries, in one response")
+        .body("`{paths, meta?, raw?}`; each item carries the status its own request would have had"),
+    op(Get, "/files/{path}", "documents", "A document with its content, rendered for viewing").query(&[
+        ("raw", "Content exactly as stored, for editing"),
+        ("anchor", "Only the subtree under the heading with this `:CUSTOM_ID:`"),
+        ("client", "Client id the view is recorded under for `/api/v1/recent`"),
+    ]),
+    op(Put, "/files/{path}", "documents", "Replace a document; `If-Match` names the revision (or GET `ETag`) it started from")
+        .body("`{frontmatter, content}`"),
+    op(Post, "/files/{path}", "documents", "Create a document; 409 if it exists")
+        .body("Optional `{frontmatter?, content?, template?}`; `template` is a document path"),
+    op(Delete, "/files/{path}", "documents", "Move a document to the trash"),
+    op(Get, "/files/{path}/backlinks", "documents", "Every file and heading linking to a document"),
+    op(Get, "/files/{path}/outline", "documents", "Heading tree without section bodies"),
+    op(Get, "/files/{path}/related", "documents", "\"See also\" suggestions"),
+    op(Get, "/files/{path}/occurrences", "documents", "Every match of `q` in one document")
+        .query(&[("q", "Text to find; case-insensitive unless it has an uppercase letter")]),
+    op(Get, "/files/{path}/meta", "documents", "Summary of a document without its body"),
+    op(Post, "/files/{path}/table", "editing", "Update one table cell or row in place")
+        .body("`{table, row, column?, value?, cells?}`"),
+    op(Post, "/files/{path}/list", "editing", "Check, indent, outdent or reorder one plain-list item")
+        .body("`{list, item, action, to?}`"),
+    op(Post, "/files/{path}/update-dblocks", "editing", "Recompute clocktable dynamic blocks"),
+    op(Post, "/files/{path}/move", "editing", "Move or rename a document, rewriting file links to it")
+        .body("`{to}`: the new document path"),
+    op(Post, "/files/{path}/merge", "editing", "Three-way merge of an edit that failed to save with 412")
+        .body("`{base, ours, theirs?}`; without `theirs`, the file as stored"),
+    op(
+        Post,
+        "/files/{path}/attachments",
+        "editing",
+        "Store the files of a multipart body next to a document and return links to them",
+    ),
+    op(Get, "/drafts", "drafts", "Documents with unsaved edits, most recent first"),
+    op(Get, "/drafts/{path}", "drafts", "The unsaved edit of a document, and whether the document changed since"),
+    op(Put, "/drafts/{path}", "drafts", "Keep an unsaved edit without touching the document")
+        .body("`{frontmatter?, content, revision?}`"),
+    op(Delete, "/drafts/{path}", "drafts", "Throw away an unsaved edit"),
+    op(Get, "/trash", "documents", "Trashed documents, most recently deleted first"),
+    op(Post, "/trash/restore", "documents", "Put a trashed document back; 409 if one exists there now")
+        .body("`{id}` from the trash listing"),
+    op(Get, "/attachments/{path}", "files", "An attachment file"),
+    op(Get, "/images/{path}", "files", "An image referenced from a document"),
+    op(Get, "/raw/{path}", "files", "Any file under a root as stored, with `Range` support"),
+    op(Post, "/crypt/unlock", "crypt", "Start a session for reading `:crypt:` subtrees; 401 if the passphrase doesn't decrypt one").body("`{passphrase}`"),
+    op(Post, "/crypt/lock", "crypt", "End the session named by the session header"),
+    op(Get, "/search", "search", "Ranked full-text search, narrowed by metadata and paged").query(&[
+        ("q", "Search text; filters alone list documents by modification time"),
+        ("tag", "Frontmatter tag, `#+FILETAGS` entry or heading tag"),
+        ("todo", "Only documents with a heading in this TODO state"),
+        ("after", "Modified on or after this day, `YYYY-MM-DD`"),
+        ("before", "Modified before this day, `YYYY-MM-DD`"),
+        ("dir", "Only documents under this directory"),
+        ("tz", "IANA timezone the days are in"),
+        ("scope", "`notes` (default) or `projects` to also search code"),
+        ("limit", "Page size; defaults to 50"),
+        ("offset", "Hits to skip before the page"),
+        ("client", "Client id the query is recorded under in search history"),
+    ]),
+    op(Get, "/search/instant", "search", "Search-as-you-type over titles and headings")
+        .query(&[("q", "Search text"), ("limit", "Most results")]),
+    op(Get, "/search/semantic", "search", "Headings nearest to the query by embedding similarity")
+        .query(&[("q", "Search text"), ("limit", "Most results")]),
+    op(Post, "/search/reindex", "search", "Rebuild the full-text index in the background"),
+    op(Get, "/search/history", "search", "Recent searches, most recent first")
+        .query(&[("client", "Client id; history without one is shared")]),
+    op(Post, "/search/history", "search", "Record a search made elsewhere")
+        .query(&[("client", "Client id; history without one is shared")])
+        .body("`{query}`"),
+    op(Delete, "/search/history", "search", "Forget one query, or the whole history").query(&[
+        ("client", "Client id; history without one is shared"),
+        ("q", "Remove only this query"),
+    ]),
+    op(Get, "/searches", "search", "Saved searches by name"),
+    op(Get, "/searches/{name}", "search", "One saved search"),
+    op(Put, "/searches/{name}", "search", "Create or replace a saved search").body("`{kind, q, params, pinned}`"),
+    op(Delete, "/searches/{name}", "search", "Remove a saved search"),
+    op(Get, "/quickswitch", "search", "Fuzzy file switcher over paths and titles")
+        .query(&[("q", "Search text; empty for the most recently modified"), ("limit", "Most results")]),
+    op(Get, "/query", "search", "Evaluate an org-ql style query against headings").query(&[
+        ("q", "Query"),
+        ("limit", "Most results"),
+        ("tz", "IANA timezone for relative dates"),
+    ]),
+    op(Get, "/recent", "search", "Recently modified, and optionally viewed, documents").query(&[
+        ("limit", "Most results"),
+        ("viewed", "Also return recently viewed documents"),
+        ("client", "Client id for `viewed`"),
+    ]),
+    op(Get, "/resolve/id/{id}", "links", "The file and heading that own an org ID"),
+    op(Get, "/resolve/title/{title}", "links", "Documents a `[[Title]]` link points at"),
+    op(Get, "/links/broken", "links", "ID, file and wiki links whose targets don't exist"),
+    op(Get, "/graph", "links", "Documents and the links between them"),
+    op(Get, "/agenda", "planning", "Scheduled, deadline and diary entries by day").query(&[
+        ("start", "First day, `YYYY-MM-DD`; defaults to today"),
+        ("days", "Days to cover"),
+        ("tz", "IANA timezone deciding what today is"),
+    ]),
+    op(Get, "/board", "planning", "TODO headings in columns by keyword")
+        .query(&[("files", "Comma-separated document paths; all documents when omitted")]),
+    op(Post, "/board/move", "planning", "Move a card to another column").body("`{file, line, title, to}`"),
+    op(Get, "/flashcards", "planning", "`:drill:` and `:fc:` cards").query(&[
+        ("due", "Only cards that are new or due"),
+        ("file", "Only cards in this document"),
+        ("tz", "IANA timezone deciding what today is"),
+    ]),
+    op(Post, "/flashcards/review", "planning", "Record a review and reschedule the card")
+        .query(&[("tz", "IANA timezone deciding what today is")])
+        .body("`{file, line, title, quality}`"),
+    op(Post, "/capture", "capture", "File captured text into a document")
+        .query(&[("tz", "IANA timezone for timestamps")])
+        .body("`{text, template?, target?, heading?}`"),
+    op(Get, "/capture/templates", "capture", "Capture templates by name"),
+    op(Put, "/capture/templates/{name}", "capture", "Create or replace a capture template")
+        .body("`{target, heading?, body}`"),
+    op(Delete, "/capture/templates/{name}", "capture", "Remove a capture template"),
+    op(Get, "/journal", "capture", "Journal entries in date order")
+        .query(&[("from", "First day, `YYYY-MM-DD`"), ("to", "Last day, `YYYY-MM-DD`")]),
+    op(Post, "/journal/today", "capture", "Create today's journal entry if needed")
+        .query(&[("tz", "IANA timezone deciding what today is")]),
+    op(Get, "/export/html", "export", "A document or subtree as a self-contained HTML page")
+        .query(&[("file", "Document path"), ("heading", "Only this subtree, by `:CUSTOM_ID:` or title")]),
+    op(Get, "/export/markdown", "export", "A document or subtree as Markdown")
+        .query(&[("file", "Document path"), ("heading", "Only this subtree, by `:CUSTOM_ID:` or title")]),
+    op(Post, "/export/pdf", "export", "A document or subtree as PDF").body("`{file, heading?}`"),
+    op(Get, "/projects", "projects", "Code projects under `projects/`"),
+    op(Get, "/projects/{name}/tree", "projects", "File tree of a project"),
+    op(Get, "/projects/{name}/file/{path}", "projects", "Read a project file"),
+    op(
+        Put,
+        "/projects/{name}/file/{path}",
+        "projects",
+        "Write a project file; `If-Match` names the revision it started from",
+    )
+    .body("`{content}`"),
+    op(Get, "/stats", "index", "Vault-wide counts"),
+    op(Get, "/index/stats", "index", "Index size, load timings and cache use"),
+    op(Get, "/index/export", "index", "The parsed index as a JSON archive"),
+    op(Post, "/index/import", "index", "Load an exported index archive").body("An archive from `/api/v1/index/export`"),
+    op(Post, "/watcher/pause", "index", "Stop reacting to file changes"),
+    op(Post, "/watcher/resume", "index", "React to file changes again and catch up"),
+];
+
+fn string_schema() -> ObjectBuilder {
+    ObjectBuilder::new().schema_type(Type::String)
+}
+
+/// The OpenAPI document for the server's HTTP API
+fn spec() -> OpenApi {
+    let mut paths = PathsBuilder::new();
+    for op in OPERATIONS {
+        let mut operation = OperationBuilder::new()
+            .summary(Some(op.summary))
+            .tag(op.tag)
+            .responses(ResponsesBuilder::new().response("200", ResponseBuilder::new().description("Success")));
+        let names = op.path.split('/').filter_map(|s| s.strip_prefix('{')?.strip_suffix('}'));
+        for name in names {
+            operation = operation.parameter(
+                ParameterBuilder::new()
+                    .name(name)
+                    .parameter_in(ParameterIn::Path)
+                    .required(Required::True)
+                    .schema(Some(string_schema())),
+            );
+        }
+        for (name, description) in op.query {
+            operation = operation.parameter(
+                ParameterBuilder::new()
+                    .name(*name)
+                    .parameter_in(ParameterIn::Query)
+                    .required(Required::False)
+                    .description(Some(*description))
+                    .schema(Some(string_schema())),
+            );
+        }
+        if let Some(body) = op.body {
+            operation = operation.request_body(Some(
+                RequestBodyBuilder::new()
+                    .description(Some(body))
+                    .content("application/json", ContentBuilder::new().build())
+                    .build(),
+            ));
+        }
+        paths = paths.path(format!("{}{}", API_PREFIX, op.path), PathItem::new(op.method.clone(), operation));
+    }
+
+    let components = ComponentsBuilder::new()
+        .security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        )
+        .security_scheme(TOKEN_PARAM, SecurityScheme::ApiKey(ApiKey::Query(ApiKeyValue::new(TOKEN_PARAM))))
+        .security_scheme(TOKEN_COOKIE, SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(TOKEN_COOKIE))))
+        .build();
+    OpenApiBuilder::new()
+        .info(
+            InfoBuilder::new()
+                .title("org-viewer")
+                .version(env!("CARGO_PKG_VERSION"))
+                .description(Some(
+                    "HTTP API of the org-viewer server. Remote clients present the token set in \
+                     `ORG_VIEWER_TOKEN` by any one of the security schemes; clients on the same \
+                     machine need none. Live updates and commands go over the WebSocket at `/ws`. \
+                     The same routes answer under `/api` as well, unversioned, for older clients.",
+                )),
+        )
+        .paths(paths)
+        .components(Some(components))
+        .security(Some(
+            ["bearer", TOKEN_PARAM, TOKEN_COOKIE].map(|scheme| SecurityRequirement::new(scheme, Vec::<String>::new())),
+        ))
+        .build()
+}
+
+/// GET /api/openapi.json - The API description, for generating clients
+pub async fn get_spec() -> Json<OpenApi> {
+    Json(spec())
+}
+
+/// GET /api/docs - Browsable API reference, rendered by Redoc
+pub async fn get_docs() -> Html<&'static str> {
+    Html(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>org-viewer API</title>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+  </head>
+  <body>
+    <redoc spec-url="/api/v1/openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/latest/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#,
+    )
+}
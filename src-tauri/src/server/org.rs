@@ -0,0 +1,554 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+use crate::server::document::is_org_file;
+
+/// TODO keywords recognised when a file doesn't declare its own
+pub const DEFAULT_TODO_KEYWORDS: &[&str] = &["TODO", "DONE"];
+
+/// Vault-wide workflow from the server config, overriding `DEFAULT_TODO_KEYWORDS`
+static CONFIGURED_TODO_KEYWORDS: RwLock<Option<TodoKeywords>> = RwLock::new(None);
+
+/// A file's TODO workflow: active states followed by done states, in declaration order
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TodoKeywords {
+    pub todo: Vec<String>,
+    pub done: Vec<String>,
+}
+
+impl Default for TodoKeywords {
+    fn default() -> Self {
+        let (last, rest) = DEFAULT_TODO_KEYWORDS.split_last().unwrap();
+        Self {
+            todo: rest.iter().map(|k| k.to_string()).collect(),
+            done: vec![last.to_string()],
+        }
+    }
+}
+
+impl TodoKeywords {
+    pub fn contains(&self, keyword: &str) -> bool {
+        self.todo.iter().chain(self.done.iter()).any(|k| k == keyword)
+    }
+
+    pub fn is_done(&self, keyword: &str) -> bool {
+        self.done.iter().any(|k| k == keyword)
+    }
+}
+
+/// Set the workflow used by files without `#+TODO:` lines (from `todoKeywords` in the config)
+pub fn set_default_todo_keywords(keywords: Option<TodoKeywords>) {
+    if let Ok(mut configured) = CONFIGURED_TODO_KEYWORDS.write() {
+        *configured = keywords;
+    }
+}
+
+/// The configured default workflow, or `TODO | DONE`
+pub fn default_todo_keywords() -> TodoKeywords {
+    CONFIGURED_TODO_KEYWORDS
+        .read()
+        .ok()
+        .and_then(|k| k.clone())
+        .unwrap_or_default()
+}
+
+/// Parse one keyword sequence like `TODO NEXT(n) | DONE(d!) CANCELLED`; without
+/// a `|` the last keyword is the done state
+pub fn parse_todo_sequence(sequence: &str) -> TodoKeywords {
+    // Strip fast-access and logging suffixes like `(t)` or `(w@/!)`
+    let words: Vec<&str> = sequence
+        .split_whitespace()
+        .map(|w| w.split('(').next().unwrap_or(w))
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let (active, done) = match words.iter().position(|w| *w == "|") {
+        Some(bar) => (&words[..bar], &words[bar + 1..]),
+        None if words.len() > 1 => words.split_at(words.len() - 1),
+        None => (&words[..], &words[..0]),
+    };
+    TodoKeywords {
+        todo: active.iter().map(|w| w.to_string()).collect(),
+        done: done.iter().map(|w| w.to_string()).collect(),
+    }
+}
+
+/// Parse `#+TODO:`, `#+SEQ_TODO:` and `#+TYP_TODO:` lines, merging every
+/// sequence the file declares. Falls back to the configured default when none are.
+pub fn parse_todo_keywords(content: &str) -> TodoKeywords {
+    let todo_re = Regex::new(r"(?im)^\s*#\+(?:SEQ_|TYP_)?TODO:[ \t]*(.*)$").unwrap();
+    let mut keywords = TodoKeywords { todo: Vec::new(), done: Vec::new() };
+
+    for caps in todo_re.captures_iter(content) {
+        let sequence = parse_todo_sequence(&caps[1]);
+        keywords.todo.extend(sequence.todo);
+        keywords.done.extend(sequence.done);
+    }
+
+    if keywords.todo.is_empty() && keywords.done.is_empty() {
+        return default_todo_keywords();
+    }
+    keywords
+}
+
+/// An org heading with its position in the file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heading {
+    pub level: usize,
+    pub title: String,
+    pub todo: Option<String>,
+    pub priority: Option<char>,
+    pub tags: Vec<String>,
+    /// 1-based line of the heading itself
+    pub line: usize,
+    /// 1-based last line of the heading's own section (before the next heading)
+    #[serde(rename = "sectionEnd")]
+    pub section_end: usize,
+    /// 1-based last line of the whole subtree
+    #[serde(rename = "subtreeEnd")]
+    pub subtree_end: usize,
+    /// Planning timestamps (inner text, e.g. `2024-01-15 Mon 10:00`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed: Option<String>,
+    /// Entries from the heading's `:PROPERTIES:` drawer (keys uppercased)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub properties: HashMap<String, String>,
+}
+
+/// A stable deep-link target declared with `:CUSTOM_ID:`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Anchor {
+    pub id: String,
+    pub line: usize,
+    pub level: usize,
+    pub title: String,
+}
+
+/// Collect `:CUSTOM_ID:` anchors from parsed headings
+pub fn custom_id_anchors(headings: &[Heading]) -> Vec<Anchor> {
+    headings
+        .iter()
+        .filter_map(|h| {
+            Some(Anchor {
+                id: h.properties.get("CUSTOM_ID")?.clone(),
+                line: h.line,
+                level: h.level,
+                title: h.title.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Text of the subtree whose heading has the given `:CUSTOM_ID:`, in the
+/// document at `path`
+pub fn subtree_by_custom_id(path: &Path, content: &str, id: &str) -> Option<String> {
+    let heading = parse_document_headings(path, content)
+        .into_iter()
+        .find(|h| h.properties.get("CUSTOM_ID").map(|v| v.as_str()) == Some(id))?;
+    let lines: Vec<&str> = content.lines().collect();
+    Some(lines[heading.line - 1..heading.subtree_end].join("\n") + "\n")
+}
+
+/// How a file marks its headings
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HeadingSyntax {
+    /// `* Title`, as in org
+    Org,
+    /// `# Title` (ATX), outside front matter and code fences; a `*` line is
+    /// a list item
+    Markdown,
+}
+
+/// Parse all headings of the document at `path` in document order: org
+/// headings in an org file, Markdown ones otherwise
+pub fn parse_document_headings(path: &Path, content: &str) -> Vec<Heading> {
+    if is_org_file(path) {
+        parse_headings(content)
+    } else {
+        parse_markdown_headings(content)
+    }
+}
+
+/// Parse all org headings in document order
+pub fn parse_headings(content: &str) -> Vec<Heading> {
+    parse_all(content, HeadingSyntax::Org)
+}
+
+/// Parse all Markdown headings in document order. TODO keywords, priority
+/// cookies and tags are read from the heading text as in org.
+pub fn parse_markdown_headings(content: &str) -> Vec<Heading> {
+    parse_all(content, HeadingSyntax::Markdown)
+}
+
+fn parse_all(content: &str, syntax: HeadingSyntax) -> Vec<Heading> {
+    let keywords = parse_todo_keywords(content);
+    let lines: Vec<&str> = content.lines().collect();
+    let mut headings = parse_heading_range(&lines, 0, lines.len(), &keywords, syntax);
+    fill_boundaries(&mut headings, lines.len());
+    headings
+}
+
+/// Which of the first `end` lines of a Markdown file are front matter or
+/// fenced code, where a `#` line isn't a heading
+fn markdown_hidden_lines(lines: &[&str], end: usize) -> Vec<bool> {
+    let mut hidden = vec![false; end];
+    let mut frontmatter = lines.first().is_some_and(|l| l.trim_end() == "---");
+    let mut fence: Option<&str> = None;
+    for (i, line) in lines.iter().enumerate().take(end) {
+        let trimmed = line.trim_start();
+        if frontmatter {
+            hidden[i] = true;
+            frontmatter = i == 0 || line.trim_end() != "---";
+        } else if let Some(open) = fence {
+            hidden[i] = true;
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            hidden[i] = true;
+            fence = Some(&trimmed[..3]);
+        }
+    }
+    hidden
+}
+
+/// Headings among `lines[start..end]`, with sections clipped to that range.
+/// `end` must fall on a section boundary for drawers and planning to be right.
+fn parse_heading_range(
+    lines: &[&str],
+    start: usize,
+    end: usize,
+    keywords: &TodoKeywords,
+    syntax: HeadingSyntax,
+) -> Vec<Heading> {
+    let (marker, heading_re, hidden) = match syntax {
+        HeadingSyntax::Org => (
+            '*',
+            Regex::new(r"^(\*+)\s+(?:([A-Z][A-Z0-9_-]*)\s+)?(?:\[#([A-Z0-9])\]\s+)?(.*?)(?:\s+(:[\w@#%:]+:))?\s*$"),
+            Vec::new(),
+        ),
+        HeadingSyntax::Markdown => (
+            '#',
+            // An optional closing sequence of `#`s isn't part of the title
+            Regex::new(
+                r"^(#{1,6})\s+(?:([A-Z][A-Z0-9_-]*)\s+)?(?:\[#([A-Z0-9])\]\s+)?(.*?)(?:\s+(:[\w@#%:]+:))?(?:\s+#+)?\s*$",
+            ),
+            markdown_hidden_lines(lines, end),
+        ),
+    };
+    let heading_re = heading_re.unwrap();
+    let mut headings: Vec<Heading> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate().take(end).skip(start) {
+        if !line.starts_with(marker) || hidden.get(i).copied().unwrap_or(false) {
+            continue;
+        }
+        let caps = match heading_re.captures(line) {
+            Some(c) => c,
+            None => continue,
+        };
+
+        let mut title = caps[4].to_string();
+        let todo = match caps.get(2).map(|m| m.as_str()) {
+            Some(kw) if keywords.contains(kw) => Some(kw.to_string()),
+            Some(word) => {
+                // Not a TODO keyword — it's the first word of the title
+                title = format!("{} {}", word, title).trim().to_string();
+                None
+            }
+            None => None,
+        };
+
+        let tags = caps
+            .get(5)
+            .map(|m| {
+                m.as_str()
+                    .split(':')
+                    .filter(|t| !t.is_empty())
+                    .map(|t| t.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        headings.push(Heading {
+            level: caps[1].len(),
+            title,
+            todo,
+            priority: caps.get(3).and_then(|m| m.as_str().chars().next()),
+            tags,
+            line: i + 1,
+            section_end: end,
+            subtree_end: end,
+            scheduled: None,
+            deadline: None,
+            closed: None,
+            properties: HashMap::new(),
+        });
+    }
+
+    fill_boundaries(&mut headings, end);
+
+    let planning_re = Regex::new(r"(SCHEDULED|DEADLINE|CLOSED):\s*[<\[]([^>\]]+)[>\]]").unwrap();
+    for heading in &mut headings {
+        let section = &lines[heading.line..heading.section_end];
+        heading.properties = parse_properties(section);
+
+        // Planning info lives on the line right after the heading
+        if let Some(first) = section.first() {
+            for caps in planning_re.captures_iter(first) {
+                let value = Some(caps[2].to_string());
+                match &caps[1] {
+                    "SCHEDULED" => heading.scheduled = value,
+                    "DEADLINE" => heading.deadline = value,
+                    _ => heading.closed = value,
+                }
+            }
+        }
+    }
+
+    headings
+}
+
+/// Fill in section and subtree boundaries for headings in a file of `len` lines
+fn fill_boundaries(headings: &mut [Heading], len: usize) {
+    for idx in 0..headings.len() {
+        headings[idx].section_end = match headings.get(idx + 1) {
+            Some(next) => next.line - 1,
+            None => len,
+        };
+        let level = headings[idx].level;
+        headings[idx].subtree_end = match headings[idx + 1..].iter().find(|h| h.level <= level) {
+            Some(end) => end.line - 1,
+            None => len,
+        };
+    }
+}
+
+/// What `reparse_headings` needs to know about the previous version of a file
+#[derive(Debug, Clone)]
+pub struct OutlineSnapshot {
+    line_hashes: Vec<u64>,
+    keywords: TodoKeywords,
+}
+
+impl OutlineSnapshot {
+    pub fn new(content: &str) -> Self {
+        Self {
+            line_hashes: content.lines().map(hash_line).collect(),
+            keywords: parse_todo_keywords(content),
+        }
+    }
+}
+
+fn hash_line(line: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Org headings of `content` after an edit, reparsing only the sections the
+/// edit touched. `previous` are the headings of the content `snapshot` was taken of;
+/// everything outside the changed lines is reused with shifted line numbers.
+pub fn reparse_headings(previous: &[Heading], snapshot: &OutlineSnapshot, content: &str) -> Vec<Heading> {
+    let keywords = parse_todo_keywords(content);
+    if keywords != snapshot.keywords {
+        // A workflow change can turn any heading's first word into a keyword
+        return parse_headings(content);
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    let hashes: Vec<u64> = lines.iter().map(|l| hash_line(l)).collect();
+    let old = &snapshot.line_hashes;
+
+    // Changed lines are old[prefix..old.len() - suffix], new[prefix..lines.len() - suffix]
+    let prefix = old.iter().zip(&hashes).take_while(|(a, b)| a == b).count();
+    let max_suffix = old.len().min(hashes.len()) - prefix;
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(hashes.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == old.len() && prefix == hashes.len() {
+        return previous.to_vec();
+    }
+    let changed_end = old.len() - suffix;
+
+    // Sections overlapping the change, including the one just before it: lines
+    // there may now continue that section if a heading was removed
+    let affected: Vec<&Heading> = previous
+        .iter()
+        .filter(|h| h.section_end >= prefix && h.line <= changed_end.max(prefix))
+        .collect();
+
+    // 1-based old-file line range to reparse, widened to section boundaries
+    let region_start = affected.first().map(|h| h.line).unwrap_or(prefix + 1).min(prefix + 1);
+    let region_end = match previous.iter().find(|h| h.line > changed_end.max(prefix)) {
+        Some(next) => next.line - 1,
+        None => old.len(),
+    };
+    let shift = |line: usize| (line + hashes.len()).saturating_sub(old.len());
+
+    let mut headings: Vec<Heading> = previous.iter().filter(|h| h.line < region_start).cloned().collect();
+    headings.extend(parse_heading_range(&lines, region_start - 1, shift(region_end), &keywords, HeadingSyntax::Org));
+    headings.extend(previous.iter().filter(|h| h.line > region_end).map(|h| {
+        let mut h = h.clone();
+        h.line = shift(h.line);
+        h
+    }));
+    fill_boundaries(&mut headings, lines.len());
+    headings
+}
+
+/// Runs once per heading, so compiled once for the process
+fn property_re() -> &'static Regex {
+    static PROPERTY_RE: OnceLock<Regex> = OnceLock::new();
+    PROPERTY_RE.get_or_init(|| Regex::new(r"^\s*:([^:\s]+):\s*(.*?)\s*$").unwrap())
+}
+
+/// Parse the first `:PROPERTIES:` drawer in a block of section lines
+pub fn parse_properties(section: &[&str]) -> HashMap<String, String> {
+    let prop_re = property_re();
+    let mut properties = HashMap::new();
+    let mut in_drawer = false;
+
+    for line in section {
+        let trimmed = line.trim();
+        if !in_drawer {
+            if trimmed.eq_ignore_ascii_case(":PROPERTIES:") {
+                in_drawer = true;
+            }
+            continue;
+        }
+        if trimmed.eq_ignore_ascii_case(":END:") {
+            break;
+        }
+        if let Some(caps) = prop_re.captures(line) {
+            properties.insert(caps[1].to_uppercase(), caps[2].to_string());
+        }
+    }
+
+    properties
+}
+
+/// Parse an org duration (`1:30`, `0:45`, `2h`, `30min`, `1d 2h`) into minutes
+pub fn parse_duration_minutes(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Some((h, m)) = value.split_once(':') {
+        return Some(h.trim().parse::<i64>().ok()? * 60 + m.trim().parse::<i64>().ok()?);
+    }
+
+    static UNIT_RE: OnceLock<Regex> = OnceLock::new();
+    let unit_re = UNIT_RE.get_or_init(|| Regex::new(r"(\d+(?:\.\d+)?)\s*(min|m|h|d|w)").unwrap());
+    let mut total = 0.0;
+    let mut matched = false;
+    for caps in unit_re.captures_iter(value) {
+        let n: f64 = caps[1].parse().ok()?;
+        total += n * match &caps[2] {
+            "min" | "m" => 1.0,
+            "h" => 60.0,
+            "d" => 60.0 * 24.0,
+            _ => 60.0 * 24.0 * 7.0,
+        };
+        matched = true;
+    }
+    if matched {
+        return Some(total.round() as i64);
+    }
+
+    value.parse::<i64>().ok()
+}
+
+/// Format minutes as org-style `H:MM`
+pub fn format_minutes(minutes: i64) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn is_planning_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("SCHEDULED:") || trimmed.starts_with("DEADLINE:") || trimmed.starts_with("CLOSED:")
+}
+
+fn join_lines(lines: Vec<String>, original: &str) -> String {
+    let mut result = lines.join("\n");
+    if original.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Set properties in the `:PROPERTIES:` drawer of the heading at 1-based
+/// `heading_line` of the file at `path`, replacing existing keys and creating
+/// the drawer (after any planning line) when missing
+pub fn set_properties(path: &Path, content: &str, heading_line: usize, updates: &[(&str, String)]) -> String {
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    let section_end = parse_document_headings(path, content)
+        .into_iter()
+        .find(|h| h.line == heading_line)
+        .map(|h| h.section_end)
+        .unwrap_or(lines.len());
+
+    let mut drawer_at = heading_line;
+    if lines.get(drawer_at).is_some_and(|l| is_planning_line(l)) {
+        drawer_at += 1;
+    }
+
+    let has_drawer = drawer_at < section_end
+        && lines[drawer_at].trim().eq_ignore_ascii_case(":PROPERTIES:");
+    if !has_drawer {
+        let mut drawer = vec![":PROPERTIES:".to_string()];
+        drawer.extend(updates.iter().map(|(k, v)| format!(":{}: {}", k, v)));
+        drawer.push(":END:".to_string());
+        lines.splice(drawer_at..drawer_at, drawer);
+        return join_lines(lines, content);
+    }
+
+    let mut end = drawer_at + 1;
+    while end < section_end && !lines[end].trim().eq_ignore_ascii_case(":END:") {
+        end += 1;
+    }
+
+    for (key, value) in updates {
+        let prefix = format!(":{}:", key.to_uppercase());
+        let existing = (drawer_at + 1..end).find(|&i| lines[i].trim_start().to_uppercase().starts_with(&prefix));
+        match existing {
+            Some(i) => lines[i] = format!(":{}: {}", key, value),
+            None => {
+                lines.insert(end, format!(":{}: {}", key, value));
+                end += 1;
+            }
+        }
+    }
+
+    join_lines(lines, content)
+}
+
+/// Set a planning timestamp (`SCHEDULED` or `DEADLINE`, active) on the heading
+/// at 1-based `heading_line`; `value` is the inner text, e.g. `2024-01-15 Mon`
+pub fn set_planning(content: &str, heading_line: usize, keyword: &str, value: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    let stamp = format!("{}: <{}>", keyword, value);
+
+    match lines.get(heading_line) {
+        Some(line) if is_planning_line(line) => {
+            let existing_re = Regex::new(&format!(r"{}:\s*<[^>]*>", regex::escape(keyword))).unwrap();
+            lines[heading_line] = if existing_re.is_match(line) {
+                existing_re.replace(line, regex::NoExpand(&stamp)).to_string()
+            } else {
+                format!("{} {}", line.trim_end(), stamp)
+            };
+        }
+        _ => lines.insert(heading_line.min(lines.len()), stamp),
+    }
+
+    join_lines(lines, content)
+}
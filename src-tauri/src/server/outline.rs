@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::server::org::Heading;
+use crate::server::AppState;
+
+#[derive(Serialize)]
+pub struct OutlineNode {
+    level: usize,
+    title: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    todo: Option<String>,
+    line: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<OutlineNode>,
+}
+
+#[derive(Serialize)]
+pub struct OutlineResponse {
+    path: String,
+    count: usize,
+    items: Vec<OutlineNode>,
+}
+
+/// Nest headings (in document order) under their nearest shallower ancestor
+fn build_tree(headings: &[Heading]) -> Vec<OutlineNode> {
+    let mut roots: Vec<OutlineNode> = Vec::new();
+    // Chain of currently open ancestors, outermost first
+    let mut stack: Vec<OutlineNode> = Vec::new();
+
+    let close = |stack: &mut Vec<OutlineNode>, roots: &mut Vec<OutlineNode>| {
+        let node = stack.pop().unwrap();
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    };
+
+    for h in headings {
+        while stack.last().is_some_and(|open| open.level >= h.level) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push(OutlineNode {
+            level: h.level,
+            title: h.title.clone(),
+            tags: h.tags.clone(),
+            todo: h.todo.clone(),
+            line: h.line,
+            children: Vec::new(),
+        });
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+
+    roots
+}
+
+/// GET /api/files/*path/outline - Heading tree without section bodies
+pub async fn get_outline(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<OutlineResponse>, StatusCode> {
+    let index = state.index.read().await;
+    if index.get_document(&path).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let headings = index.get_headings(&path);
+    Ok(Json(OutlineResponse {
+        path,
+        count: headings.len(),
+        items: build_tree(headings),
+    }))
+}
@@ -0,0 +1,107 @@
+use serde::Serialize;
+use similar::{Algorithm, DiffTag};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::server::crypt::find_encrypted;
+use crate::server::document::metadata_head;
+use crate::server::index::DocumentIndex;
+use crate::server::routes::render_content;
+use crate::server::AppState;
+
+/// Longest spent diffing one document; past it the diff gets coarser, not
+/// wrong
+pub const DIFF_DEADLINE: Duration = Duration::from_millis(100);
+
+/// A patch is only sent while its inserted text is at most this fraction of
+/// the new content; beyond that refetching costs about the same
+const MAX_PATCH_RATIO: f64 = 0.5;
+
+/// One run of replaced lines. `line` is the 0-based index in the old
+/// content of the first line of `delete`, or where `insert` goes if
+/// nothing is deleted. Deleted lines are sent in full so a client can check
+/// its copy matches before applying.
+#[derive(Debug, Clone, Serialize)]
+pub struct Hunk {
+    pub line: usize,
+    pub delete: Vec<String>,
+    pub insert: Vec<String>,
+}
+
+/// Edits turning one version of a document into the next, line by line.
+/// Lines are split on `\n` only, as JavaScript's `split('\n')` does, so a
+/// `\r` stays part of its line on both ends.
+#[derive(Debug, Clone, Serialize)]
+pub struct Patch {
+    pub hunks: Vec<Hunk>,
+}
+
+/// Line diff from `old` to `new`, or `None` if they're equal or the patch
+/// would be about as big as `new` itself
+pub fn line_patch(old: &str, new: &str) -> Option<Patch> {
+    if old == new {
+        return None;
+    }
+    let old_lines: Vec<&str> = old.split('\n').collect();
+    let new_lines: Vec<&str> = new.split('\n').collect();
+    let ops = similar::capture_diff_slices_deadline(
+        Algorithm::Myers,
+        &old_lines,
+        &new_lines,
+        Some(Instant::now() + DIFF_DEADLINE),
+    );
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut inserted = 0;
+    for op in ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        inserted += new_lines[new_range.clone()].iter().map(|l| l.len() + 1).sum::<usize>();
+        let delete = old_lines[old_range.clone()].iter().map(|l| l.to_string());
+        let insert = new_lines[new_range].iter().map(|l| l.to_string());
+        // Myers reports a replacement as a deletion next to an insertion
+        match hunks.last_mut() {
+            Some(last) if last.line + last.delete.len() == old_range.start => {
+                last.delete.extend(delete);
+                last.insert.extend(insert);
+            }
+            _ => hunks.push(Hunk {
+                line: old_range.start,
+                delete: delete.collect(),
+                insert: insert.collect(),
+            }),
+        }
+    }
+
+    if inserted as f64 > new.len() as f64 * MAX_PATCH_RATIO {
+        return None;
+    }
+    Some(Patch { hunks })
+}
+
+/// Patch from the content a viewer of `path` was last served to what it
+/// would be served now, given the document's file content before and after
+/// a change. `None` when clients should refetch instead: when the metadata
+/// changed, since the title, tags and the like come with the document
+/// rather than its content, or when encrypted subtrees are involved, since
+/// what a viewer holds depends on its crypt session.
+pub async fn document_patch(
+    state: &AppState,
+    index: &DocumentIndex,
+    path: &str,
+    old: &str,
+    new: &str,
+) -> Option<Patch> {
+    if metadata_head(old) != metadata_head(new) {
+        return None;
+    }
+    let file = Path::new(path);
+    if !find_encrypted(file, old).is_empty() || !find_encrypted(file, new).is_empty() {
+        return None;
+    }
+    let (old, _) = render_content(state, index, path, old).await;
+    let (new, _) = render_content(state, index, path, new).await;
+    line_patch(&old, &new)
+}
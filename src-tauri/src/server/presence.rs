@@ -0,0 +1,79 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+use crate::server::events::EventKind;
+use crate::server::AppState;
+
+/// Longest device name kept; names are shown to other clients as is
+const MAX_NAME_LEN: usize = 64;
+
+/// What one connection reported having open
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientPresence {
+    /// Chosen by the client, so it can tell itself apart in the list
+    pub id: String,
+    /// Device name to show, like "phone"
+    pub name: String,
+    /// Documents open for viewing
+    pub documents: Vec<String>,
+    /// Documents open in an editor
+    pub editing: Vec<String>,
+}
+
+/// Who has which document open, by connection
+#[derive(Default)]
+pub struct Presence {
+    clients: RwLock<HashMap<u64, ClientPresence>>,
+}
+
+impl Presence {
+    /// Everyone's presence, ordered by name for a stable display
+    pub async fn list(&self) -> Vec<ClientPresence> {
+        let mut clients: Vec<ClientPresence> = self.clients.read().await.values().cloned().collect();
+        clients.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        clients
+    }
+}
+
+/// Handle `{"type": "presence", "id", "name", "documents", "editing"}`,
+/// which replaces what connection `conn` has open, and tell everyone.
+/// Returns false for other messages.
+pub async fn handle(state: &AppState, conn: u64, message: &serde_json::Value) -> bool {
+    if message["type"] != "presence" {
+        return false;
+    }
+    let strings = |key: &str| -> Vec<String> {
+        message[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+            .map(|s| s.to_string())
+            .collect()
+    };
+    let presence = ClientPresence {
+        id: message["id"].as_str().unwrap_or_default().to_string(),
+        name: message["name"].as_str().unwrap_or("unknown").chars().take(MAX_NAME_LEN).collect(),
+        documents: strings("documents"),
+        editing: strings("editing"),
+    };
+    state.presence.clients.write().await.insert(conn, presence);
+    broadcast(state).await;
+    true
+}
+
+/// Forget a connection that closed, and tell everyone if it had reported in
+pub async fn leave(state: &AppState, conn: u64) {
+    let removed = state.presence.clients.write().await.remove(&conn).is_some();
+    if removed {
+        broadcast(state).await;
+    }
+}
+
+async fn broadcast(state: &AppState) {
+    let clients = state.presence.list().await;
+    state
+        .events
+        .send(EventKind::Presence, None, serde_json::json!({ "clients": clients }));
+}
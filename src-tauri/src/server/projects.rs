@@ -1,12 +1,15 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json,
 };
 use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::server::conditional::{check_if_match, etag, Revision};
+use crate::server::events::EventKind;
 use crate::server::{log_to_file, AppState};
 
 // --- Types ---
@@ -34,12 +37,25 @@ pub struct TreeEntry {
     children: Option<Vec<TreeEntry>>,
 }
 
+#[derive(Serialize)]
+pub struct CodeMatch {
+    project: String,
+    /// Path relative to the org root
+    pub path: String,
+    /// 1-based line of the match
+    line: usize,
+    language: Option<String>,
+    context: String,
+}
+
 #[derive(Serialize)]
 pub struct ProjectFile {
     path: String,
     content: String,
     language: Option<String>,
     size: u64,
+    /// Revision of the file as stored, to send back in `If-Match` when saving
+    revision: String,
 }
 
 // --- Exclusion Logic ---
@@ -101,6 +117,69 @@ fn should_exclude_org_root_entry(name: &str, is_dir: bool) -> bool {
     }
 }
 
+// --- Live Updates ---
+
+/// How a watched path under `projects/` changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProjectChange {
+    /// Created, removed or renamed, so the file tree changed
+    Tree,
+    /// Contents written
+    File,
+}
+
+/// Changes seen since the last broadcast, as (change, project, path within
+/// the project)
+pub type ProjectChanges = BTreeSet<(ProjectChange, String, String)>;
+
+/// The project a path under `org_root/projects` belongs to and its path
+/// within it; `None` for anything the file tree hides
+pub fn project_path(org_root: &std::path::Path, path: &std::path::Path) -> Option<(String, String)> {
+    let relative = path.strip_prefix(org_root.join("projects")).ok()?;
+    let mut names = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string());
+    let project = names.next()?;
+    if project.starts_with('.') {
+        return None;
+    }
+    let rest: Vec<String> = names.collect();
+    // The last name may be a file or a directory, so check it as both
+    if rest
+        .iter()
+        .any(|name| should_exclude_entry(name, true) || should_exclude_entry(name, false))
+    {
+        return None;
+    }
+    Some((project, rest.join("/")))
+}
+
+/// Tell clients about project changes: one `project-tree-changed` per
+/// project with files added, removed or renamed (an empty path is the
+/// project directory itself), and a `project-file-changed` per file written
+pub fn broadcast_changes(state: &AppState, changes: &ProjectChanges) {
+    let mut trees: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (change, project, path) in changes {
+        match change {
+            ProjectChange::Tree => trees.entry(project).or_default().push(path),
+            ProjectChange::File => {
+                let payload = serde_json::json!({
+                    "project": project,
+                    "path": path
+                });
+                state.events.send(EventKind::ProjectFileChanged, None, payload);
+            }
+        }
+    }
+    for (project, paths) in trees {
+        let payload = serde_json::json!({
+            "project": project,
+            "paths": paths
+        });
+        state.events.send(EventKind::ProjectTreeChanged, None, payload);
+    }
+}
+
 /// Get the org root's folder name for use as a virtual project name
 fn org_root_name(state: &AppState) -> String {
     state.org_root.file_name()
@@ -178,6 +257,83 @@ fn is_binary_extension(filename: &str) -> bool {
     )
 }
 
+/// Files larger than this are skipped by code search (generated or minified)
+const MAX_SEARCH_FILE_SIZE: u64 = 1_000_000;
+
+/// Matches per file in code search, so one noisy file can't crowd out the rest
+const MAX_MATCHES_PER_FILE: usize = 5;
+
+/// Search text files under `projects/` line by line, with the same exclusions
+/// as the project tree. A line matches when it contains every whitespace-separated
+/// term, case-insensitively. `dir` restricts results to a subdirectory of the org root.
+pub fn search_project_files(org_root: &std::path::Path, query: &str, dir: Option<&str>, limit: usize) -> Vec<CodeMatch> {
+    let terms: Vec<String> = query.split_whitespace().map(|t| t.to_lowercase()).collect();
+    let mut matches = Vec::new();
+    if terms.is_empty() {
+        return matches;
+    }
+
+    let projects_dir = org_root.join("projects");
+    let walker = walkdir::WalkDir::new(&projects_dir)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || (!name.starts_with('.') && !should_exclude_entry(&name, e.file_type().is_dir()))
+        });
+
+    for entry in walker.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !entry.file_type().is_file() || is_binary_extension(&name) {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(org_root)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        if dir.is_some_and(|d| !relative.starts_with(&format!("{}/", d))) {
+            continue;
+        }
+        if entry.metadata().map(|m| m.len() > MAX_SEARCH_FILE_SIZE).unwrap_or(true) {
+            continue;
+        }
+        // Non-UTF-8 content is treated as binary
+        let content = match std::fs::read_to_string(entry.path()) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+
+        let project = relative.split('/').nth(1).unwrap_or_default().to_string();
+        let language = detect_language(&name);
+        let mut in_file = 0;
+        for (i, line) in content.lines().enumerate() {
+            let lower = line.to_lowercase();
+            if !terms.iter().all(|t| lower.contains(t.as_str())) {
+                continue;
+            }
+            matches.push(CodeMatch {
+                project: project.clone(),
+                path: relative.clone(),
+                line: i + 1,
+                language: language.clone(),
+                context: line.trim().chars().take(200).collect(),
+            });
+            if matches.len() >= limit {
+                return matches;
+            }
+            in_file += 1;
+            if in_file >= MAX_MATCHES_PER_FILE {
+                break;
+            }
+        }
+    }
+
+    matches
+}
+
 // --- Handlers ---
 
 /// GET /api/projects - List all projects
@@ -386,12 +542,14 @@ pub async fn get_file(
         .unwrap_or(0);
 
     let language = detect_language(&filename);
+    let revision = etag(content.as_bytes());
 
     Ok(Json(ProjectFile {
         path: file_path,
         content,
         language,
         size,
+        revision,
     }))
 }
 
@@ -404,8 +562,9 @@ pub struct PutProjectFileRequest {
 pub async fn put_file(
     State(state): State<Arc<AppState>>,
     Path((name, file_path)): Path<(String, String)>,
+    headers: HeaderMap,
     Json(payload): Json<PutProjectFileRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<Json<Revision>, StatusCode> {
     log_to_file(&format!("[projects] PUT /api/projects/{}/file/{}", name, file_path));
 
     let project_dir = match resolve_project_dir(&state, &name) {
@@ -430,6 +589,9 @@ pub async fn put_file(
         log_to_file(&format!("[projects] PUT rejected - path traversal: {}", file_path));
         return Err(StatusCode::FORBIDDEN);
     }
+    check_if_match(&headers, &canonical_path).await.inspect_err(|status| {
+        log_to_file(&format!("[projects] PUT rejected - {} for If-Match: {}", status, file_path));
+    })?;
 
     // Write content
     if let Err(e) = tokio::fs::write(&canonical_path, &payload.content).await {
@@ -438,5 +600,5 @@ pub async fn put_file(
     }
 
     log_to_file(&format!("[projects] PUT success: {}/{}", name, file_path));
-    Ok(StatusCode::OK)
+    Ok(Json(Revision::of(payload.content.as_bytes())))
 }
@@ -0,0 +1,329 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::server::document::OrgDocument;
+use crate::server::effort::parse_org_timestamp;
+use crate::server::org::Heading;
+use crate::server::timezone;
+use crate::server::{log_to_file, AppState};
+
+/// Comparison applied to a planning date
+#[derive(Debug, Clone)]
+enum DateCmp {
+    Before(NaiveDate),
+    BeforeOrOn(NaiveDate),
+    After(NaiveDate),
+    AfterOrOn(NaiveDate),
+    On(NaiveDate),
+    /// Any value at all (`deadline:any`)
+    Exists,
+}
+
+impl DateCmp {
+    fn matches(&self, date: Option<NaiveDate>) -> bool {
+        match (self, date) {
+            (DateCmp::Exists, d) => d.is_some(),
+            (_, None) => false,
+            (DateCmp::Before(x), Some(d)) => d < *x,
+            (DateCmp::BeforeOrOn(x), Some(d)) => d <= *x,
+            (DateCmp::After(x), Some(d)) => d > *x,
+            (DateCmp::AfterOrOn(x), Some(d)) => d >= *x,
+            (DateCmp::On(x), Some(d)) => d == *x,
+        }
+    }
+}
+
+/// One query predicate, e.g. `todo:TODO` or `-tag:someday`
+#[derive(Debug, Clone)]
+enum Predicate {
+    Todo(Vec<String>),
+    Tag(Vec<String>),
+    Priority(Vec<String>),
+    Level(usize),
+    Scheduled(DateCmp),
+    Deadline(DateCmp),
+    Closed(DateCmp),
+    Property(String, Option<String>),
+    File(String),
+    Text(String),
+}
+
+/// Parse a date operand relative to `today`: `today`, `tomorrow`,
+/// `+3d`/`-2w`/`7d` offsets, or an ISO date
+fn parse_date_value(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match value {
+        "today" => return Some(today),
+        "tomorrow" => return Some(today + Duration::days(1)),
+        "yesterday" => return Some(today - Duration::days(1)),
+        _ => {}
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(r) => (-1, r),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let unit = rest.chars().last()?;
+    let n: i64 = rest[..rest.len() - unit.len_utf8()].parse().ok()?;
+    let days = match unit {
+        'd' => n,
+        'w' => n * 7,
+        'm' => n * 30,
+        'y' => n * 365,
+        _ => return None,
+    };
+    Some(today + Duration::days(sign * days))
+}
+
+fn parse_date_cmp(value: &str, today: NaiveDate) -> Option<DateCmp> {
+    match value {
+        "any" | "" => return Some(DateCmp::Exists),
+        "overdue" | "past" => return Some(DateCmp::Before(today)),
+        "future" => return Some(DateCmp::After(today)),
+        _ => {}
+    }
+
+    if let Some(v) = value.strip_prefix("<=") {
+        return parse_date_value(v, today).map(DateCmp::BeforeOrOn);
+    }
+    if let Some(v) = value.strip_prefix(">=") {
+        return parse_date_value(v, today).map(DateCmp::AfterOrOn);
+    }
+    if let Some(v) = value.strip_prefix('<') {
+        return parse_date_value(v, today).map(DateCmp::Before);
+    }
+    if let Some(v) = value.strip_prefix('>') {
+        return parse_date_value(v, today).map(DateCmp::After);
+    }
+    parse_date_value(value, today).map(DateCmp::On)
+}
+
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_lowercase())
+        .collect()
+}
+
+/// Parse a query like `todo:TODO,NEXT tag:work deadline:<7d -tag:someday`.
+/// Returns (predicate, negated) pairs; unknown `key:value` terms are errors.
+fn parse_query(q: &str, today: NaiveDate) -> Result<Vec<(Predicate, bool)>, String> {
+    let mut predicates = Vec::new();
+
+    for term in q.split_whitespace() {
+        let (negated, term) = match term.strip_prefix('-').or_else(|| term.strip_prefix('!')) {
+            Some(t) if !t.is_empty() => (true, t),
+            _ => (false, term),
+        };
+
+        let predicate = match term.split_once(':') {
+            Some((key, value)) => {
+                let bad_date = || format!("Invalid date in '{}'", term);
+                match key.to_lowercase().as_str() {
+                    "todo" => Predicate::Todo(list(value)),
+                    "tag" | "tags" => Predicate::Tag(list(value)),
+                    "priority" | "prio" => Predicate::Priority(list(value)),
+                    "level" => Predicate::Level(value.parse().map_err(|_| format!("Invalid level '{}'", value))?),
+                    "scheduled" => Predicate::Scheduled(parse_date_cmp(value, today).ok_or_else(bad_date)?),
+                    "deadline" => Predicate::Deadline(parse_date_cmp(value, today).ok_or_else(bad_date)?),
+                    "closed" => Predicate::Closed(parse_date_cmp(value, today).ok_or_else(bad_date)?),
+                    "prop" | "property" => match value.split_once('=') {
+                        Some((k, v)) => Predicate::Property(k.to_uppercase(), Some(v.to_string())),
+                        None => Predicate::Property(value.to_uppercase(), None),
+                    },
+                    "file" | "path" => Predicate::File(value.to_lowercase()),
+                    "title" | "text" => Predicate::Text(value.to_lowercase()),
+                    other => return Err(format!("Unknown query key '{}'", other)),
+                }
+            }
+            None => Predicate::Text(term.to_lowercase()),
+        };
+        predicates.push((predicate, negated));
+    }
+
+    Ok(predicates)
+}
+
+fn heading_date(value: &Option<String>) -> Option<NaiveDate> {
+    value.as_deref().and_then(parse_org_timestamp).map(|dt| dt.date())
+}
+
+fn matches(predicate: &Predicate, doc: &OrgDocument, heading: &Heading) -> bool {
+    match predicate {
+        Predicate::Todo(states) => match &heading.todo {
+            Some(todo) => states.is_empty() || states.contains(&todo.to_lowercase()),
+            None => false,
+        },
+        Predicate::Tag(tags) => {
+            // File-level tags are inherited by every heading
+            let mut all = heading.tags.iter().chain(doc.tags.iter()).chain(doc.file_tags.iter());
+            all.any(|t| tags.contains(&t.to_lowercase()))
+        }
+        Predicate::Priority(p) => heading
+            .priority
+            .map(|c| p.contains(&c.to_lowercase().to_string()))
+            .unwrap_or(false),
+        Predicate::Level(level) => heading.level == *level,
+        Predicate::Scheduled(cmp) => cmp.matches(heading_date(&heading.scheduled)),
+        Predicate::Deadline(cmp) => cmp.matches(heading_date(&heading.deadline)),
+        Predicate::Closed(cmp) => cmp.matches(heading_date(&heading.closed)),
+        Predicate::Property(key, value) => match (heading.properties.get(key), value) {
+            (Some(actual), Some(expected)) => actual.eq_ignore_ascii_case(expected),
+            (Some(_), None) => true,
+            (None, _) => false,
+        },
+        Predicate::File(fragment) => doc.path.to_lowercase().contains(fragment),
+        Predicate::Text(text) => heading.title.to_lowercase().contains(text),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QueryParams {
+    q: String,
+    limit: Option<usize>,
+    /// IANA timezone for relative dates like `today`; defaults to the configured one
+    tz: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct QueryMatch {
+    file: String,
+    line: usize,
+    level: usize,
+    title: String,
+    todo: Option<String>,
+    priority: Option<char>,
+    tags: Vec<String>,
+    scheduled: Option<String>,
+    deadline: Option<String>,
+    /// First body line under the heading, for display
+    context: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct QueryResponse {
+    query: String,
+    count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    items: Vec<QueryMatch>,
+}
+
+/// First meaningful body line of a heading (skips planning and drawers)
+fn heading_context(lines: &[&str], heading: &Heading) -> Option<String> {
+    let mut in_drawer = false;
+    for line in lines.get(heading.line..heading.section_end)? {
+        let trimmed = line.trim();
+        if in_drawer {
+            in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+            continue;
+        }
+        if trimmed.starts_with(':') && trimmed.ends_with(':') && trimmed.len() > 1 {
+            in_drawer = true;
+            continue;
+        }
+        if trimmed.is_empty()
+            || trimmed.starts_with("SCHEDULED:")
+            || trimmed.starts_with("DEADLINE:")
+            || trimmed.starts_with("CLOSED:")
+        {
+            continue;
+        }
+        return Some(trimmed.chars().take(200).collect());
+    }
+    None
+}
+
+/// GET /api/query?q= - Evaluate an org-ql style query against indexed headings
+pub async fn query(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<QueryParams>,
+) -> Json<QueryResponse> {
+    let today = match timezone::now(&state.config, params.tz.as_deref()) {
+        Ok(now) => now.date(),
+        Err(_) => {
+            return Json(QueryResponse {
+                error: Some(format!("Unknown timezone: {}", params.tz.unwrap_or_default())),
+                query: params.q,
+                count: 0,
+                items: Vec::new(),
+            })
+        }
+    };
+    let predicates = match parse_query(&params.q, today) {
+        Ok(p) => p,
+        Err(e) => {
+            return Json(QueryResponse {
+                query: params.q,
+                count: 0,
+                error: Some(e),
+                items: Vec::new(),
+            })
+        }
+    };
+
+    let index = state.index.read().await;
+    let mut hits: Vec<(&OrgDocument, &Heading)> = index
+        .documents_with_headings()
+        .flat_map(|(doc, headings)| headings.iter().map(move |h| (doc, h)))
+        .filter(|(doc, heading)| {
+            predicates
+                .iter()
+                .all(|(p, negated)| matches(p, doc, heading) != *negated)
+        })
+        .collect();
+    hits.sort_by(|a, b| a.0.path.cmp(&b.0.path).then(a.1.line.cmp(&b.1.line)));
+    hits.truncate(params.limit.unwrap_or(500));
+    let hits: Vec<(String, PathBuf, Heading)> = hits
+        .into_iter()
+        .map(|(doc, h)| (doc.path.clone(), state.roots.resolve(&doc.path), h.clone()))
+        .collect();
+    drop(index);
+
+    // Read each matching file once to pull context lines
+    let read_context = move || {
+        let mut contents: HashMap<PathBuf, String> = HashMap::new();
+        hits.into_iter()
+            .map(|(file, full_path, h)| {
+                let text = contents
+                    .entry(full_path)
+                    .or_insert_with_key(|p| std::fs::read_to_string(p).unwrap_or_default());
+                let lines: Vec<&str> = text.lines().collect();
+                QueryMatch {
+                    context: heading_context(&lines, &h),
+                    file,
+                    line: h.line,
+                    level: h.level,
+                    title: h.title,
+                    todo: h.todo,
+                    priority: h.priority,
+                    tags: h.tags,
+                    scheduled: h.scheduled,
+                    deadline: h.deadline,
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let items = tokio::task::spawn_blocking(read_context).await.unwrap_or_else(|e| {
+        log_to_file(&format!("[query] Reading context failed: {}", e));
+        Vec::new()
+    });
+
+    Json(QueryResponse {
+        query: params.q,
+        count: items.len(),
+        error: None,
+        items,
+    })
+}
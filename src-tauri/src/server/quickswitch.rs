@@ -0,0 +1,107 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::document::strip_document_extension;
+use crate::server::AppState;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct QuickSwitchQuery {
+    #[serde(default)]
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct Candidate {
+    path: String,
+    title: String,
+    score: i64,
+    /// Which text matched best: `title` or `path`
+    matched: String,
+    /// Character offsets of the matched characters within that text, for highlighting
+    positions: Vec<usize>,
+}
+
+#[derive(Serialize)]
+pub struct QuickSwitchResponse {
+    query: String,
+    count: usize,
+    items: Vec<Candidate>,
+}
+
+/// GET /api/quickswitch?q=&limit= - Fuzzy file switcher over paths and titles.
+/// Smart case: an uppercase letter in the query makes matching case-sensitive.
+/// An empty query returns the most recently modified files.
+pub async fn quickswitch(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<QuickSwitchQuery>,
+) -> Json<QuickSwitchResponse> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let index = state.index.read().await;
+    let pattern = query.q.trim();
+
+    let items: Vec<Candidate> = if pattern.is_empty() {
+        let mut docs = index.get_documents();
+        docs.sort_by_key(|d| std::cmp::Reverse(index.get_mtime_secs(&d.path).unwrap_or(0)));
+        docs.into_iter()
+            .take(limit)
+            .map(|d| Candidate {
+                path: d.path.clone(),
+                title: d.title.clone(),
+                score: 0,
+                matched: "path".to_string(),
+                positions: Vec::new(),
+            })
+            .collect()
+    } else {
+        let matcher = SkimMatcherV2::default().smart_case();
+        let mut candidates: Vec<Candidate> = index
+            .get_documents()
+            .into_iter()
+            .filter_map(|d| {
+                // Paths match without the extension, so ".md" doesn't pull in every file
+                let path = strip_document_extension(&d.path);
+                let by_title = matcher.fuzzy_indices(&d.title, pattern).map(|m| (m, "title"));
+                let by_path = matcher.fuzzy_indices(path, pattern).map(|m| (m, "path"));
+                let ((score, positions), matched) = match (by_title, by_path) {
+                    (Some(t), Some(p)) if p.0 .0 > t.0 .0 => p,
+                    (Some(t), _) => t,
+                    (None, Some(p)) => p,
+                    (None, None) => return None,
+                };
+                Some(Candidate {
+                    path: d.path.clone(),
+                    title: d.title.clone(),
+                    score,
+                    matched: matched.to_string(),
+                    positions,
+                })
+            })
+            .collect();
+
+        // Best score first; among equals, shorter paths are usually what was meant
+        candidates.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(a.path.len().cmp(&b.path.len()))
+                .then(a.path.cmp(&b.path))
+        });
+        candidates.truncate(limit);
+        candidates
+    };
+
+    Json(QuickSwitchResponse {
+        query: query.q,
+        count: items.len(),
+        items,
+    })
+}
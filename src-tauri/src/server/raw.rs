@@ -0,0 +1,60 @@
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+};
+use std::sync::Arc;
+use tower_http::services::ServeFile;
+
+use crate::server::{log_to_file, AppState};
+
+/// Types whose documents can run script: a synced HTML or SVG note opened
+/// inline could otherwise call the API with the user's token
+const ACTIVE_TYPES: &[&str] = &["text/html", "application/xhtml+xml", "image/svg+xml", "text/xml", "application/xml"];
+
+/// GET /api/raw/*path - Any file under a root as stored, e.g. a PDF or an
+/// audio note linked from a document. Honours `Range` so media can seek and
+/// start playing before it's all downloaded, and `If-Modified-Since`.
+/// Dotfiles, such as the config and the index, aren't served. Files are
+/// served from the app's own origin, so they're sandboxed, and types a
+/// browser would run script in are downloaded instead of displayed.
+pub async fn get_raw(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    req: Request,
+) -> Result<Response, StatusCode> {
+    if path.split(['/', '\\']).any(|s| s.starts_with('.')) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let full_path = state.roots.resolve(&path);
+    let canonical = full_path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&path, &canonical) {
+        log_to_file(&format!("[raw] Rejected path traversal: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !canonical.is_file() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // Content type is guessed from the name the file was asked for, not the
+    // target of a symlink
+    let mime = mime_guess::from_path(&full_path).first_or_octet_stream();
+    let active = ACTIVE_TYPES.contains(&mime.essence_str());
+    let mime = if active { mime_guess::mime::APPLICATION_OCTET_STREAM } else { mime };
+    let mut response = ServeFile::new_with_mime(&canonical, &mime)
+        .try_call(req)
+        .await
+        .map_err(|e| {
+            log_to_file(&format!("[raw] Failed to serve {}: {}", path, e));
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .map(Body::new);
+    let headers = response.headers_mut();
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static("sandbox"));
+    if active {
+        headers.insert(header::CONTENT_DISPOSITION, HeaderValue::from_static("attachment"));
+    }
+    Ok(response)
+}
@@ -0,0 +1,163 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::server::{log_to_file, AppState};
+
+/// Viewed documents live next to `.org-viewer-config.json` in the org root
+const RECENT_FILENAME: &str = ".org-viewer-recent.json";
+
+/// Views kept per client
+const MAX_VIEWED: usize = 100;
+
+/// Key for views recorded without a client id
+const GLOBAL_CLIENT: &str = "";
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewEntry {
+    path: String,
+    /// RFC 3339 time of the most recent view
+    at: String,
+}
+
+/// Most recent first, keyed by client id
+type Views = BTreeMap<String, Vec<ViewEntry>>;
+
+fn load_views(state: &AppState) -> Views {
+    let path = state.org_root.join(RECENT_FILENAME);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_to_file(&format!("[recent] Invalid recent files {:?}: {}", path, e));
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+/// Move `path` to the front of a client's recently viewed documents
+pub fn record_view(state: &AppState, client: Option<&str>, path: &str) {
+    let mut views = load_views(state);
+    let entries = views.entry(client.unwrap_or(GLOBAL_CLIENT).to_string()).or_default();
+    entries.retain(|e| e.path != path);
+    entries.insert(
+        0,
+        ViewEntry {
+            path: path.to_string(),
+            at: chrono::Utc::now().to_rfc3339(),
+        },
+    );
+    entries.truncate(MAX_VIEWED);
+    save_views(state, &views);
+}
+
+fn save_views(state: &AppState, views: &Views) {
+    let json = match serde_json::to_string_pretty(views) {
+        Ok(j) => j,
+        Err(_) => return,
+    };
+    if let Err(e) = std::fs::write(state.org_root.join(RECENT_FILENAME), json) {
+        log_to_file(&format!("[recent] Failed to save recent files: {}", e));
+    }
+}
+
+/// Point views of renamed documents at their new paths, as (from, to)
+pub fn rename_views(state: &AppState, renamed: &[(String, String)]) {
+    let mut views = load_views(state);
+    let mut changed = false;
+    for entry in views.values_mut().flatten() {
+        if let Some((_, to)) = renamed.iter().find(|(from, _)| *from == entry.path) {
+            entry.path = to.clone();
+            changed = true;
+        }
+    }
+    if changed {
+        save_views(state, &views);
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RecentQuery {
+    limit: Option<usize>,
+    /// Also return recently viewed documents
+    #[serde(default)]
+    viewed: bool,
+    /// Client id for `viewed`; views without one are shared by every device
+    client: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RecentDocument {
+    path: String,
+    title: String,
+    #[serde(rename = "type")]
+    doc_type: String,
+    /// Unix seconds
+    mtime: u64,
+}
+
+#[derive(Serialize)]
+pub struct ViewedDocument {
+    path: String,
+    title: String,
+    /// RFC 3339 time of the most recent view
+    at: String,
+}
+
+#[derive(Serialize)]
+pub struct RecentResponse {
+    modified: Vec<RecentDocument>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    viewed: Option<Vec<ViewedDocument>>,
+}
+
+/// GET /api/recent?limit=&viewed=&client= - Recently modified documents, and
+/// optionally recently viewed ones, most recent first
+pub async fn get_recent(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RecentQuery>,
+) -> Json<RecentResponse> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let index = state.index.read().await;
+
+    let mut modified: Vec<RecentDocument> = index
+        .get_documents()
+        .into_iter()
+        .filter_map(|d| {
+            Some(RecentDocument {
+                path: d.path.clone(),
+                title: d.title.clone(),
+                doc_type: d.doc_type.clone(),
+                mtime: index.get_mtime_secs(&d.path)?,
+            })
+        })
+        .collect();
+    modified.sort_by(|a, b| b.mtime.cmp(&a.mtime).then_with(|| a.path.cmp(&b.path)));
+    modified.truncate(limit);
+
+    // Documents deleted since they were viewed are left out
+    let viewed = query.viewed.then(|| {
+        load_views(&state)
+            .remove(query.client.as_deref().unwrap_or(GLOBAL_CLIENT))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|v| {
+                let doc = index.get_document(&v.path)?;
+                Some(ViewedDocument {
+                    path: v.path,
+                    title: doc.title.clone(),
+                    at: v.at,
+                })
+            })
+            .take(limit)
+            .collect()
+    });
+
+    Json(RecentResponse { modified, viewed })
+}
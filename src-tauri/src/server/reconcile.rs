@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::server::events::EventKind;
+use crate::server::index::DocumentIndex;
+use crate::server::{log_to_file, AppState};
+
+/// Minutes between scans when `reconcileMinutes` isn't configured
+pub const DEFAULT_RECONCILE_MINUTES: u64 = 10;
+
+/// Periodically re-walk every root and bring the index in line with the
+/// files, catching changes the watcher missed: network drives, rsync, or
+/// edits while the watcher was restarting. Runs until the server stops, every
+/// `reconcileMinutes`; returns at once when that's 0.
+pub async fn run(state: Arc<AppState>) {
+    let minutes = state.config.reconcile_minutes;
+    if minutes == 0 {
+        log_to_file("[reconcile] Periodic scan disabled");
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(minutes * 60));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick fires immediately, right after the startup load
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        // A full rebuild is already bringing everything up to date, and a
+        // paused watcher means files are mid-way through a bulk change
+        if state.reindexing.load(Ordering::SeqCst) || state.watcher.read().await.paused {
+            continue;
+        }
+        reconcile(&state).await;
+    }
+}
+
+/// Scan once and queue whatever differs from the index
+pub async fn reconcile(state: &Arc<AppState>) {
+    let check = state.index.read().await.verify_cache();
+    if let Err(reason) = check {
+        rebuild_cache(state, &reason).await;
+    }
+
+    let (changed, removed) = match scan_changes(state).await {
+        Some(c) => c,
+        None => return,
+    };
+    if changed.is_empty() && removed.is_empty() {
+        return;
+    }
+
+    log_to_file(&format!(
+        "[reconcile] Found {} changed and {} removed files the watcher missed",
+        changed.len(),
+        removed.len()
+    ));
+    for path in changed.into_iter().chain(removed) {
+        state.dirty.push(path);
+    }
+}
+
+/// Walk the roots and compare with the index: (changed or new files, files
+/// of documents that are gone)
+pub async fn scan_changes(state: &AppState) -> Option<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let roots = state.roots.clone();
+    let scanned = match tokio::task::spawn_blocking(move || DocumentIndex::scan(&roots)).await {
+        Ok(s) => s,
+        Err(e) => {
+            log_to_file(&format!("[reconcile] Scan failed: {}", e));
+            return None;
+        }
+    };
+
+    let (changed, removed) = state.index.read().await.diff_scan(&scanned);
+    Some((
+        changed.into_iter().map(|(path, _)| path).collect(),
+        removed.iter().map(|relative| state.roots.resolve(relative)).collect(),
+    ))
+}
+
+/// Rewrite a cache that failed validation from the in-memory index, which
+/// was loaded from a good cache or the files themselves. Clients see
+/// `index-rebuild` messages (`started`, `done`) around it.
+async fn rebuild_cache(state: &Arc<AppState>, reason: &str) {
+    log_to_file(&format!("[reconcile] Index cache is corrupt ({}); rebuilding it", reason));
+    broadcast_rebuild(state, "started", reason);
+
+    let index = state.index.clone().read_owned().await;
+    if let Err(e) = tokio::task::spawn_blocking(move || index.rewrite_cache()).await {
+        log_to_file(&format!("[reconcile] Rebuilding index cache failed: {}", e));
+        return;
+    }
+
+    broadcast_rebuild(state, "done", reason);
+    log_to_file("[reconcile] Index cache rebuilt");
+}
+
+fn broadcast_rebuild(state: &AppState, phase: &str, reason: &str) {
+    let payload = serde_json::json!({
+        "task": "cache-rebuild",
+        "phase": phase,
+        "reason": reason
+    });
+    state.events.send(EventKind::IndexProgress, None, payload);
+}
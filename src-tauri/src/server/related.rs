@@ -0,0 +1,136 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use crate::server::semantic::dot;
+use crate::server::AppState;
+
+const MAX_RELATED: usize = 10;
+
+/// Each signal is in 0..=1 before weighting
+const TAG_WEIGHT: f32 = 1.0;
+const SHARED_LINK_WEIGHT: f32 = 1.0;
+const DIRECT_LINK_WEIGHT: f32 = 0.5;
+const SIMILARITY_WEIGHT: f32 = 1.0;
+
+#[derive(Serialize)]
+pub struct RelatedDoc {
+    path: String,
+    title: String,
+    score: f32,
+    #[serde(rename = "sharedTags", skip_serializing_if = "Vec::is_empty")]
+    shared_tags: Vec<String>,
+    /// Documents both link to or are linked from
+    #[serde(rename = "sharedLinks", skip_serializing_if = "Vec::is_empty")]
+    shared_links: Vec<String>,
+    /// One links to the other
+    linked: bool,
+    /// Embedding cosine similarity, when semantic search is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    similarity: Option<f32>,
+}
+
+#[derive(Serialize)]
+pub struct RelatedResponse {
+    path: String,
+    count: usize,
+    items: Vec<RelatedDoc>,
+}
+
+fn jaccard<T: Ord>(a: &BTreeSet<T>, b: &BTreeSet<T>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+/// GET /api/files/*path/related - "See also" suggestions from shared tags,
+/// overlapping links, and embedding similarity when available
+pub async fn get_related(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<RelatedResponse>, StatusCode> {
+    let vectors = if state.config.embeddings.is_some() {
+        state.semantic.document_vectors().await
+    } else {
+        HashMap::new()
+    };
+
+    let index = state.index.read().await;
+    let docs = index.get_documents();
+    if index.get_document(&path).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // Link neighbourhood per document: resolved outgoing links plus backlinks
+    let mut neighbours: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for doc in &docs {
+        for source in &doc.backlinks {
+            neighbours.entry(source.as_str()).or_default().insert(doc.path.as_str());
+            neighbours.entry(doc.path.as_str()).or_default().insert(source.as_str());
+        }
+    }
+    let tags_of = |path: &str| -> BTreeSet<String> {
+        let doc = index.get_document(path);
+        let headings = index.get_headings(path);
+        doc.into_iter()
+            .flat_map(|d| d.tags.iter().chain(d.file_tags.iter()))
+            .chain(headings.iter().flat_map(|h| h.tags.iter()))
+            .map(|t| t.to_lowercase())
+            .collect()
+    };
+
+    let empty = BTreeSet::new();
+    let own_tags = tags_of(&path);
+    let own_links = neighbours.get(path.as_str()).unwrap_or(&empty);
+    let own_vector = vectors.get(&path);
+
+    let mut items: Vec<RelatedDoc> = docs
+        .iter()
+        .filter(|d| d.path != path)
+        .filter_map(|d| {
+            let tags = tags_of(&d.path);
+            let links = neighbours.get(d.path.as_str()).unwrap_or(&empty);
+            // Each other's neighbours don't count as shared
+            let own_shared: BTreeSet<&str> = own_links.iter().copied().filter(|p| *p != d.path).collect();
+            let other_shared: BTreeSet<&str> = links.iter().copied().filter(|p| *p != path).collect();
+            let linked = own_links.contains(d.path.as_str());
+            let similarity = own_vector
+                .zip(vectors.get(&d.path))
+                .map(|(a, b)| dot(a, b));
+
+            let score = TAG_WEIGHT * jaccard(&own_tags, &tags)
+                + SHARED_LINK_WEIGHT * jaccard(&own_shared, &other_shared)
+                + if linked { DIRECT_LINK_WEIGHT } else { 0.0 }
+                + SIMILARITY_WEIGHT * similarity.unwrap_or(0.0).max(0.0);
+            if score <= 0.0 {
+                return None;
+            }
+
+            Some(RelatedDoc {
+                path: d.path.clone(),
+                title: d.title.clone(),
+                score,
+                shared_tags: own_tags.intersection(&tags).cloned().collect(),
+                shared_links: own_shared.intersection(&other_shared).map(|p| p.to_string()).collect(),
+                linked,
+                similarity,
+            })
+        })
+        .collect();
+
+    items.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.path.cmp(&b.path)));
+    items.truncate(MAX_RELATED);
+
+    Ok(Json(RelatedResponse {
+        path,
+        count: items.len(),
+        items,
+    }))
+}
@@ -0,0 +1,134 @@
+use chrono::{Duration, NaiveDateTime};
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::server::effort::parse_org_timestamp;
+use crate::server::events::EventKind;
+use crate::server::org::parse_todo_keywords;
+use crate::server::timezone;
+use crate::server::{log_to_file, AppState};
+
+/// Minutes of warning before a timed entry is due when `reminderMinutes`
+/// isn't configured
+pub const DEFAULT_REMINDER_MINUTES: u64 = 10;
+
+/// How often upcoming entries are checked
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// A timed SCHEDULED or DEADLINE entry coming up
+struct Due {
+    file: String,
+    line: usize,
+    title: String,
+    todo: Option<String>,
+    /// `scheduled` or `deadline`
+    kind: &'static str,
+    at: NaiveDateTime,
+}
+
+/// Send a `reminder` event for each open SCHEDULED or DEADLINE entry with a
+/// time of day, `reminderMinutes` before it's due, or as many minutes as
+/// the heading's `:APPT_WARNTIME:` property says, like org's appt.el. Runs
+/// until the server stops; returns at once when `reminderMinutes` is 0.
+pub async fn run(state: Arc<AppState>) {
+    let minutes = state.config.reminder_minutes;
+    if minutes == 0 {
+        log_to_file("[reminders] Reminders disabled");
+        return;
+    }
+
+    // Entries already announced, by file, line, kind and due time, so each
+    // is announced once however many checks fall within its warning time
+    let mut sent: HashSet<(String, usize, &'static str, NaiveDateTime)> = HashSet::new();
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        let now = match timezone::now(&state.config, None) {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
+        sent.retain(|(_, _, _, at)| *at > now);
+
+        for due in upcoming(&state, now, minutes).await {
+            let key = (due.file.clone(), due.line, due.kind, due.at);
+            if sent.contains(&key) {
+                continue;
+            }
+            // Rounded up, so an entry due at 10:05 is "in 5 minutes" at 10:00:30
+            let until = ((due.at - now).num_seconds() + 59) / 60;
+            log_to_file(&format!("[reminders] {} {} in {} minutes", due.kind, due.title, until));
+            // Sent to every client, whatever documents it subscribed to
+            state.events.send(
+                EventKind::Reminder,
+                None,
+                json!({
+                    "file": due.file,
+                    "line": due.line,
+                    "title": due.title,
+                    "todo": due.todo,
+                    "kind": due.kind,
+                    "time": due.at.format("%Y-%m-%d %H:%M").to_string(),
+                    "minutes": until,
+                }),
+            );
+            sent.insert(key);
+        }
+    }
+}
+
+/// Open entries due after `now` and within their warning time of it
+async fn upcoming(state: &AppState, now: NaiveDateTime, minutes: u64) -> Vec<Due> {
+    let mut candidates = Vec::new();
+    {
+        let index = state.index.read().await;
+        for (doc, headings) in index.documents_with_headings() {
+            for heading in headings {
+                let warning = heading
+                    .properties
+                    .get("APPT_WARNTIME")
+                    .and_then(|w| w.trim().parse::<i64>().ok())
+                    .unwrap_or(minutes as i64);
+                for (kind, value) in [("scheduled", &heading.scheduled), ("deadline", &heading.deadline)] {
+                    // Entries without a time of day have nothing to count down to
+                    let value = match value.as_deref().filter(|v| v.contains(':')) {
+                        Some(v) => v,
+                        None => continue,
+                    };
+                    let at = match parse_org_timestamp(value) {
+                        Some(t) => t,
+                        None => continue,
+                    };
+                    if at > now && at <= now + Duration::minutes(warning) {
+                        candidates.push(Due {
+                            file: doc.path.clone(),
+                            line: heading.line,
+                            title: heading.title.clone(),
+                            todo: heading.todo.clone(),
+                            kind,
+                            at,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // Only files with something due are read, to tell done keywords apart
+    let mut due = Vec::new();
+    for candidate in candidates {
+        if let Some(todo) = &candidate.todo {
+            let content = match tokio::fs::read_to_string(state.roots.resolve(&candidate.file)).await {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+            if parse_todo_keywords(&content).is_done(todo) {
+                continue;
+            }
+        }
+        due.push(candidate);
+    }
+    due
+}
@@ -0,0 +1,226 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::path::Path as FsPath;
+use std::sync::Arc;
+
+use crate::server::create::new_document_path;
+use crate::server::document::{is_document_file, is_org_file};
+use crate::server::images::resolve_relative;
+use crate::server::trash::move_file;
+use crate::server::{dirty, log_to_file, AppState};
+
+#[derive(Deserialize)]
+pub struct MoveRequest {
+    /// New document path
+    to: String,
+}
+
+#[derive(Serialize)]
+pub struct RewrittenFile {
+    path: String,
+    /// Links rewritten in it
+    links: usize,
+}
+
+#[derive(Serialize)]
+pub struct MoveResponse {
+    from: String,
+    to: String,
+    /// Documents whose file links were rewritten, including the moved one
+    /// when its own relative links needed it
+    rewritten: Vec<RewrittenFile>,
+}
+
+/// Rewrite the target of each file link outside code blocks for which
+/// `retarget` gives a new one: `[[file:...]]` links in an org file, keeping
+/// any `::search` part and description, or `[text](...)` links in Markdown,
+/// keeping any `#anchor` and title. Returns the content and how many links
+/// changed.
+fn rewrite_file_links(
+    content: &str,
+    org: bool,
+    mut retarget: impl FnMut(&str) -> Option<String>,
+) -> (String, usize) {
+    let (link_re, marker) = if org {
+        (Regex::new(r"\[\[file:([^\]]+?)(::[^\]]*)?\](\[[^\]]*\])?\]").unwrap(), "[[file:")
+    } else {
+        (Regex::new(r#"\]\(([^)#\s]+)(#[^)\s]*)?(\s+"[^"]*")?\)"#).unwrap(), "](")
+    };
+    let mut count = 0;
+    let mut in_block = false;
+    let mut out = String::with_capacity(content.len());
+    for line in content.split_inclusive('\n') {
+        // Links inside code are examples, not references
+        let trimmed = line.trim_start().to_lowercase();
+        if trimmed.starts_with("#+begin_src") || trimmed.starts_with("#+begin_example") || trimmed.starts_with("```") {
+            in_block = !trimmed.starts_with("```") || !in_block;
+        } else if trimmed.starts_with("#+end_src") || trimmed.starts_with("#+end_example") {
+            in_block = false;
+        }
+        if in_block || !line.contains(marker) {
+            out.push_str(line);
+            continue;
+        }
+        let rewritten = link_re.replace_all(line, |caps: &Captures| match retarget(&caps[1]) {
+            Some(target) => {
+                count += 1;
+                let search = caps.get(2).map_or("", |m| m.as_str());
+                let description = caps.get(3).map_or("", |m| m.as_str());
+                if org {
+                    format!("[[file:{}{}]{}]", target, search, description)
+                } else {
+                    format!("]({}{}{})", target, search, description)
+                }
+            }
+            None => caps[0].to_string(),
+        });
+        out.push_str(&rewritten);
+    }
+    (out, count)
+}
+
+/// Relative link from the document at `source` to `target`, both document
+/// paths; a leading `./` is kept when `original` had one
+fn relative_link(source: &str, target: &str, original: &str) -> String {
+    let mut from_dir: Vec<&str> = source.split('/').collect();
+    from_dir.pop();
+    let to_parts: Vec<&str> = target.split('/').collect();
+    let common = from_dir
+        .iter()
+        .zip(&to_parts[..to_parts.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+    let link = "../".repeat(from_dir.len() - common) + &to_parts[common..].join("/");
+    if original.starts_with("./") && !link.starts_with("../") {
+        format!("./{}", link)
+    } else {
+        link
+    }
+}
+
+/// POST /api/files/*path/move - Move or rename a document, rewriting the
+/// file links to it across the vault and its own relative links so none are
+/// left broken. Links by ID, title or file name survive a move
+/// as they are. 409 if a file exists at the destination already.
+pub async fn move_document(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Json(payload): Json<MoveRequest>,
+) -> Result<Json<MoveResponse>, StatusCode> {
+    log_to_file(&format!("[server] POST /api/files/{}/move -> {}", path, payload.to));
+    let from_path = state.roots.resolve(&path);
+    let canonical = from_path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&path, &canonical) {
+        log_to_file(&format!("[server] Move rejected - path traversal attempt: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !canonical.is_file() || !is_document_file(&from_path) || payload.to == path {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let to = payload.to;
+    let to_path = new_document_path(&state, &to)?;
+    if to_path.exists() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    // Documents with a file link to the old path, found before the move is
+    // indexed and their links start resolving elsewhere. Markdown links
+    // aren't indexed, so every Markdown document is a candidate.
+    let sources: Vec<String> = {
+        let index = state.index.read().await;
+        let mut sources: Vec<String> = index
+            .get_documents()
+            .into_iter()
+            .filter(|doc| doc.path != path)
+            .filter(|doc| {
+                !is_org_file(FsPath::new(&doc.path))
+                    || doc.links.iter().any(|link| {
+                        link.strip_prefix("file:").is_some_and(|target| {
+                            let target = target.split("::").next().unwrap_or(target);
+                            resolve_relative(&doc.path, target).as_deref() == Some(path.as_str())
+                        })
+                    })
+            })
+            .map(|doc| doc.path.clone())
+            .collect();
+        sources.sort();
+        sources
+    };
+
+    move_file(&from_path, &to_path).map_err(|e| {
+        log_to_file(&format!("[server] Move failed for {}: {}", path, e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    log_to_file(&format!("[server] Moved {} to {}", path, to));
+
+    let (roots, from, dest) = (state.roots.clone(), path.clone(), to.clone());
+    let rewrite_sources = move || {
+        let mut rewritten = Vec::new();
+        let mut changed = Vec::new();
+        for source in sources {
+            let source_path = roots.resolve(&source);
+            let content = match std::fs::read_to_string(&source_path) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+            let (content, links) = rewrite_file_links(&content, is_org_file(&source_path), |target| {
+                (resolve_relative(&source, target).as_deref() == Some(from.as_str()))
+                    .then(|| relative_link(&source, &dest, target))
+            });
+            if links == 0 {
+                continue;
+            }
+            if let Err(e) = std::fs::write(&source_path, &content) {
+                log_to_file(&format!("[server] Move failed to rewrite links in {}: {}", source, e));
+                continue;
+            }
+            rewritten.push(RewrittenFile { path: source, links });
+            changed.push(source_path);
+        }
+        (rewritten, changed)
+    };
+    let (mut rewritten, mut changed) = tokio::task::spawn_blocking(rewrite_sources).await.map_err(|e| {
+        log_to_file(&format!("[server] Move failed to rewrite links to {}: {}", to, e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    // The moved document keeps its content for now, so the index sees the
+    // move as a rename rather than a removal and a new document
+    let org = is_org_file(&from_path);
+    changed.extend([from_path, to_path.clone()]);
+    dirty::apply_now(&state, changed).await;
+
+    // Then its own relative links, to anything still where it was. Its
+    // content is still in the syntax it was written in.
+    let (roots, from, dest, file) = (state.roots.clone(), path.clone(), to.clone(), to_path.clone());
+    let rewrite_own = move || {
+        let content = std::fs::read_to_string(&file).unwrap_or_default();
+        let (content, links) = rewrite_file_links(&content, org, |target| {
+            let before = resolve_relative(&from, target)?;
+            let before = if before == from { dest.clone() } else { before };
+            let moved = resolve_relative(&dest, target).as_deref() != Some(before.as_str());
+            (moved && roots.resolve(&before).exists()).then(|| relative_link(&dest, &before, target))
+        });
+        if links == 0 {
+            return Ok(0);
+        }
+        std::fs::write(&file, &content).map(|()| links)
+    };
+    let result = tokio::task::spawn_blocking(rewrite_own)
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+    match result {
+        Ok(0) => {}
+        Ok(links) => {
+            rewritten.push(RewrittenFile { path: to.clone(), links });
+            dirty::apply_now(&state, vec![to_path]).await;
+        }
+        Err(e) => log_to_file(&format!("[server] Move failed to rewrite links in {}: {}", to, e)),
+    }
+
+    Ok(Json(MoveResponse { from: path, to, rewritten }))
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::server::ignore_file::IgnoreRules;
+use crate::server::log_to_file;
+
+/// Marks a document path as belonging to an additional root: `@work/notes/x.md`
+const ROOT_PREFIX: char = '@';
+
+/// The directories documents are indexed from: the org root itself, plus any
+/// `roots` from the config. Documents in an additional root get paths
+/// namespaced as `@name/...`; the org root's keep plain relative paths, and
+/// config and caches stay there.
+#[derive(Debug, Clone)]
+pub struct Roots {
+    primary: PathBuf,
+    extra: BTreeMap<String, PathBuf>,
+    /// Each root's `.orgviewerignore`
+    ignore: IgnoreRules,
+    /// Walk and watch through symlinks (`followSymlinks`)
+    follow_symlinks: bool,
+    /// Directories indexed for metadata only (`metadataOnly`)
+    metadata_only: Vec<PathBuf>,
+}
+
+impl Roots {
+    /// Relative root directories are taken from the org root. Roots that don't
+    /// exist or have unusable names are skipped with a log line.
+    pub fn new(primary: &Path, extra: &BTreeMap<String, PathBuf>) -> Self {
+        let mut roots = BTreeMap::new();
+        for (name, dir) in extra {
+            let dir = primary.join(dir);
+            if name.is_empty() || name.contains(['/', '\\']) {
+                log_to_file(&format!("[roots] Skipping root with invalid name {:?}", name));
+                continue;
+            }
+            if !dir.is_dir() {
+                log_to_file(&format!("[roots] Skipping root {}: {:?} is not a directory", name, dir));
+                continue;
+            }
+            roots.insert(name.clone(), dir);
+        }
+        let ignore = IgnoreRules::load(std::iter::once(primary).chain(roots.values().map(|d| d.as_path())));
+        Self {
+            primary: primary.to_path_buf(),
+            extra: roots,
+            ignore,
+            follow_symlinks: false,
+            metadata_only: Vec::new(),
+        }
+    }
+
+    pub fn with_follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    pub fn follow_symlinks(&self) -> bool {
+        self.follow_symlinks
+    }
+
+    /// Directories given as document paths, e.g. `reference/dumps` or `@work/archive`
+    pub fn with_metadata_only(mut self, dirs: &[String]) -> Self {
+        self.metadata_only = dirs.iter().map(|dir| self.resolve(dir.trim_end_matches('/'))).collect();
+        self
+    }
+
+    pub fn metadata_only(&self) -> &[PathBuf] {
+        &self.metadata_only
+    }
+
+    /// Whether `full` lies in a directory indexed for metadata only
+    pub fn is_metadata_only(&self, full: &Path) -> bool {
+        self.metadata_only.iter().any(|dir| full.starts_with(dir))
+    }
+
+    /// Whether a metadata-only directory lies somewhere below `dir`
+    pub fn has_metadata_only_within(&self, dir: &Path) -> bool {
+        self.metadata_only.iter().any(|d| d != dir && d.starts_with(dir))
+    }
+
+    pub fn primary(&self) -> &Path {
+        &self.primary
+    }
+
+    /// Every root directory with the prefix of its document paths, the org
+    /// root first with an empty prefix
+    pub fn iter(&self) -> impl Iterator<Item = (String, &Path)> {
+        std::iter::once((String::new(), self.primary.as_path())).chain(
+            self.extra
+                .iter()
+                .map(|(name, dir)| (format!("{}{}/", ROOT_PREFIX, name), dir.as_path())),
+        )
+    }
+
+    /// The root directory a document path lives in, and the path relative to it.
+    /// Unknown `@name/` prefixes fall through to the org root, where they won't exist.
+    pub fn split<'a>(&self, path: &'a str) -> (&Path, &'a str) {
+        if let Some(rest) = path.strip_prefix(ROOT_PREFIX) {
+            if let Some((name, relative)) = rest.split_once('/') {
+                if let Some(dir) = self.extra.get(name) {
+                    return (dir, relative);
+                }
+            }
+        }
+        (&self.primary, path)
+    }
+
+    /// Filesystem path of a document path
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        let (root, relative) = self.split(path);
+        root.join(relative)
+    }
+
+    /// The `@name/` prefix of a document path, empty for the org root
+    pub fn prefix_of<'a>(&self, path: &'a str) -> &'a str {
+        let relative = self.split(path).1;
+        &path[..path.len() - relative.len()]
+    }
+
+    /// Root directory of a document path
+    pub fn root_of(&self, path: &str) -> &Path {
+        self.split(path).0
+    }
+
+    /// The innermost root containing a filesystem path, with its prefix, so a
+    /// root nested inside the org root claims its own files
+    pub fn locate(&self, full: &Path) -> Option<(String, &Path)> {
+        let mut found: Option<(String, &Path)> = None;
+        for (prefix, root) in self.iter() {
+            if full.starts_with(root) && found.as_ref().is_none_or(|(_, f)| root.starts_with(f)) {
+                found = Some((prefix, root));
+            }
+        }
+        found
+    }
+
+    /// Whether the `.orgviewerignore` of the root containing `full` ignores
+    /// it (`Some(true)`) or re-includes it with a `!` pattern (`Some(false)`)
+    pub fn ignore_match(&self, full: &Path, is_dir: bool) -> Option<bool> {
+        let (_, root) = self.locate(full)?;
+        self.ignore.matched(root, full, is_dir)
+    }
+
+    /// Re-read the `.orgviewerignore` of the root directory `root`
+    pub fn reload_ignore(&self, root: &Path) {
+        self.ignore.reload(root);
+    }
+
+    /// Traversal check for a document path that resolved to `canonical`
+    pub fn contains(&self, path: &str, canonical: &Path) -> bool {
+        let (root, relative) = self.split(path);
+        self.contains_relative(root, Path::new(relative), canonical)
+    }
+
+    /// Whether `relative` under the root directory `root`, resolved to
+    /// `canonical`, stays inside the root. Normally the resolved path must
+    /// lie in the resolved root. With `followSymlinks` a symlink in the root
+    /// may lead anywhere, so a path without `..` components is allowed too;
+    /// `..` after a link would climb out of the link's target instead.
+    pub fn contains_relative(&self, root: &Path, relative: &Path, canonical: &Path) -> bool {
+        if root.canonicalize().is_ok_and(|r| canonical.starts_with(r)) {
+            return true;
+        }
+        self.follow_symlinks
+            && relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    }
+
+    /// Document path of a file under any root
+    pub fn relativize(&self, full: &Path) -> Option<String> {
+        let (prefix, root) = self.locate(full)?;
+        let relative = full.strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/");
+        Some(format!("{}{}", prefix, relative))
+    }
+}
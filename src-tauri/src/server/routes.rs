@@ -1,14 +1,28 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    extract::{FromRequest, Multipart, Path, Query, Request, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use crate::server::{log_to_file, AppState};
+use crate::server::crypt::{
+    decrypt_content, encrypt_content, has_plaintext_crypt, session_passphrase, CRYPT_SESSION_HEADER,
+};
 use crate::server::document::serialize_document;
+use crate::server::ids::rewrite_id_links;
+use crate::server::images::rewrite_image_links;
+use crate::server::includes::resolve_includes;
+use crate::server::index::DocumentIndex;
+use crate::server::macros::expand_macros;
+use crate::server::org::subtree_by_custom_id;
+use crate::server::watcher::WatcherStatus;
+use crate::server::{
+    attachments, backlinks, conditional, create, dblocks, drafts, lists, merge, meta, occurrences, outline, projects, recent, related, rename, search_history, streaming, tables, timezone,
+};
 
 #[derive(Serialize)]
 pub struct HealthResponse {
@@ -38,6 +52,7 @@ pub async fn debug_log(Json(payload): Json<DebugLogRequest>) -> StatusCode {
 #[derive(Serialize)]
 pub struct StatusResponse {
     server: ServerStats,
+    watcher: WatcherStatus,
     documents: DocumentStats,
     tags: TagStats,
     recent: Vec<RecentDoc>,
@@ -46,8 +61,9 @@ pub struct StatusResponse {
 #[derive(Serialize)]
 pub struct ServerStats {
     uptime: u64,
+    /// Open WebSocket connections
     #[serde(rename = "connectedClients")]
-    connected_clients: u32,
+    connected_clients: usize,
     #[serde(rename = "lastIndexed")]
     last_indexed: String,
 }
@@ -119,9 +135,10 @@ pub async fn status(State(state): State<Arc<AppState>>) -> Json<StatusResponse>
     Json(StatusResponse {
         server: ServerStats {
             uptime: state.start_time.elapsed().as_secs(),
-            connected_clients: 1,
+            connected_clients: state.connections.load(Ordering::Relaxed),
             last_indexed: chrono::Utc::now().to_rfc3339(),
         },
+        watcher: state.watcher.read().await.clone(),
         documents: DocumentStats {
             total: stats.total,
             by_type: stats.by_type,
@@ -139,22 +156,74 @@ pub async fn status(State(state): State<Arc<AppState>>) -> Json<StatusResponse>
 pub struct ListFilesQuery {
     #[serde(rename = "type")]
     doc_type: Option<String>,
+    /// Frontmatter tag or `#+FILETAGS` entry, case-insensitive
+    tag: Option<String>,
+    /// `#+CATEGORY` value, case-insensitive
+    category: Option<String>,
+    /// Only documents under this directory
+    dir: Option<String>,
+    /// Modified after this instant (RFC 3339), or on or after this day
+    /// (`YYYY-MM-DD`, in `tz`)
+    modified_after: Option<String>,
+    /// IANA timezone a `modified_after` day is in; defaults to the configured one
+    tz: Option<String>,
+    /// `path` (default), `title` or `modified`; a leading `-` reverses it,
+    /// e.g. `-modified` for the most recently modified first
+    sort: Option<String>,
+    /// Page size; all matching documents when absent
+    limit: Option<usize>,
+    /// Documents to skip before the page
+    #[serde(default)]
+    offset: usize,
 }
 
 #[derive(Serialize)]
 pub struct ListFilesResponse {
+    /// Documents in this page
     count: usize,
+    /// Documents across all pages
+    total: usize,
+    offset: usize,
+    /// Offset of the next page, absent on the last one
+    #[serde(rename = "nextOffset", skip_serializing_if = "Option::is_none")]
+    next_offset: Option<usize>,
     items: Vec<serde_json::Value>,
 }
 
+/// GET /api/files?type=&tag=&category=&dir=&modified_after=&sort=&limit=&offset= -
+/// Document metadata, filtered, sorted and paged
 pub async fn list_files(
     State(state): State<Arc<AppState>>,
     Query(query): Query<ListFilesQuery>,
-) -> Json<ListFilesResponse> {
-    let index = state.index.read().await;
-    let docs = index.get_documents();
+) -> Result<Json<ListFilesResponse>, StatusCode> {
+    let dir = query.dir.as_deref().map(|d| d.trim_matches('/')).filter(|d| !d.is_empty());
+    let (descending, sort) = match query.sort.as_deref() {
+        Some(s) => match s.strip_prefix('-') {
+            Some(field) => (true, field),
+            None => (false, s),
+        },
+        None => (false, "path"),
+    };
+    if !["path", "title", "modified"].contains(&sort) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // Unix seconds the modification time has to exceed
+    let modified_after: Option<u64> = match query.modified_after.as_deref() {
+        None => None,
+        Some(value) => Some(match chrono::DateTime::parse_from_rfc3339(value) {
+            Ok(instant) => instant.timestamp().max(0) as u64,
+            Err(_) => {
+                let day = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| StatusCode::BAD_REQUEST)?;
+                let midnight = timezone::to_utc(&state.config, query.tz.as_deref(), day.and_time(chrono::NaiveTime::MIN))?;
+                (midnight.timestamp() - 1).max(0) as u64
+            }
+        }),
+    };
 
-    let items: Vec<serde_json::Value> = docs
+    let index = state.index.read().await;
+    let mtime = |path: &str| index.get_mtime_secs(path).unwrap_or(0);
+    let mut docs: Vec<_> = index
+        .get_documents()
         .into_iter()
         .filter(|d| {
             query
@@ -163,26 +232,179 @@ pub async fn list_files(
                 .map(|t| &d.doc_type == t)
                 .unwrap_or(true)
         })
+        .filter(|d| {
+            query.tag.as_ref().is_none_or(|tag| {
+                d.tags.iter().chain(d.file_tags.iter()).any(|t| t.eq_ignore_ascii_case(tag))
+            })
+        })
+        .filter(|d| {
+            query
+                .category
+                .as_ref()
+                .is_none_or(|c| d.category.as_ref().is_some_and(|dc| dc.eq_ignore_ascii_case(c)))
+        })
+        .filter(|d| dir.is_none_or(|dir| d.path.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/'))))
+        .filter(|d| modified_after.is_none_or(|after| mtime(&d.path) > after))
+        .collect();
+
+    match sort {
+        "title" => docs.sort_by_cached_key(|d| (d.title.to_lowercase(), d.path.clone())),
+        "modified" => docs.sort_by(|a, b| mtime(&a.path).cmp(&mtime(&b.path)).then_with(|| a.path.cmp(&b.path))),
+        _ => docs.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+    if descending {
+        docs.reverse();
+    }
+
+    let total = docs.len();
+    let items: Vec<serde_json::Value> = docs
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
         .map(|d| serde_json::to_value(d).unwrap())
         .collect();
+    let next_offset = Some(query.offset + items.len()).filter(|&next| next < total);
 
-    Json(ListFilesResponse {
+    Ok(Json(ListFilesResponse {
         count: items.len(),
+        total,
+        offset: query.offset,
+        next_offset,
         items,
-    })
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct GetFileQuery {
+    /// Return content exactly as stored on disk (for editing) — skips include
+    /// resolution, macro expansion, and link rewriting
+    #[serde(default)]
+    raw: bool,
+    /// Return only the subtree under the heading with this `:CUSTOM_ID:`
+    anchor: Option<String>,
+    /// Search text for the `occurrences` action
+    q: Option<String>,
+    /// Client id the view is recorded under for `/api/recent`
+    client: Option<String>,
 }
 
+/// GET /api/files/*path[/<action>] - Serve a document or one of its sub-resources
 pub async fn get_file(
     State(state): State<Arc<AppState>>,
     Path(path): Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Query(query): Query<GetFileQuery>,
+    headers: HeaderMap,
+) -> Response {
+    match split_file_action(&path, GET_FILE_ACTIONS) {
+        (doc, Some("backlinks")) => backlinks::get_backlinks(State(state), Path(doc.to_string()))
+            .await
+            .into_response(),
+        (doc, Some("meta")) => meta::get_meta(State(state), Path(doc.to_string()))
+            .await
+            .into_response(),
+        (doc, Some("outline")) => outline::get_outline(State(state), Path(doc.to_string()))
+            .await
+            .into_response(),
+        (doc, Some("related")) => related::get_related(State(state), Path(doc.to_string()))
+            .await
+            .into_response(),
+        (doc, Some("occurrences")) => occurrences::get_occurrences(
+            State(state),
+            Path(doc.to_string()),
+            Query(occurrences::OccurrencesQuery { q: query.q.unwrap_or_default() }),
+        )
+        .await
+        .into_response(),
+        _ => get_document(state, path, query, headers).await.into_response(),
+    }
+}
+
+async fn get_document(
+    state: Arc<AppState>,
+    path: String,
+    query: GetFileQuery,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
     let index = state.index.read().await;
+    let mut doc = index.get_document_with_content(&path).await.ok_or(StatusCode::NOT_FOUND)?;
+    doc.revision = conditional::file_revision(&state.roots.resolve(&path)).await;
+    // Raw reads are the editor loading a document already being viewed
+    if !query.raw {
+        recent::record_view(&state, query.client.as_deref(), &doc.path);
+    }
 
-    if let Some(doc) = index.get_document_with_content(&path).await {
-        Ok(Json(serde_json::to_value(doc).unwrap()))
+    // The body also reflects backlinking documents, so it's only as old as
+    // the newest of them. Bodies that depend on a crypt session or on
+    // included files can't be dated and rely on the ETag alone.
+    let mut last_modified = std::iter::once(&doc.path)
+        .chain(doc.backlinks.iter())
+        .filter_map(|p| index.get_mtime_secs(p))
+        .max();
+    // gpg may take a while, and nothing may write the index meanwhile
+    drop(index);
+    let vary = if doc.encrypted.is_empty() {
+        None
     } else {
-        Err(StatusCode::NOT_FOUND)
+        last_modified = None;
+        Some(CRYPT_SESSION_HEADER)
+    };
+
+    // Decrypt :crypt: subtrees when the client has an unlocked crypt session
+    if !doc.encrypted.is_empty() {
+        if let (Some(passphrase), Some(content)) =
+            (session_passphrase(&state, &headers).await, doc.content.as_ref())
+        {
+            match decrypt_content(&state.roots.resolve(&path), content, &passphrase).await {
+                Ok(plain) => {
+                    doc.content = Some(plain);
+                    doc.encrypted.clear();
+                }
+                Err(e) => log_to_file(&format!("[crypt] Decryption failed for {}: {}", path, e)),
+            }
+        }
+    }
+
+    if !query.raw {
+        if let Some(c) = doc.content.take() {
+            let index = state.index.read().await;
+            let (rendered, included) = render_content(&state, &index, &path, &c).await;
+            if included {
+                last_modified = None;
+            }
+            doc.content = Some(rendered);
+        }
+    }
+    // Slice after expansion so file-level macros and includes still apply
+    if let Some(anchor) = &query.anchor {
+        let content = doc.content.as_deref().ok_or(StatusCode::NOT_FOUND)?;
+        doc.content = Some(subtree_by_custom_id(&state.roots.resolve(&path), content, anchor).ok_or(StatusCode::NOT_FOUND)?);
+    }
+    if doc.content.as_ref().is_some_and(|c| c.len() > streaming::STREAM_THRESHOLD) {
+        let content = doc.content.take().unwrap_or_default();
+        let value = serde_json::to_value(doc).unwrap();
+        return Ok(streaming::json_with_content(value, content));
     }
+    let revision = doc.revision.clone();
+    let value = serde_json::to_value(doc).unwrap();
+    Ok(conditional::json_response(&headers, &value, last_modified, vary, revision.as_deref()))
+}
+
+/// A document's content as served for viewing: includes resolved, macros
+/// expanded, and ID and image links rewritten to paths the client can
+/// follow. Also returns whether any includes were resolved, since the
+/// result then depends on more than the document's own file.
+pub async fn render_content(
+    state: &AppState,
+    index: &DocumentIndex,
+    path: &str,
+    content: &str,
+) -> (String, bool) {
+    let (root, relative) = state.roots.split(path);
+    let included = resolve_includes(root, relative, content).await;
+    let has_includes = included != content;
+    let expanded = expand_macros(&included);
+    let linked = rewrite_id_links(&expanded, |id| index.resolve_id(id));
+    (rewrite_image_links(path, &linked), has_includes)
 }
 
 #[derive(Deserialize)]
@@ -191,27 +413,47 @@ pub struct UpdateFileRequest {
     content: String,
 }
 
+/// PUT /api/files/*path - Replace a document. `If-Match` must name the
+/// revision the edit started from; returns the new one.
 pub async fn put_file(
     State(state): State<Arc<AppState>>,
     Path(path): Path<String>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateFileRequest>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<Json<conditional::Revision>, StatusCode> {
     log_to_file(&format!("[server] PUT /api/files/{}", path));
 
     // Validate path - prevent directory traversal
-    let full_path = state.org_root.join(&path);
-    let canonical_root = state.org_root.canonicalize()
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let full_path = state.roots.resolve(&path);
     let canonical_path = full_path.canonicalize()
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
-    if !canonical_path.starts_with(&canonical_root) {
+    if !state.roots.contains(&path, &canonical_path) {
         log_to_file(&format!("[server] PUT rejected - path traversal attempt: {}", path));
         return Err(StatusCode::FORBIDDEN);
     }
+    conditional::check_if_match(&headers, &canonical_path).await.inspect_err(|status| {
+        log_to_file(&format!("[server] PUT rejected - {} for If-Match: {}", status, path));
+    })?;
+
+    // Re-encrypt :crypt: subtrees; refuse to write them out in plain text
+    let mut content = payload.content;
+    if has_plaintext_crypt(&full_path, &content) {
+        let passphrase = match session_passphrase(&state, &headers).await {
+            Some(p) => p,
+            None => {
+                log_to_file(&format!("[server] PUT rejected - plaintext crypt subtree without session: {}", path));
+                return Err(StatusCode::PRECONDITION_REQUIRED);
+            }
+        };
+        content = encrypt_content(&full_path, &content, &passphrase).await.map_err(|e| {
+            log_to_file(&format!("[crypt] Encryption failed for {}: {}", path, e));
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
 
     // Reconstruct file with frontmatter
-    let file_content = serialize_document(&payload.frontmatter, &payload.content);
+    let file_content = serialize_document(&full_path, &payload.frontmatter, &content);
 
     // Write to filesystem
     if let Err(e) = std::fs::write(&full_path, &file_content) {
@@ -220,41 +462,253 @@ pub async fn put_file(
     }
 
     log_to_file(&format!("[server] PUT success: {}", path));
+    drafts::discard(&state, &path).await;
     // File watcher will auto-refresh index
-    Ok(StatusCode::OK)
+    Ok(Json(conditional::Revision::of(file_content.as_bytes())))
+}
+
+/// Sub-resources addressed as `/api/files/{*path}/<action>`. The wildcard has
+/// to be the last route segment, so these are split off the path by hand.
+const GET_FILE_ACTIONS: &[&str] = &["backlinks", "outline", "related", "occurrences", "meta"];
+const POST_FILE_ACTIONS: &[&str] = &["table", "list", "update-dblocks", "move", "attachments", "merge"];
+
+/// Split `notes/a.md/table` into (`notes/a.md`, Some("table")) for known actions
+fn split_file_action<'a>(path: &'a str, actions: &[&str]) -> (&'a str, Option<&'a str>) {
+    if let Some((doc, action)) = path.rsplit_once('/') {
+        if actions.contains(&action) {
+            return (doc, Some(action));
+        }
+    }
+    (path, None)
+}
+
+/// POST /api/files/*path/<action> - Dispatch document sub-resource actions;
+/// without an action, create the document
+pub async fn post_file(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    req: Request,
+) -> Response {
+    match split_file_action(&path, POST_FILE_ACTIONS) {
+        (doc, Some("table")) => match Json::from_request(req, &()).await {
+            Ok(payload) => tables::update_table(State(state), Path(doc.to_string()), payload)
+                .await
+                .into_response(),
+            Err(rejection) => rejection.into_response(),
+        },
+        (doc, Some("list")) => match Json::from_request(req, &()).await {
+            Ok(payload) => lists::update_list(State(state), Path(doc.to_string()), payload)
+                .await
+                .into_response(),
+            Err(rejection) => rejection.into_response(),
+        },
+        (doc, Some("update-dblocks")) => dblocks::update_dblocks(State(state), Path(doc.to_string()))
+            .await
+            .into_response(),
+        (doc, Some("move")) => match Json::from_request(req, &()).await {
+            Ok(payload) => rename::move_document(State(state), Path(doc.to_string()), payload)
+                .await
+                .into_response(),
+            Err(rejection) => rejection.into_response(),
+        },
+        (doc, Some("merge")) => match Json::from_request(req, &()).await {
+            Ok(payload) => merge::merge_document(State(state), Path(doc.to_string()), payload)
+                .await
+                .into_response(),
+            Err(rejection) => rejection.into_response(),
+        },
+        (doc, Some("attachments")) => match Multipart::from_request(req, &()).await {
+            Ok(multipart) => attachments::upload_attachments(State(state), Path(doc.to_string()), multipart)
+                .await
+                .into_response(),
+            Err(rejection) => rejection.into_response(),
+        },
+        (doc, None) => {
+            // The body is optional: an empty file needs nothing more
+            let body = match axum::body::Bytes::from_request(req, &()).await {
+                Ok(b) => b,
+                Err(rejection) => return rejection.into_response(),
+            };
+            let payload = if body.is_empty() {
+                create::CreateFileRequest::default()
+            } else {
+                match serde_json::from_slice(&body) {
+                    Ok(p) => p,
+                    Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+                }
+            };
+            create::create_file(&state, doc, payload).await.into_response()
+        }
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
 }
 
 #[derive(Deserialize)]
 pub struct SearchQuery {
+    #[serde(default)]
     q: String,
+    /// Frontmatter tag, `#+FILETAGS` entry or heading tag, case-insensitive
+    tag: Option<String>,
+    /// Only documents with a heading in this TODO state, e.g. `TODO`
+    todo: Option<String>,
+    /// Modified on or after this day, `YYYY-MM-DD`
+    after: Option<String>,
+    /// Modified before this day, `YYYY-MM-DD`
+    before: Option<String>,
+    /// Only documents under this directory
+    dir: Option<String>,
+    /// IANA timezone the `after`/`before` days are in; defaults to the configured one
+    tz: Option<String>,
+    /// `notes` (default) or `projects` to also search code under `projects/`
+    scope: Option<String>,
+    /// Page size; defaults to 50
+    limit: Option<usize>,
+    /// Hits to skip before the page
+    #[serde(default)]
+    offset: usize,
+    /// Client id the query is recorded under in search history; shared when absent
+    client: Option<String>,
 }
 
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_SEARCH_LIMIT: usize = 500;
+
+/// Code matches returned alongside notes for `scope=projects`
+const MAX_CODE_MATCHES: usize = 100;
+
 #[derive(Serialize)]
 pub struct SearchResponse {
     query: String,
+    /// Hits in this page
     count: usize,
+    /// Hits across all pages
     total: usize,
+    offset: usize,
+    limit: usize,
+    /// Offset of the next page, absent on the last one
+    #[serde(rename = "nextOffset", skip_serializing_if = "Option::is_none")]
+    next_offset: Option<usize>,
     items: Vec<serde_json::Value>,
+    /// Matching lines in project files, present on the first page for `scope=projects`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<Vec<projects::CodeMatch>>,
 }
 
+/// GET /api/search?q=&tag=&todo=&after=&before=&dir=&scope=&limit=&offset= - Ranked
+/// full-text search, narrowed by indexed metadata and paged. Filters alone (empty
+/// `q`) list matching documents by modification time.
 pub async fn search(
     State(state): State<Arc<AppState>>,
     Query(query): Query<SearchQuery>,
-) -> Json<SearchResponse> {
+) -> Result<Json<SearchResponse>, StatusCode> {
+    let parse_day = |day: &Option<String>| -> Result<Option<chrono::NaiveDate>, StatusCode> {
+        day.as_deref()
+            .map(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| StatusCode::BAD_REQUEST))
+            .transpose()
+    };
+    let after = parse_day(&query.after)?;
+    let before = parse_day(&query.before)?;
+    let dir = query.dir.as_deref().map(|d| d.trim_matches('/')).filter(|d| !d.is_empty());
+    let include_code = match query.scope.as_deref() {
+        None | Some("notes") => false,
+        Some("projects") => true,
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    // Project files aren't in the document index; scan them off the async runtime.
+    // Code matches aren't paged, so they come with the first page only.
+    let code = if include_code && query.offset == 0 {
+        let org_root = state.org_root.clone();
+        let (q, dir) = (query.q.clone(), dir.map(|d| d.to_string()));
+        let matches = tokio::task::spawn_blocking(move || {
+            projects::search_project_files(&org_root, &q, dir.as_deref(), MAX_CODE_MATCHES)
+        })
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        Some(matches)
+    } else {
+        None
+    };
+
     let index = state.index.read().await;
-    let results = index.search(&query.q);
 
+    // Modification days in the user's timezone, computed up front since the
+    // conversion can fail on a bad `tz`
+    let mut modified: HashMap<&str, chrono::NaiveDate> = HashMap::new();
+    if after.is_some() || before.is_some() {
+        for doc in index.get_documents() {
+            let secs = index.get_mtime_secs(&doc.path).unwrap_or(0) as i64;
+            let instant = chrono::DateTime::from_timestamp(secs, 0).unwrap_or_default();
+            let day = timezone::user_time(&state.config, query.tz.as_deref(), instant)?.date();
+            modified.insert(doc.path.as_str(), day);
+        }
+    }
+
+    let results = index.search_filtered(&query.q, |d| {
+        if let Some(dir) = dir {
+            if !d.path.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/')) {
+                return false;
+            }
+        }
+        let headings = index.get_headings(&d.path);
+        if let Some(tag) = &query.tag {
+            let tag = tag.trim_start_matches(':');
+            let tagged = d.tags.iter().chain(d.file_tags.iter()).any(|t| t.eq_ignore_ascii_case(tag))
+                || headings.iter().any(|h| h.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+            if !tagged {
+                return false;
+            }
+        }
+        if let Some(todo) = &query.todo {
+            if !headings.iter().any(|h| h.todo.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(todo))) {
+                return false;
+            }
+        }
+        let day = modified.get(d.path.as_str());
+        if after.is_some_and(|a| day.is_none_or(|day| *day < a)) {
+            return false;
+        }
+        if before.is_some_and(|b| day.is_none_or(|day| *day >= b)) {
+            return false;
+        }
+        true
+    });
+
+    // Paging through results isn't a new search
+    if query.offset == 0 {
+        if let Err(status) = search_history::record(&state, query.client.as_deref(), &query.q) {
+            log_to_file(&format!("[search] Could not record history: {}", status));
+        }
+    }
+
+    let total = results.len();
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
     let items: Vec<serde_json::Value> = results
         .into_iter()
+        .skip(query.offset)
+        .take(limit)
         .map(|d| serde_json::to_value(d).unwrap())
         .collect();
+    let next_offset = Some(query.offset + items.len()).filter(|&next| next < total);
 
-    Json(SearchResponse {
+    // Project READMEs are indexed as notes already
+    let code = code.map(|matches| {
+        matches
+            .into_iter()
+            .filter(|m| index.get_document(&m.path).is_none())
+            .collect()
+    });
+
+    Ok(Json(SearchResponse {
         query: query.q,
         count: items.len(),
-        total: items.len(),
+        total,
+        offset: query.offset,
+        limit,
+        next_offset,
         items,
-    })
+        code,
+    }))
 }
 
 #[derive(Serialize)]
@@ -0,0 +1,96 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::server::{log_to_file, AppState};
+
+/// Saved searches live next to `.org-viewer-config.json` in the org root, so
+/// every client connected to this server sees the same list
+const SEARCHES_FILENAME: &str = ".org-viewer-searches.json";
+
+fn default_kind() -> String {
+    "search".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    /// `search` runs against /api/search, `query` against /api/query
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    /// The `q` parameter
+    #[serde(default)]
+    pub q: String,
+    /// Other parameters for the endpoint, e.g. `tag`, `todo`, `dir`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, String>,
+    /// Shown in the client sidebar
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+fn load_searches(state: &AppState) -> BTreeMap<String, SavedSearch> {
+    let path = state.org_root.join(SEARCHES_FILENAME);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_to_file(&format!("[searches] Invalid saved searches file {:?}: {}", path, e));
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn save_searches(state: &AppState, searches: &BTreeMap<String, SavedSearch>) -> Result<(), StatusCode> {
+    let json = serde_json::to_string_pretty(searches).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    std::fs::write(state.org_root.join(SEARCHES_FILENAME), json).map_err(|e| {
+        log_to_file(&format!("[searches] Failed to save searches: {}", e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// GET /api/searches - List saved searches by name
+pub async fn list_searches(State(state): State<Arc<AppState>>) -> Json<BTreeMap<String, SavedSearch>> {
+    Json(load_searches(&state))
+}
+
+/// GET /api/searches/{name} - Fetch one saved search
+pub async fn get_search(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<SavedSearch>, StatusCode> {
+    load_searches(&state).remove(&name).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// PUT /api/searches/{name} - Create or replace a saved search
+pub async fn put_search(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Json(search): Json<SavedSearch>,
+) -> Result<StatusCode, StatusCode> {
+    if search.kind != "search" && search.kind != "query" {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mut searches = load_searches(&state);
+    let created = searches.insert(name.clone(), search).is_none();
+    save_searches(&state, &searches)?;
+    log_to_file(&format!("[searches] Saved search {}", name));
+    Ok(if created { StatusCode::CREATED } else { StatusCode::OK })
+}
+
+/// DELETE /api/searches/{name} - Remove a saved search
+pub async fn delete_search(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let mut searches = load_searches(&state);
+    if searches.remove(&name).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    save_searches(&state, &searches)?;
+    log_to_file(&format!("[searches] Deleted search {}", name));
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,289 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+use gray_matter::{engine::YAML, Matter};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{AllQuery, QueryParser};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, STORED, STRING,
+};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+use crate::server::document::OrgDocument;
+use crate::server::events::EventKind;
+use crate::server::org::Heading;
+use crate::server::{log_to_file, AppState};
+
+const SEARCH_DIRNAME: &str = ".org-viewer-search";
+const VERSION_FILENAME: &str = "org-viewer-version";
+
+/// Bumped whenever the schema or tokenization changes; older indexes are rebuilt
+const SEARCH_VERSION: u32 = 2;
+
+const WRITER_MEMORY: usize = 20_000_000;
+
+/// Documents re-indexed per index lock during a rebuild, so searches and the
+/// watcher aren't blocked for the whole run
+const REINDEX_BATCH: usize = 100;
+
+#[derive(Clone, Copy)]
+struct Fields {
+    path: Field,
+    title: Field,
+    tags: Field,
+    headings: Field,
+    body: Field,
+    mtime: Field,
+}
+
+/// Full-text index over document titles, tags and bodies, kept in step with
+/// `DocumentIndex` and persisted under `.org-viewer-search/`
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: IndexWriter,
+    fields: Fields,
+}
+
+/// Whether a query uses operators, phrases or field prefixes rather than plain words
+pub fn uses_query_syntax(query: &str) -> bool {
+    query.contains('"')
+        || query.split_whitespace().any(|word| {
+            matches!(word, "AND" | "OR" | "NOT") || word.starts_with('-') || word.starts_with('+') || word.contains(':')
+        })
+}
+
+fn build_schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    // Stemmed English text, so "running" finds "run"
+    let stemmed = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer("en_stem")
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+    // Field names double as query prefixes, e.g. `tag:work` or `heading:"weekly review"`
+    let fields = Fields {
+        path: builder.add_text_field("path", STRING | STORED),
+        title: builder.add_text_field("title", stemmed.clone()),
+        tags: builder.add_text_field("tag", stemmed.clone()),
+        headings: builder.add_text_field("heading", stemmed.clone()),
+        body: builder.add_text_field("body", stemmed),
+        mtime: builder.add_u64_field("mtime", STORED),
+    };
+    (builder.build(), fields)
+}
+
+impl SearchIndex {
+    /// Open the on-disk index, recreating it when the version changed. Falls back
+    /// to an in-memory index if the directory can't be used (e.g. locked by
+    /// another instance).
+    pub fn open(org_root: &Path) -> Self {
+        let dir = org_root.join(SEARCH_DIRNAME);
+        match Self::open_dir(&dir) {
+            Ok(search) => search,
+            Err(e) => {
+                log_to_file(&format!("[search] Using in-memory index: {}", e));
+                let (schema, fields) = build_schema();
+                Self::from_index(Index::create_in_ram(schema), fields).expect("in-memory search index")
+            }
+        }
+    }
+
+    fn open_dir(dir: &PathBuf) -> tantivy::Result<Self> {
+        let version_path = dir.join(VERSION_FILENAME);
+        let current = std::fs::read_to_string(&version_path)
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok());
+        if current != Some(SEARCH_VERSION) && dir.exists() {
+            log_to_file(&format!("[search] Rebuilding index with version {}", SEARCH_VERSION));
+            let _ = std::fs::remove_dir_all(dir);
+        }
+        std::fs::create_dir_all(dir)?;
+
+        let (schema, fields) = build_schema();
+        let directory = MmapDirectory::open(dir)?;
+        let index = Index::open_or_create(directory, schema)?;
+        let search = Self::from_index(index, fields)?;
+        std::fs::write(&version_path, SEARCH_VERSION.to_string())?;
+        Ok(search)
+    }
+
+    fn from_index(index: Index, fields: Fields) -> tantivy::Result<Self> {
+        let reader = index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into()?;
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        Ok(Self {
+            index,
+            reader,
+            writer,
+            fields,
+        })
+    }
+
+    /// Stage a document for indexing, replacing any previous version. Call
+    /// `commit` to make staged changes searchable.
+    pub fn stage(&mut self, doc: &OrgDocument, headings: &[Heading], content: &str, mtime: u64) {
+        let f = self.fields;
+        let body = Matter::<YAML>::new().parse(content).content;
+        let tags = doc
+            .tags
+            .iter()
+            .chain(doc.file_tags.iter())
+            .chain(headings.iter().flat_map(|h| h.tags.iter()))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let heading_titles = headings.iter().map(|h| h.title.as_str()).collect::<Vec<_>>().join("\n");
+
+        self.writer.delete_term(Term::from_field_text(f.path, &doc.path));
+        if let Err(e) = self.writer.add_document(doc!(
+            f.path => doc.path.clone(),
+            f.title => doc.title.clone(),
+            f.tags => tags,
+            f.headings => heading_titles,
+            f.body => body,
+            f.mtime => mtime,
+        )) {
+            log_to_file(&format!("[search] Failed to index {}: {}", doc.path, e));
+        }
+    }
+
+    pub fn stage_removal(&mut self, path: &str) {
+        self.writer.delete_term(Term::from_field_text(self.fields.path, path));
+    }
+
+    pub fn stage_clear(&mut self) {
+        if let Err(e) = self.writer.delete_all_documents() {
+            log_to_file(&format!("[search] Failed to clear index: {}", e));
+        }
+    }
+
+    pub fn commit(&mut self) {
+        if let Err(e) = self.writer.commit() {
+            log_to_file(&format!("[search] Commit failed: {}", e));
+            return;
+        }
+        if let Err(e) = self.reader.reload() {
+            log_to_file(&format!("[search] Reader reload failed: {}", e));
+        }
+    }
+
+    /// Path -> indexed mtime for every document in the index
+    pub fn indexed_mtimes(&self) -> HashMap<String, u64> {
+        let searcher = self.reader.searcher();
+        let limit = (searcher.num_docs() as usize).max(1);
+        let hits = match searcher.search(&AllQuery, &TopDocs::with_limit(limit)) {
+            Ok(h) => h,
+            Err(_) => return HashMap::new(),
+        };
+
+        hits.into_iter()
+            .filter_map(|(_, address)| {
+                let doc: TantivyDocument = searcher.doc(address).ok()?;
+                let path = doc.get_first(self.fields.path)?.as_str()?.to_string();
+                let mtime = doc.get_first(self.fields.mtime)?.as_u64()?;
+                Some((path, mtime))
+            })
+            .collect()
+    }
+
+    /// Ranked paths for a query, best first. Titles weigh most, then tags and headings.
+    ///
+    /// Terms must all match unless joined with `OR`; `NOT`/`-` excludes, quotes
+    /// match phrases, and `title:`, `tag:`, `heading:` or `body:` restrict a term
+    /// to one field.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(String, f32)> {
+        let f = self.fields;
+        let mut parser = QueryParser::for_index(&self.index, vec![f.title, f.tags, f.headings, f.body]);
+        parser.set_conjunction_by_default();
+        parser.set_field_boost(f.title, 3.0);
+        parser.set_field_boost(f.tags, 2.0);
+        parser.set_field_boost(f.headings, 2.0);
+        // The parser only knows `-` for exclusion
+        let query = Regex::new(r"\bNOT\s+").unwrap().replace_all(query, "-");
+        // Lenient parsing: stray quotes or colons in user input shouldn't fail the search
+        let (parsed, _errors) = parser.parse_query_lenient(&query);
+
+        let searcher = self.reader.searcher();
+        let hits = match searcher.search(&parsed, &TopDocs::with_limit(limit)) {
+            Ok(h) => h,
+            Err(e) => {
+                log_to_file(&format!("[search] Query failed: {}", e));
+                return Vec::new();
+            }
+        };
+
+        hits.into_iter()
+            .filter_map(|(score, address)| {
+                let doc: TantivyDocument = searcher.doc(address).ok()?;
+                let path = doc.get_first(f.path)?.as_str()?.to_string();
+                Some((path, score))
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct ReindexResponse {
+    documents: usize,
+}
+
+fn broadcast_progress(state: &AppState, phase: &str, done: usize, total: usize) {
+    let payload = serde_json::json!({
+        "task": "reindex",
+        "phase": phase,
+        "done": done,
+        "total": total
+    });
+    state.events.send(EventKind::IndexProgress, None, payload);
+}
+
+/// POST /api/search/reindex - Rebuild the full-text index from the files on disk
+/// in the background. Progress goes out over the WebSocket as `reindex` messages
+/// (`started`, `progress`, `done`); 409 while a rebuild is already running.
+pub async fn reindex(State(state): State<Arc<AppState>>) -> Result<(StatusCode, Json<ReindexResponse>), StatusCode> {
+    if state.reindexing.swap(true, Ordering::SeqCst) {
+        return Err(StatusCode::CONFLICT);
+    }
+    let paths: Vec<String> = state.index.read().await.get_documents().iter().map(|d| d.path.clone()).collect();
+    let documents = paths.len();
+    tokio::spawn(run_reindex(state.clone(), paths));
+    Ok((StatusCode::ACCEPTED, Json(ReindexResponse { documents })))
+}
+
+async fn run_reindex(state: Arc<AppState>, paths: Vec<String>) {
+    let started = std::time::Instant::now();
+    let total = paths.len();
+    log_to_file(&format!("[search] Rebuilding text index for {} documents", total));
+    broadcast_progress(&state, "started", 0, total);
+
+    let mut done = 0;
+    for batch in paths.chunks(REINDEX_BATCH) {
+        // Read outside the lock; each document replaces its previous entry, so
+        // searches keep working on the old entries until their batch commits
+        let mut contents = Vec::new();
+        for path in batch {
+            if let Ok(content) = tokio::fs::read_to_string(state.roots.resolve(path)).await {
+                contents.push((path, content));
+            }
+        }
+        {
+            let mut index = state.index.write().await;
+            for (path, content) in &contents {
+                index.restage_search(path, content);
+            }
+            index.commit_search();
+        }
+        done += batch.len();
+        broadcast_progress(&state, "progress", done, total);
+    }
+
+    state.index.write().await.finish_search_rebuild();
+    state.reindexing.store(false, Ordering::SeqCst);
+    broadcast_progress(&state, "done", total, total);
+    log_to_file(&format!("[search] Text index rebuilt in {:?}", started.elapsed()));
+}
@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::server::{log_to_file, AppState};
+
+/// History lives next to `.org-viewer-config.json` in the org root
+const HISTORY_FILENAME: &str = ".org-viewer-search-history.json";
+
+/// Entries kept per client
+const MAX_HISTORY: usize = 50;
+
+/// Key for history recorded without a client id
+const GLOBAL_CLIENT: &str = "";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    query: String,
+    /// RFC 3339 time of the most recent use
+    at: String,
+}
+
+/// Most recent first, keyed by client id
+type History = BTreeMap<String, Vec<HistoryEntry>>;
+
+fn load_history(state: &AppState) -> History {
+    let path = state.org_root.join(HISTORY_FILENAME);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log_to_file(&format!("[history] Invalid search history file {:?}: {}", path, e));
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn save_history(state: &AppState, history: &History) -> Result<(), StatusCode> {
+    let json = serde_json::to_string_pretty(history).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    std::fs::write(state.org_root.join(HISTORY_FILENAME), json).map_err(|e| {
+        log_to_file(&format!("[history] Failed to save search history: {}", e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Move `query` to the front of a client's history
+pub fn record(state: &AppState, client: Option<&str>, query: &str) -> Result<(), StatusCode> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(());
+    }
+    let mut history = load_history(state);
+    let entries = history.entry(client.unwrap_or(GLOBAL_CLIENT).to_string()).or_default();
+    entries.retain(|e| e.query != query);
+    entries.insert(
+        0,
+        HistoryEntry {
+            query: query.to_string(),
+            at: chrono::Utc::now().to_rfc3339(),
+        },
+    );
+    entries.truncate(MAX_HISTORY);
+    save_history(state, &history)
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// Client id; history without one is shared by every device
+    client: Option<String>,
+    /// For DELETE: remove only this query instead of clearing the history
+    q: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    count: usize,
+    items: Vec<HistoryEntry>,
+}
+
+/// GET /api/search/history?client= - Recent searches, most recent first
+pub async fn get_history(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HistoryQuery>,
+) -> Json<HistoryResponse> {
+    let items = load_history(&state)
+        .remove(query.client.as_deref().unwrap_or(GLOBAL_CLIENT))
+        .unwrap_or_default();
+    Json(HistoryResponse {
+        count: items.len(),
+        items,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct RecordRequest {
+    query: String,
+}
+
+/// POST /api/search/history?client= - Record a search made elsewhere, e.g. a
+/// quick-switcher pick
+pub async fn post_history(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HistoryQuery>,
+    Json(payload): Json<RecordRequest>,
+) -> Result<StatusCode, StatusCode> {
+    if payload.query.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    record(&state, query.client.as_deref(), &payload.query)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/search/history?client=&q= - Forget one query, or the whole history
+pub async fn delete_history(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<StatusCode, StatusCode> {
+    let mut history = load_history(&state);
+    let client = query.client.as_deref().unwrap_or(GLOBAL_CLIENT);
+    match &query.q {
+        Some(q) => {
+            let entries = history.get_mut(client).ok_or(StatusCode::NOT_FOUND)?;
+            let before = entries.len();
+            entries.retain(|e| e.query != q.trim());
+            if entries.len() == before {
+                return Err(StatusCode::NOT_FOUND);
+            }
+        }
+        None => {
+            history.remove(client);
+        }
+    }
+    save_history(&state, &history)?;
+    log_to_file(&format!("[history] Removed search history for client {:?}", client));
+    Ok(StatusCode::NO_CONTENT)
+}
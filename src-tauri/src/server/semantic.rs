@@ -0,0 +1,414 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::server::config::EmbeddingsConfig;
+use crate::server::index::stable_hash;
+use crate::server::org::Heading;
+use crate::server::{log_to_file, AppState};
+
+const EMBEDDINGS_FILENAME: &str = ".org-viewer-embeddings.json";
+
+/// Bumped whenever chunking or the file format changes; older files are discarded
+const EMBEDDINGS_VERSION: u32 = 2;
+
+/// Texts per request to the embedding service
+const BATCH_SIZE: usize = 32;
+
+/// Longer sections are cut; most embedding models have a small context window
+const MAX_CHUNK_CHARS: usize = 2000;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+
+/// Embedding of one heading's own section (or a document's preamble)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeadingEmbedding {
+    /// 1-based line of the heading; 1 for the text before the first heading
+    pub line: usize,
+    pub title: String,
+    /// Hash of the embedded text, so unchanged sections aren't re-embedded
+    hash: u64,
+    /// Unit length, so cosine similarity is a dot product
+    pub vector: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedEmbeddings {
+    version: u32,
+    /// Vectors from different models aren't comparable
+    model: String,
+    entries: HashMap<String, Vec<HeadingEmbedding>>,
+}
+
+/// Per-heading embeddings for semantic search, kept in `.org-viewer-embeddings.json`
+pub struct SemanticIndex {
+    path: PathBuf,
+    entries: RwLock<HashMap<String, Vec<HeadingEmbedding>>>,
+    /// Held for a whole refresh so overlapping refreshes don't embed the same text twice
+    refreshing: Mutex<()>,
+}
+
+/// Title and headings of an indexed document
+type Outline = (String, Vec<Heading>);
+
+struct Chunk {
+    line: usize,
+    title: String,
+    text: String,
+    hash: u64,
+}
+
+impl SemanticIndex {
+    pub fn load(org_root: &Path, config: Option<&EmbeddingsConfig>) -> Self {
+        let path = org_root.join(EMBEDDINGS_FILENAME);
+        let entries = match (config, std::fs::read_to_string(&path)) {
+            (Some(config), Ok(content)) => match serde_json::from_str::<PersistedEmbeddings>(&content) {
+                Ok(p) if p.version == EMBEDDINGS_VERSION && p.model == config.model => p.entries,
+                Ok(_) => {
+                    log_to_file("[semantic] Embedding model or format changed, re-embedding");
+                    HashMap::new()
+                }
+                Err(e) => {
+                    log_to_file(&format!("[semantic] Invalid embeddings file: {}", e));
+                    HashMap::new()
+                }
+            },
+            _ => HashMap::new(),
+        };
+
+        Self {
+            path,
+            entries: RwLock::new(entries),
+            refreshing: Mutex::new(()),
+        }
+    }
+
+    /// Each embedded document's mean heading vector, normalized
+    pub async fn document_vectors(&self) -> HashMap<String, Vec<f32>> {
+        self.entries
+            .read()
+            .await
+            .iter()
+            .filter_map(|(path, headings)| {
+                let dims = headings.first()?.vector.len();
+                let mut mean = vec![0.0; dims];
+                for h in headings.iter().filter(|h| h.vector.len() == dims) {
+                    for (m, v) in mean.iter_mut().zip(&h.vector) {
+                        *m += v;
+                    }
+                }
+                normalize(&mut mean);
+                Some((path.clone(), mean))
+            })
+            .collect()
+    }
+
+    async fn save(&self, model: &str) {
+        let entries = self.entries.read().await;
+        let persisted = PersistedEmbeddings {
+            version: EMBEDDINGS_VERSION,
+            model: model.to_string(),
+            entries: entries.clone(),
+        };
+        match serde_json::to_string(&persisted) {
+            Ok(json) => {
+                if let Err(e) = tokio::fs::write(&self.path, json).await {
+                    log_to_file(&format!("[semantic] Failed to save embeddings: {}", e));
+                }
+            }
+            Err(e) => log_to_file(&format!("[semantic] Failed to serialize embeddings: {}", e)),
+        }
+    }
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Split a document into the preamble and each heading's own section
+fn chunk_document(content: &str, headings: &[Heading], doc_title: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut chunks = Vec::new();
+    let mut push = |line: usize, title: &str, text: String| {
+        let text: String = text.trim().chars().take(MAX_CHUNK_CHARS).collect();
+        if text.is_empty() {
+            return;
+        }
+        chunks.push(Chunk {
+            line,
+            title: title.to_string(),
+            hash: stable_hash(text.as_bytes()),
+            text,
+        });
+    };
+
+    let preamble_end = headings.first().map(|h| h.line - 1).unwrap_or(lines.len());
+    let preamble = lines[..preamble_end.min(lines.len())].join("\n");
+    // Frontmatter is metadata, not prose
+    let preamble = gray_matter::Matter::<gray_matter::engine::YAML>::new().parse(&preamble).content;
+    if !preamble.trim().is_empty() {
+        push(1, doc_title, format!("{}\n{}", doc_title, preamble));
+    }
+
+    for heading in headings {
+        let start = heading.line.min(lines.len());
+        let end = heading.section_end.min(lines.len()).max(start);
+        let body = lines[start..end].join("\n");
+        push(heading.line, &heading.title, format!("{}\n{}", heading.title, body));
+    }
+    chunks
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+async fn embed(client: &reqwest::Client, config: &EmbeddingsConfig, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let mut request = client
+        .post(&config.url)
+        .json(&serde_json::json!({ "model": config.model, "input": texts }));
+    if let Some(key) = &config.api_key {
+        request = request.bearer_auth(key);
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("embedding service returned {}", response.status()));
+    }
+    let mut body: EmbeddingResponse = response.json().await.map_err(|e| e.to_string())?;
+    if body.data.len() != texts.len() {
+        return Err(format!("expected {} embeddings, got {}", texts.len(), body.data.len()));
+    }
+    body.data.sort_by_key(|d| d.index);
+    Ok(body
+        .data
+        .into_iter()
+        .map(|d| {
+            let mut vector = d.embedding;
+            normalize(&mut vector);
+            vector
+        })
+        .collect())
+}
+
+/// Carry renamed documents' vectors over to their new paths, as (from, to)
+pub async fn rename(state: Arc<AppState>, renamed: Vec<(String, String)>) {
+    let config = match &state.config.embeddings {
+        Some(c) => c.clone(),
+        None => return,
+    };
+    let _guard = state.semantic.refreshing.lock().await;
+    let mut moved = false;
+    {
+        let mut entries = state.semantic.entries.write().await;
+        for (from, to) in renamed {
+            if let Some(headings) = entries.remove(&from) {
+                entries.insert(to, headings);
+                moved = true;
+            }
+        }
+    }
+    if moved {
+        state.semantic.save(&config.model).await;
+    }
+}
+
+/// Bring embeddings up to date for `paths`, or for the whole index when `None`
+/// (which also drops documents that no longer exist). Only sections whose text
+/// changed are sent to the embedding service. A no-op when semantic search is off.
+pub async fn refresh(state: Arc<AppState>, paths: Option<Vec<String>>) {
+    let config = match &state.config.embeddings {
+        Some(c) => c.clone(),
+        None => return,
+    };
+    let _guard = state.semantic.refreshing.lock().await;
+
+    // Snapshot what to embed without holding the index lock across requests
+    let mut documents: Vec<(String, Option<Outline>)> = Vec::new();
+    {
+        let index = state.index.read().await;
+        let targets = match paths {
+            Some(p) => p,
+            None => {
+                let known: HashSet<String> = index.get_documents().iter().map(|d| d.path.clone()).collect();
+                state.semantic.entries.write().await.retain(|path, _| known.contains(path));
+                known.into_iter().collect()
+            }
+        };
+        for path in targets {
+            let doc = index
+                .get_document(&path)
+                .map(|d| (d.title.clone(), index.get_headings(&path).to_vec()));
+            documents.push((path, doc));
+        }
+    }
+
+    let mut updates: Vec<(String, Vec<Chunk>)> = Vec::new();
+    let mut removed = false;
+    for (path, doc) in documents {
+        let (title, headings) = match doc {
+            Some(d) => d,
+            None => {
+                removed |= state.semantic.entries.write().await.remove(&path).is_some();
+                continue;
+            }
+        };
+        let content = match tokio::fs::read_to_string(state.roots.resolve(&path)).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        updates.push((path, chunk_document(&content, &headings, &title)));
+    }
+
+    // Reuse vectors for unchanged text; collect the rest for embedding
+    let mut vectors: HashMap<u64, Vec<f32>> = HashMap::new();
+    {
+        let entries = state.semantic.entries.read().await;
+        for (path, _) in &updates {
+            for existing in entries.get(path).into_iter().flatten() {
+                vectors.insert(existing.hash, existing.vector.clone());
+            }
+        }
+    }
+    let mut pending: Vec<(u64, String)> = Vec::new();
+    let mut queued: HashSet<u64> = HashSet::new();
+    for chunk in updates.iter().flat_map(|(_, chunks)| chunks) {
+        if !vectors.contains_key(&chunk.hash) && queued.insert(chunk.hash) {
+            pending.push((chunk.hash, chunk.text.clone()));
+        }
+    }
+
+    if !pending.is_empty() {
+        log_to_file(&format!("[semantic] Embedding {} sections", pending.len()));
+    }
+    let client = reqwest::Client::new();
+    for batch in pending.chunks(BATCH_SIZE) {
+        let texts: Vec<String> = batch.iter().map(|(_, t)| t.clone()).collect();
+        match embed(&client, &config, &texts).await {
+            Ok(embedded) => {
+                for ((hash, _), vector) in batch.iter().zip(embedded) {
+                    vectors.insert(*hash, vector);
+                }
+            }
+            Err(e) => {
+                // Files missing any vector keep their previous embeddings until the next refresh
+                log_to_file(&format!("[semantic] Embedding failed: {}", e));
+                break;
+            }
+        }
+    }
+
+    let mut changed = removed;
+    {
+        let mut entries = state.semantic.entries.write().await;
+        for (path, chunks) in updates {
+            if !chunks.iter().all(|c| vectors.contains_key(&c.hash)) {
+                continue;
+            }
+            let embedded: Vec<HeadingEmbedding> = chunks
+                .into_iter()
+                .map(|c| HeadingEmbedding {
+                    line: c.line,
+                    title: c.title,
+                    vector: vectors[&c.hash].clone(),
+                    hash: c.hash,
+                })
+                .collect();
+            let unchanged = entries.get(&path).is_some_and(|old| {
+                old.len() == embedded.len()
+                    && old.iter().zip(&embedded).all(|(a, b)| a.hash == b.hash && a.line == b.line)
+            });
+            if !unchanged {
+                entries.insert(path, embedded);
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        state.semantic.save(&config.model).await;
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SemanticQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct SemanticMatch {
+    file: String,
+    line: usize,
+    title: String,
+    score: f32,
+}
+
+#[derive(Serialize)]
+pub struct SemanticResponse {
+    query: String,
+    count: usize,
+    items: Vec<SemanticMatch>,
+}
+
+/// GET /api/search/semantic?q=&limit= - Headings nearest to the query by
+/// embedding similarity. 503 when no embedding service is configured.
+pub async fn semantic_search(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SemanticQuery>,
+) -> Result<Json<SemanticResponse>, StatusCode> {
+    let config = state.config.embeddings.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let client = reqwest::Client::new();
+    let target = embed(&client, config, std::slice::from_ref(&query.q))
+        .await
+        .map_err(|e| {
+            log_to_file(&format!("[semantic] Query embedding failed: {}", e));
+            StatusCode::BAD_GATEWAY
+        })?
+        .pop()
+        .ok_or(StatusCode::BAD_GATEWAY)?;
+
+    let entries = state.semantic.entries.read().await;
+    let mut items: Vec<SemanticMatch> = entries
+        .iter()
+        .flat_map(|(path, headings)| {
+            headings.iter().filter(|h| h.vector.len() == target.len()).map(|h| SemanticMatch {
+                file: path.clone(),
+                line: h.line,
+                title: h.title.clone(),
+                score: dot(&target, &h.vector),
+            })
+        })
+        .collect();
+    items.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.file.cmp(&b.file)).then(a.line.cmp(&b.line)));
+    items.truncate(limit);
+
+    Ok(Json(SemanticResponse {
+        query: query.q,
+        count: items.len(),
+        items,
+    }))
+}
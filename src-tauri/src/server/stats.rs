@@ -0,0 +1,135 @@
+use axum::{extract::State, response::Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::server::org::parse_todo_keywords;
+use crate::server::timezone;
+use crate::server::AppState;
+
+#[derive(Serialize)]
+pub struct FileStats {
+    path: String,
+    words: usize,
+    headings: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct TodoStats {
+    active: usize,
+    done: usize,
+    /// done / (active + done), 0 when there are no TODO headings
+    #[serde(rename = "doneRatio")]
+    done_ratio: f64,
+}
+
+/// Files last modified in one month, with running totals across months
+#[derive(Serialize)]
+pub struct GrowthBucket {
+    month: String,
+    files: usize,
+    bytes: u64,
+    #[serde(rename = "cumulativeFiles")]
+    cumulative_files: usize,
+    #[serde(rename = "cumulativeBytes")]
+    cumulative_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct VaultStats {
+    #[serde(rename = "totalFiles")]
+    total_files: usize,
+    #[serde(rename = "totalWords")]
+    total_words: usize,
+    #[serde(rename = "totalHeadings")]
+    total_headings: usize,
+    #[serde(rename = "totalBytes")]
+    total_bytes: u64,
+    #[serde(rename = "byType")]
+    by_type: HashMap<String, usize>,
+    todo: TodoStats,
+    files: Vec<FileStats>,
+    growth: Vec<GrowthBucket>,
+}
+
+/// GET /api/stats - Vault-wide counts for dashboards
+pub async fn get_stats(State(state): State<Arc<AppState>>) -> Json<VaultStats> {
+    let index = state.index.read().await;
+
+    let mut files: Vec<FileStats> = Vec::new();
+    let mut todo = TodoStats { active: 0, done: 0, done_ratio: 0.0 };
+    // month -> (files, bytes)
+    let mut months: BTreeMap<String, (usize, u64)> = BTreeMap::new();
+
+    for (doc, headings) in index.documents_with_headings() {
+        let full_path = state.roots.resolve(&doc.path);
+        let content = match tokio::fs::read_to_string(&full_path).await {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        let metadata = tokio::fs::metadata(&full_path).await.ok();
+        let bytes = metadata.as_ref().map(|m| m.len()).unwrap_or(content.len() as u64);
+
+        let keywords = parse_todo_keywords(&content);
+        for keyword in headings.iter().filter_map(|h| h.todo.as_deref()) {
+            if keywords.is_done(keyword) {
+                todo.done += 1;
+            } else {
+                todo.active += 1;
+            }
+        }
+
+        let modified = metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|m| timezone::user_time(&state.config, None, DateTime::<Utc>::from(m)).ok());
+        if let Some(modified) = modified {
+            let month = modified.format("%Y-%m").to_string();
+            let bucket = months.entry(month).or_insert((0, 0));
+            bucket.0 += 1;
+            bucket.1 += bytes;
+        }
+
+        files.push(FileStats {
+            path: doc.path.clone(),
+            words: content.split_whitespace().count(),
+            headings: headings.len(),
+            bytes,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let total = todo.active + todo.done;
+    if total > 0 {
+        todo.done_ratio = todo.done as f64 / total as f64;
+    }
+
+    let mut cumulative_files = 0;
+    let mut cumulative_bytes = 0;
+    let growth = months
+        .into_iter()
+        .map(|(month, (count, bytes))| {
+            cumulative_files += count;
+            cumulative_bytes += bytes;
+            GrowthBucket {
+                month,
+                files: count,
+                bytes,
+                cumulative_files,
+                cumulative_bytes,
+            }
+        })
+        .collect();
+
+    Json(VaultStats {
+        total_files: files.len(),
+        total_words: files.iter().map(|f| f.words).sum(),
+        total_headings: files.iter().map(|f| f.headings).sum(),
+        total_bytes: files.iter().map(|f| f.bytes).sum(),
+        by_type: index.get_stats().by_type,
+        todo,
+        files,
+        growth,
+    })
+}
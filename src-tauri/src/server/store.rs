@@ -0,0 +1,456 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::server::index::{stable_hash, CachedEntry};
+
+pub const DB_FILENAME: &str = ".org-viewer-index.db";
+
+/// Where an unreadable database is moved before starting a fresh one
+const CORRUPT_FILENAME: &str = ".org-viewer-index.db.corrupt";
+
+/// The JSON cache this database replaced; imported and removed on first open
+const LEGACY_FILENAME: &str = ".org-viewer-index.json";
+
+/// Last format of the JSON cache, whose entries match `CachedEntry`
+const LEGACY_VERSION: u32 = 4;
+
+/// Database layout, kept in `PRAGMA user_version`. Bump it together with a new
+/// step in `MIGRATIONS`.
+const SCHEMA_VERSION: u32 = 8;
+
+/// Forward migrations: `(from, sql)` upgrades a version-`from` database to
+/// `from + 1`. Databases older than the first step are rebuilt from scratch.
+const MIGRATIONS: &[(u32, &str)] = &[
+    (5, "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);"),
+    (6, "ALTER TABLE documents ADD COLUMN hash INTEGER NOT NULL DEFAULT 0;"),
+    (7, "ALTER TABLE documents ADD COLUMN checksum INTEGER NOT NULL DEFAULT 0;"),
+];
+
+/// Bumped when parsing output or how it's checksummed changes, so cached
+/// entries are reparsed even though the database layout is the same
+pub const PARSER_VERSION: &str = "5";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS documents (
+        path TEXT PRIMARY KEY,
+        mtime INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        doc_type TEXT NOT NULL,
+        status TEXT,
+        document TEXT NOT NULL,
+        hash INTEGER NOT NULL DEFAULT 0,
+        checksum INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS headings (
+        path TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        line INTEGER NOT NULL,
+        level INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        todo TEXT,
+        heading TEXT NOT NULL,
+        PRIMARY KEY (path, position)
+    );
+    CREATE TABLE IF NOT EXISTS links (
+        source TEXT NOT NULL,
+        target TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        path TEXT NOT NULL,
+        tag TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS links_source ON links (source);
+    CREATE INDEX IF NOT EXISTS links_target ON links (target);
+    CREATE INDEX IF NOT EXISTS tags_path ON tags (path);
+    CREATE INDEX IF NOT EXISTS tags_tag ON tags (tag);
+    CREATE INDEX IF NOT EXISTS headings_todo ON headings (todo);
+";
+
+/// SQLite-backed cache of parsed documents, headings, links and tags in
+/// `.org-viewer-index.db`. Full entries are stored as JSON alongside the
+/// columns used for lookups, and writes touch only the changed documents.
+pub struct IndexStore {
+    conn: Mutex<Connection>,
+}
+
+/// Layout of the legacy JSON cache
+#[derive(Deserialize)]
+struct LegacyIndex {
+    version: u32,
+    entries: HashMap<String, CachedEntry>,
+}
+
+fn drop_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "DROP TABLE IF EXISTS documents;
+         DROP TABLE IF EXISTS headings;
+         DROP TABLE IF EXISTS links;
+         DROP TABLE IF EXISTS tags;
+         DROP TABLE IF EXISTS meta;",
+    )
+}
+
+/// Bring the database to `SCHEMA_VERSION`, migrating step by step where
+/// possible and starting over otherwise
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let mut version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let oldest = MIGRATIONS.first().map(|(from, _)| *from).unwrap_or(SCHEMA_VERSION);
+
+    if version != 0 && (version < oldest || version > SCHEMA_VERSION) {
+        println!(
+            "Index cache schema {} can't be migrated to {}; rebuilding",
+            version, SCHEMA_VERSION
+        );
+        drop_tables(conn)?;
+        version = 0;
+    }
+
+    if version == 0 {
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        return Ok(());
+    }
+
+    for (from, sql) in MIGRATIONS.iter().filter(|(from, _)| *from >= version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", from + 1)?;
+        tx.commit()?;
+        println!("Migrated index cache schema {} -> {}", from, from + 1);
+    }
+    Ok(())
+}
+
+/// Drop cached entries written by a different parser version
+fn check_parser_version(conn: &Connection) -> rusqlite::Result<()> {
+    let stored: Option<String> = conn
+        .query_row("SELECT value FROM meta WHERE key = 'parser_version'", [], |row| row.get(0))
+        .optional()?;
+    if stored.as_deref() == Some(PARSER_VERSION) {
+        return Ok(());
+    }
+    if stored.is_some() {
+        println!("Document parser changed since the index cache was written; reparsing all documents");
+    }
+    conn.execute_batch("DELETE FROM documents; DELETE FROM headings; DELETE FROM links; DELETE FROM tags;")?;
+    conn.execute(RECORD_COUNT, [])?;
+    conn.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('parser_version', ?1)",
+        [PARSER_VERSION],
+    )?;
+    Ok(())
+}
+
+/// Remember how many documents the cache holds, to spot rows lost or left
+/// behind on the next load
+const RECORD_COUNT: &str =
+    "INSERT OR REPLACE INTO meta (key, value) VALUES ('document_count', (SELECT COUNT(*) FROM documents))";
+
+/// Checksum of a document's stored JSON and its headings', in order
+fn entry_checksum<'a>(document: &str, headings: impl Iterator<Item = &'a str>) -> u64 {
+    let mut all = document.to_string();
+    for heading in headings {
+        all.push('\n');
+        all.push_str(heading);
+    }
+    stable_hash(all.as_bytes())
+}
+
+/// SQLite's own consistency check of the database file
+fn quick_check(conn: &Connection) -> Result<(), String> {
+    let result: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if result == "ok" {
+        Ok(())
+    } else {
+        Err(format!("integrity check failed: {}", result))
+    }
+}
+
+/// Documents the last write left in the cache
+fn recorded_count(conn: &Connection) -> Result<Option<usize>, String> {
+    let count: Option<String> = conn
+        .query_row("SELECT value FROM meta WHERE key = 'document_count'", [], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(count.and_then(|c| c.parse().ok()))
+}
+
+fn open_connection(path: &Path) -> rusqlite::Result<Connection> {
+    let mut conn = Connection::open(path)?;
+    // WAL keeps readers of the file (backups, external tools) from blocking writes
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    migrate(&mut conn)?;
+    check_parser_version(&conn)?;
+    Ok(conn)
+}
+
+impl IndexStore {
+    /// Open the database at the org root. If it can't be opened, entries are
+    /// kept in an in-memory database and the next start parses from scratch.
+    pub fn open(org_root: &Path) -> Self {
+        let path = org_root.join(DB_FILENAME);
+        let conn = open_connection(&path).or_else(|e| {
+            // Most likely a damaged file; keep it for inspection and start over
+            println!("Failed to open index database, moving it aside: {}", e);
+            let _ = std::fs::remove_file(org_root.join(CORRUPT_FILENAME));
+            std::fs::rename(&path, org_root.join(CORRUPT_FILENAME)).map_err(|_| e)?;
+            for suffix in ["-wal", "-shm"] {
+                let _ = std::fs::remove_file(org_root.join(format!("{}{}", DB_FILENAME, suffix)));
+            }
+            open_connection(&path)
+        });
+        let conn = conn.unwrap_or_else(|e| {
+            println!("Failed to open index database: {}", e);
+            let conn = Connection::open_in_memory().expect("in-memory index database");
+            conn.execute_batch(SCHEMA).expect("index database schema");
+            conn
+        });
+        let store = Self { conn: Mutex::new(conn) };
+        store.import_legacy(&org_root.join(LEGACY_FILENAME));
+        store
+    }
+
+    /// Carry entries over from the JSON cache so upgrading doesn't force a
+    /// full reparse, then remove it
+    fn import_legacy(&self, path: &Path) {
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(_) => return,
+        };
+        match serde_json::from_str::<LegacyIndex>(&content) {
+            Ok(legacy) if legacy.version == LEGACY_VERSION => {
+                let entries: Vec<(&str, CachedEntry)> = legacy
+                    .entries
+                    .iter()
+                    .map(|(path, entry)| (path.as_str(), entry.clone()))
+                    .collect();
+                self.write(&entries, &[]);
+                println!("Imported {} entries from legacy index cache", entries.len());
+            }
+            Ok(legacy) => println!("Discarding legacy index cache with version {}", legacy.version),
+            Err(e) => println!("Discarding unreadable legacy index cache: {}", e),
+        }
+        if let Err(e) = std::fs::remove_file(path) {
+            println!("Failed to remove legacy index cache {:?}: {}", path, e);
+        }
+    }
+
+    /// Every cached entry, keyed by relative path. Fails without returning
+    /// anything if the cache doesn't pass validation, since partially
+    /// corrupt entries would be served as if they were current.
+    pub fn load(&self) -> Result<HashMap<String, CachedEntry>, String> {
+        let conn = self.conn.lock().unwrap();
+        quick_check(&conn)?;
+
+        let mut headings: HashMap<String, Vec<String>> = HashMap::new();
+        {
+            let mut statement = conn
+                .prepare("SELECT path, heading FROM headings ORDER BY path, position")
+                .map_err(|e| e.to_string())?;
+            let rows = statement
+                .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                let (path, json) = row.map_err(|e| e.to_string())?;
+                headings.entry(path).or_default().push(json);
+            }
+        }
+
+        let mut entries = HashMap::new();
+        let mut statement = conn
+            .prepare("SELECT path, mtime, document, hash, checksum FROM documents")
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (path, mtime, json, hash, checksum) = row.map_err(|e| e.to_string())?;
+            let heading_rows = headings.remove(&path).unwrap_or_default();
+            // Rows migrated from before checksums have none to compare
+            if checksum != 0 && entry_checksum(&json, heading_rows.iter().map(|h| h.as_str())) != checksum as u64 {
+                return Err(format!("checksum mismatch for {}", path));
+            }
+            let document: crate::server::document::OrgDocument =
+                serde_json::from_str(&json).map_err(|e| format!("unreadable entry {}: {}", path, e))?;
+            if document.path != path {
+                return Err(format!("entry {} claims to be {}", path, document.path));
+            }
+            let parsed_headings = heading_rows
+                .iter()
+                .map(|h| serde_json::from_str(h))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("unreadable headings for {}: {}", path, e))?;
+            entries.insert(
+                path,
+                CachedEntry {
+                    document,
+                    mtime_secs: mtime as u64,
+                    headings: parsed_headings,
+                    hash: hash as u64,
+                },
+            );
+        }
+
+        if let Some(path) = headings.keys().next() {
+            return Err(format!("headings for {} without a document", path));
+        }
+        match recorded_count(&conn)? {
+            Some(count) if count != entries.len() => Err(format!(
+                "{} documents cached but the last write left {}",
+                entries.len(),
+                count
+            )),
+            _ => Ok(entries),
+        }
+    }
+
+    /// Check the database is intact and holds `expected` documents
+    pub fn verify(&self, expected: usize) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        quick_check(&conn)?;
+        let stored: i64 = conn
+            .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if stored as usize != expected {
+            return Err(format!("{} documents cached but {} indexed", stored, expected));
+        }
+        Ok(())
+    }
+
+    /// Insert or replace `entries` and delete `removed`, in one transaction
+    pub fn write(&self, entries: &[(&str, CachedEntry)], removed: &[String]) {
+        let mut conn = self.conn.lock().unwrap();
+        if let Err(e) = Self::write_tx(&mut conn, entries, removed) {
+            println!("Failed to save index cache: {}", e);
+        }
+    }
+
+    fn write_tx(conn: &mut Connection, entries: &[(&str, CachedEntry)], removed: &[String]) -> rusqlite::Result<()> {
+        let tx = conn.transaction()?;
+        {
+            let mut delete_document = tx.prepare_cached("DELETE FROM documents WHERE path = ?1")?;
+            let mut delete_headings = tx.prepare_cached("DELETE FROM headings WHERE path = ?1")?;
+            let mut delete_links = tx.prepare_cached("DELETE FROM links WHERE source = ?1")?;
+            let mut delete_tags = tx.prepare_cached("DELETE FROM tags WHERE path = ?1")?;
+            let mut insert_document = tx.prepare_cached(
+                "INSERT INTO documents (path, mtime, title, doc_type, status, document, hash, checksum) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            let mut insert_heading = tx.prepare_cached(
+                "INSERT INTO headings (path, position, line, level, title, todo, heading) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            let mut insert_link = tx.prepare_cached("INSERT INTO links (source, target) VALUES (?1, ?2)")?;
+            let mut insert_tag = tx.prepare_cached("INSERT INTO tags (path, tag) VALUES (?1, ?2)")?;
+
+            let paths = entries.iter().map(|(p, _)| *p).chain(removed.iter().map(|p| p.as_str()));
+            for path in paths {
+                delete_document.execute([path])?;
+                delete_headings.execute([path])?;
+                delete_links.execute([path])?;
+                delete_tags.execute([path])?;
+            }
+
+            for (path, entry) in entries {
+                let doc = &entry.document;
+                let json = serde_json::to_string(doc).unwrap_or_default();
+                let heading_jsons: Vec<String> = entry
+                    .headings
+                    .iter()
+                    .map(|h| serde_json::to_string(h).unwrap_or_default())
+                    .collect();
+                let checksum = entry_checksum(&json, heading_jsons.iter().map(|h| h.as_str()));
+                insert_document.execute(params![
+                    path,
+                    entry.mtime_secs as i64,
+                    doc.title,
+                    doc.doc_type,
+                    doc.status,
+                    json,
+                    entry.hash as i64,
+                    checksum as i64
+                ])?;
+                for ((position, heading), json) in entry.headings.iter().enumerate().zip(heading_jsons) {
+                    insert_heading.execute(params![
+                        path,
+                        position as i64,
+                        heading.line as i64,
+                        heading.level as i64,
+                        heading.title,
+                        heading.todo,
+                        json
+                    ])?;
+                }
+                for link in &doc.links {
+                    insert_link.execute(params![path, link])?;
+                }
+                for tag in doc.tags.iter().chain(doc.file_tags.iter()) {
+                    insert_tag.execute(params![path, tag])?;
+                }
+            }
+        }
+        tx.execute(RECORD_COUNT, [])?;
+        tx.commit()
+    }
+
+    /// A value from the meta table
+    pub fn load_meta(&self, key: &str) -> Option<String> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+            .optional()
+            .unwrap_or_else(|e| {
+                println!("Failed to read index cache meta {}: {}", key, e);
+                None
+            })
+    }
+
+    /// Store a value in the meta table
+    pub fn save_meta(&self, key: &str, value: &str) {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", [key, value]) {
+            println!("Failed to save index cache meta {}: {}", key, e);
+        }
+    }
+
+    /// Remove every cached entry
+    pub fn clear(&self) {
+        let conn = self.conn.lock().unwrap();
+        let result = conn
+            .execute_batch("DELETE FROM documents; DELETE FROM headings; DELETE FROM links; DELETE FROM tags;")
+            .and_then(|_| conn.execute(RECORD_COUNT, []));
+        if let Err(e) = result {
+            println!("Failed to clear index cache: {}", e);
+        }
+    }
+
+    /// Recreate every table empty, for a cache whose contents can't be
+    /// trusted. If even that fails the cache moves to memory for this run.
+    pub fn reset(&self) {
+        let mut conn = self.conn.lock().unwrap();
+        let result = drop_tables(&conn)
+            .and_then(|_| conn.execute_batch(SCHEMA))
+            .and_then(|_| check_parser_version(&conn));
+        if let Err(e) = result {
+            println!("Failed to reset index cache, keeping it in memory: {}", e);
+            let memory = Connection::open_in_memory().expect("in-memory index database");
+            memory.execute_batch(SCHEMA).expect("index database schema");
+            *conn = memory;
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderValue},
+    response::Response,
+};
+use futures_util::{stream, StreamExt};
+use std::convert::Infallible;
+
+/// Document bodies larger than this are streamed rather than serialized into
+/// one buffer
+pub const STREAM_THRESHOLD: usize = 4 * 1024 * 1024;
+
+/// Bytes of content escaped per chunk
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Next chunk boundary at or after `CHUNK_SIZE` bytes from `start`, kept on a
+/// char boundary
+fn chunk_end(content: &str, start: usize) -> usize {
+    let mut end = (start + CHUNK_SIZE).min(content.len());
+    while !content.is_char_boundary(end) {
+        end += 1;
+    }
+    end
+}
+
+/// A JSON object response whose `content` field is written out in chunks as
+/// the client reads, so a multi-megabyte document is never held twice. The
+/// body is produced on the fly and can't be hashed up front, so it goes out
+/// without an `ETag`.
+pub fn json_with_content(fields: serde_json::Value, content: String) -> Response {
+    // The other fields follow the content: `{"content":"...",<rest>}`
+    let rest = match &fields {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            let object = serde_json::to_string(&fields).unwrap_or_default();
+            format!("\",{}", &object[1..])
+        }
+        _ => "\"}".to_string(),
+    };
+
+    let head = stream::once(async { Ok::<_, Infallible>(String::from("{\"content\":\"")) });
+    let body = stream::unfold((content, 0), |(content, start)| async move {
+        if start >= content.len() {
+            return None;
+        }
+        let end = chunk_end(&content, start);
+        let escaped = serde_json::to_string(&content[start..end]).unwrap_or_default();
+        // Drop the quotes serde adds around the slice
+        let chunk = escaped[1..escaped.len() - 1].to_string();
+        Some((Ok(chunk), (content, end)))
+    });
+    let tail = stream::once(async move { Ok(rest) });
+
+    let mut response = Response::new(Body::from_stream(head.chain(body).chain(tail)));
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
@@ -0,0 +1,282 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::server::{log_to_file, AppState};
+
+#[derive(Debug, Clone)]
+enum TableRow {
+    Cells(Vec<String>),
+    /// Horizontal rule (`|---+---|` or `|---|:---:|`), with per-column
+    /// markdown alignment markers preserved
+    Rule(Vec<(bool, bool)>),
+}
+
+/// A table found in a document, as 0-based line range (end exclusive)
+#[derive(Debug, Clone)]
+struct Table {
+    start: usize,
+    end: usize,
+    indent: String,
+    /// org tables join rule columns with `+`, markdown with `|`
+    org_style: bool,
+    rows: Vec<TableRow>,
+}
+
+fn is_table_line(line: &str) -> bool {
+    line.trim_start().starts_with('|')
+}
+
+fn is_rule(cells: &[&str]) -> bool {
+    !cells.is_empty()
+        && cells.iter().all(|c| {
+            let c = c.trim();
+            !c.is_empty() && c.chars().all(|ch| matches!(ch, '-' | ':' | '+'))
+        })
+}
+
+/// Split a table line into cells, keeping markdown's escaped `\|` inside cells
+fn split_cells(line: &str) -> Vec<&str> {
+    let inner = line.trim().trim_start_matches('|');
+    let inner = inner.strip_suffix('|').unwrap_or(inner);
+
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut prev = ' ';
+    for (i, c) in inner.char_indices() {
+        if c == '|' && prev != '\\' {
+            cells.push(&inner[start..i]);
+            start = i + 1;
+        }
+        prev = c;
+    }
+    cells.push(&inner[start..]);
+    cells
+}
+
+/// Find all tables in document order
+fn find_tables(lines: &[&str]) -> Vec<Table> {
+    let mut tables = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        if !is_table_line(lines[i]) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < lines.len() && is_table_line(lines[i]) {
+            i += 1;
+        }
+
+        let first = lines[start];
+        let indent = first[..first.len() - first.trim_start().len()].to_string();
+        let mut org_style = false;
+        let rows = lines[start..i]
+            .iter()
+            .map(|line| {
+                // Org rules look like |---+---|, a single cell when split on '|'
+                let trimmed = line.trim();
+                if trimmed.starts_with("|-") && trimmed.contains('+') {
+                    org_style = true;
+                    let columns = trimmed.matches('+').count() + 1;
+                    return TableRow::Rule(vec![(false, false); columns]);
+                }
+                let cells = split_cells(line);
+                if is_rule(&cells) {
+                    TableRow::Rule(
+                        cells
+                            .iter()
+                            .map(|c| {
+                                let c = c.trim();
+                                (c.starts_with(':'), c.ends_with(':'))
+                            })
+                            .collect(),
+                    )
+                } else {
+                    TableRow::Cells(cells.iter().map(|c| c.trim().to_string()).collect())
+                }
+            })
+            .collect();
+
+        tables.push(Table {
+            start,
+            end: i,
+            indent,
+            org_style,
+            rows,
+        });
+    }
+
+    tables
+}
+
+/// Render a table with every column padded to its widest cell
+fn render_table(table: &Table) -> Vec<String> {
+    let columns = table
+        .rows
+        .iter()
+        .map(|r| match r {
+            TableRow::Cells(c) => c.len(),
+            TableRow::Rule(a) => a.len(),
+        })
+        .max()
+        .unwrap_or(0);
+
+    let mut widths = vec![1usize; columns];
+    for row in &table.rows {
+        if let TableRow::Cells(cells) = row {
+            for (i, cell) in cells.iter().enumerate() {
+                widths[i] = widths[i].max(cell.chars().count());
+            }
+        }
+    }
+
+    table
+        .rows
+        .iter()
+        .map(|row| match row {
+            TableRow::Cells(cells) => {
+                let padded: Vec<String> = widths
+                    .iter()
+                    .enumerate()
+                    .map(|(i, w)| {
+                        let cell = cells.get(i).map(|s| s.as_str()).unwrap_or("");
+                        let pad = w - cell.chars().count();
+                        format!(" {}{} ", cell, " ".repeat(pad))
+                    })
+                    .collect();
+                format!("{}|{}|", table.indent, padded.join("|"))
+            }
+            TableRow::Rule(align) => {
+                let segments: Vec<String> = widths
+                    .iter()
+                    .enumerate()
+                    .map(|(i, w)| {
+                        let (left, right) = align.get(i).copied().unwrap_or((false, false));
+                        let mut seg = "-".repeat(w + 2);
+                        if left {
+                            seg.replace_range(0..1, ":");
+                        }
+                        if right {
+                            seg.replace_range(seg.len() - 1.., ":");
+                        }
+                        seg
+                    })
+                    .collect();
+                let joiner = if table.org_style { "+" } else { "|" };
+                format!("{}|{}|", table.indent, segments.join(joiner))
+            }
+        })
+        .collect()
+}
+
+/// Realign table lines generated elsewhere (e.g. dynamic blocks)
+pub fn align_table(lines: &[String]) -> Vec<String> {
+    let refs: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+    match find_tables(&refs).first() {
+        Some(table) => render_table(table),
+        None => lines.to_vec(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTableRequest {
+    /// 0-based index of the table within the document
+    table: usize,
+    /// 0-based data row (horizontal rules are not counted)
+    row: usize,
+    /// Column to update; required together with `value`
+    column: Option<usize>,
+    value: Option<String>,
+    /// Replace the whole row instead of a single cell
+    cells: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+pub struct UpdateTableResponse {
+    /// Realigned table text as written to disk
+    table: Vec<String>,
+}
+
+/// Apply a cell or row update to a document's content, returning the new
+/// content and the rendered table
+fn apply_table_update(content: &str, req: &UpdateTableRequest) -> Result<(String, Vec<String>), StatusCode> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut table = find_tables(&lines)
+        .into_iter()
+        .nth(req.table)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let row = table
+        .rows
+        .iter_mut()
+        .filter_map(|r| match r {
+            TableRow::Cells(c) => Some(c),
+            TableRow::Rule(_) => None,
+        })
+        .nth(req.row)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Cells can't contain a bare column separator
+    let pipe = if table.org_style { "\\vert" } else { "\\|" };
+    let clean = |s: &str| s.replace('|', pipe).replace('\n', " ").trim().to_string();
+
+    match (&req.cells, req.column, &req.value) {
+        (Some(cells), _, _) => *row = cells.iter().map(|c| clean(c)).collect(),
+        (None, Some(column), Some(value)) => {
+            if row.len() <= column {
+                row.resize(column + 1, String::new());
+            }
+            row[column] = clean(value);
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    }
+
+    let rendered = render_table(&table);
+    let mut output: Vec<String> = lines[..table.start].iter().map(|l| l.to_string()).collect();
+    output.extend(rendered.iter().cloned());
+    output.extend(lines[table.end..].iter().map(|l| l.to_string()));
+
+    let mut new_content = output.join("\n");
+    if content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    Ok((new_content, rendered))
+}
+
+/// POST /api/files/*path/table - Update one table cell or row in place
+pub async fn update_table(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+    Json(payload): Json<UpdateTableRequest>,
+) -> Result<Json<UpdateTableResponse>, StatusCode> {
+    log_to_file(&format!("[tables] Update table {} in {}", payload.table, path));
+
+    // Validate path - prevent directory traversal
+    let full_path = state.roots.resolve(&path);
+    let canonical_path = full_path.canonicalize()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&path, &canonical_path) {
+        log_to_file(&format!("[tables] Rejected path traversal: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let content = tokio::fs::read_to_string(&canonical_path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let (new_content, table) = apply_table_update(&content, &payload)?;
+
+    if let Err(e) = tokio::fs::write(&canonical_path, new_content).await {
+        log_to_file(&format!("[tables] Failed to write: {}", e));
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // File watcher will auto-refresh index
+    Ok(Json(UpdateTableResponse { table }))
+}
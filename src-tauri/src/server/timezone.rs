@@ -0,0 +1,63 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::server::config::ServerConfig;
+use crate::server::log_to_file;
+
+/// `?tz=` override for endpoints whose other input is a JSON body
+#[derive(Deserialize)]
+pub struct TzQuery {
+    pub tz: Option<String>,
+}
+
+/// Pick the user's timezone: the request's `tz` parameter, then `timezone`
+/// from the config. `None` means the server's local time.
+fn resolve(config: &ServerConfig, requested: Option<&str>) -> Result<Option<Tz>, StatusCode> {
+    if let Some(name) = requested.filter(|n| !n.is_empty()) {
+        return name.parse::<Tz>().map(Some).map_err(|_| StatusCode::BAD_REQUEST);
+    }
+    match &config.timezone {
+        Some(name) => match name.parse::<Tz>() {
+            Ok(tz) => Ok(Some(tz)),
+            Err(_) => {
+                log_to_file(&format!("[timezone] Unknown timezone in config: {}", name));
+                Ok(None)
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+/// Convert an instant to wall-clock time in the user's timezone. Org timestamps
+/// carry no zone, so they are compared against values from here.
+pub fn user_time(
+    config: &ServerConfig,
+    requested: Option<&str>,
+    instant: DateTime<Utc>,
+) -> Result<NaiveDateTime, StatusCode> {
+    Ok(match resolve(config, requested)? {
+        Some(tz) => instant.with_timezone(&tz).naive_local(),
+        None => instant.with_timezone(&Local).naive_local(),
+    })
+}
+
+/// The instant a wall-clock time in the user's timezone falls at: the
+/// earlier one when clocks go back, and as if UTC in the hour they skip
+pub fn to_utc(
+    config: &ServerConfig,
+    requested: Option<&str>,
+    local: NaiveDateTime,
+) -> Result<DateTime<Utc>, StatusCode> {
+    let instant = match resolve(config, requested)? {
+        Some(tz) => tz.from_local_datetime(&local).earliest().map(|t| t.with_timezone(&Utc)),
+        None => Local.from_local_datetime(&local).earliest().map(|t| t.with_timezone(&Utc)),
+    };
+    Ok(instant.unwrap_or_else(|| local.and_utc()))
+}
+
+/// Current wall-clock time in the user's timezone
+pub fn now(config: &ServerConfig, requested: Option<&str>) -> Result<NaiveDateTime, StatusCode> {
+    user_time(config, requested, Utc::now())
+}
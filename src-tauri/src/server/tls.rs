@@ -0,0 +1,70 @@
+use axum_server::tls_rustls::RustlsConfig;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::server::log_to_file;
+
+/// Renewal tools write the certificate and key one after the other; wait
+/// this long after the last change before reloading both
+const RELOAD_DELAY: Duration = Duration::from_secs(2);
+
+/// Reload the HTTPS certificate and key whenever their files change, e.g.
+/// after `tailscale cert` renews them, so new connections get the new
+/// certificate without a restart. A pair that fails to load is logged and
+/// the current one kept. Runs until the server stops.
+pub async fn watch_certificates(config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    let (tx, mut rx) = mpsc::channel(16);
+    let mut watcher = match RecommendedWatcher::new(
+        move |res: notify::Result<Event>| {
+            let _ = tx.blocking_send(res);
+        },
+        notify::Config::default(),
+    ) {
+        Ok(w) => w,
+        Err(e) => {
+            log_to_file(&format!("[tls] Can't watch certificates, renewals need a restart: {}", e));
+            return;
+        }
+    };
+
+    // Renewals often replace the files rather than rewrite them, which a
+    // watch on the file itself wouldn't survive, so watch their directories
+    let mut dirs: Vec<&Path> = [&cert, &key].iter().filter_map(|p| p.parent()).collect();
+    dirs.dedup();
+    for dir in dirs {
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            log_to_file(&format!("[tls] Failed to watch {:?}: {}", dir, e));
+            return;
+        }
+    }
+    log_to_file(&format!("[tls] Watching {:?} and {:?} for renewals", cert, key));
+
+    let names = [cert.file_name(), key.file_name()];
+    while let Some(res) = rx.recv().await {
+        let relevant = match res {
+            Ok(event) => {
+                // Reading the files on reload raises access events; skip those
+                matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+                    && event.paths.iter().any(|p| names.contains(&p.file_name()))
+            }
+            Err(e) => {
+                log_to_file(&format!("[tls] Certificate watcher error: {}", e));
+                continue;
+            }
+        };
+        if !relevant {
+            continue;
+        }
+
+        // Let the rest of the renewal land
+        while let Ok(Some(_)) = tokio::time::timeout(RELOAD_DELAY, rx.recv()).await {}
+
+        match config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => log_to_file("[tls] Reloaded TLS certificate"),
+            Err(e) => log_to_file(&format!("[tls] Failed to reload TLS certificate, keeping the old one: {}", e)),
+        }
+    }
+}
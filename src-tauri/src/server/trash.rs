@@ -0,0 +1,159 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{NaiveDateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+use crate::server::create::new_document_path;
+use crate::server::document::is_document_file;
+use crate::server::{dirty, log_to_file, AppState};
+
+/// Trashed documents live here in the org root, hidden from the index
+const TRASH_DIR: &str = ".trash";
+
+/// Names the directory of one deletion; no colons, which Windows forbids
+const STAMP_FORMAT: &str = "%Y-%m-%dT%H-%M-%S%.3f";
+
+/// A trashed document. It sits at `.trash/<stamp>/<path>`, and its `id` is
+/// `<stamp>/<path>`, so the original path survives without a manifest.
+#[derive(Serialize)]
+pub struct TrashedFile {
+    id: String,
+    /// Where it was, and is restored to
+    path: String,
+    /// RFC 3339
+    #[serde(rename = "deletedAt")]
+    deleted_at: String,
+    size: u64,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreRequest {
+    id: String,
+}
+
+fn trash_dir(state: &AppState) -> PathBuf {
+    state.org_root.join(TRASH_DIR)
+}
+
+/// Move a file, copying when the trash is on another filesystem, as for a
+/// document in an extra root on another drive
+pub fn move_file(from: &FsPath, to: &FsPath) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from)
+}
+
+/// DELETE /api/files/*path - Move a document to `.trash/`
+pub async fn delete_file(
+    State(state): State<Arc<AppState>>,
+    Path(path): Path<String>,
+) -> Result<Json<TrashedFile>, StatusCode> {
+    log_to_file(&format!("[server] DELETE /api/files/{}", path));
+    let full_path = state.roots.resolve(&path);
+    let canonical = full_path.canonicalize().map_err(|_| StatusCode::NOT_FOUND)?;
+    if !state.roots.contains(&path, &canonical) {
+        log_to_file(&format!("[server] DELETE rejected - path traversal attempt: {}", path));
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !canonical.is_file() || !is_document_file(&full_path) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let now = Utc::now();
+    let id = format!("{}/{}", now.format(STAMP_FORMAT), path);
+    let size = std::fs::metadata(&full_path).map(|m| m.len()).unwrap_or(0);
+    move_file(&full_path, &trash_dir(&state).join(&id)).map_err(|e| {
+        log_to_file(&format!("[server] DELETE failed to move {} to trash: {}", path, e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    log_to_file(&format!("[server] DELETE trashed: {}", path));
+    dirty::apply_now(&state, vec![full_path]).await;
+    Ok(Json(TrashedFile {
+        id,
+        path,
+        deleted_at: now.to_rfc3339_opts(SecondsFormat::Millis, false),
+        size,
+    }))
+}
+
+/// GET /api/trash - Trashed documents, most recently deleted first
+pub async fn list_trash(State(state): State<Arc<AppState>>) -> Json<Vec<TrashedFile>> {
+    let dir = trash_dir(&state);
+    let mut items: Vec<TrashedFile> = WalkDir::new(&dir)
+        .min_depth(2)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let id = e.path().strip_prefix(&dir).ok()?.to_string_lossy().replace('\\', "/");
+            let (stamp, path) = id.split_once('/')?;
+            let deleted = NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT).ok()?.and_utc();
+            Some(TrashedFile {
+                path: path.to_string(),
+                deleted_at: deleted.to_rfc3339_opts(SecondsFormat::Millis, false),
+                size: e.metadata().map(|m| m.len()).unwrap_or(0),
+                id,
+            })
+        })
+        .collect();
+    items.sort_by(|a, b| b.id.cmp(&a.id));
+    Json(items)
+}
+
+/// POST /api/trash/restore - Move a trashed document back where it was.
+/// 409 if a document has been created there since.
+pub async fn restore(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RestoreRequest>,
+) -> Result<Json<TrashedFile>, StatusCode> {
+    let (stamp, path) = payload.id.split_once('/').ok_or(StatusCode::BAD_REQUEST)?;
+    if payload.id.split(['/', '\\']).any(|s| s == "..") {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let deleted = NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT)
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+        .and_utc();
+    let trashed = trash_dir(&state).join(&payload.id);
+    if !trashed.is_file() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let full_path = new_document_path(&state, path)?;
+    if full_path.exists() {
+        return Err(StatusCode::CONFLICT);
+    }
+    let size = std::fs::metadata(&trashed).map(|m| m.len()).unwrap_or(0);
+    move_file(&trashed, &full_path).map_err(|e| {
+        log_to_file(&format!("[server] Failed to restore {}: {}", payload.id, e));
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Tidy up the deletion's directories once they're empty
+    let stamp_dir = trash_dir(&state).join(stamp);
+    for dir in trashed.ancestors().skip(1).take_while(|d| d.starts_with(&stamp_dir)) {
+        if std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+
+    log_to_file(&format!("[server] Restored {} from trash", path));
+    dirty::apply_now(&state, vec![full_path]).await;
+    Ok(Json(TrashedFile {
+        path: path.to_string(),
+        deleted_at: deleted.to_rfc3339_opts(SecondsFormat::Millis, false),
+        size,
+        id: payload.id,
+    }))
+}
@@ -1,90 +1,467 @@
-use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
+use axum::{extract::State, response::Json};
+use notify::event::ModifyKind;
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::time::Instant;
 
-use crate::server::{log_to_file, AppState};
+use crate::server::document::is_document_file;
+use crate::server::events;
+use crate::server::ignore_file::IGNORE_FILENAME;
+use crate::server::projects::{self, ProjectChange, ProjectChanges};
+use crate::server::{log_to_file, reconcile, AppState};
+
+/// Seconds between scans in polling mode when `watchPollSeconds` isn't set
+pub const DEFAULT_POLL_SECONDS: u64 = 5;
+
+/// Filesystem types inotify can't see remote changes on, from `/proc/mounts`
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "fuse.sshfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "fuse.rclone",
+    "davfs",
+];
+
+/// How to watch the roots, from `watchMode` in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// Native events, polling on network filesystems or when the OS runs
+    /// out of watches
+    #[default]
+    Auto,
+    /// Native events only
+    Native,
+    /// Always poll
+    Poll,
+}
+
+/// First and longest wait before restarting a watcher that died
+const RESTART_MIN_DELAY: Duration = Duration::from_secs(1);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// The watcher actually running, for `/api/status`
+#[derive(Debug, Clone, Serialize)]
+pub struct WatcherStatus {
+    /// `native`, `poll`, or `off` before it starts or after it fails
+    pub mode: &'static str,
+    /// The OS mechanism behind the mode: `inotify`, `FSEvents`,
+    /// `ReadDirectoryChangesW`, `kqueue` or `polling`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<&'static str>,
+    /// Whether the watcher task is running; it's restarted when it dies
+    pub alive: bool,
+    /// Why polling was chosen, or why watching stopped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(rename = "pollSeconds", skip_serializing_if = "Option::is_none")]
+    pub poll_seconds: Option<u64>,
+    /// Set between `POST /api/watcher/pause` and `/resume`
+    pub paused: bool,
+    /// RFC 3339 time of the pause
+    #[serde(rename = "pausedAt", skip_serializing_if = "Option::is_none")]
+    pub paused_at: Option<String>,
+    /// File events received since startup
+    #[serde(rename = "eventsProcessed")]
+    pub events_processed: u64,
+    /// RFC 3339 time of the latest event
+    #[serde(rename = "lastEventAt", skip_serializing_if = "Option::is_none")]
+    pub last_event_at: Option<String>,
+    /// Errors reported by the watcher, including the ones that ended it
+    pub errors: u64,
+    /// Times the watcher was restarted after dying
+    pub restarts: u64,
+}
+
+impl Default for WatcherStatus {
+    fn default() -> Self {
+        Self {
+            mode: "off",
+            backend: None,
+            alive: false,
+            reason: None,
+            poll_seconds: None,
+            paused: false,
+            paused_at: None,
+            events_processed: 0,
+            last_event_at: None,
+            errors: 0,
+            restarts: 0,
+        }
+    }
+}
+
+/// Longest pending changes wait while events keep coming
+const PENDING_MAX_WAIT: Duration = Duration::from_secs(2);
+
+/// Changes the watcher holds until events go quiet
+#[derive(Default)]
+struct Pending {
+    projects: ProjectChanges,
+    /// Roots whose `.orgviewerignore` changed
+    ignores: BTreeSet<PathBuf>,
+}
+
+impl Pending {
+    fn is_empty(&self) -> bool {
+        self.projects.is_empty() && self.ignores.is_empty()
+    }
+
+    fn flush(&mut self, state: &Arc<AppState>) {
+        if !self.projects.is_empty() {
+            projects::broadcast_changes(state, &self.projects);
+            self.projects.clear();
+        }
+        if !self.ignores.is_empty() {
+            for root in std::mem::take(&mut self.ignores) {
+                log_to_file(&format!("[ignore] {:?} changed; rescanning", root.join(IGNORE_FILENAME)));
+                state.roots.reload_ignore(&root);
+            }
+            // A changed ignore file changes what belongs in the index
+            let state = state.clone();
+            tokio::spawn(async move { reconcile::reconcile(&state).await });
+        }
+    }
+}
 
 pub struct FileWatcher;
 
 impl FileWatcher {
+    /// Run the watcher, restarting it whenever it fails, panics or its
+    /// event stream ends, so changes don't silently stop being picked up.
+    /// Restarts back off from RESTART_MIN_DELAY to RESTART_MAX_DELAY while
+    /// it keeps dying quickly, and rescan the roots for missed changes.
+    pub async fn supervise(state: Arc<AppState>) {
+        let mut delay = RESTART_MIN_DELAY;
+        loop {
+            let started = Instant::now();
+            let reason = match tokio::spawn(Self::watch(state.clone())).await {
+                Ok(Ok(())) => "event stream ended".to_string(),
+                Ok(Err(e)) => e.to_string(),
+                Err(e) => format!("watcher task panicked: {}", e),
+            };
+            log_to_file(&format!("File watcher stopped: {}; restarting in {:?}", reason, delay));
+            {
+                let mut status = state.watcher.write().await;
+                status.mode = "off";
+                status.backend = None;
+                status.alive = false;
+                status.reason = Some(reason);
+                status.errors += 1;
+            }
+
+            if started.elapsed() > RESTART_MAX_DELAY {
+                delay = RESTART_MIN_DELAY;
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(RESTART_MAX_DELAY);
+
+            state.watcher.write().await.restarts += 1;
+            let catch_up = state.clone();
+            tokio::spawn(async move { reconcile::reconcile(&catch_up).await });
+        }
+    }
+
     pub async fn watch(state: Arc<AppState>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let (tx, mut rx) = mpsc::channel(100);
 
-        let mut watcher = RecommendedWatcher::new(
-            move |res: Result<Event, notify::Error>| {
-                if let Ok(event) = res {
-                    let _ = tx.blocking_send(event);
+        let network = state.roots.iter().find_map(|(_, root)| Self::network_filesystem(root));
+        let poll_reason = match (state.config.watch_mode, network) {
+            (WatchMode::Poll, _) => Some("configured".to_string()),
+            (WatchMode::Auto, Some((root, fs))) => Some(format!("{:?} is on {}", root, fs)),
+            _ => None,
+        };
+        let mut watcher = match poll_reason {
+            Some(reason) => Self::start_polling(&state, tx.clone(), reason).await?,
+            None => match Self::start_native(&state, tx.clone()).await {
+                Ok(w) => w,
+                Err(e) if state.config.watch_mode == WatchMode::Auto && Self::is_limit(&e) => {
+                    Self::start_polling(&state, tx.clone(), e.to_string()).await?
+                }
+                Err(e) => return Err(e.into()),
+            },
+        };
+
+        // Keep watcher alive and process events. Document changes go to the
+        // dirty queue, which batches them; project and ignore file changes are
+        // held here until events go quiet for the debounce window, or for at
+        // most PENDING_MAX_WAIT while they keep coming.
+        let window = Duration::from_millis(state.config.debounce_ms);
+        let mut pending = Pending::default();
+        let mut deadline = Instant::now();
+        loop {
+            let next = if pending.is_empty() {
+                rx.recv().await
+            } else {
+                let wait = window.min(deadline.saturating_duration_since(Instant::now()));
+                match tokio::time::timeout(wait, rx.recv()).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        pending.flush(&state);
+                        continue;
+                    }
+                }
+            };
+            let event = match next {
+                Some(Ok(e)) => e,
+                // Running out of watches as directories are added leaves
+                // new ones unwatched; polling sees everything
+                Some(Err(e)) if Self::is_limit(&e) && state.config.watch_mode == WatchMode::Auto => {
+                    if state.watcher.read().await.mode == "native" {
+                        drop(watcher);
+                        watcher = Self::start_polling(&state, tx.clone(), e.to_string()).await?;
+                        // Changes while switching over would otherwise be missed
+                        let state = state.clone();
+                        tokio::spawn(async move { reconcile::reconcile(&state).await });
+                    }
+                    continue;
                 }
+                Some(Err(e)) => {
+                    log_to_file(&format!("File watcher error: {}", e));
+                    state.watcher.write().await.errors += 1;
+                    continue;
+                }
+                None => break,
+            };
+            let paused = {
+                let mut status = state.watcher.write().await;
+                status.events_processed += 1;
+                status.last_event_at = Some(chrono::Utc::now().to_rfc3339());
+                status.paused
+            };
+            if pending.is_empty() {
+                deadline = Instant::now() + PENDING_MAX_WAIT.max(window);
+            }
+            // A directory appearing next to a metadata-only one isn't under a
+            // recursive watch, so it needs its own
+            if let EventKind::Create(_) = event.kind {
+                for path in &event.paths {
+                    let shallow = path.parent().is_some_and(|p| state.roots.has_metadata_only_within(p));
+                    if shallow && path.is_dir() {
+                        if let Err(e) = Self::watch_tree(&state, watcher.as_mut(), path) {
+                            log_to_file(&format!("File watcher failed to watch {:?}: {}", path, e));
+                        }
+                    }
+                }
+            }
+            // While paused, changes are left for the rescan on resume
+            if !paused {
+                Self::handle_event(&state, &event, &mut pending);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn start_native(
+        state: &AppState,
+        tx: mpsc::Sender<notify::Result<Event>>,
+    ) -> notify::Result<Box<dyn Watcher + Send>> {
+        let mut watcher = RecommendedWatcher::new(
+            move |res: notify::Result<Event>| {
+                let _ = tx.blocking_send(res);
             },
-            Config::default().with_poll_interval(Duration::from_secs(2)),
+            Config::default().with_follow_symlinks(state.roots.follow_symlinks()),
         )?;
+        Self::watch_roots(state, &mut watcher)?;
+        Self::set_status(state, "native", Self::native_backend(), None, None).await;
+        Ok(Box::new(watcher))
+    }
 
-        watcher.watch(&state.org_root, RecursiveMode::Recursive)?;
+    async fn start_polling(
+        state: &AppState,
+        tx: mpsc::Sender<notify::Result<Event>>,
+        reason: String,
+    ) -> notify::Result<Box<dyn Watcher + Send>> {
+        let seconds = state.config.poll_seconds.max(1);
+        log_to_file(&format!("File watcher polling every {}s: {}", seconds, reason));
+        let mut watcher = PollWatcher::new(
+            move |res: notify::Result<Event>| {
+                let _ = tx.blocking_send(res);
+            },
+            Config::default()
+                .with_poll_interval(Duration::from_secs(seconds))
+                .with_follow_symlinks(state.roots.follow_symlinks()),
+        )?;
+        Self::watch_roots(state, &mut watcher)?;
+        Self::set_status(state, "poll", "polling", Some(reason), Some(seconds)).await;
+        Ok(Box::new(watcher))
+    }
 
-        log_to_file(&format!("File watcher started for {:?}", state.org_root));
+    fn watch_roots(state: &AppState, watcher: &mut dyn Watcher) -> notify::Result<()> {
+        let roots: Vec<&Path> = state.roots.iter().map(|(_, root)| root).collect();
+        for root in &roots {
+            // A root nested in another is already covered by the outer watch
+            if roots.iter().any(|other| other != root && root.starts_with(other)) {
+                continue;
+            }
+            Self::watch_tree(state, watcher, root)?;
+            log_to_file(&format!("File watcher started for {:?}", root));
+        }
+        Ok(())
+    }
 
-        // Keep watcher alive and process events
-        while let Some(event) = rx.recv().await {
-            Self::handle_event(&state, &event).await;
+    /// Watch `dir` and everything below it except metadata-only directories:
+    /// recursively when there are none inside, else `dir` on its own and
+    /// each subdirectory in turn
+    fn watch_tree(state: &AppState, watcher: &mut dyn Watcher, dir: &Path) -> notify::Result<()> {
+        if state.roots.is_metadata_only(dir) {
+            return Ok(());
+        }
+        if !state.roots.has_metadata_only_within(dir) {
+            return watcher.watch(dir, RecursiveMode::Recursive);
         }
 
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        for entry in std::fs::read_dir(dir)?.flatten() {
+            let path = entry.path();
+            if path.is_dir() && (state.roots.follow_symlinks() || !path.is_symlink()) {
+                Self::watch_tree(state, watcher, &path)?;
+            }
+        }
         Ok(())
     }
 
-    async fn handle_event(state: &AppState, event: &Event) {
-        use notify::EventKind;
+    async fn set_status(
+        state: &AppState,
+        mode: &'static str,
+        backend: &'static str,
+        reason: Option<String>,
+        poll_seconds: Option<u64>,
+    ) {
+        let mut status = state.watcher.write().await;
+        status.mode = mode;
+        status.backend = Some(backend);
+        status.alive = true;
+        status.reason = reason;
+        status.poll_seconds = poll_seconds;
+    }
+
+    /// What `RecommendedWatcher` uses on this platform
+    fn native_backend() -> &'static str {
+        if cfg!(target_os = "linux") || cfg!(target_os = "android") {
+            "inotify"
+        } else if cfg!(target_os = "macos") {
+            "FSEvents"
+        } else if cfg!(windows) {
+            "ReadDirectoryChangesW"
+        } else {
+            "kqueue"
+        }
+    }
+
+    /// Whether the OS ran out of inotify watches or instances
+    fn is_limit(error: &notify::Error) -> bool {
+        match &error.kind {
+            notify::ErrorKind::MaxFilesWatch => true,
+            notify::ErrorKind::Io(e) => e.raw_os_error() == Some(24) || e.raw_os_error() == Some(28),
+            _ => false,
+        }
+    }
+
+    /// The network filesystem type `root` is mounted on, if any
+    #[cfg(target_os = "linux")]
+    fn network_filesystem(root: &Path) -> Option<(PathBuf, String)> {
+        let root = root.canonicalize().ok()?;
+        let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+        // The longest mount point containing the root is the one it's on
+        let (_, fs) = mounts
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let point = fields.nth(1)?.replace("\\040", " ");
+                let fs = fields.next()?;
+                root.starts_with(&point).then(|| (point, fs.to_string()))
+            })
+            .max_by_key(|(point, _)| point.len())?;
+        NETWORK_FILESYSTEMS.contains(&fs.as_str()).then_some((root, fs))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn network_filesystem(_root: &Path) -> Option<(PathBuf, String)> {
+        None
+    }
 
+    fn handle_event(state: &Arc<AppState>, event: &Event, pending: &mut Pending) {
         for path in &event.paths {
-            // Only handle markdown files
-            if !path.extension().map(|e| e == "md").unwrap_or(false) {
+            if path.file_name().is_some_and(|name| name == IGNORE_FILENAME) {
+                // Reading the file raises events too; only a new version counts
+                let rewritten = matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_))
+                );
+                if let Some((_, root)) = state.roots.iter().find(|(_, root)| path.parent() == Some(*root)) {
+                    if rewritten {
+                        pending.ignores.insert(root.to_path_buf());
+                    }
+                }
                 continue;
             }
 
-            // Skip excluded directories
-            if Self::is_excluded(path, &state.org_root) {
+            let ignored = state.roots.ignore_match(path, path.is_dir());
+            if ignored == Some(true) || state.roots.is_metadata_only(path) {
                 continue;
             }
 
-            let relative_path = path
-                .strip_prefix(&state.org_root)
-                .unwrap_or(path)
-                .to_string_lossy()
-                .replace('\\', "/");
+            // Files under projects/ also feed the code browser
+            if let Some((project, relative)) = projects::project_path(&state.org_root, path) {
+                let change = match event.kind {
+                    EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+                        Some(ProjectChange::Tree)
+                    }
+                    EventKind::Modify(ModifyKind::Data(_)) | EventKind::Modify(ModifyKind::Any) => {
+                        Some(ProjectChange::File)
+                    }
+                    _ => None,
+                };
+                if let Some(change) = change {
+                    pending.projects.insert((change, project, relative));
+                }
+            }
+
+            // Only handle Markdown and org documents
+            if !is_document_file(path) || Self::is_lock_file(path) {
+                continue;
+            }
+
+            let relative_path = match state.roots.relativize(path) {
+                Some(r) => r,
+                None => continue,
+            };
+
+            // Skip excluded directories, unless `.orgviewerignore` re-includes them
+            if ignored.is_none() && Self::is_excluded(path, state.roots.root_of(&relative_path)) {
+                continue;
+            }
 
             match event.kind {
-                EventKind::Create(_) | EventKind::Modify(_) => {
-                    log_to_file(&format!("File changed: {}", relative_path));
-                    let mut index = state.index.write().await;
-                    index.refresh_document(path);
-
-                    // Notify WebSocket clients
-                    let msg = serde_json::json!({
-                        "type": "update",
-                        "path": relative_path,
-                        "timestamp": chrono::Utc::now().timestamp_millis()
-                    });
-                    let _ = state.ws_tx.send(msg.to_string());
-                }
-                EventKind::Remove(_) => {
-                    log_to_file(&format!("File removed: {}", relative_path));
-                    let mut index = state.index.write().await;
-                    index.remove_document(path);
-
-                    // Notify WebSocket clients
-                    let msg = serde_json::json!({
-                        "type": "remove",
-                        "path": relative_path,
-                        "timestamp": chrono::Utc::now().timestamp_millis()
-                    });
-                    let _ = state.ws_tx.send(msg.to_string());
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
+                    state.dirty.push(path.clone());
                 }
                 _ => {}
             }
         }
     }
 
+    /// Emacs marks a file being edited with a `.#name.md` symlink, created
+    /// and removed around saves; it's not a document
+    fn is_lock_file(path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(".#"))
+    }
+
     fn is_excluded(path: &Path, org_root: &Path) -> bool {
         let relative = path.strip_prefix(org_root).unwrap_or(path);
         let path_str = relative.to_string_lossy();
@@ -109,3 +486,48 @@ impl FileWatcher {
         false
     }
 }
+
+fn broadcast_status(state: &AppState, status: &WatcherStatus) {
+    let payload = serde_json::json!({ "watcher": status });
+    state.events.send(events::EventKind::ServerStatus, None, payload);
+}
+
+/// POST /api/watcher/pause - Stop reacting to file changes, e.g. around a
+/// scripted mass edit or a git rebase over the vault, so it doesn't cause a
+/// reindex per step. Periodic rescans stop too. Pausing twice is harmless.
+pub async fn pause(State(state): State<Arc<AppState>>) -> Json<WatcherStatus> {
+    let status = {
+        let mut status = state.watcher.write().await;
+        if !status.paused {
+            status.paused = true;
+            status.paused_at = Some(chrono::Utc::now().to_rfc3339());
+            log_to_file("File watcher paused");
+        }
+        status.clone()
+    };
+    broadcast_status(&state, &status);
+    Json(status)
+}
+
+/// POST /api/watcher/resume - Start reacting to file changes again, and
+/// rescan the roots to catch up on everything that changed while paused
+pub async fn resume(State(state): State<Arc<AppState>>) -> Json<WatcherStatus> {
+    let (status, was_paused) = {
+        let mut status = state.watcher.write().await;
+        let was_paused = status.paused;
+        status.paused = false;
+        status.paused_at = None;
+        (status.clone(), was_paused)
+    };
+    if was_paused {
+        log_to_file("File watcher resumed; rescanning");
+        // The ignore files may have changed as well
+        for (_, root) in state.roots.iter() {
+            state.roots.reload_ignore(root);
+        }
+        let catch_up = state.clone();
+        tokio::spawn(async move { reconcile::reconcile(&catch_up).await });
+    }
+    broadcast_status(&state, &status);
+    Json(status)
+}